@@ -132,6 +132,9 @@ var MaschineMikroMK3 = (function (exports) {
     // Fixed velocity value when enabled
     const FIXED_VELOCITY_VALUE = 100;
 
+    // Heartbeat to the driver, which warns when it stops (host_watchdog_secs in the driver config)
+    const HOST_PING_INTERVAL_MS = 1000;
+
     // === Global state container ===
     // All mutable state is stored here for easy access across modules
 
@@ -173,6 +176,14 @@ var MaschineMikroMK3 = (function (exports) {
         noteRepeatTaskId: 0,     // Increment to invalidate old tasks
         heldPadNotes: {},        // note -> { velocity, taskId }
 
+        // Swing / humanize for note repeat and the step sequencer
+        grooveSettings: {
+            swing: 50,            // % (50 = straight)
+            humanizeVelocity: 0,  // max +/- velocity offset
+            humanizeTiming: 0,    // max late offset in ms
+            editParam: -1         // groove parameter on Shift + encoder (-1 = tempo)
+        },
+
         // Step sequencer
         stepSequencer: {
             steps: [],           // Array of step states (on/off)
@@ -277,11 +288,13 @@ var MaschineMikroMK3 = (function (exports) {
     let lastScreenText = "";
     let screenUpdatePending = false;
     let pendingScreenText = "";
+    // Last text sent per line with sendScreenLine
+    let lastScreenLines = ["", ""];
 
     /**
      * Send text to the Maschine screen (debounced)
      * SysEx format: F0 00 21 09 <cmd> <data...> F7
-     * Commands: 01 = Screen Text, 02 = Screen Clear
+     * Commands: 01 = Screen Text, 02 = Screen Clear, 03 = Host Ping, 04 = Screen Line
      */
     function sendScreenText(text) {
         // Debounce: if same text, skip
@@ -315,6 +328,34 @@ var MaschineMikroMK3 = (function (exports) {
         }
         sysexData += " F7";
         state.midiOut.sendSysex(sysexData);
+        lastScreenLines = ["", ""];
+    }
+
+    /**
+     * Set one of the two screen lines (0 = top, 1 = bottom), keeping the other one
+     * SysEx format: F0 00 21 09 04 <line> <text...> F7
+     */
+    function sendScreenLine(line, text) {
+        const truncated = text.substring(0, 16);
+        if (truncated === lastScreenLines[line]) return;
+        lastScreenLines[line] = truncated;
+        // A full-screen text sent after this should go out even if it didn't change
+        lastScreenText = "";
+
+        let sysexData = "F0 00 21 09 04 0" + line;
+        for (let i = 0; i < truncated.length; i++) {
+            const charCode = truncated.charCodeAt(i) & 0x7F; // Keep 7-bit ASCII
+            sysexData += " " + ("0" + charCode.toString(16)).slice(-2).toUpperCase();
+        }
+        sysexData += " F7";
+        state.midiOut.sendSysex(sysexData);
+    }
+
+    /**
+     * Tell the driver the script is alive (see HOST_PING_INTERVAL_MS)
+     */
+    function sendHostPing() {
+        state.midiOut.sendSysex("F0 00 21 09 03 F7");
     }
 
     /**
@@ -408,6 +449,74 @@ var MaschineMikroMK3 = (function (exports) {
         return getColorFromName(state.manualHitColor.get());
     }
 
+    // Groove parameters adjustable with Shift + encoder (selected with the Swing button)
+    const GROOVE_PARAMS = [
+        { key: 'swing', label: 'SWING', min: 50, max: 75 },           // % (50 = straight)
+        { key: 'humanizeVelocity', label: 'HUM VEL', min: 0, max: 40 }, // +/- velocity
+        { key: 'humanizeTiming', label: 'HUM TIME', min: 0, max: 30 }   // ms, late only
+    ];
+
+    /**
+     * Select the next groove parameter for Shift + encoder (after the last one: back to tempo)
+     */
+    function cycleGrooveParam() {
+        const next = state.grooveSettings.editParam + 1;
+        state.grooveSettings.editParam = next < GROOVE_PARAMS.length ? next : -1;
+        setButtonLed(BTN.SWING, state.grooveSettings.editParam >= 0 ? 127 : 0);
+
+        if (state.grooveSettings.editParam >= 0) {
+            showGrooveParam();
+        } else {
+            host.showPopupNotification("Shift + Encoder: Tempo");
+        }
+    }
+
+    /**
+     * Adjust the selected groove parameter, returns false if none is selected
+     */
+    function adjustGrooveParam(delta) {
+        if (state.grooveSettings.editParam < 0) return false;
+
+        const param = GROOVE_PARAMS[state.grooveSettings.editParam];
+        const value = state.grooveSettings[param.key] + delta;
+        state.grooveSettings[param.key] = Math.max(param.min, Math.min(param.max, value));
+        showGrooveParam();
+        return true;
+    }
+
+    function showGrooveParam() {
+        const param = GROOVE_PARAMS[state.grooveSettings.editParam];
+        const text = param.label + " " + state.grooveSettings[param.key];
+        sendScreenText(text);
+        host.showPopupNotification(text);
+    }
+
+    /**
+     * Delay (ms) from repeat tick `tick` to the next one, with swing applied.
+     * Ticks come in pairs; the off-beat lands at `swing` % of the pair.
+     */
+    function swingDelay(tick, interval) {
+        const swing = state.grooveSettings.swing / 100;
+        return Math.round(2 * interval * (tick % 2 === 0 ? swing : 1 - swing));
+    }
+
+    /**
+     * Random late offset (ms) for a generated note
+     */
+    function humanizeTiming() {
+        return Math.floor(Math.random() * (state.grooveSettings.humanizeTiming + 1));
+    }
+
+    /**
+     * Velocity with random variation, kept within 1..127
+     */
+    function humanizeVelocity(velocity) {
+        const amount = state.grooveSettings.humanizeVelocity;
+        if (amount === 0) return velocity;
+        const offset = Math.floor(Math.random() * (2 * amount + 1)) - amount;
+        return Math.max(1, Math.min(127, velocity + offset));
+    }
+
     /**
      * Toggle note repeat on/off
      */
@@ -432,7 +541,7 @@ var MaschineMikroMK3 = (function (exports) {
         
         // Store with current task ID to validate later
         state.heldPadNotes[note] = { velocity: velocity, taskId: state.noteRepeatTaskId };
-        scheduleNoteRepeatTick(note, state.noteRepeatTaskId);
+        scheduleNoteRepeatTick(note, state.noteRepeatTaskId, 0);
     }
 
     /**
@@ -447,7 +556,7 @@ var MaschineMikroMK3 = (function (exports) {
     /**
      * Schedule the next note repeat tick
      */
-    function scheduleNoteRepeatTick(note, taskId) {
+    function scheduleNoteRepeatTick(note, taskId, tick) {
         host.scheduleTask(function() {
             // Validate: note still held, same task generation, repeat enabled, play mode
             if (!state.heldPadNotes[note]) return;
@@ -455,21 +564,24 @@ var MaschineMikroMK3 = (function (exports) {
             if (!state.noteRepeatEnabled) return;
             if (state.currentMode !== MODES.PLAY) return;
             
-            const vel = state.heldPadNotes[note].velocity;
-            
-            // Send note on
-            state.noteInput.sendRawMidiEvent(0x90, note, vel);
+            const vel = humanizeVelocity(state.heldPadNotes[note].velocity);
             
-            // Schedule note off
+            // Send note on (humanized notes only ever land late, so the grid doesn't drift)
             host.scheduleTask(function() {
-                if (state.currentMode === MODES.PLAY) {
-                    state.noteInput.sendRawMidiEvent(0x80, note, 0);
-                }
-            }, Math.max(20, state.noteRepeatInterval / 2));
+                if (state.currentMode !== MODES.PLAY) return;
+                state.noteInput.sendRawMidiEvent(0x90, note, vel);
+                
+                // Schedule note off
+                host.scheduleTask(function() {
+                    if (state.currentMode === MODES.PLAY) {
+                        state.noteInput.sendRawMidiEvent(0x80, note, 0);
+                    }
+                }, Math.max(20, state.noteRepeatInterval / 2));
+            }, humanizeTiming());
             
             // Schedule next repeat
-            scheduleNoteRepeatTick(note, taskId);
-        }, state.noteRepeatInterval);
+            scheduleNoteRepeatTick(note, taskId, tick + 1);
+        }, swingDelay(tick, state.noteRepeatInterval));
     }
 
     /**
@@ -687,7 +799,7 @@ var MaschineMikroMK3 = (function (exports) {
                 state.cursorClip.setStep(
                     padIndex,
                     0,  // y=0 (current note row)
-                    127,  // Full velocity
+                    humanizeVelocity(127),  // Full velocity, humanized
                     state.stepSequencer.resolution
                 );
             }
@@ -1459,8 +1571,8 @@ var MaschineMikroMK3 = (function (exports) {
                 if (state.isShiftPressed) {
                     state.groove.getEnabled().toggle();
                 } else {
-                    // Could show groove panel
-                    host.showPopupNotification("Swing: Use encoder to adjust");
+                    // Select swing / humanize for Shift + encoder
+                    cycleGrooveParam();
                 }
                 break;
 
@@ -1582,8 +1694,10 @@ var MaschineMikroMK3 = (function (exports) {
         if (delta < -8) delta = -8;
 
         if (state.isShiftPressed) {
-            // Tempo adjustment (works in all modes)
-            state.transport.tempo().incRaw(delta);
+            // Groove parameter selected with the Swing button, otherwise tempo (works in all modes)
+            if (!adjustGrooveParam(delta)) {
+                state.transport.tempo().incRaw(delta);
+            }
             return;
         }
         
@@ -1805,9 +1919,20 @@ var MaschineMikroMK3 = (function (exports) {
             sendScreenText(MODE_NAMES[state.currentMode]);
         }, 100);
 
+        // Keep the driver's host watchdog fed
+        pingDriver();
+
         println("Maschine Mikro MK3 (Linux) initialized with modes, note repeat, fixed velocity, and step sequencer");
     }
 
+    /**
+     * Ping the driver, then again after HOST_PING_INTERVAL_MS
+     */
+    function pingDriver() {
+        sendHostPing();
+        host.scheduleTask(pingDriver, HOST_PING_INTERVAL_MS);
+    }
+
     /**
      * Setup user preferences
      */
@@ -1907,8 +2032,18 @@ var MaschineMikroMK3 = (function (exports) {
         trackNameValue.markInterested();
         trackNameValue.addValueObserver(function(name) {
             // Only update screen in Play/Mixer mode
-            if (state.currentMode === 0 || state.currentMode === 3) { // PLAY or MIXER
+            if (state.currentMode === 0) { // PLAY
                 sendScreenText(name);
+            } else if (state.currentMode === 3) { // MIXER: name on top, volume below
+                sendScreenLine(0, name);
+            }
+        });
+
+        const trackVolumeValue = state.cursorTrack.volume().displayedValue();
+        trackVolumeValue.markInterested();
+        trackVolumeValue.addValueObserver(function(volume) {
+            if (state.currentMode === 3) { // MIXER
+                sendScreenLine(1, volume);
             }
         });
 
@@ -90,3 +90,6 @@ export const PAD_COLORS = {
 
 // Fixed velocity value when enabled
 export const FIXED_VELOCITY_VALUE = 100;
+
+// Heartbeat to the driver, which warns when it stops (host_watchdog_secs in the driver config)
+export const HOST_PING_INTERVAL_MS = 1000;
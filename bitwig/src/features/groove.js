@@ -0,0 +1,72 @@
+import { state } from '../state.js';
+import { setButtonLed } from '../led.js';
+import { sendScreenText } from '../screen.js';
+import { BTN } from '../constants.js';
+
+// Groove parameters adjustable with Shift + encoder (selected with the Swing button)
+const GROOVE_PARAMS = [
+    { key: 'swing', label: 'SWING', min: 50, max: 75 },           // % (50 = straight)
+    { key: 'humanizeVelocity', label: 'HUM VEL', min: 0, max: 40 }, // +/- velocity
+    { key: 'humanizeTiming', label: 'HUM TIME', min: 0, max: 30 }   // ms, late only
+];
+
+/**
+ * Select the next groove parameter for Shift + encoder (after the last one: back to tempo)
+ */
+export function cycleGrooveParam() {
+    const next = state.grooveSettings.editParam + 1;
+    state.grooveSettings.editParam = next < GROOVE_PARAMS.length ? next : -1;
+    setButtonLed(BTN.SWING, state.grooveSettings.editParam >= 0 ? 127 : 0);
+
+    if (state.grooveSettings.editParam >= 0) {
+        showGrooveParam();
+    } else {
+        host.showPopupNotification("Shift + Encoder: Tempo");
+    }
+}
+
+/**
+ * Adjust the selected groove parameter, returns false if none is selected
+ */
+export function adjustGrooveParam(delta) {
+    if (state.grooveSettings.editParam < 0) return false;
+
+    const param = GROOVE_PARAMS[state.grooveSettings.editParam];
+    const value = state.grooveSettings[param.key] + delta;
+    state.grooveSettings[param.key] = Math.max(param.min, Math.min(param.max, value));
+    showGrooveParam();
+    return true;
+}
+
+function showGrooveParam() {
+    const param = GROOVE_PARAMS[state.grooveSettings.editParam];
+    const text = param.label + " " + state.grooveSettings[param.key];
+    sendScreenText(text);
+    host.showPopupNotification(text);
+}
+
+/**
+ * Delay (ms) from repeat tick `tick` to the next one, with swing applied.
+ * Ticks come in pairs; the off-beat lands at `swing` % of the pair.
+ */
+export function swingDelay(tick, interval) {
+    const swing = state.grooveSettings.swing / 100;
+    return Math.round(2 * interval * (tick % 2 === 0 ? swing : 1 - swing));
+}
+
+/**
+ * Random late offset (ms) for a generated note
+ */
+export function humanizeTiming() {
+    return Math.floor(Math.random() * (state.grooveSettings.humanizeTiming + 1));
+}
+
+/**
+ * Velocity with random variation, kept within 1..127
+ */
+export function humanizeVelocity(velocity) {
+    const amount = state.grooveSettings.humanizeVelocity;
+    if (amount === 0) return velocity;
+    const offset = Math.floor(Math.random() * (2 * amount + 1)) - amount;
+    return Math.max(1, Math.min(127, velocity + offset));
+}
@@ -1,6 +1,7 @@
 import { state } from '../state.js';
 import { setButtonLed } from '../led.js';
 import { BTN, MODES } from '../constants.js';
+import { swingDelay, humanizeTiming, humanizeVelocity } from './groove.js';
 
 /**
  * Toggle note repeat on/off
@@ -26,7 +27,7 @@ export function startNoteRepeat(note, velocity) {
     
     // Store with current task ID to validate later
     state.heldPadNotes[note] = { velocity: velocity, taskId: state.noteRepeatTaskId };
-    scheduleNoteRepeatTick(note, state.noteRepeatTaskId);
+    scheduleNoteRepeatTick(note, state.noteRepeatTaskId, 0);
 }
 
 /**
@@ -41,7 +42,7 @@ export function stopNoteRepeat(note) {
 /**
  * Schedule the next note repeat tick
  */
-function scheduleNoteRepeatTick(note, taskId) {
+function scheduleNoteRepeatTick(note, taskId, tick) {
     host.scheduleTask(function() {
         // Validate: note still held, same task generation, repeat enabled, play mode
         if (!state.heldPadNotes[note]) return;
@@ -49,21 +50,24 @@ function scheduleNoteRepeatTick(note, taskId) {
         if (!state.noteRepeatEnabled) return;
         if (state.currentMode !== MODES.PLAY) return;
         
-        const vel = state.heldPadNotes[note].velocity;
+        const vel = humanizeVelocity(state.heldPadNotes[note].velocity);
         
-        // Send note on
-        state.noteInput.sendRawMidiEvent(0x90, note, vel);
-        
-        // Schedule note off
+        // Send note on (humanized notes only ever land late, so the grid doesn't drift)
         host.scheduleTask(function() {
-            if (state.currentMode === MODES.PLAY) {
-                state.noteInput.sendRawMidiEvent(0x80, note, 0);
-            }
-        }, Math.max(20, state.noteRepeatInterval / 2));
+            if (state.currentMode !== MODES.PLAY) return;
+            state.noteInput.sendRawMidiEvent(0x90, note, vel);
+            
+            // Schedule note off
+            host.scheduleTask(function() {
+                if (state.currentMode === MODES.PLAY) {
+                    state.noteInput.sendRawMidiEvent(0x80, note, 0);
+                }
+            }, Math.max(20, state.noteRepeatInterval / 2));
+        }, humanizeTiming());
         
         // Schedule next repeat
-        scheduleNoteRepeatTick(note, taskId);
-    }, state.noteRepeatInterval);
+        scheduleNoteRepeatTick(note, taskId, tick + 1);
+    }, swingDelay(tick, state.noteRepeatInterval));
 }
 
 /**
@@ -3,6 +3,7 @@ import { BTN, MODES, FIXED_VELOCITY_VALUE } from '../constants.js';
 import { setButtonLed } from '../led.js';
 import { toggleNoteRepeat, cycleNoteRepeatInterval } from '../features/noteRepeat.js';
 import { toggleFixedVelocity } from '../features/fixedVelocity.js';
+import { cycleGrooveParam } from '../features/groove.js';
 import { setMode, cycleMode, clearAllSteps } from '../modes/index.js';
 
 /**
@@ -241,8 +242,8 @@ export function onButton(button, pressed, value) {
             if (state.isShiftPressed) {
                 state.groove.getEnabled().toggle();
             } else {
-                // Could show groove panel
-                host.showPopupNotification("Swing: Use encoder to adjust");
+                // Select swing / humanize for Shift + encoder
+                cycleGrooveParam();
             }
             break;
 
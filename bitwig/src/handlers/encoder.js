@@ -2,6 +2,7 @@ import { state } from '../state.js';
 import { MODES } from '../constants.js';
 import { handleStepEncoderTurn } from '../modes/step.js';
 import { handleClipEncoderTurn } from '../modes/clip.js';
+import { adjustGrooveParam } from '../features/groove.js';
 
 /**
  * Decode the relative encoder value
@@ -38,8 +39,10 @@ export function onEncoder(delta) {
     if (delta < -8) delta = -8;
 
     if (state.isShiftPressed) {
-        // Tempo adjustment (works in all modes)
-        state.transport.tempo().incRaw(delta);
+        // Groove parameter selected with the Swing button, otherwise tempo (works in all modes)
+        if (!adjustGrooveParam(delta)) {
+            state.transport.tempo().incRaw(delta);
+        }
         return;
     }
     
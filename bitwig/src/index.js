@@ -4,9 +4,9 @@
 // are in the rollup banner (must be at global scope for Bitwig)
 
 import { state, initializeState } from './state.js';
-import { BTN, MODE_NAMES, PAD_NOTES } from './constants.js';
+import { BTN, MODE_NAMES, PAD_NOTES, HOST_PING_INTERVAL_MS } from './constants.js';
 import { setButtonLed, flushLeds, allLedsOff } from './led.js';
-import { sendScreenText } from './screen.js';
+import { sendScreenText, sendScreenLine, sendHostPing } from './screen.js';
 import { rgbToPadColor } from './utils/colors.js';
 import { onMidi } from './handlers/midi.js';
 import { updateModeIndicators, updatePadLedsForMode } from './modes/index.js';
@@ -57,9 +57,20 @@ function init() {
         sendScreenText(MODE_NAMES[state.currentMode]);
     }, 100);
 
+    // Keep the driver's host watchdog fed
+    pingDriver();
+
     println("Maschine Mikro MK3 (Linux) initialized with modes, note repeat, fixed velocity, and step sequencer");
 }
 
+/**
+ * Ping the driver, then again after HOST_PING_INTERVAL_MS
+ */
+function pingDriver() {
+    sendHostPing();
+    host.scheduleTask(pingDriver, HOST_PING_INTERVAL_MS);
+}
+
 /**
  * Setup user preferences
  */
@@ -159,8 +170,18 @@ function setupObservers() {
     trackNameValue.markInterested();
     trackNameValue.addValueObserver(function(name) {
         // Only update screen in Play/Mixer mode
-        if (state.currentMode === 0 || state.currentMode === 3) { // PLAY or MIXER
+        if (state.currentMode === 0) { // PLAY
             sendScreenText(name);
+        } else if (state.currentMode === 3) { // MIXER: name on top, volume below
+            sendScreenLine(0, name);
+        }
+    });
+
+    const trackVolumeValue = state.cursorTrack.volume().displayedValue();
+    trackVolumeValue.markInterested();
+    trackVolumeValue.addValueObserver(function(volume) {
+        if (state.currentMode === 3) { // MIXER
+            sendScreenLine(1, volume);
         }
     });
 
@@ -3,6 +3,7 @@ import { PAD_NOTES, PAD_COLORS } from '../constants.js';
 import { setPadLed } from '../led.js';
 import { sendScreenText } from '../screen.js';
 import { getNoteNameFromMidi } from '../utils/notes.js';
+import { humanizeVelocity } from '../features/groove.js';
 
 /**
  * Update step sequencer LEDs
@@ -47,7 +48,7 @@ export function toggleStep(padIndex) {
             state.cursorClip.setStep(
                 padIndex,
                 0,  // y=0 (current note row)
-                127,  // Full velocity
+                humanizeVelocity(127),  // Full velocity, humanized
                 state.stepSequencer.resolution
             );
         }
@@ -4,11 +4,13 @@ import { state } from './state.js';
 let lastScreenText = "";
 let screenUpdatePending = false;
 let pendingScreenText = "";
+// Last text sent per line with sendScreenLine
+let lastScreenLines = ["", ""];
 
 /**
  * Send text to the Maschine screen (debounced)
  * SysEx format: F0 00 21 09 <cmd> <data...> F7
- * Commands: 01 = Screen Text, 02 = Screen Clear
+ * Commands: 01 = Screen Text, 02 = Screen Clear, 03 = Host Ping, 04 = Screen Line
  */
 export function sendScreenText(text) {
     // Debounce: if same text, skip
@@ -42,6 +44,27 @@ function doSendScreenText(text) {
     }
     sysexData += " F7";
     state.midiOut.sendSysex(sysexData);
+    lastScreenLines = ["", ""];
+}
+
+/**
+ * Set one of the two screen lines (0 = top, 1 = bottom), keeping the other one
+ * SysEx format: F0 00 21 09 04 <line> <text...> F7
+ */
+export function sendScreenLine(line, text) {
+    const truncated = text.substring(0, 16);
+    if (truncated === lastScreenLines[line]) return;
+    lastScreenLines[line] = truncated;
+    // A full-screen text sent after this should go out even if it didn't change
+    lastScreenText = "";
+
+    let sysexData = "F0 00 21 09 04 0" + line;
+    for (let i = 0; i < truncated.length; i++) {
+        const charCode = truncated.charCodeAt(i) & 0x7F; // Keep 7-bit ASCII
+        sysexData += " " + ("0" + charCode.toString(16)).slice(-2).toUpperCase();
+    }
+    sysexData += " F7";
+    state.midiOut.sendSysex(sysexData);
 }
 
 /**
@@ -49,5 +72,13 @@ function doSendScreenText(text) {
  */
 export function sendScreenClear() {
     lastScreenText = "";
+    lastScreenLines = ["", ""];
     state.midiOut.sendSysex("F0 00 21 09 02 F7");
 }
+
+/**
+ * Tell the driver the script is alive (see HOST_PING_INTERVAL_MS)
+ */
+export function sendHostPing() {
+    state.midiOut.sendSysex("F0 00 21 09 03 F7");
+}
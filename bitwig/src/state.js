@@ -41,6 +41,14 @@ export const state = {
     noteRepeatTaskId: 0,     // Increment to invalidate old tasks
     heldPadNotes: {},        // note -> { velocity, taskId }
 
+    // Swing / humanize for note repeat and the step sequencer
+    grooveSettings: {
+        swing: 50,            // % (50 = straight)
+        humanizeVelocity: 0,  // max +/- velocity offset
+        humanizeTiming: 0,    // max late offset in ms
+        editParam: -1         // groove parameter on Shift + encoder (-1 = tempo)
+    },
+
     // Step sequencer
     stepSequencer: {
         steps: [],           // Array of step states (on/off)
@@ -0,0 +1,76 @@
+use maschine_library::screen::Screen;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Set by the MIDI input callback and by `MidiOut::flush`
+static MIDI_IN_SEEN: AtomicBool = AtomicBool::new(false);
+static MIDI_OUT_SEEN: AtomicBool = AtomicBool::new(false);
+
+/// How long one message keeps its block lit
+const BLINK: Duration = Duration::from_millis(60);
+/// Size of each block, in pixels
+const BLOCK: usize = 3;
+/// Left edge of the input and output blocks in the top right corner
+const BLOCK_COLUMNS: [usize; 2] = [128 - 2 * BLOCK - 1, 128 - BLOCK];
+
+/// Records MIDI received from the host
+pub(crate) fn midi_in() {
+    MIDI_IN_SEEN.store(true, Ordering::Relaxed);
+}
+
+/// Records MIDI sent by the driver
+pub(crate) fn midi_out() {
+    MIDI_OUT_SEEN.store(true, Ordering::Relaxed);
+}
+
+/// Two small blocks in the top right corner of the screen blinking on MIDI traffic:
+/// the left one for input from the host, the right one for the driver's output.
+pub(crate) struct ActivityIndicator {
+    blocks: [Block; 2],
+}
+
+#[derive(Clone, Copy, Default)]
+struct Block {
+    lit_until: Option<Instant>,
+    shown: bool,
+}
+
+impl ActivityIndicator {
+    pub(crate) fn new() -> Self {
+        // Traffic before the indicator existed doesn't count
+        MIDI_IN_SEEN.store(false, Ordering::Relaxed);
+        MIDI_OUT_SEEN.store(false, Ordering::Relaxed);
+        Self {
+            blocks: [Block::default(); 2],
+        }
+    }
+
+    /// True when a block turned on or off and the screen needs a write
+    pub(crate) fn poll(&mut self, now: Instant) -> bool {
+        let seen = [
+            MIDI_IN_SEEN.swap(false, Ordering::Relaxed),
+            MIDI_OUT_SEEN.swap(false, Ordering::Relaxed),
+        ];
+        let mut changed = false;
+        for (block, seen) in self.blocks.iter_mut().zip(seen) {
+            if seen {
+                block.lit_until = Some(now + BLINK);
+            }
+            let lit = block.lit_until.is_some_and(|until| now < until);
+            changed |= lit != block.shown;
+            block.shown = lit;
+        }
+        changed
+    }
+
+    /// Draws both blocks (on or off) over the screen content
+    pub(crate) fn draw(&self, screen: &mut Screen) {
+        for (&column, block) in BLOCK_COLUMNS.iter().zip(&self.blocks) {
+            for row in 0..BLOCK {
+                for col in column..column + BLOCK {
+                    screen.set(row, col, block.shown);
+                }
+            }
+        }
+    }
+}
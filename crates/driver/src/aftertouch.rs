@@ -0,0 +1,187 @@
+use crate::slider::SLIDER_LEDS;
+use maschine_library::lights::{Brightness, Lights};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// How pad pressure is sent to MIDI output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AftertouchMode {
+    Off,
+    /// Polyphonic key pressure (0xA0), one stream per pad note
+    Poly,
+    /// Channel pressure (0xD0), highest pressure of all held pads
+    Channel,
+}
+
+pub(crate) fn parse_aftertouch_mode(s: &str) -> Result<AftertouchMode, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok(AftertouchMode::Off),
+        "poly" => Ok(AftertouchMode::Poly),
+        "channel" => Ok(AftertouchMode::Channel),
+        other => Err(format!(
+            "invalid aftertouch={other:?} (expected: \"off\", \"poly\", \"channel\")"
+        )),
+    }
+}
+
+/// Routes pad pressure to a CC (configured in `aftertouch_cc`), sent in addition to any
+/// poly/channel aftertouch
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct AftertouchCcRoute {
+    /// CC number (0-127)
+    pub cc: u8,
+    /// MIDI channel (0-15)
+    #[serde(default)]
+    pub channel: u8,
+    /// Logical pad indices (0-15) feeding the CC; empty means all pads
+    #[serde(default)]
+    pub pads: Vec<usize>,
+}
+
+impl AftertouchCcRoute {
+    pub(crate) fn matches(&self, pad: usize) -> bool {
+        self.pads.is_empty() || self.pads.contains(&pad)
+    }
+}
+
+/// A pad reserved for a pressure-driven FX throw (configured in `fx_throws`): it plays no
+/// note, its pressure drives a CC that snaps back to 0 on release
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct FxThrow {
+    /// Logical pad index (0-15)
+    pub pad: usize,
+    /// CC number (0-127)
+    pub cc: u8,
+    /// MIDI channel (0-15)
+    #[serde(default)]
+    pub channel: u8,
+    /// CC value at full pressure
+    #[serde(default = "default_max")]
+    pub max: u8,
+}
+
+fn default_max() -> u8 {
+    127
+}
+
+impl FxThrow {
+    /// CC value for a pad pressure (0-127)
+    pub(crate) fn value(&self, pressure: u8) -> u8 {
+        (pressure as u16 * self.max as u16 / 127) as u8
+    }
+}
+
+/// Decimates the pad pressure stream so receivers aren't flooded.
+///
+/// A new value is let through only if it differs enough from the last one sent for that pad
+/// and enough time has passed. Releases (pressure 0) always pass so nothing gets stuck.
+pub(crate) struct AftertouchLimiter {
+    last: [Option<(u8, Instant)>; 16],
+    min_interval: Duration,
+    min_change: u8,
+}
+
+impl AftertouchLimiter {
+    pub(crate) fn new(min_interval: Duration, min_change: u8) -> Self {
+        Self {
+            last: [None; 16],
+            min_interval,
+            min_change,
+        }
+    }
+
+    /// Returns the pressure to send, or None if this update should be dropped
+    pub(crate) fn filter(&mut self, pad: usize, pressure: u8, now: Instant) -> Option<u8> {
+        if let Some((last_pressure, last_time)) = self.last[pad] {
+            if pressure == last_pressure {
+                return None;
+            }
+            let big_enough = pressure.abs_diff(last_pressure) >= self.min_change;
+            let late_enough = now.duration_since(last_time) >= self.min_interval;
+            if pressure != 0 && !(big_enough && late_enough) {
+                return None;
+            }
+        }
+        self.last[pad] = Some((pressure, now));
+        Some(pressure)
+    }
+
+    /// Forget the pad's stream, e.g. on note on/off
+    pub(crate) fn reset(&mut self, pad: usize) {
+        self.last[pad] = None;
+    }
+
+    /// Last pressure sent for the pad (0 if none)
+    pub(crate) fn pressure(&self, pad: usize) -> u8 {
+        self.last[pad].map(|(p, _)| p).unwrap_or(0)
+    }
+
+    /// Highest pressure currently held across all pads (for channel pressure)
+    pub(crate) fn max_pressure(&self) -> u8 {
+        self.max_pressure_of(|_| true)
+    }
+
+    /// Highest pressure currently held across the pads `route` takes
+    pub(crate) fn route_pressure(&self, route: &AftertouchCcRoute) -> u8 {
+        self.max_pressure_of(|pad| route.matches(pad))
+    }
+
+    fn max_pressure_of(&self, include: impl Fn(usize) -> bool) -> u8 {
+        self.last
+            .iter()
+            .enumerate()
+            .filter(|(pad, _)| include(*pad))
+            .filter_map(|(_, l)| l.map(|(p, _)| p))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Shows the pressure of the held pads as a bar on the slider LEDs
+/// (`aftertouch_slider_display`), drawn over the lights when they're written: letting go
+/// of the pads brings back whatever the strip showed.
+#[derive(Default)]
+pub(crate) struct PressureMeter {
+    held: [bool; 16],
+    /// Highest pressure of the held pads, 0-127
+    pressure: u8,
+    changed: bool,
+}
+
+impl PressureMeter {
+    /// Whether the bar is up: while a pad is held
+    pub(crate) fn active(&self) -> bool {
+        self.held.contains(&true)
+    }
+
+    pub(crate) fn hold(&mut self, pad: usize, held: bool) {
+        self.held[pad] = held;
+        if !self.active() {
+            self.pressure = 0;
+        }
+        self.changed = true;
+    }
+
+    pub(crate) fn set_pressure(&mut self, pressure: u8) {
+        self.changed |= pressure != self.pressure;
+        self.pressure = pressure;
+    }
+
+    /// Whether the bar needs drawing (or taking down) since the last call
+    pub(crate) fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Draws the bar over `lights`, the tip brightest
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        let lit = (self.pressure as i32 * SLIDER_LEDS + 63) / 127;
+        for i in 0..SLIDER_LEDS {
+            let brightness = match lit - i {
+                1 => Brightness::Bright,
+                2.. => Brightness::Normal,
+                _ => Brightness::Off,
+            };
+            lights.set_slider(i as usize, brightness);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::settings::Settings;
+use alsa::seq::{Addr, EventType, PortCap, PortSubscribe, PortType, Seq};
+use std::ffi::CString;
+use std::process::Command;
+use std::sync::Arc;
+use std::{thread, time};
+
+#[derive(Debug, Clone)]
+pub(crate) struct SeqPort {
+    client_id: u32,
+    port_id: u32,
+    client_name: String,
+    port_name: String,
+}
+
+pub(crate) fn parse_aconnect_list(output: &str) -> Vec<SeqPort> {
+    // aconnect -l format:
+    // client 128: 'Name' [type=user,pid=...]
+    //     0 'Port name'
+    let mut ports = Vec::new();
+    let mut cur_client_id: Option<u32> = None;
+    let mut cur_client_name: Option<String> = None;
+
+    for line in output.lines() {
+        let line = line.trim_end();
+        if let Some(rest) = line.strip_prefix("client ") {
+            // Parse "128: 'Name' ..."
+            let mut parts = rest.splitn(2, ':');
+            let id_part = parts.next().unwrap_or("").trim();
+            let tail = parts.next().unwrap_or("");
+            let id = id_part.parse::<u32>().ok();
+
+            // Find first quoted string for name.
+            let name = tail
+                .split('\'')
+                .nth(1)
+                .map(|s| s.to_string());
+
+            cur_client_id = id;
+            cur_client_name = name;
+            continue;
+        }
+
+        // Port lines are indented and start with a number: "0 'Port name'"
+        let l = line.trim_start();
+        let first = l.split_whitespace().next().unwrap_or("");
+        if first.chars().all(|c| c.is_ascii_digit()) && l.contains('\'') {
+            let port_id = first.parse::<u32>().ok();
+            let port_name = l.split('\'').nth(1).map(|s| s.to_string());
+
+            if let (Some(client_id), Some(client_name), Some(port_id), Some(port_name)) = (
+                cur_client_id,
+                cur_client_name.clone(),
+                port_id,
+                port_name,
+            ) {
+                ports.push(SeqPort {
+                    client_id,
+                    port_id,
+                    client_name,
+                    port_name,
+                });
+            }
+        }
+    }
+
+    ports
+}
+
+pub(crate) fn run_aconnect(from: &SeqPort, to: &SeqPort) -> Result<(), String> {
+    let status = Command::new("aconnect")
+        .arg(format!("{}:{}", from.client_id, from.port_id))
+        .arg(format!("{}:{}", to.client_id, to.port_id))
+        .status()
+        .map_err(|e| format!("failed to execute aconnect: {e}"))?;
+    if !status.success() {
+        return Err(format!("aconnect exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Connects the driver's ports to the virmidi rawmidi bridge (what Bitwig enumerates).
+/// With `connect_input = false` only the driver -> virmidi direction is wired, which is
+/// what's needed after the output port had to be recreated.
+pub(crate) fn try_autoconnect_virmidi(settings: &Settings, connect_input: bool) -> Result<(), String> {
+    // Creating the virtual MIDI ports and having them appear in `aconnect -l` can be slightly racy.
+    // Retry a few times before giving up.
+    let mut last_err: Option<String> = None;
+    for _attempt in 0..20 {
+        let output = Command::new("aconnect")
+            .arg("-l")
+            .output()
+            .map_err(|e| format!("failed to run `aconnect -l`: {e}"))?;
+        if !output.status.success() {
+            last_err = Some(format!("`aconnect -l` failed with {}", output.status));
+            thread::sleep(time::Duration::from_millis(50));
+            continue;
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let ports = parse_aconnect_list(&text);
+
+        let driver_out = match ports
+            .iter()
+            .find(|p| p.client_name == settings.client_name && p.port_name == settings.port_name)
+            .cloned()
+        {
+            Some(p) => p,
+            None => {
+                last_err = Some(format!(
+                    "could not find driver output port \"{}\" / \"{}\" in `aconnect -l`",
+                    settings.client_name, settings.port_name
+                ));
+                thread::sleep(time::Duration::from_millis(50));
+                continue;
+            }
+        };
+
+        let driver_in_client = format!("{} In", settings.client_name);
+        let driver_in = match ports
+            .iter()
+            .find(|p| p.client_name == driver_in_client && p.port_name == settings.port_name_in)
+            .cloned()
+        {
+            Some(p) => Some(p),
+            None if !connect_input => None,
+            None => {
+                last_err = Some(format!(
+                    "could not find driver input port \"{}\" / \"{}\" in `aconnect -l`",
+                    driver_in_client, settings.port_name_in
+                ));
+                thread::sleep(time::Duration::from_millis(50));
+                continue;
+            }
+        };
+
+        let virmidi_candidates: Vec<SeqPort> = ports
+            .iter()
+            .filter(|p| is_virmidi_client(settings, &p.client_name))
+            .cloned()
+            .collect();
+
+        if virmidi_candidates.is_empty() {
+            last_err = Some(
+                "no virmidi ALSA sequencer ports found (is snd-virmidi loaded? did Bitwig open it once?)"
+                    .to_string(),
+            );
+            thread::sleep(time::Duration::from_millis(50));
+            continue;
+        }
+
+        let virmidi_port = match virmidi_candidates
+            .into_iter()
+            .find(|p| p.port_id as usize == settings.virmidi_port)
+        {
+            Some(p) => p,
+            None => {
+                last_err = Some(format!(
+                    "virmidi client found, but no port {} exists",
+                    settings.virmidi_port
+                ));
+                thread::sleep(time::Duration::from_millis(50));
+                continue;
+            }
+        };
+
+        // Driver -> Bitwig (via virmidi rawmidi)
+        run_aconnect(&driver_out, &virmidi_port)?;
+
+        let Some(driver_in) = driver_in.filter(|_| connect_input) else {
+            eprintln!(
+                "Auto-connected: {}:{} -> {}:{}",
+                driver_out.client_id,
+                driver_out.port_id,
+                virmidi_port.client_id,
+                virmidi_port.port_id
+            );
+            return Ok(());
+        };
+
+        // Bitwig -> Driver (LEDs), also via virmidi rawmidi
+        run_aconnect(&virmidi_port, &driver_in)?;
+
+        eprintln!(
+            "Auto-connected: {}:{} -> {}:{} and back -> {}:{}",
+            driver_out.client_id,
+            driver_out.port_id,
+            virmidi_port.client_id,
+            virmidi_port.port_id,
+            driver_in.client_id,
+            driver_in.port_id
+        );
+
+        return Ok(());
+    }
+
+    Err(last_err.unwrap_or_else(|| "auto-connect failed".to_string()))
+}
+
+/// Whether `client_name` is the virmidi client the driver connects to
+fn is_virmidi_client(settings: &Settings, client_name: &str) -> bool {
+    if settings.virmidi_client_name.trim().is_empty() {
+        client_name.starts_with("Virtual Raw MIDI")
+    } else {
+        client_name == settings.virmidi_client_name
+    }
+}
+
+/// Listens on ALSA's System Announce port for the rest of the run and connects to virmidi
+/// whenever one of its ports appears (snd-virmidi loaded late, Bitwig opening the device),
+/// so the routing no longer depends on everything being there at startup.
+pub(crate) fn watch_announcements(settings: Arc<Settings>) -> Result<(), String> {
+    let seq = Seq::open(None, None, false).map_err(|e| format!("can't open sequencer: {e}"))?;
+    let name = CString::new(format!("{} Announce", settings.client_name))
+        .map_err(|e| e.to_string())?;
+    seq.set_client_name(&name).map_err(|e| e.to_string())?;
+    let port = seq
+        .create_simple_port(
+            &name,
+            PortCap::WRITE | PortCap::SUBS_WRITE | PortCap::NO_EXPORT,
+            PortType::MIDI_GENERIC | PortType::APPLICATION,
+        )
+        .map_err(|e| format!("can't create announce port: {e}"))?;
+    let subscription = PortSubscribe::empty().map_err(|e| e.to_string())?;
+    subscription.set_sender(Addr::system_announce());
+    subscription.set_dest(Addr {
+        client: seq.client_id().map_err(|e| e.to_string())?,
+        port,
+    });
+    seq.subscribe_port(&subscription)
+        .map_err(|e| format!("can't subscribe to System Announce: {e}"))?;
+
+    thread::spawn(move || {
+        let mut input = seq.input();
+        loop {
+            let event = match input.event_input() {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("ALSA announce listener stopped: {e}");
+                    return;
+                }
+            };
+            if event.get_type() != EventType::PortStart {
+                continue;
+            }
+            let Some(addr) = event.get_data::<Addr>() else {
+                continue;
+            };
+            let is_virmidi = seq
+                .get_any_client_info(addr.client)
+                .ok()
+                .and_then(|info| info.get_name().ok().map(|n| is_virmidi_client(&settings, n)))
+                .unwrap_or(false);
+            if !is_virmidi || addr.port as usize != settings.virmidi_port {
+                continue;
+            }
+            eprintln!("virmidi port {}:{} appeared, connecting", addr.client, addr.port);
+            if let Err(e) = try_autoconnect_virmidi(&settings, true) {
+                eprintln!("Auto-connect to virmidi failed (continuing): {e}");
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::settings::Settings;
+use maschine_library::controls::Buttons;
+use serde::Deserialize;
+
+/// A button's own MIDI message, from the `[button_mappings]` table, e.g.
+/// `play = { type = "cc", number = 115 }` or `rec = { latch = true }`
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ButtonMapping {
+    /// "cc" or "note"; the button's default CC if left out, together with `number`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// CC or note number (0-127)
+    pub number: Option<u8>,
+    /// MIDI channel (0-15); `button_channel` if left out
+    pub channel: Option<u8>,
+    /// Toggle on each press (127, then 0) instead of following the button
+    #[serde(default)]
+    pub latch: bool,
+}
+
+/// What a button sends: 127 on press and 0 on release as a CC, or a note held with it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ButtonMessage {
+    Cc { channel: u8, number: u8 },
+    Note { channel: u8, number: u8 },
+}
+
+impl ButtonMessage {
+    fn parse(mapping: &ButtonMapping, default: Self, button_channel: u8) -> Result<Self, String> {
+        let (kind, number) = match (&mapping.kind, mapping.number) {
+            (Some(kind), Some(number)) => (kind, number),
+            (None, None) if mapping.channel.is_none() => return Ok(default),
+            (None, None) => return Err("channel needs a type and number".to_string()),
+            _ => return Err("type and number go together".to_string()),
+        };
+        let channel = mapping.channel.unwrap_or(button_channel);
+        if channel > 15 {
+            return Err("channel should be 0 to 15".to_string());
+        }
+        if number > 127 {
+            return Err("number should be 0 to 127".to_string());
+        }
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "cc" => Ok(ButtonMessage::Cc { channel, number }),
+            "note" => Ok(ButtonMessage::Note { channel, number }),
+            other => Err(format!(
+                "invalid type={other:?} (expected: \"cc\", \"note\")"
+            )),
+        }
+    }
+
+    /// The MIDI message for a press or a release
+    pub(crate) fn bytes(self, pressed: bool) -> [u8; 3] {
+        match self {
+            ButtonMessage::Cc { channel, number } => {
+                [0xB0 | channel, number, if pressed { 127 } else { 0 }]
+            }
+            ButtonMessage::Note { channel, number } if pressed => [0x90 | channel, number, 127],
+            ButtonMessage::Note { channel, number } => [0x80 | channel, number, 0],
+        }
+    }
+}
+
+impl std::fmt::Display for ButtonMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ButtonMessage::Cc { channel, number } => write!(f, "CC {number} (channel {channel})"),
+            ButtonMessage::Note { channel, number } => {
+                write!(f, "note {number} (channel {channel})")
+            }
+        }
+    }
+}
+
+/// What every button sends: its `[button_mappings]` entry, else CC `button_cc_offset` +
+/// its index on `button_channel`. The host lights a button LED with the same message,
+/// except on latching buttons, whose LED shows their own on/off state.
+pub(crate) struct ButtonMap {
+    messages: [ButtonMessage; Buttons::COUNT],
+    latches: [bool; Buttons::COUNT],
+    /// Latching buttons that are on
+    latched: [bool; Buttons::COUNT],
+}
+
+impl ButtonMap {
+    /// Errors name the mapping at fault; two buttons can't share a message
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let channel = settings.button_channel;
+        let mut messages: [ButtonMessage; Buttons::COUNT] = std::array::from_fn(|idx| {
+            let number = settings.button_cc_offset.saturating_add(idx as u8);
+            ButtonMessage::Cc { channel, number }
+        });
+        let mut latches = [false; Buttons::COUNT];
+        for (name, mapping) in &settings.button_mappings {
+            let err = |e: String| format!("button_mappings.{name}: {e}");
+            let button = Buttons::from_name(name).ok_or_else(|| err("unknown button".into()))?;
+            let idx = button as usize;
+            messages[idx] = ButtonMessage::parse(mapping, messages[idx], channel).map_err(err)?;
+            latches[idx] = mapping.latch;
+        }
+        let button: fn(usize) -> Option<Buttons> = num::FromPrimitive::from_usize;
+        for (idx, message) in messages.iter().enumerate() {
+            if let Some(other) = messages[..idx].iter().position(|m| m == message) {
+                let (a, b) = (button(other).unwrap(), button(idx).unwrap());
+                return Err(format!(
+                    "button_mappings: {a:?} and {b:?} both send {message}"
+                ));
+            }
+        }
+        Ok(Self {
+            messages,
+            latches,
+            latched: [false; Buttons::COUNT],
+        })
+    }
+
+    pub(crate) fn message(&self, button: Buttons) -> ButtonMessage {
+        self.messages[button as usize]
+    }
+
+    pub(crate) fn latches(&self, button: Buttons) -> bool {
+        self.latches[button as usize]
+    }
+
+    /// A button went down or up: whether to send it as on or off, if at all. Latching
+    /// buttons flip on each press and send nothing on release.
+    pub(crate) fn press(&mut self, button: Buttons, pressed: bool) -> Option<bool> {
+        let idx = button as usize;
+        if !self.latches[idx] {
+            return Some(pressed);
+        }
+        if !pressed {
+            return None;
+        }
+        self.latched[idx] = !self.latched[idx];
+        Some(self.latched[idx])
+    }
+
+    /// Whether a button sends (and gets its light from) `cc` on `channel`
+    pub(crate) fn has_cc(&self, channel: u8, cc: u8) -> bool {
+        self.messages.contains(&ButtonMessage::Cc {
+            channel,
+            number: cc,
+        })
+    }
+
+    /// The button a CC, Note On or Note Off from the host (`status` without the channel)
+    /// is for
+    pub(crate) fn button_of(&self, status: u8, channel: u8, number: u8) -> Option<Buttons> {
+        let message = match status {
+            0xB0 => ButtonMessage::Cc { channel, number },
+            0x80 | 0x90 => ButtonMessage::Note { channel, number },
+            _ => return None,
+        };
+        let idx = self.messages.iter().position(|m| *m == message)?;
+        num::FromPrimitive::from_usize(idx)
+    }
+
+    /// Buttons with a `[button_mappings]` entry, for the startup summary
+    pub(crate) fn remapped(&self, settings: &Settings) -> Vec<(Buttons, ButtonMessage)> {
+        let mut remapped: Vec<_> = settings
+            .button_mappings
+            .keys()
+            .filter_map(|name| Buttons::from_name(name))
+            .map(|button| (button, self.message(button)))
+            .collect();
+        remapped.sort_by_key(|(button, _)| *button as usize);
+        remapped
+    }
+}
@@ -0,0 +1,187 @@
+use crate::render_screen_lines;
+use hidapi::{HidDevice, HidResult};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+/// The three hits asked for on every pad, with the velocity each should end up at
+const HITS: [(&str, PadColors, f64); 3] = [
+    ("SOFT", PadColors::Green, 40.0),
+    ("MEDIUM", PadColors::Yellow, 80.0),
+    ("HARD", PadColors::Red, 120.0),
+];
+/// Gain is kept in a sane range so one odd hit can't make a pad unplayable
+const GAIN_RANGE: (f64, f64) = (0.25, 4.0);
+/// Header of the calibration block written into the config
+const CONFIG_HEADER: &str = "# Per-pad velocity calibration, written by `driver calibrate`";
+
+/// Per-pad scaling derived from the soft/medium/hard hits
+struct PadCalibration {
+    gain: f64,
+    threshold: u16,
+}
+
+impl PadCalibration {
+    /// Least-squares gain mapping the three raw hits onto their target velocities,
+    /// and a threshold at half the soft hit so ghost touches below it are ignored.
+    fn from_hits(raw: [u16; 3]) -> Self {
+        let (mut num, mut den) = (0.0, 0.0);
+        for (&value, (_, _, target)) in raw.iter().zip(HITS) {
+            let velocity = f64::from(value >> 5);
+            num += target * velocity;
+            den += velocity * velocity;
+        }
+        let gain = if den > 0.0 { num / den } else { 1.0 };
+        Self {
+            gain: (gain.clamp(GAIN_RANGE.0, GAIN_RANGE.1) * 100.0).round() / 100.0,
+            threshold: raw[0] / 2,
+        }
+    }
+}
+
+/// Calibration wizard: asks for a soft, medium and hard hit on every pad (on the screen,
+/// with the pad lit), then writes `pad_gain` and `pad_threshold` into `config_path`, or
+/// prints them when no config file is given. Stop aborts without writing anything.
+pub(crate) fn run_calibration(device: &HidDevice, config_path: Option<&str>) -> HidResult<()> {
+    let mut lights = Lights::new();
+    let mut screen = Screen::new();
+    let mut pads = Vec::with_capacity(16);
+
+    let mut pad = 0;
+    while pad < 16 {
+        let mut raw = [0u16; 3];
+        for (i, (strength, color, _)) in HITS.iter().enumerate() {
+            lights.reset();
+            lights.set_pad(pad, *color, Brightness::Bright);
+            lights.write(device)?;
+            let title = format!("PAD {}", pad + 1);
+            render_screen_lines(&mut screen, &title, &format!("HIT {strength}"));
+            screen.write(device)?;
+            let Some(value) = next_hit(device, pad)? else {
+                println!("Calibration aborted, nothing written");
+                return clear(device, &mut lights, &mut screen);
+            };
+            raw[i] = value;
+        }
+
+        // Hits out of order are most likely a mis-hit: ask for the pad again
+        if !(raw[0] < raw[1] && raw[1] < raw[2]) {
+            println!("Pad {}: hits {:?} aren't soft < medium < hard, retrying", pad + 1, raw);
+            render_screen_lines(&mut screen, &format!("PAD {}", pad + 1), "TRY AGAIN");
+            screen.write(device)?;
+            thread::sleep(Duration::from_millis(1000));
+            continue;
+        }
+        let calibration = PadCalibration::from_hits(raw);
+        println!(
+            "Pad {}: raw {:?} -> gain {} threshold {}",
+            pad + 1,
+            raw,
+            calibration.gain,
+            calibration.threshold
+        );
+        pads.push(calibration);
+        pad += 1;
+    }
+
+    let block = config_block(&pads);
+    match config_path {
+        Some(path) => match write_config(path, &block) {
+            Ok(()) => {
+                println!("Calibration written to {path}");
+                render_screen_lines(&mut screen, "CALIBRATED", "SAVED");
+            }
+            Err(e) => {
+                println!("Can't write calibration to {path}: {e}\n{block}");
+                render_screen_lines(&mut screen, "CALIBRATED", "SAVE FAILED");
+            }
+        },
+        None => {
+            println!("No config file (-c, ~/.config or /etc), add this to yours:\n{block}");
+            render_screen_lines(&mut screen, "CALIBRATED", "SEE TERMINAL");
+        }
+    }
+    lights.reset();
+    lights.write(device)?;
+    screen.write(device)?;
+    thread::sleep(Duration::from_secs(2));
+    clear(device, &mut lights, &mut screen)
+}
+
+fn clear(device: &HidDevice, lights: &mut Lights, screen: &mut Screen) -> HidResult<()> {
+    lights.reset();
+    lights.write(device)?;
+    screen.reset();
+    screen.write(device)
+}
+
+/// Waits for a hit on `pad` and returns its raw 12-bit value; None when Stop is pressed.
+/// Hits on other pads are ignored.
+fn next_hit(device: &HidDevice, pad: usize) -> HidResult<Option<u16>> {
+    let mut buf = [0u8; 64];
+    loop {
+        let size = device.read_timeout(&mut buf, 10)?;
+        if size < 1 {
+            continue;
+        }
+        if buf[0] == 0x01 {
+            let stop = Buttons::Stop as usize;
+            if buf[1 + stop / 8] & (1 << (stop % 8)) != 0 {
+                return Ok(None);
+            }
+        } else if buf[0] == 0x02 {
+            for i in (1..size.saturating_sub(2)).step_by(3) {
+                let (idx, evt) = (buf[i], buf[i + 1] & 0xf0);
+                if i > 1 && idx == 0 && evt == 0 && buf[i + 2] == 0 {
+                    break;
+                }
+                let val = ((buf[i + 1] as u16 & 0x0f) << 8) + buf[i + 2] as u16;
+                let hit: Option<PadEventType> = num::FromPrimitive::from_u8(evt);
+                if matches!(hit, Some(PadEventType::NoteOn)) && idx as usize == pad && val > 0 {
+                    return Ok(Some(val));
+                }
+            }
+        }
+    }
+}
+
+/// The config lines holding the calibration
+fn config_block(pads: &[PadCalibration]) -> String {
+    let gains: Vec<String> = pads.iter().map(|p| p.gain.to_string()).collect();
+    let thresholds: Vec<String> = pads.iter().map(|p| p.threshold.to_string()).collect();
+    format!(
+        "{CONFIG_HEADER}\npad_gain = [{}]\npad_threshold = [{}]\n",
+        gains.join(", "),
+        thresholds.join(", ")
+    )
+}
+
+/// Puts the calibration block at the top of the config (top-level keys must come before
+/// any table), replacing the lines of a previous calibration.
+fn write_config(path: &str, block: &str) -> std::io::Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let is_key = |line: &str, key: &str| {
+        line.strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    };
+    let kept: Vec<&str> = existing
+        .lines()
+        .filter(|line| {
+            *line != CONFIG_HEADER && !is_key(line, "pad_gain") && !is_key(line, "pad_threshold")
+        })
+        .collect();
+    let mut text = block.to_string();
+    if !kept.is_empty() {
+        text.push('\n');
+        text.push_str(&kept.join("\n"));
+        text.push('\n');
+    }
+    fs::write(path, text)
+}
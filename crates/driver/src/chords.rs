@@ -0,0 +1,262 @@
+use crate::settings::Settings;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// A chord defined in the config, played when `pad` is hit: either explicit `notes`, or a
+/// `chord` type built on `root` (the pad's own note if not given)
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ChordConfig {
+    /// Logical pad index (0-15)
+    pub pad: usize,
+    /// MIDI notes (0-127)
+    #[serde(default)]
+    pub notes: Vec<u8>,
+    /// Chord type, e.g. "maj", "min7" (see `chord_intervals`)
+    #[serde(default)]
+    pub chord: String,
+    /// MIDI note the chord type is built on
+    pub root: Option<u8>,
+}
+
+/// Semitones above the root of each note of a chord type
+pub(crate) fn chord_intervals(name: &str) -> Result<&'static [u8], String> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "maj" => Ok(&[0, 4, 7]),
+        "min" => Ok(&[0, 3, 7]),
+        "dim" => Ok(&[0, 3, 6]),
+        "aug" => Ok(&[0, 4, 8]),
+        "sus2" => Ok(&[0, 2, 7]),
+        "sus4" => Ok(&[0, 5, 7]),
+        "5" => Ok(&[0, 7, 12]),
+        "6" => Ok(&[0, 4, 7, 9]),
+        "min6" => Ok(&[0, 3, 7, 9]),
+        "7" => Ok(&[0, 4, 7, 10]),
+        "maj7" => Ok(&[0, 4, 7, 11]),
+        "min7" => Ok(&[0, 3, 7, 10]),
+        "dim7" => Ok(&[0, 3, 6, 9]),
+        "m7b5" => Ok(&[0, 3, 6, 10]),
+        "add9" => Ok(&[0, 4, 7, 14]),
+        "9" => Ok(&[0, 4, 7, 10, 14]),
+        "maj9" => Ok(&[0, 4, 7, 11, 14]),
+        "min9" => Ok(&[0, 3, 7, 10, 14]),
+        other => Err(format!(
+            "invalid chord {other:?} (expected: \"maj\", \"min\", \"dim\", \"aug\", \"sus2\", \
+             \"sus4\", \"5\", \"6\", \"min6\", \"7\", \"maj7\", \"min7\", \"dim7\", \"m7b5\", \
+             \"add9\", \"9\", \"maj9\", \"min9\")"
+        )),
+    }
+}
+
+/// What a configured chord plays
+#[derive(Clone)]
+enum PadChord {
+    Notes(Vec<u8>),
+    /// Intervals on a fixed root, or on whatever note the pad plays (following the layout,
+    /// bank and octave)
+    Type { root: Option<u8>, intervals: &'static [u8] },
+}
+
+/// Chords from the config (`chords`): a pad with one sends all its note ons and offs together
+#[derive(Default)]
+pub(crate) struct PadChords {
+    pads: [Option<PadChord>; 16],
+}
+
+impl PadChords {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let mut chords = Self::default();
+        for chord in &settings.chords {
+            let err = |what: &str| format!("chords: pad {}: {what}", chord.pad);
+            if chord.pad >= 16 {
+                return Err(err("pad should be 0 to 15"));
+            }
+            let pad_chord = match (chord.notes.is_empty(), chord.chord.is_empty()) {
+                (false, true) => {
+                    if chord.notes.iter().any(|n| *n >= 128) {
+                        return Err(err("notes should be 0 to 127"));
+                    }
+                    PadChord::Notes(chord.notes.clone())
+                }
+                (true, false) => {
+                    let intervals = chord_intervals(&chord.chord).map_err(|e| err(&e))?;
+                    if chord.root.is_some_and(|root| root >= 128) {
+                        return Err(err("root should be 0 to 127"));
+                    }
+                    PadChord::Type { root: chord.root, intervals }
+                }
+                _ => return Err(err("needs either notes or a chord type")),
+            };
+            if chords.pads[chord.pad].replace(pad_chord).is_some() {
+                return Err(err("has two chords"));
+            }
+        }
+        Ok(chords)
+    }
+
+    /// Notes a hit on `pad` plays when it would play `note` on its own, if it has a chord.
+    /// Notes of a chord type that would go above 127 are left out.
+    pub(crate) fn notes(&self, pad: usize, note: u8) -> Option<Vec<u8>> {
+        match self.pads[pad].as_ref()? {
+            PadChord::Notes(notes) => Some(notes.clone()),
+            PadChord::Type { root, intervals } => {
+                let root = root.unwrap_or(note);
+                Some(intervals.iter().map(|i| root + i).filter(|n| *n < 128).collect())
+            }
+        }
+    }
+}
+
+enum Capture {
+    Idle,
+    /// Armed, waiting for the pad the chord goes to
+    ChoosePad,
+    /// Collecting notes for `pad`; the window closes at `until` (set by the first note)
+    Collect {
+        pad: usize,
+        notes: Vec<u8>,
+        until: Option<Instant>,
+    },
+}
+
+/// Chords captured on pads, replayed with the hit velocity (over any configured chord).
+///
+/// Capture: `toggle_capture` arms it, the next pad hit picks the target pad, then every note
+/// played within `window` of the first one (on the merged MIDI input or the pads) becomes the
+/// chord. Stored chords are written to `chords_file`, if set, and loaded back on startup.
+pub(crate) struct ChordMemory {
+    chords: [Option<Vec<u8>>; 16],
+    capture: Capture,
+    window: Duration,
+    file: String,
+}
+
+impl ChordMemory {
+    pub(crate) fn new(window: Duration, file: &str) -> Result<Self, String> {
+        let mut chords: [Option<Vec<u8>>; 16] = Default::default();
+        if !file.is_empty() && std::path::Path::new(file).exists() {
+            for (pad, notes) in load_chords(file)? {
+                chords[pad] = Some(notes);
+            }
+        }
+        Ok(Self {
+            chords,
+            capture: Capture::Idle,
+            window,
+            file: file.to_string(),
+        })
+    }
+
+    /// Notes stored on the pad, if any
+    pub(crate) fn chord(&self, pad: usize) -> Option<&[u8]> {
+        self.chords[pad].as_deref()
+    }
+
+    /// Arms capture, or cancels it if already running. Returns the screen lines to show.
+    pub(crate) fn toggle_capture(&mut self) -> [String; 2] {
+        self.capture = match self.capture {
+            Capture::Idle => Capture::ChoosePad,
+            _ => Capture::Idle,
+        };
+        match self.capture {
+            Capture::Idle => ["CHORD".to_string(), "CANCELLED".to_string()],
+            _ => ["CHORD".to_string(), "HIT A PAD".to_string()],
+        }
+    }
+
+    /// Whether the next pad hit picks the capture target (and shouldn't play)
+    pub(crate) fn choosing_pad(&self) -> bool {
+        matches!(self.capture, Capture::ChoosePad)
+    }
+
+    /// Picks the pad the captured chord goes to
+    pub(crate) fn choose_pad(&mut self, pad: usize) -> [String; 2] {
+        self.capture = Capture::Collect {
+            pad,
+            notes: Vec::new(),
+            until: None,
+        };
+        [format!("PAD {pad}"), "PLAY CHORD".to_string()]
+    }
+
+    /// Removes the pad's chord (instead of capturing one)
+    pub(crate) fn clear(&mut self, pad: usize) -> [String; 2] {
+        self.capture = Capture::Idle;
+        self.chords[pad] = None;
+        self.save();
+        [format!("PAD {pad}"), "CLEARED".to_string()]
+    }
+
+    /// Feeds a played note (Note On) into a running capture
+    pub(crate) fn observe_note(&mut self, note: u8, now: Instant) {
+        if let Capture::Collect { notes, until, .. } = &mut self.capture {
+            if until.is_none() {
+                *until = Some(now + self.window);
+            }
+            if !notes.contains(&note) {
+                notes.push(note);
+            }
+        }
+    }
+
+    /// Stores the chord once the capture window has closed. Returns the screen lines to show.
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<[String; 2]> {
+        let Capture::Collect {
+            pad,
+            notes,
+            until: Some(until),
+        } = &mut self.capture
+        else {
+            return None;
+        };
+        if now < *until {
+            return None;
+        }
+        let pad = *pad;
+        let mut notes = std::mem::take(notes);
+        notes.sort_unstable();
+        println!("Chord stored on pad {pad}: {notes:?}");
+        let lines = [format!("PAD {pad}"), format!("{} NOTES", notes.len())];
+        self.chords[pad] = Some(notes);
+        self.capture = Capture::Idle;
+        self.save();
+        Some(lines)
+    }
+
+    fn save(&self) {
+        if self.file.is_empty() {
+            return;
+        }
+        let mut text = String::from("# pad: notes (written by the driver)\n");
+        for (pad, chord) in self.chords.iter().enumerate() {
+            if let Some(notes) = chord {
+                let notes: Vec<String> = notes.iter().map(|n| n.to_string()).collect();
+                text += &format!("{pad}: {}\n", notes.join(" "));
+            }
+        }
+        if let Err(e) = std::fs::write(&self.file, text) {
+            eprintln!("Can't save chords to {}: {e}", self.file);
+        }
+    }
+}
+
+/// Loads chords saved by the driver: one `<pad>: <note> <note> ...` per line
+fn load_chords(path: &str) -> Result<Vec<(usize, Vec<u8>)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let mut chords = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = || format!("{path}:{}: expected \"pad: note note ...\"", lineno + 1);
+        let (pad, notes) = line.split_once(':').ok_or_else(err)?;
+        let pad = pad.trim().parse::<usize>().ok().filter(|p| *p < 16).ok_or_else(err)?;
+        let notes = notes
+            .split_whitespace()
+            .map(|n| n.parse::<u8>().ok().filter(|n| *n < 128))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(err)?;
+        chords.push((pad, notes));
+    }
+    Ok(chords)
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// MIDI clock resolution: pulses per quarter note
+pub(crate) const PPQN: u64 = 24;
+
+/// The driver's own tempo source, for when there is no DAW to follow.
+///
+/// Pulses are counted from the start so timing doesn't drift with the main loop's
+/// polling; the loop asks which pulses became due since it last looked.
+pub(crate) struct InternalClock {
+    bpm: f64,
+    started: Option<Instant>,
+    next_pulse: u64,
+    /// Pulses at the start that only count in; the transport starts after them
+    count_in: u64,
+}
+
+impl InternalClock {
+    pub(crate) fn new(bpm: f64) -> Self {
+        Self {
+            bpm,
+            started: None,
+            next_pulse: 0,
+            count_in: 0,
+        }
+    }
+
+    pub(crate) fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Changes the tempo, keeping the position in the song
+    pub(crate) fn set_bpm(&mut self, now: Instant, bpm: f64) {
+        if let Some(started) = self.started {
+            let beats = now.duration_since(started).as_secs_f64() * self.bpm / 60.0;
+            let since = Duration::from_secs_f64(beats * 60.0 / bpm);
+            self.started = Some(now.checked_sub(since).unwrap_or(started));
+        }
+        self.bpm = bpm;
+    }
+
+    /// Pulls the beat grid toward a tap at `now`: the phase moves by `amount` (0-1) of its
+    /// distance to the nearest beat, so the clock follows a drummer without jumping
+    pub(crate) fn nudge(&mut self, now: Instant, amount: f64) {
+        let Some(started) = self.started else {
+            return;
+        };
+        let beats = now.duration_since(started).as_secs_f64() * self.bpm / 60.0;
+        // Positive: the clock is ahead of the tap
+        let offset = (beats - beats.round()) * amount * 60.0 / self.bpm;
+        let shift = Duration::from_secs_f64(offset.abs());
+        self.started = if offset > 0.0 {
+            started.checked_add(shift)
+        } else {
+            started.checked_sub(shift)
+        }
+        .or(Some(started));
+    }
+
+    pub(crate) fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Starts counting pulses, the first `count_in_beats` beats being a count-in
+    pub(crate) fn start(&mut self, now: Instant, count_in_beats: u64) {
+        self.started = Some(now);
+        self.next_pulse = 0;
+        self.count_in = count_in_beats * PPQN;
+    }
+
+    pub(crate) fn count_in_pulses(&self) -> u64 {
+        self.count_in
+    }
+
+    /// Whether the count-in is over (the transport has started)
+    pub(crate) fn past_count_in(&self) -> bool {
+        self.running() && self.next_pulse > self.count_in
+    }
+
+    pub(crate) fn stop(&mut self) {
+        self.started = None;
+    }
+
+    /// Pulses (counted from the start) that became due since the last poll
+    pub(crate) fn poll(&mut self, now: Instant) -> Range<u64> {
+        let Some(started) = self.started else {
+            return 0..0;
+        };
+        let elapsed = now.duration_since(started).as_secs_f64();
+        let due = (elapsed * self.bpm / 60.0 * PPQN as f64) as u64 + 1;
+        let pulses = self.next_pulse..due.max(self.next_pulse);
+        self.next_pulse = pulses.end;
+        pulses
+    }
+}
+
+/// Taps slower than this start a new measurement
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Tempo from the average of the last few tap intervals
+pub(crate) struct TapTempo {
+    taps: VecDeque<Instant>,
+    /// Intervals averaged
+    intervals: usize,
+}
+
+impl TapTempo {
+    pub(crate) fn new(intervals: usize) -> Self {
+        Self {
+            taps: VecDeque::new(),
+            intervals,
+        }
+    }
+
+    /// Records a tap; returns the tempo (rounded to 0.1 BPM) once there are two taps
+    pub(crate) fn tap(&mut self, now: Instant) -> Option<f64> {
+        if let Some(&last) = self.taps.back() {
+            let interval = now.duration_since(last);
+            // A pause, or a tap far off the current tempo, starts over
+            let off = self.average().is_some_and(|average| {
+                interval > average.mul_f64(1.5) || interval < average.mul_f64(0.5)
+            });
+            if interval > TAP_TIMEOUT {
+                self.taps.clear();
+            } else if off {
+                self.taps.clear();
+                self.taps.push_back(last);
+            }
+        }
+        self.taps.push_back(now);
+        while self.taps.len() > self.intervals + 1 {
+            self.taps.pop_front();
+        }
+        let bpm = 60.0 / self.average()?.as_secs_f64();
+        Some((bpm.clamp(20.0, 300.0) * 10.0).round() / 10.0)
+    }
+
+    fn average(&self) -> Option<Duration> {
+        let (first, last) = (self.taps.front()?, self.taps.back()?);
+        let n = self.taps.len() as u32 - 1;
+        (n > 0).then(|| last.duration_since(*first) / n)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::keyboard::{Keyboard, note_name, parse_scale};
+use crate::palette::{level, rgb};
+use crate::settings::Settings;
+use crate::standalone_lights;
+use maschine_library::controls::PadTransform;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use std::io::IsTerminal;
+
+/// A pad in the preview: its label and, when lit, the color it shows as
+type Cell = (String, Option<(u8, u8, u8)>);
+
+/// Prints what the pads will look like with the (validated) settings: the notes they play
+/// and the light states the config defines, each pad in its color at its brightness (color
+/// names only when the output isn't a terminal).
+pub(crate) fn check_config(settings: &Settings) {
+    let colored = std::io::stdout().is_terminal();
+
+    println!("Config OK\n");
+    println!("Pad notes ({}):", settings.pad_mode);
+    let transform = settings.pad_transform();
+    let notes: Vec<Cell> = (0..16).map(|pad| (pad_label(settings, pad), None)).collect();
+    print_grid(&notes, transform, colored);
+
+    let mut base = Lights::new();
+    base.set_high_contrast(settings.accessibility);
+
+    let mut idle = base.clone();
+    standalone_lights(&mut idle, settings);
+    println!("\nIdle pad lights:");
+    print_lights(&idle, transform, colored);
+
+    if settings.boot_animation == "frames" {
+        for (i, frame) in settings.boot_frames.iter().enumerate() {
+            // Validated along with the settings
+            let Ok(lights) = frame.render(&base) else { continue };
+            println!("\nBoot frame {i} ({} ms):", frame.ms);
+            print_lights(&lights, transform, colored);
+        }
+    }
+}
+
+fn pad_label(settings: &Settings, pad: usize) -> String {
+    if let Some(split) = settings.velocity_splits.iter().find(|s| s.pad == pad) {
+        // Soft to hard
+        let notes: Vec<String> = split.notes.iter().map(|&n| note_name(n)).collect();
+        return notes.join("/");
+    }
+    let note = if settings.pad_mode == "keyboard" {
+        let scale = parse_scale(&settings.keyboard_scale).expect("validated");
+        Keyboard::new(settings.keyboard_root, scale)
+            .with_split(settings)
+            .note(pad)
+    } else {
+        settings.notemaps.get(pad).copied()
+    };
+    if let Some(chord) = settings.chords.iter().find(|c| c.pad == pad) {
+        if !chord.notes.is_empty() {
+            let notes: Vec<String> = chord.notes.iter().map(|&n| note_name(n)).collect();
+            return notes.join("/");
+        }
+        if let Some(root) = chord.root.or(note) {
+            return format!("{} {}", note_name(root), chord.chord);
+        }
+    }
+    match note {
+        Some(note) => format!("{} {note}", note_name(note)),
+        None => "-".to_string(),
+    }
+}
+
+fn print_lights(lights: &Lights, transform: PadTransform, colored: bool) {
+    let cells: Vec<Cell> = (0..16)
+        .map(|pad| {
+            let (color, brightness) = lights.get_pad(pad);
+            if color == PadColors::Off || brightness == Brightness::Off {
+                return ("off".to_string(), None);
+            }
+            let (r, g, b) = rgb(color);
+            let scale = |c: u8| (c as f32 * level(brightness)).round() as u8;
+            let label = format!("{color:?} {brightness:?}").to_ascii_lowercase();
+            (label, Some((scale(r), scale(g), scale(b))))
+        })
+        .collect();
+    print_grid(&cells, transform, colored);
+}
+
+/// Prints 16 cells (by logical pad) as the pads sit on the device: pad 0 bottom left, then
+/// moved by the pad transform
+fn print_grid(cells: &[Cell], transform: PadTransform, colored: bool) {
+    const WIDTH: usize = 18;
+
+    for row in (0..4).rev() {
+        let line: Vec<String> = (row * 4..row * 4 + 4)
+            .map(|position| &cells[transform.apply(position)])
+            .map(|(label, color)| {
+                let label: String = label.chars().take(WIDTH).collect();
+                match color {
+                    Some((r, g, b)) if colored => {
+                        // Dark text on light colors, light text on dark ones
+                        let luma = 0.3 * *r as f32 + 0.59 * *g as f32 + 0.11 * *b as f32;
+                        let fg = if luma > 110.0 { 30 } else { 97 };
+                        format!("\x1b[{fg};48;2;{r};{g};{b}m {label:^WIDTH$} \x1b[0m")
+                    }
+                    _ => format!(" {label:^WIDTH$} "),
+                }
+            })
+            .collect();
+        println!("{}", line.join(" "));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::settings::Settings;
+use serde::Deserialize;
+use serde::de::{self, Visitor};
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+use toml::de::{DeArray, DeTable, DeValue, Deserializer};
+
+/// Layout of the config file this driver reads, as `config_version`. Files without one
+/// are version 1.
+pub(crate) const CONFIG_VERSION: u32 = 2;
+
+/// Rewrites a config table from one version to the next; returns what it changed, with
+/// the byte offset of where that was in the file
+type Migration = fn(&mut DeTable<'_>) -> Vec<(usize, String)>;
+
+/// Migrations by the version they start from, version 1 first
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize - 1] = [merge_bank_notemaps];
+
+/// Environment variables setting one setting each over the file, e.g.
+/// `MASCHINE__CLIENT_NAME=...` for `client_name`
+const ENV_PREFIX: &str = "MASCHINE__";
+/// Directory of the config file under `$XDG_CONFIG_HOME` and `/etc`
+const CONFIG_DIR: &str = "maschine-mikro-mk3";
+
+/// A setting from the environment: the variable, the setting and its value as TOML
+struct EnvOverride {
+    var: String,
+    name: String,
+    value: String,
+}
+
+/// Settings given on the command line, applied over the file and the environment
+#[derive(Debug, Default)]
+pub(crate) struct CliOverrides {
+    pub(crate) layout: Option<String>,
+    pub(crate) client_name: Option<String>,
+    pub(crate) port_name: Option<String>,
+    /// Channel of the pads, buttons, encoder and slider
+    pub(crate) channel: Option<u8>,
+    pub(crate) no_self_test: bool,
+    pub(crate) no_autoconnect: bool,
+}
+
+impl CliOverrides {
+    pub(crate) fn apply(&self, settings: &mut Settings) {
+        if let Some(layout) = &self.layout {
+            settings.layout = layout.clone();
+        }
+        if let Some(name) = &self.client_name {
+            settings.client_name = name.clone();
+        }
+        if let Some(name) = &self.port_name {
+            settings.port_name = name.clone();
+        }
+        if let Some(channel) = self.channel {
+            settings.pad_channel = channel;
+            settings.button_channel = channel;
+            settings.encoder_channel = channel;
+            settings.slider_channel = channel;
+        }
+        if self.no_self_test {
+            settings.self_test = "off".to_string();
+        }
+        if self.no_autoconnect {
+            settings.autoconnect_virmidi = false;
+        }
+    }
+}
+
+/// The config file to read: `path` (given with -c), or the first that exists of
+/// `$XDG_CONFIG_HOME/maschine-mikro-mk3/config.toml` (`~/.config` without
+/// XDG_CONFIG_HOME) and `/etc/maschine-mikro-mk3/config.toml`
+pub(crate) fn find(path: Option<&str>) -> Option<String> {
+    if let Some(path) = path {
+        return Some(path.to_string());
+    }
+    let user = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    user.into_iter()
+        .chain([PathBuf::from("/etc")])
+        .map(|dir| dir.join(CONFIG_DIR).join("config.toml"))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Reads the config file at `path` (none: every setting at its default) with the
+/// `MASCHINE__` environment variables on top. Returns the settings (still to be validated)
+/// and notes on what was migrated or ignored, each starting with `path:line:column` or
+/// the variable.
+pub(crate) fn load(path: Option<&str>) -> Result<(Settings, Vec<String>), String> {
+    let text = match path {
+        Some(path) => {
+            std::fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?
+        }
+        None => String::new(),
+    };
+    let path = path.unwrap_or_default();
+    let mut env_notes = Vec::new();
+    let overrides = env_overrides(&mut env_notes)?;
+    let (settings, notes) = parse_layered(&text, &overrides).map_err(|e| format!("{path}: {e}"))?;
+    let notes = notes
+        .into_iter()
+        .map(|note| format!("{path}:{note}"))
+        .chain(env_notes)
+        .collect();
+    Ok((settings, notes))
+}
+
+/// The `MASCHINE__` variables, each checked on its own so an error names it. A value that
+/// isn't TOML is taken as a string: `MASCHINE__LAYOUT=chromatic` works unquoted.
+fn env_overrides(notes: &mut Vec<String>) -> Result<Vec<EnvOverride>, String> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(var, _)| var.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort();
+    let mut overrides = Vec::new();
+    for (var, value) in vars {
+        let name = var[ENV_PREFIX.len()..].to_ascii_lowercase();
+        let value = if DeValue::parse(&value).is_ok() {
+            value
+        } else {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        let (_, unknown) =
+            parse(&format!("{name} = {value}")).map_err(|e| format!("{var}: {e}"))?;
+        if !unknown.is_empty() {
+            notes.push(format!("{var}: unknown setting {name:?}, ignored"));
+            continue;
+        }
+        overrides.push(EnvOverride { var, name, value });
+    }
+    Ok(overrides)
+}
+
+/// Parses a config file: migrates it from its `config_version` to the current one, then
+/// deserializes it. Errors and notes point at the line and column they're about.
+pub(crate) fn parse(text: &str) -> Result<(Settings, Vec<String>), String> {
+    parse_layered(text, &[])
+}
+
+/// `parse`, with settings from the environment replacing the file's
+fn parse_layered(text: &str, overrides: &[EnvOverride]) -> Result<(Settings, Vec<String>), String> {
+    let mut table = DeTable::parse(text).map_err(|e| e.to_string())?;
+    let version = match table.get_mut().remove("config_version") {
+        Some(value) => config_version(&value).map_err(|e| {
+            let (line, column) = position(text, value.span().start);
+            format!("config_version at line {line}, column {column}: {e}")
+        })?,
+        None => 1,
+    };
+    let mut notes = Vec::new();
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        for (offset, change) in migration(table.get_mut()) {
+            let (line, column) = position(text, offset);
+            notes.push(format!("{line}:{column}: {change}"));
+        }
+    }
+    if !notes.is_empty() {
+        let read_as = format!("config_version {version} read as {CONFIG_VERSION}");
+        notes.insert(
+            0,
+            format!("1:1: {read_as}, update the file to silence these notes"),
+        );
+    }
+    for setting in overrides {
+        let value = DeValue::parse(&setting.value).map_err(|e| format!("{}: {e}", setting.var))?;
+        let key = Spanned::new(0..0, setting.name.as_str().into());
+        // The file's spans don't cover the value
+        table
+            .get_mut()
+            .insert(key, Spanned::new(0..0, value.into_inner()));
+    }
+    let names = setting_names();
+    for key in table
+        .get_ref()
+        .keys()
+        .filter(|key| !names.contains(&key.get_ref().as_ref()))
+    {
+        let (line, column) = position(text, key.span().start);
+        let name = key.get_ref();
+        notes.push(format!(
+            "{line}:{column}: unknown setting {name:?}, ignored"
+        ));
+    }
+    let settings = Settings::deserialize(Deserializer::from(table)).map_err(|mut e| {
+        e.set_input(Some(text));
+        e.to_string()
+    })?;
+    Ok((settings, notes))
+}
+
+fn config_version(value: &Spanned<DeValue<'_>>) -> Result<u32, String> {
+    let version = value
+        .get_ref()
+        .as_integer()
+        .and_then(|n| u32::from_str_radix(n.as_str(), n.radix()).ok())
+        .ok_or("expected a whole number")?;
+    match version {
+        1..=CONFIG_VERSION => Ok(version),
+        _ if version > CONFIG_VERSION => Err(format!(
+            "version {version} is newer than this driver reads ({CONFIG_VERSION})"
+        )),
+        _ => Err(format!("should be 1 to {CONFIG_VERSION}")),
+    }
+}
+
+/// Line and column of a byte offset into `text`, both counted from 1
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Names of all the settings, as serde knows them
+fn setting_names() -> &'static [&'static str] {
+    /// Stops at the struct and keeps its field names
+    struct Names<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for Names<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only the names"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+            identifier ignored_any
+        }
+    }
+
+    let mut names: &'static [&'static str] = &[];
+    let _ = Settings::deserialize(Names(&mut names));
+    names
+}
+
+/// Version 2: `notemaps_bank_a` to `notemaps_bank_d` became the one `bank_notemaps` list,
+/// banks without notes as `[]`
+fn merge_bank_notemaps(table: &mut DeTable<'_>) -> Vec<(usize, String)> {
+    let banks = ["a", "b", "c", "d"]
+        .map(|bank| table.remove_entry(format!("notemaps_bank_{bank}").as_str()));
+    // The list goes where the first bank was
+    let Some((first, _)) = banks.iter().flatten().next() else {
+        return Vec::new();
+    };
+    let span = first.span();
+    let last = banks.iter().rposition(Option::is_some).unwrap_or_default();
+    let mut changes = Vec::new();
+    let mut list = DeArray::new();
+    for (bank, entry) in banks.into_iter().take(last + 1).enumerate() {
+        list.push(match entry {
+            Some((key, notes)) => {
+                let change = format!(
+                    "{} is entry {} of bank_notemaps now",
+                    key.get_ref(),
+                    bank + 1
+                );
+                changes.push((key.span().start, change));
+                notes
+            }
+            None => Spanned::new(span.clone(), DeValue::Array(DeArray::new())),
+        });
+    }
+    let key = Spanned::new(span.clone(), "bank_notemaps".into());
+    table.insert(key, Spanned::new(span, DeValue::Array(list)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_bank_notemaps_are_merged() {
+        let text = "notemaps_bank_b = [1, 2, 3]\n";
+        let (settings, notes) = parse(text).unwrap();
+        assert_eq!(settings.bank_notemaps, [vec![], vec![1, 2, 3]]);
+        assert_eq!(
+            notes,
+            [
+                "1:1: config_version 1 read as 2, update the file to silence these notes",
+                "1:1: notemaps_bank_b is entry 2 of bank_notemaps now",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_config_version_is_version_1() {
+        let versioned = "config_version = 1\nnotemaps_bank_a = [36]\n";
+        let (settings, _) = parse(versioned).unwrap();
+        let (unversioned, _) = parse("notemaps_bank_a = [36]\n").unwrap();
+        assert_eq!(settings.bank_notemaps, unversioned.bank_notemaps);
+        assert_eq!(unversioned.bank_notemaps, [vec![36]]);
+    }
+
+    #[test]
+    fn current_version_is_not_migrated() {
+        let text = format!("config_version = {CONFIG_VERSION}\nbank_notemaps = [[36]]\n");
+        let (settings, notes) = parse(&text).unwrap();
+        assert_eq!(settings.bank_notemaps, [vec![36]]);
+        assert!(notes.is_empty(), "{notes:?}");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{pad_stats, transport};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Commands the running driver accepts on its control socket (one per line)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ControlCommand {
+    /// All Notes Off / All Sound Off everywhere, clear held notes
+    Panic,
+    /// Query: replies with the host transport state ("playing", "paused", "stopped")
+    Transport,
+    /// Query: replies with per-pad hit counts and velocities, one pad per "; "-separated item
+    PadStats,
+    /// Clears the pad statistics
+    PadStatsReset,
+    /// Forgets the saved session, back to the config's layout, octave and patch
+    ResetSession,
+    /// Turns the stage light (everything white and bright) on or off
+    StageLight,
+}
+
+impl ControlCommand {
+    fn parse(line: &str) -> Result<Self, String> {
+        match line.trim() {
+            "panic" => Ok(Self::Panic),
+            "transport" => Ok(Self::Transport),
+            "pad-stats" => Ok(Self::PadStats),
+            "pad-stats-reset" => Ok(Self::PadStatsReset),
+            "reset-session" => Ok(Self::ResetSession),
+            "stage-light" => Ok(Self::StageLight),
+            other => Err(format!("unknown command {other:?}")),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Panic => "panic",
+            Self::Transport => "transport",
+            Self::PadStats => "pad-stats",
+            Self::PadStatsReset => "pad-stats-reset",
+            Self::ResetSession => "reset-session",
+            Self::StageLight => "stage-light",
+        }
+    }
+}
+
+/// Where the driver listens for control commands
+pub(crate) fn socket_path() -> PathBuf {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join("maschine-mikro-mk3.sock")
+}
+
+/// Starts listening on the control socket; received commands are delivered to the returned
+/// receiver, which the main loop polls.
+pub(crate) fn listen() -> Result<Receiver<ControlCommand>, String> {
+    let path = socket_path();
+    // A previous run may have left its socket behind
+    let _ = std::fs::remove_file(&path);
+    let listener =
+        UnixListener::bind(&path).map_err(|e| format!("can't bind {}: {e}", path.display()))?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || handle_client(stream, tx));
+        }
+    });
+    Ok(rx)
+}
+
+fn handle_client(stream: UnixStream, tx: Sender<ControlCommand>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match ControlCommand::parse(&line) {
+            // Queries are answered right here from the shared state
+            Ok(ControlCommand::Transport) => transport::state().name().to_string(),
+            Ok(ControlCommand::PadStats) => pad_stats::report(),
+            Ok(ControlCommand::PadStatsReset) => {
+                pad_stats::reset();
+                "ok".to_string()
+            }
+            Ok(cmd) => match tx.send(cmd) {
+                Ok(()) => "ok".to_string(),
+                Err(_) => "error: driver is shutting down".to_string(),
+            },
+            Err(e) => format!("error: {e}"),
+        };
+        if writeln!(writer, "{reply}").is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends a command to the running driver and returns its reply
+pub(crate) fn send_command(cmd: ControlCommand) -> Result<String, String> {
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        format!("can't connect to {} (is the driver running?): {e}", path.display())
+    })?;
+    writeln!(stream, "{}", cmd.name()).map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    Ok(reply.trim().to_string())
+}
@@ -0,0 +1,267 @@
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use serde::Deserialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+
+/// A macro page defined in the config
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct DeskPage {
+    /// Shown on the screen
+    pub name: String,
+    /// Key or combo per logical pad, e.g. "ctrl+shift+t"; "" leaves the pad unused
+    pub keys: Vec<String>,
+}
+
+/// The built-in first page: a numeric keypad (7-8-9 on the top row, 0 bottom left)
+const NUMPAD: [&str; 16] = [
+    "kp0", "kpdot", "kpenter", "kpplus", //
+    "kp1", "kp2", "kp3", "kpminus", //
+    "kp4", "kp5", "kp6", "kpasterisk", //
+    "kp7", "kp8", "kp9", "kpslash",
+];
+
+/// Linux input key codes by name (see linux/input-event-codes.h)
+const KEYS: &[(&str, u16)] = &[
+    ("esc", 1), ("minus", 12), ("equal", 13), ("backspace", 14), ("tab", 15),
+    ("leftbrace", 26), ("rightbrace", 27), ("enter", 28), ("ctrl", 29), ("semicolon", 39),
+    ("apostrophe", 40), ("grave", 41), ("shift", 42), ("backslash", 43), ("comma", 51),
+    ("dot", 52), ("slash", 53), ("kpasterisk", 55), ("alt", 56), ("space", 57),
+    ("capslock", 58), ("numlock", 69), ("scrolllock", 70), ("kp7", 71), ("kp8", 72),
+    ("kp9", 73), ("kpminus", 74), ("kp4", 75), ("kp5", 76), ("kp6", 77), ("kpplus", 78),
+    ("kp1", 79), ("kp2", 80), ("kp3", 81), ("kp0", 82), ("kpdot", 83), ("f11", 87),
+    ("f12", 88), ("kpenter", 96), ("rightctrl", 97), ("kpslash", 98), ("rightalt", 100),
+    ("home", 102), ("up", 103), ("pageup", 104), ("left", 105), ("right", 106),
+    ("end", 107), ("down", 108), ("pagedown", 109), ("insert", 110), ("delete", 111),
+    ("mute", 113), ("volumedown", 114), ("volumeup", 115), ("super", 125),
+    ("nextsong", 163), ("playpause", 164), ("previoussong", 165), ("stop", 166),
+];
+const LETTERS: &str = "qwertyuiopasdfghjklzxcvbnm";
+/// Key codes of the letters above: three rows of the QWERTY layout
+const LETTER_CODES: [u16; 26] = [
+    16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 30, 31, 32, 33, 34, 35, 36, 37, 38, 44, 45, 46,
+    47, 48, 49, 50,
+];
+
+/// Key code of a key name: letters, digits, f1-f12 and the names in `KEYS`
+fn key_code(name: &str) -> Option<u16> {
+    let name = name.trim().to_ascii_lowercase();
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ 'a'..='z'), None) => LETTERS.find(c).map(|i| LETTER_CODES[i]),
+        (Some('0'), None) => Some(11),
+        (Some(c @ '1'..='9'), None) => Some(c as u16 - '1' as u16 + 2),
+        _ => match name.strip_prefix('f').and_then(|n| n.parse::<u16>().ok()) {
+            Some(n @ 1..=10) => Some(58 + n),
+            _ => KEYS.iter().find(|(n, _)| *n == name).map(|(_, code)| *code),
+        },
+    }
+}
+
+/// Parses "ctrl+shift+t" into key codes, modifiers first
+fn parse_combo(combo: &str) -> Result<Vec<u16>, String> {
+    combo
+        .split('+')
+        .map(|key| key_code(key).ok_or(format!("unknown key {key:?} in {combo:?}")))
+        .collect()
+}
+
+/// Checks a key combo from the config (for `Settings::validate`)
+pub(crate) fn validate_combo(combo: &str) -> Result<(), String> {
+    parse_combo(combo).map(|_| ())
+}
+
+/// Checks the configured pages (for `Settings::validate`)
+pub(crate) fn validate_pages(pages: &[DeskPage]) -> Result<(), String> {
+    for page in pages {
+        if page.keys.len() > 16 {
+            return Err(format!("desk_pages: {:?} has more than 16 keys", page.name));
+        }
+        for combo in page.keys.iter().filter(|k| !k.is_empty()) {
+            parse_combo(combo).map_err(|e| format!("desk_pages: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+// uinput ioctls (linux/uinput.h)
+const UI_SET_EVBIT: libc::Ioctl = 0x4004_5564;
+const UI_SET_KEYBIT: libc::Ioctl = 0x4004_5565;
+const UI_DEV_SETUP: libc::Ioctl = 0x405c_5503;
+const UI_DEV_CREATE: libc::Ioctl = 0x5501;
+const UI_DEV_DESTROY: libc::Ioctl = 0x5502;
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+
+/// A virtual keyboard created through /dev/uinput
+struct VirtualKeyboard {
+    file: File,
+}
+
+impl VirtualKeyboard {
+    fn create(name: &str, keys: &[u16]) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .write(true)
+            .open("/dev/uinput")
+            .map_err(|e| format!("can't open /dev/uinput: {e}"))?;
+        let fd = file.as_raw_fd();
+
+        // SAFETY: plain ioctls on an open uinput fd with the argument types uinput expects
+        unsafe {
+            let mut setup: libc::uinput_setup = std::mem::zeroed();
+            setup.id.bustype = 0x03; // BUS_USB
+            setup.id.vendor = 0x17cc;
+            setup.id.product = 0x1700;
+            for (dst, src) in setup.name.iter_mut().zip(name.bytes().take(79)) {
+                *dst = src as libc::c_char;
+            }
+            let ok = libc::ioctl(fd, UI_SET_EVBIT, EV_KEY as libc::c_int) >= 0
+                && keys
+                    .iter()
+                    .all(|&key| libc::ioctl(fd, UI_SET_KEYBIT, key as libc::c_int) >= 0)
+                && libc::ioctl(fd, UI_DEV_SETUP, &setup) >= 0
+                && libc::ioctl(fd, UI_DEV_CREATE) >= 0;
+            if !ok {
+                return Err(format!("uinput setup failed: {}", std::io::Error::last_os_error()));
+            }
+        }
+        Ok(Self { file })
+    }
+
+    fn emit(&mut self, type_: u16, code: u16, value: i32) {
+        // SAFETY: input_event is plain old data
+        let event = unsafe {
+            let mut event: libc::input_event = std::mem::zeroed();
+            event.type_ = type_;
+            event.code = code;
+            event.value = value;
+            event
+        };
+        // SAFETY: viewing the POD struct as bytes for the write
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                (&event as *const libc::input_event).cast::<u8>(),
+                size_of::<libc::input_event>(),
+            )
+        };
+        if let Err(e) = self.file.write_all(bytes) {
+            eprintln!("Desk mode: can't send key event: {e}");
+        }
+    }
+
+    /// Presses (in order) or releases (in reverse) a key combo
+    fn combo(&mut self, keys: &[u16], down: bool) {
+        if down {
+            keys.iter().for_each(|&key| self.emit(EV_KEY, key, 1));
+        } else {
+            keys.iter().rev().for_each(|&key| self.emit(EV_KEY, key, 0));
+        }
+        self.emit(EV_SYN, 0, 0);
+    }
+}
+
+impl Drop for VirtualKeyboard {
+    fn drop(&mut self) {
+        // SAFETY: destroying the device created on this fd
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY);
+        }
+    }
+}
+
+/// One key combo on a virtual keyboard of its own, typed on demand
+pub(crate) struct KeyMacro {
+    keyboard: VirtualKeyboard,
+    keys: Vec<u16>,
+}
+
+impl KeyMacro {
+    pub(crate) fn new(name: &str, combo: &str) -> Result<Self, String> {
+        let keys = parse_combo(combo)?;
+        let keyboard = VirtualKeyboard::create(name, &keys)?;
+        Ok(Self { keyboard, keys })
+    }
+
+    /// Presses and releases the combo
+    pub(crate) fn tap(&mut self) {
+        self.keyboard.combo(&self.keys, true);
+        self.keyboard.combo(&self.keys, false);
+    }
+}
+
+type PageKeys = [Option<Vec<u16>>; 16];
+
+/// Desk mode: the pads type keys and macros on a virtual keyboard, one page at a time.
+///
+/// The first page is a numeric keypad, further pages come from the config; Group steps
+/// through them.
+pub(crate) struct Desk {
+    keyboard: VirtualKeyboard,
+    pages: Vec<(String, PageKeys)>,
+    page: usize,
+    /// What each pad pressed, so it's released even if the page changed meanwhile
+    held: PageKeys,
+}
+
+impl Desk {
+    pub(crate) fn new(client_name: &str, configured: &[DeskPage]) -> Result<Self, String> {
+        let numpad = DeskPage {
+            name: "NUMPAD".to_string(),
+            keys: NUMPAD.iter().map(|k| k.to_string()).collect(),
+        };
+        let mut pages = Vec::new();
+        for page in std::iter::once(&numpad).chain(configured) {
+            let mut keys: PageKeys = Default::default();
+            for (pad, combo) in page.keys.iter().enumerate().filter(|(_, k)| !k.is_empty()) {
+                keys[pad] = Some(parse_combo(combo)?);
+            }
+            pages.push((page.name.clone(), keys));
+        }
+
+        let mut codes: Vec<u16> =
+            pages.iter().flat_map(|(_, keys)| keys.iter().flatten().flatten()).copied().collect();
+        codes.sort_unstable();
+        codes.dedup();
+        let keyboard = VirtualKeyboard::create(&format!("{client_name} Desk"), &codes)?;
+        Ok(Self {
+            keyboard,
+            pages,
+            page: 0,
+            held: Default::default(),
+        })
+    }
+
+    pub(crate) fn pad(&mut self, pad: usize, down: bool) {
+        if down {
+            let Some(keys) = self.pages[self.page].1[pad].clone() else {
+                return;
+            };
+            self.keyboard.combo(&keys, true);
+            self.held[pad] = Some(keys);
+        } else if let Some(keys) = self.held[pad].take() {
+            self.keyboard.combo(&keys, false);
+        }
+    }
+
+    /// Steps through the pages, wrapping around
+    pub(crate) fn switch_page(&mut self, delta: i32) {
+        let n = self.pages.len() as i32;
+        self.page = (self.page as i32 + delta).rem_euclid(n) as usize;
+        println!("Desk page {}", self.pages[self.page].0);
+    }
+
+    /// Screen lines: mode and page name
+    pub(crate) fn lines(&self) -> [String; 2] {
+        ["DESK".to_string(), self.pages[self.page].0.to_ascii_uppercase()]
+    }
+
+    /// Pads with a key on the current page light up
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        for (pad, keys) in self.pages[self.page].1.iter().enumerate() {
+            match keys {
+                Some(_) => lights.set_pad(pad, PadColors::Cyan, Brightness::Normal),
+                None => lights.set_pad(pad, PadColors::Off, Brightness::Off),
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+/// Turning faster than this (steps per second) speeds the encoder up
+const ACCELERATION_THRESHOLD: f64 = 10.0;
+/// Turns further apart than this start slow again
+const ACCELERATION_RESET: Duration = Duration::from_millis(150);
+/// Largest step any relative encoding carries
+const MAX_STEP: i32 = 63;
+
+/// How a relative encoder turn is written into a CC value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RelativeEncoding {
+    /// 64 + delta: 65 = one step clockwise, 63 = one step back
+    BinaryOffset,
+    /// Delta as a 7-bit two's complement: 1 = one step clockwise, 127 = one step back
+    TwosComplement,
+    /// Bit 6 set for counter-clockwise: 1 = one step clockwise, 65 = one step back
+    SignMagnitude,
+}
+
+pub(crate) fn parse_relative_encoding(s: &str) -> Result<RelativeEncoding, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "offset" => Ok(RelativeEncoding::BinaryOffset),
+        "twos_complement" => Ok(RelativeEncoding::TwosComplement),
+        "sign_magnitude" => Ok(RelativeEncoding::SignMagnitude),
+        other => Err(format!(
+            "invalid encoder_encoding={other:?} \
+             (expected: \"offset\", \"twos_complement\", \"sign_magnitude\")"
+        )),
+    }
+}
+
+impl RelativeEncoding {
+    /// CC value of a turn by `delta` steps (clamped to ±63)
+    pub(crate) fn encode(self, delta: i32) -> u8 {
+        let delta = delta.clamp(-MAX_STEP, MAX_STEP);
+        match self {
+            RelativeEncoding::BinaryOffset => (64 + delta) as u8,
+            RelativeEncoding::TwosComplement => (delta & 0x7f) as u8,
+            RelativeEncoding::SignMagnitude if delta < 0 => 0x40 | delta.unsigned_abs() as u8,
+            RelativeEncoding::SignMagnitude => delta as u8,
+        }
+    }
+
+    /// How one step each way reads, for the startup summary
+    pub(crate) fn describe(self) -> String {
+        format!("{}=CW, {}=CCW", self.encode(1), self.encode(-1))
+    }
+}
+
+/// Scales encoder turns by how fast the encoder spins, so a quick flick sweeps a whole
+/// range while slow turns keep single steps
+pub(crate) struct Acceleration {
+    /// Extra steps per step for every `ACCELERATION_THRESHOLD` of speed above it; 0 = off
+    amount: f64,
+    last_turn: Option<Instant>,
+}
+
+impl Acceleration {
+    pub(crate) fn new(amount: f64) -> Self {
+        Self {
+            amount,
+            last_turn: None,
+        }
+    }
+
+    /// The turn by `delta` steps, sped up by how soon it came after the previous one
+    pub(crate) fn apply(&mut self, delta: i32, now: Instant) -> i32 {
+        let since = self
+            .last_turn
+            .map(|last| now.saturating_duration_since(last));
+        self.last_turn = Some(now);
+        let Some(since) = since.filter(|s| self.amount > 0.0 && *s < ACCELERATION_RESET) else {
+            return delta;
+        };
+        let speed = delta.unsigned_abs() as f64 / since.as_secs_f64().max(0.001);
+        let over = (speed - ACCELERATION_THRESHOLD).max(0.0) / ACCELERATION_THRESHOLD;
+        let scaled = (delta as f64 * (1.0 + self.amount * over)).round() as i32;
+        scaled.clamp(-MAX_STEP, MAX_STEP)
+    }
+}
@@ -0,0 +1,61 @@
+use maschine_library::controls::Buttons;
+use serde::Deserialize;
+
+/// What one encoder detent does when bound in `encoder_actions`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum EncoderAction {
+    /// Relative CC 1, as without a binding
+    Cc,
+    /// Transpose the pads by an octave
+    Octave,
+    /// Next/previous built-in pad layout
+    Layout,
+    /// Next/previous bank, sent right away (Bank Select + Program Change)
+    Bank,
+    /// Next/previous program, sent right away
+    Program,
+}
+
+pub(crate) fn parse_encoder_action(s: &str) -> Result<EncoderAction, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "cc" => Ok(EncoderAction::Cc),
+        "octave" => Ok(EncoderAction::Octave),
+        "layout" => Ok(EncoderAction::Layout),
+        "bank" => Ok(EncoderAction::Bank),
+        "program" => Ok(EncoderAction::Program),
+        other => Err(format!(
+            "invalid encoder action {other:?} \
+             (expected: \"cc\", \"octave\", \"layout\", \"bank\", \"program\")"
+        )),
+    }
+}
+
+/// An entry of `encoder_actions`: turning the encoder while `modifier` is held (or at any
+/// time, if empty) runs `action` once per detent
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct EncoderBinding {
+    /// Button name as in `Buttons`; empty binds plain turns
+    #[serde(default)]
+    pub modifier: String,
+    pub action: String,
+}
+
+impl EncoderBinding {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !self.modifier.is_empty() && Buttons::from_name(&self.modifier).is_none() {
+            return Err(format!("encoder_actions: unknown button {:?}", self.modifier));
+        }
+        parse_encoder_action(&self.action).map(|_| ())
+    }
+}
+
+/// The action of the first binding whose modifier is held (validated by `Settings::validate`)
+pub(crate) fn bound_action(
+    bindings: &[EncoderBinding],
+    held: impl Fn(Buttons) -> bool,
+) -> Option<EncoderAction> {
+    bindings
+        .iter()
+        .find(|b| b.modifier.is_empty() || Buttons::from_name(&b.modifier).is_some_and(&held))
+        .map(|b| parse_encoder_action(&b.action).expect("Invalid encoder_actions"))
+}
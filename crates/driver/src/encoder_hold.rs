@@ -0,0 +1,77 @@
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
+/// Longest `encoder_hold_ms`
+pub(crate) const HOLD_MAX_MS: u64 = 5_000;
+
+/// `encoder_hold_ms`: a push on the encoder let go quickly without turning is a click,
+/// anything else a hold. A click sends the push's message (press and release together)
+/// when it's let go. A hold sends none of it: the push only modifies the turns
+/// (`encoder_secondary_button`, `encoder_actions`), and once it's held past the threshold
+/// without turning, sends `encoder_hold_cc` until it's let go.
+pub(crate) struct EncoderHold {
+    threshold: Duration,
+    hold_cc: Option<u8>,
+    channel: u8,
+    /// When the encoder went down, while the push can still be a click
+    pushed: Option<Instant>,
+    /// Whether `encoder_hold_cc` is on
+    holding: bool,
+}
+
+impl EncoderHold {
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        (settings.encoder_hold_ms > 0).then(|| Self {
+            threshold: Duration::from_millis(settings.encoder_hold_ms),
+            hold_cc: settings.encoder_hold_cc,
+            channel: settings.button_channel,
+            pushed: None,
+            holding: false,
+        })
+    }
+
+    pub(crate) fn push(&mut self, now: Instant) {
+        self.pushed = Some(now);
+    }
+
+    /// The encoder turned: a push that's down is a hold now
+    pub(crate) fn turned(&mut self) {
+        self.pushed = None;
+    }
+
+    /// Whether a push may still become a hold, so the main loop should poll on time
+    pub(crate) fn pending(&self) -> bool {
+        self.pushed.is_some()
+    }
+
+    /// Turns a push held past the threshold into a hold
+    pub(crate) fn poll(&mut self, port: &mut MidiOut, now: Timestamp) {
+        let Some(pushed) = self.pushed else {
+            return;
+        };
+        if now.instant().duration_since(pushed) < self.threshold {
+            return;
+        }
+        self.pushed = None;
+        if let Some(cc) = self.hold_cc {
+            port.send(&[0xB0 | self.channel, cc, 127], now);
+            println!("{} Encoder hold -> CC {}", now, cc);
+            self.holding = true;
+        }
+    }
+
+    /// The encoder was let go: whether the push was a click
+    pub(crate) fn release(&mut self, port: &mut MidiOut, now: Timestamp) -> bool {
+        if self.holding
+            && let Some(cc) = self.hold_cc
+        {
+            port.send(&[0xB0 | self.channel, cc, 0], now);
+            self.holding = false;
+        }
+        self.pushed
+            .take()
+            .is_some_and(|pushed| now.instant().duration_since(pushed) < self.threshold)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
+/// The encoder as an expression pedal: turning while touching it moves a target level,
+/// and the CC glides there in small steps instead of jumping a detent at a time, the way
+/// orchestral libraries expect CC 11 to move.
+pub(crate) struct Expression {
+    channel: u8,
+    cc: u8,
+    /// Sends `cc` and `cc + 32` as a 14-bit pair
+    high_resolution: bool,
+    /// Levels per detent
+    step: f32,
+    /// Time constant of the glide; zero jumps straight to the target
+    smoothing: Duration,
+    target: f32,
+    /// Where the glide is, and the last value sent (14 bits with `high_resolution`)
+    level: f32,
+    sent: Option<u16>,
+    last_poll: Option<Instant>,
+}
+
+impl Expression {
+    pub(crate) fn new(
+        channel: u8,
+        cc: u8,
+        high_resolution: bool,
+        step: u8,
+        smoothing: Duration,
+    ) -> Self {
+        // Libraries play at full expression until told otherwise
+        Self {
+            channel,
+            cc,
+            high_resolution,
+            step: step as f32,
+            smoothing,
+            target: 127.0,
+            level: 127.0,
+            sent: None,
+            last_poll: None,
+        }
+    }
+
+    /// Moves the target by `delta` detents; returns the new target for the screen
+    pub(crate) fn turn(&mut self, delta: i32) -> u8 {
+        self.target = (self.target + delta as f32 * self.step).clamp(0.0, 127.0);
+        if self.sent.is_none() {
+            // Nothing went out yet: start gliding from the level the host assumes
+            self.sent = Some(self.value(127.0));
+        }
+        self.target as u8
+    }
+
+    /// Whether the level is still gliding to the target, so the main loop should poll on time
+    pub(crate) fn pending(&self) -> bool {
+        self.sent.is_some() && self.level != self.target
+    }
+
+    /// Sends the next step of the glide, if the level changed enough to show
+    pub(crate) fn poll(&mut self, port: &mut MidiOut, at: Timestamp) {
+        let now = at.instant();
+        let elapsed = self.last_poll.map_or(Duration::ZERO, |last| now - last);
+        self.last_poll = Some(now);
+        let Some(sent) = self.sent else { return };
+
+        if self.smoothing.is_zero() {
+            self.level = self.target;
+        } else {
+            let k = 1.0 - (-elapsed.as_secs_f32() / self.smoothing.as_secs_f32()).exp();
+            self.level += (self.target - self.level) * k;
+            // The tail of the curve never quite gets there
+            if (self.target - self.level).abs() < 0.5 {
+                self.level = self.target;
+            }
+        }
+        let value = self.value(self.level);
+        if value == sent {
+            return;
+        }
+        if self.high_resolution {
+            // MSB first: hosts apply the pair when the LSB arrives
+            port.send(&[0xB0 | self.channel, self.cc, (value >> 7) as u8], at);
+            port.send(&[0xB0 | self.channel, self.cc + 32, (value & 0x7F) as u8], at);
+        } else {
+            port.send(&[0xB0 | self.channel, self.cc, value as u8], at);
+        }
+        self.sent = Some(value);
+    }
+
+    /// The CC value of a level (0-127): 7 bits, or 14 with `high_resolution`
+    fn value(&self, level: f32) -> u16 {
+        if self.high_resolution {
+            (level / 127.0 * 16383.0).round() as u16
+        } else {
+            level.round() as u16
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::settings::Settings;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+// Raised from any thread (the MIDI output is recreated in the background), taken by the
+// main loop
+static RAISED: AtomicU8 = AtomicU8::new(0);
+
+/// Half a period of the fault button blink
+const BLINK_HALF: Duration = Duration::from_millis(250);
+
+/// Recoverable errors worth telling the performer about. The driver keeps running through
+/// them, but something may have gone missing (notes, an echo, lights).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Fault {
+    /// Sending to the MIDI output port failed; it's being recreated
+    MidiSend = 1,
+    /// The `echo_output_port` went away
+    MidiEcho = 2,
+    /// The controller was unplugged (or stopped answering) and came back
+    DeviceReconnect = 4,
+    /// The second controller of `sync_controllers` went away
+    SyncLost = 8,
+    /// The config file couldn't be reloaded; the settings in use stay
+    ConfigReload = 16,
+}
+
+impl Fault {
+    const ALL: [Fault; 5] = [
+        Fault::MidiSend,
+        Fault::MidiEcho,
+        Fault::DeviceReconnect,
+        Fault::SyncLost,
+        Fault::ConfigReload,
+    ];
+
+    /// Code shown on the screen, as listed in the README
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Fault::MidiSend => "E1",
+            Fault::MidiEcho => "E2",
+            Fault::DeviceReconnect => "E3",
+            Fault::SyncLost => "E4",
+            Fault::ConfigReload => "E5",
+        }
+    }
+
+    /// What happened, short enough for the screen in large text
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Fault::MidiSend => "MIDI OUT",
+            Fault::MidiEcho => "ECHO OUT",
+            Fault::DeviceReconnect => "USB LOST",
+            Fault::SyncLost => "SYNC OFF",
+            Fault::ConfigReload => "CONFIG",
+        }
+    }
+}
+
+/// Records a fault for the main loop to show
+pub(crate) fn raise(fault: Fault) {
+    RAISED.fetch_or(fault as u8, Ordering::SeqCst);
+}
+
+/// Shows faults on the device: `take` hands the new ones to the screen, and `fault_button`
+/// blinks for `fault_blink_secs` after each, then goes back to what it showed before.
+pub(crate) struct FaultIndicator {
+    button: Option<Buttons>,
+    blink_for: Duration,
+    /// When the blinking started and stops; None while not blinking
+    blinking: Option<(Instant, Instant)>,
+    /// The button's light before blinking started, taken on the first blink
+    restore: Option<Brightness>,
+    lit: bool,
+}
+
+impl FaultIndicator {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        Self {
+            button: Buttons::from_name(&settings.fault_button),
+            blink_for: Duration::from_secs(settings.fault_blink_secs),
+            blinking: None,
+            restore: None,
+            lit: false,
+        }
+    }
+
+    /// Faults raised since the last call, starting the blink if there are any
+    pub(crate) fn take(&mut self, now: Instant) -> Vec<Fault> {
+        let raised = RAISED.swap(0, Ordering::SeqCst);
+        if raised == 0 {
+            return Vec::new();
+        }
+        if self.button.is_some() && !self.blink_for.is_zero() {
+            let started = self.blinking.map_or(now, |(started, _)| started);
+            self.blinking = Some((started, now + self.blink_for));
+        }
+        Fault::ALL
+            .into_iter()
+            .filter(|fault| raised & *fault as u8 != 0)
+            .collect()
+    }
+
+    pub(crate) fn blinking(&self) -> bool {
+        self.blinking.is_some()
+    }
+
+    /// Blinks the fault button; true when its light changed
+    pub(crate) fn blink(&mut self, lights: &mut Lights, now: Instant) -> bool {
+        let (Some(button), Some((started, until))) = (self.button, self.blinking) else {
+            return false;
+        };
+        let restore = *self
+            .restore
+            .get_or_insert_with(|| lights.get_button(button));
+        if now >= until {
+            self.blinking = None;
+            self.restore = None;
+            self.lit = false;
+            lights.set_button(button, restore);
+            return true;
+        }
+        let lit =
+            (now.duration_since(started).as_millis() / BLINK_HALF.as_millis()).is_multiple_of(2);
+        if lit == self.lit {
+            return false;
+        }
+        self.lit = lit;
+        let level = if lit {
+            Brightness::Bright
+        } else {
+            Brightness::Off
+        };
+        lights.set_button(button, level);
+        true
+    }
+}
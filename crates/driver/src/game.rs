@@ -0,0 +1,177 @@
+use crate::render_screen_lines;
+use hidapi::{HidDevice, HidResult};
+use maschine_library::controls::{Buttons, PadEventType};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// One color per pad, so each pad is recognizable by its color alone
+const PAD_COLORS: [PadColors; 16] = [
+    PadColors::Red,
+    PadColors::Orange,
+    PadColors::Yellow,
+    PadColors::Lime,
+    PadColors::Green,
+    PadColors::Mint,
+    PadColors::Cyan,
+    PadColors::Turquoise,
+    PadColors::Blue,
+    PadColors::Plum,
+    PadColors::Violet,
+    PadColors::Purple,
+    PadColors::Magenta,
+    PadColors::Fuchsia,
+    PadColors::White,
+    PadColors::LightOrange,
+];
+/// Time allowed for each pad of the reproduction
+const INPUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Input that matters to the game
+enum Event {
+    Pad(usize),
+    Quit,
+}
+
+/// Simon says: the pads play a growing color sequence, the player hits it back.
+///
+/// The sequence gets one pad longer and a bit faster each round; a wrong pad or a
+/// timeout ends the game. The time from each sequence end to the first hit is logged,
+/// which makes it a rough latency test too. Stop quits.
+pub(crate) fn run_simon(device: &HidDevice) -> HidResult<()> {
+    let mut lights = Lights::new();
+    let mut screen = Screen::new();
+    let mut rng = XorShift::seeded();
+    let mut best = 0;
+
+    loop {
+        let mut sequence: Vec<usize> = Vec::new();
+        let score = loop {
+            sequence.push(rng.pad());
+            let round = sequence.len();
+            render_screen_lines(&mut screen, &format!("ROUND {round}"), "WATCH");
+            screen.write(device)?;
+            thread::sleep(Duration::from_millis(600));
+
+            // Shorter flashes as the sequence grows
+            let on = Duration::from_millis(450u64.saturating_sub(round as u64 * 20).max(150));
+            for &pad in &sequence {
+                show_pad(device, &mut lights, Some(pad))?;
+                thread::sleep(on);
+                show_pad(device, &mut lights, None)?;
+                thread::sleep(on / 3);
+            }
+
+            render_screen_lines(&mut screen, &format!("ROUND {round}"), "YOUR TURN");
+            screen.write(device)?;
+            // Hits made while the sequence played don't count
+            while device.read_timeout(&mut [0u8; 64], 0)? > 0 {}
+            let turn_start = Instant::now();
+            let mut correct = true;
+            for (i, &expected) in sequence.iter().enumerate() {
+                let Some(event) = next_event(device, INPUT_TIMEOUT)? else {
+                    correct = false;
+                    break;
+                };
+                let pad = match event {
+                    Event::Quit => return quit(device, &mut lights, &mut screen),
+                    Event::Pad(pad) => pad,
+                };
+                if i == 0 {
+                    println!("Round {round}: first hit after {:?}", turn_start.elapsed());
+                }
+                show_pad(device, &mut lights, Some(pad))?;
+                thread::sleep(Duration::from_millis(150));
+                show_pad(device, &mut lights, None)?;
+                if pad != expected {
+                    correct = false;
+                    break;
+                }
+            }
+            if !correct {
+                break round - 1;
+            }
+        };
+
+        best = best.max(score);
+        println!("Game over: score {score} (best {best})");
+        for pad in 0..16 {
+            lights.set_pad(pad, PadColors::Red, Brightness::Bright);
+        }
+        lights.write(device)?;
+        render_screen_lines(&mut screen, "GAME OVER", &format!("SCORE {score} BEST {best}"));
+        screen.write(device)?;
+        thread::sleep(Duration::from_secs(2));
+        lights.reset();
+        lights.write(device)?;
+    }
+}
+
+/// Lights one pad in its color (all others off), or none
+fn show_pad(device: &HidDevice, lights: &mut Lights, pad: Option<usize>) -> HidResult<()> {
+    for (i, &color) in PAD_COLORS.iter().enumerate() {
+        if Some(i) == pad {
+            lights.set_pad(i, color, Brightness::Bright);
+        } else {
+            lights.set_pad(i, PadColors::Off, Brightness::Off);
+        }
+    }
+    lights.write(device)
+}
+
+fn quit(device: &HidDevice, lights: &mut Lights, screen: &mut Screen) -> HidResult<()> {
+    lights.reset();
+    lights.write(device)?;
+    screen.reset();
+    screen.write(device)
+}
+
+/// Waits for a pad hit or Stop; None on timeout
+fn next_event(device: &HidDevice, timeout: Duration) -> HidResult<Option<Event>> {
+    let mut buf = [0u8; 64];
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let size = device.read_timeout(&mut buf, 10)?;
+        if size < 1 {
+            continue;
+        }
+        if buf[0] == 0x01 {
+            let stop = Buttons::Stop as usize;
+            if buf[1 + stop / 8] & (1 << (stop % 8)) != 0 {
+                return Ok(Some(Event::Quit));
+            }
+        } else if buf[0] == 0x02 {
+            for i in (1..size.saturating_sub(2)).step_by(3) {
+                let (idx, evt) = (buf[i], buf[i + 1] & 0xf0);
+                if i > 1 && idx == 0 && evt == 0 && buf[i + 2] == 0 {
+                    break;
+                }
+                let hit: Option<PadEventType> = num::FromPrimitive::from_u8(evt);
+                if matches!(hit, Some(PadEventType::NoteOn)) && idx < 16 {
+                    return Ok(Some(Event::Pad(idx as usize)));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Small PRNG, plenty for picking pads
+struct XorShift(u64);
+
+impl XorShift {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self(nanos | 1)
+    }
+
+    fn pad(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % 16) as usize
+    }
+}
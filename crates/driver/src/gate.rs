@@ -0,0 +1,184 @@
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
+/// Longest gate either limit can be set to
+pub(crate) const GATE_MAX_MS: u64 = 60_000;
+/// Timer wheel resolution and size: one slot per millisecond, a full turn every 512 ms.
+/// Later note offs go round the wheel until they're due.
+const TICK: Duration = Duration::from_millis(1);
+const SLOTS: usize = 512;
+
+/// A note off waiting for its time
+struct PendingOff {
+    due: Instant,
+    pad: usize,
+    /// The pad's generation when scheduled; a newer hit makes the entry stale
+    generation: u64,
+}
+
+/// What the pad last started
+#[derive(Default)]
+struct PadGate {
+    /// Notes sounding, the channel they play on and the release velocity to send
+    notes: Vec<u8>,
+    channel: u8,
+    velocity: u8,
+    started: Option<Instant>,
+    /// Still sounding after the pad was released (held to the minimum gate)
+    released: bool,
+    generation: u64,
+}
+
+/// Minimum and maximum gate times of the pads: a short tap still sounds for the minimum
+/// gate, and a note held past the maximum gate is ended by the driver. Delayed note offs
+/// wait in a timer wheel that the main loop turns every pass.
+pub(crate) struct Gates {
+    min: [Duration; 16],
+    /// Zero: no limit
+    max: [Duration; 16],
+    pads: [PadGate; 16],
+    wheel: Vec<Vec<PendingOff>>,
+    /// Last tick the wheel was turned to
+    tick: u64,
+    origin: Instant,
+}
+
+impl Gates {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let per_pad = |name: &str, global: u64, values: &[u64]| {
+            let values = match values.len() {
+                0 => vec![global; 16],
+                16 => values.to_vec(),
+                _ => return Err(format!("{name} needs 16 values (or none)")),
+            };
+            if values.iter().any(|&ms| ms > GATE_MAX_MS) {
+                return Err(format!("{name} values should be 0 to {GATE_MAX_MS}"));
+            }
+            let mut gates = [Duration::ZERO; 16];
+            for (gate, ms) in gates.iter_mut().zip(values) {
+                *gate = Duration::from_millis(ms);
+            }
+            Ok(gates)
+        };
+        let min = per_pad("pad_gate_min_ms", settings.gate_min_ms, &settings.pad_gate_min_ms)?;
+        let max = per_pad("pad_gate_max_ms", settings.gate_max_ms, &settings.pad_gate_max_ms)?;
+        if settings.gate_min_ms > GATE_MAX_MS || settings.gate_max_ms > GATE_MAX_MS {
+            return Err(format!("gate_min_ms/gate_max_ms should be 0 to {GATE_MAX_MS}"));
+        }
+        if let Some(pad) = (0..16).find(|&pad| !max[pad].is_zero() && max[pad] < min[pad]) {
+            return Err(format!("pad {pad}: the maximum gate is shorter than the minimum"));
+        }
+        Ok(Self {
+            min,
+            max,
+            pads: Default::default(),
+            wheel: (0..SLOTS).map(|_| Vec::new()).collect(),
+            tick: 0,
+            origin: Instant::now(),
+        })
+    }
+
+    /// Whether any pad has a gate limit, so the main loop can skip the wheel otherwise
+    pub(crate) fn enabled(&self) -> bool {
+        self.min.iter().chain(&self.max).any(|gate| !gate.is_zero())
+    }
+
+    /// Whether note offs are waiting for their gate, so the main loop should poll on time
+    pub(crate) fn pending(&self) -> bool {
+        self.wheel.iter().any(|slot| !slot.is_empty())
+    }
+
+    /// A pad started `notes`. A note off still waiting from its last hit goes out first
+    /// so it can't cut the new note short.
+    pub(crate) fn note_on(
+        &mut self,
+        pad: usize,
+        channel: u8,
+        notes: &[u8],
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) {
+        if self.pads[pad].released {
+            self.send_off(pad, port, at);
+        }
+        let now = at.instant();
+        let gate = &mut self.pads[pad];
+        gate.generation += 1;
+        gate.notes = notes.to_vec();
+        gate.channel = channel;
+        gate.velocity = 0;
+        gate.started = Some(now);
+        gate.released = false;
+        if !self.max[pad].is_zero() {
+            self.schedule(pad, now + self.max[pad]);
+        }
+    }
+
+    /// The pad was released: returns whether the note offs should go out now. They don't
+    /// when the maximum gate already ended the notes, or when the minimum gate isn't over
+    /// yet (they're sent once it is).
+    pub(crate) fn note_off(&mut self, pad: usize, velocity: u8, now: Instant) -> bool {
+        let gate = &mut self.pads[pad];
+        let Some(started) = gate.started else {
+            return false;
+        };
+        let min_end = started + self.min[pad];
+        if now >= min_end {
+            // The maximum gate has nothing left to end
+            gate.started = None;
+            gate.generation += 1;
+            return true;
+        }
+        gate.velocity = velocity;
+        gate.released = true;
+        gate.generation += 1;
+        self.schedule(pad, min_end);
+        false
+    }
+
+    /// Sends the note offs that are due
+    pub(crate) fn poll(&mut self, port: &mut MidiOut, at: Timestamp) {
+        let now = at.instant();
+        let target = self.tick_of(now);
+        // Each slot is visited once per turn, so a long pause never needs more than one turn
+        let first = self.tick.max(target.saturating_sub(SLOTS as u64 - 1));
+        for tick in first..=target {
+            let slot = &mut self.wheel[tick as usize % SLOTS];
+            if slot.is_empty() {
+                continue;
+            }
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                std::mem::take(slot).into_iter().partition(|off| off.due <= now);
+            *slot = waiting;
+            for off in due {
+                if off.generation == self.pads[off.pad].generation {
+                    self.send_off(off.pad, port, at);
+                }
+            }
+        }
+        self.tick = target;
+    }
+
+    fn schedule(&mut self, pad: usize, due: Instant) {
+        let generation = self.pads[pad].generation;
+        let slot = self.tick_of(due) as usize % SLOTS;
+        self.wheel[slot].push(PendingOff { due, pad, generation });
+    }
+
+    fn tick_of(&self, at: Instant) -> u64 {
+        (at.saturating_duration_since(self.origin).as_nanos() / TICK.as_nanos()) as u64
+    }
+
+    fn send_off(&mut self, pad: usize, port: &mut MidiOut, at: Timestamp) {
+        let gate = &mut self.pads[pad];
+        for &note in &gate.notes {
+            port.send(&[0x80 | gate.channel, note, gate.velocity], at);
+        }
+        println!("{at} Pad {pad} gate end {:?}", gate.notes);
+        gate.started = None;
+        gate.released = false;
+        gate.generation += 1;
+    }
+}
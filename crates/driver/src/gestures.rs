@@ -0,0 +1,196 @@
+use crate::button_map::ButtonMap;
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::shift_layer::{Action, ShiftCommand, ShiftMapping};
+use crate::timestamp::Timestamp;
+use maschine_library::controls::Buttons;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Longest long press or double tap window
+pub(crate) const GESTURE_MAX_MS: u64 = 5_000;
+
+/// A button's gestures, from the `[button_gestures]` table, e.g.
+/// `Rec = { long_press = { cc = 100 }, double_tap = { command = "panic" } }`. Each one is
+/// a CC, note or command as in `[shift_mappings]`.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ButtonGestures {
+    /// Sent once the button is held `long_press_ms`, released with the button
+    pub long_press: Option<ShiftMapping>,
+    /// `long_press_ms` for this button
+    pub long_press_ms: Option<u64>,
+    /// Sent by a second press within `double_tap_ms` of the first, released with it
+    pub double_tap: Option<ShiftMapping>,
+    /// `double_tap_ms` for this button
+    pub double_tap_ms: Option<u64>,
+}
+
+/// When each button last went down, kept with the control state
+pub(crate) struct ButtonTimers {
+    /// Buttons held, since when
+    pressed_at: [Option<Instant>; Buttons::COUNT],
+    /// Last press that could start a double tap
+    tapped_at: [Option<Instant>; Buttons::COUNT],
+}
+
+impl ButtonTimers {
+    pub(crate) fn new() -> Self {
+        Self {
+            pressed_at: [None; Buttons::COUNT],
+            tapped_at: [None; Buttons::COUNT],
+        }
+    }
+
+    /// Follows a button going down or up
+    pub(crate) fn button(&mut self, button: Buttons, pressed: bool, at: Instant) {
+        let idx = button as usize;
+        self.pressed_at[idx] = pressed.then_some(at);
+    }
+}
+
+/// A gesture and how long it takes
+type Gesture = Option<(Action, Duration)>;
+
+/// Checks `button_gestures` (for `Settings::validate`). Gesture CCs may not reuse the
+/// buttons' own CCs on their channel.
+pub(crate) fn validate_gestures(settings: &Settings, buttons: &ButtonMap) -> Result<(), String> {
+    for name in settings.button_gestures.keys() {
+        let err = |e: String| format!("button_gestures.{name}: {e}");
+        Buttons::from_name(name).ok_or_else(|| err("unknown button".into()))?;
+        let [long_press, double_tap] = gestures(settings, name).map_err(err)?;
+        if long_press.is_none() && double_tap.is_none() {
+            return Err(err("set long_press, double_tap or both".to_string()));
+        }
+        for (action, _) in [long_press, double_tap].into_iter().flatten() {
+            if let Action::Cc(channel, cc) = action
+                && buttons.has_cc(channel, cc)
+            {
+                return Err(err(format!("CC {cc} collides with the button CCs")));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A button's long press and double tap
+fn gestures(settings: &Settings, name: &str) -> Result<[Gesture; 2], String> {
+    let config = &settings.button_gestures[name];
+    let gesture = |mapping: &Option<ShiftMapping>, ms: Option<u64>, default: u64, what: &str| {
+        let Some(mapping) = mapping else {
+            return Ok(None);
+        };
+        let ms = ms.unwrap_or(default);
+        if !(1..=GESTURE_MAX_MS).contains(&ms) {
+            return Err(format!("{what}_ms should be 1 to {GESTURE_MAX_MS}"));
+        }
+        let action = mapping
+            .action(settings.button_channel)
+            .map_err(|e| format!("{what}: {e}"))?;
+        Ok(Some((action, Duration::from_millis(ms))))
+    };
+    let long_press = gesture(
+        &config.long_press,
+        config.long_press_ms,
+        settings.long_press_ms,
+        "long_press",
+    )?;
+    let double_tap = gesture(
+        &config.double_tap,
+        config.double_tap_ms,
+        settings.double_tap_ms,
+        "double_tap",
+    )?;
+    Ok([long_press, double_tap])
+}
+
+/// Long presses and double taps on the buttons in `[button_gestures]`. The button still
+/// sends its own message on every press; a gesture sends its mapping on top, held until
+/// the button is let go.
+pub(crate) struct Gestures {
+    long_press: [Gesture; Buttons::COUNT],
+    double_tap: [Gesture; Buttons::COUNT],
+    /// Gestures that went off and are held with their button
+    held: [Option<Action>; Buttons::COUNT],
+}
+
+impl Gestures {
+    /// None without any gestures (validated by `Settings::validate`)
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        if settings.button_gestures.is_empty() {
+            return None;
+        }
+        let mut long_press = [None; Buttons::COUNT];
+        let mut double_tap = [None; Buttons::COUNT];
+        for name in settings.button_gestures.keys() {
+            let idx = Buttons::from_name(name).expect("Invalid button_gestures") as usize;
+            [long_press[idx], double_tap[idx]] = gestures(settings, name).ok()?;
+        }
+        Some(Self {
+            long_press,
+            double_tap,
+            held: [None; Buttons::COUNT],
+        })
+    }
+
+    /// A button went down or up (after `ButtonTimers::button`); returns the command to run
+    /// on a double tap
+    pub(crate) fn button(
+        &mut self,
+        timers: &mut ButtonTimers,
+        button: Buttons,
+        pressed: bool,
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) -> Option<ShiftCommand> {
+        let idx = button as usize;
+        if !pressed {
+            return self.held[idx].take()?.fire(false, port, at);
+        }
+        let (action, window) = self.double_tap[idx]?;
+        let previous = timers.tapped_at[idx].replace(at.instant());
+        if previous.is_none_or(|tap| at.instant().duration_since(tap) > window) {
+            return None;
+        }
+        // A third tap starts over
+        timers.tapped_at[idx] = None;
+        println!("{} Button {:?} double tap", at, button);
+        self.held[idx] = Some(action);
+        action.fire(true, port, at)
+    }
+
+    /// Whether a held button may still become a long press, so the main loop should poll
+    /// on time
+    pub(crate) fn pending(&self, timers: &ButtonTimers) -> bool {
+        (0..Buttons::COUNT).any(|idx| {
+            self.long_press[idx].is_some()
+                && self.held[idx].is_none()
+                && timers.pressed_at[idx].is_some()
+        })
+    }
+
+    /// Sets off the long presses that are due; returns a command to run, one per call
+    pub(crate) fn poll(
+        &mut self,
+        timers: &ButtonTimers,
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) -> Option<ShiftCommand> {
+        for idx in 0..Buttons::COUNT {
+            let (Some((action, hold)), None, Some(pressed_at)) =
+                (self.long_press[idx], self.held[idx], timers.pressed_at[idx])
+            else {
+                continue;
+            };
+            if at.instant().duration_since(pressed_at) < hold {
+                continue;
+            }
+            let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+            println!("{} Button {:?} long press", at, button.unwrap());
+            self.held[idx] = Some(action);
+            if let Some(command) = action.fire(true, port, at) {
+                return Some(command);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,200 @@
+//! Golden tests for the whole input path: recorded HID reports go through `main_loop` on a
+//! `MockTransport`, and the MIDI bytes it sends must match the fixture exactly.
+//!
+//! Fixtures are the `*.hid` files in `tests/fixtures`, one line each:
+//!
+//! - `# ...`: a comment
+//! - `set <key> = <value>`: a setting, in config file (TOML) syntax
+//! - `< 02 00 1f ff`: an input report in hex; the rest of the 64 bytes are zero
+//! - `<2 02 00 1f ff`: an input report from a second controller, as with
+//!   `sync_controllers`. The two are read in turn, the second first, until either runs out.
+//! - `> 90 24 64`: a MIDI message the reports should produce, in the order sent
+//!
+//! Report and message lines may end in a `# ...` comment.
+//!
+//! Run with `UPDATE_FIXTURES=1` to rewrite the `>` lines from what the driver sends now
+//! (after checking the difference is intended).
+
+use crate::midi_out::MidiOut;
+use crate::sync::Linked;
+use crate::{Connections, config_file, layouts, main_loop, params};
+use maschine_library::hid::MockTransport;
+use maschine_library::lights::Lights;
+use maschine_library::screen::Screen;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+/// Settings every fixture starts from: the startup animation would cover the first reports
+const BASE_SETTINGS: &str = "boot_animation = \"off\"\n";
+const REPORT_LEN: usize = 64;
+
+struct Fixture {
+    settings: String,
+    reports: Vec<Vec<u8>>,
+    /// From the second controller
+    second_reports: Vec<Vec<u8>>,
+    expected: Vec<Vec<u8>>,
+}
+
+/// Hex bytes, up to a trailing comment
+fn parse_hex(line: &str) -> Result<Vec<u8>, String> {
+    let bytes = line.split('#').next().unwrap_or_default();
+    bytes
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).map_err(|e| format!("{byte:?}: {e}")))
+        .collect()
+}
+
+fn parse_fixture(text: &str) -> Result<Fixture, String> {
+    let mut fixture = Fixture {
+        settings: BASE_SETTINGS.to_string(),
+        reports: Vec::new(),
+        second_reports: Vec::new(),
+        expected: Vec::new(),
+    };
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let parsed = if line.is_empty() || line.starts_with('#') {
+            Ok(())
+        } else if let Some(setting) = line.strip_prefix("set ") {
+            fixture.settings.push_str(setting);
+            fixture.settings.push('\n');
+            Ok(())
+        } else if let Some(report) = line.strip_prefix('<') {
+            let (reports, report) = match report.strip_prefix('2') {
+                Some(report) => (&mut fixture.second_reports, report),
+                None => (&mut fixture.reports, report),
+            };
+            parse_hex(report).and_then(|mut report| {
+                if report.len() > REPORT_LEN {
+                    return Err(format!("reports are at most {REPORT_LEN} bytes"));
+                }
+                report.resize(REPORT_LEN, 0);
+                reports.push(report);
+                Ok(())
+            })
+        } else if let Some(message) = line.strip_prefix('>') {
+            parse_hex(message).map(|message| fixture.expected.push(message))
+        } else {
+            Err("expected a comment, \"set\", \"<\" or \">\"".to_string())
+        };
+        parsed.map_err(|e| format!("line {}: {e}", n + 1))?;
+    }
+    Ok(fixture)
+}
+
+/// Runs the reports through the main loop until they're used up; returns the MIDI sent
+fn run_fixture(fixture: &Fixture) -> Result<Vec<Vec<u8>>, String> {
+    let (mut settings, notes) = config_file::parse(&fixture.settings)?;
+    // A misspelt setting would leave the fixture testing the default
+    if let Some(note) = notes.iter().find(|note| note.contains("unknown setting")) {
+        return Err(note.clone());
+    }
+    settings.validate()?;
+    settings.apply_layout();
+    layouts::set_active_notemaps(&settings.notemaps);
+    // What an earlier fixture set in the settings menu
+    params::reset();
+    let settings = Arc::new(settings);
+
+    let mut lights = Lights::new();
+    lights.set_high_contrast(settings.accessibility);
+    lights.set_pad_transform(settings.pad_transform());
+    let mut connections = Connections {
+        port: MidiOut::capture(Arc::clone(&settings)),
+        control: None,
+        _merge_connection: None,
+        merged: None,
+        launchpad: None,
+    };
+    let device = MockTransport::new(fixture.reports.iter().cloned());
+    let second = MockTransport::new(fixture.second_reports.iter().cloned());
+    let second = (!fixture.second_reports.is_empty()).then_some(&second);
+    // Ends with the read past the last report
+    let _ = main_loop(
+        &Linked::new(&device, second),
+        Arc::new(Mutex::new(lights)),
+        Arc::new(AtomicBool::new(false)),
+        Arc::new(Mutex::new(Screen::new())),
+        Arc::new(AtomicBool::new(false)),
+        &mut connections,
+        settings,
+    );
+    Ok(connections.port.captured().to_vec())
+}
+
+fn hex(message: &[u8]) -> String {
+    message
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Replaces the fixture's `>` lines with `sent`, after everything else
+fn update_fixture(path: &Path, text: &str, sent: &[Vec<u8>]) {
+    let mut lines: Vec<String> = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .map(str::to_string)
+        .collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.push(String::new());
+    lines.extend(sent.iter().map(|message| format!("> {}", hex(message))));
+    std::fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+fn fixture_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(FIXTURES)
+        .expect("Can't read the fixtures directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "hid"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// One test for all fixtures: the main loop shares state between runs through statics (the
+// active notemaps, the host transport), so they can't run in parallel
+#[test]
+fn hid_fixtures_send_the_recorded_midi() {
+    let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+    let paths = fixture_paths();
+    assert!(!paths.is_empty(), "No fixtures in {FIXTURES}");
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (sent, expected) = match parse_fixture(&text)
+            .and_then(|fixture| run_fixture(&fixture).map(|sent| (sent, fixture.expected)))
+        {
+            Ok((sent, _)) if update => {
+                update_fixture(&path, &text, &sent);
+                continue;
+            }
+            Ok((sent, expected)) if sent == expected => continue,
+            Ok((sent, expected)) => (sent, expected),
+            Err(e) => {
+                failures.push(format!("{name}: {e}"));
+                continue;
+            }
+        };
+        let lines = |messages: &[Vec<u8>]| -> String {
+            messages
+                .iter()
+                .map(|message| format!("  > {}\n", hex(message)))
+                .collect()
+        };
+        failures.push(format!(
+            "{name}: MIDI differs\nexpected:\n{}sent:\n{}",
+            lines(&expected),
+            lines(&sent)
+        ));
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,148 @@
+use maschine_library::lights::Lights;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A host (a DAW, a lighting script) that claimed the LED feedback with SysEx Session
+/// Claim. It keeps its own copy of the lights, so the one shown can change without the
+/// others' feedback leaking through.
+struct HostSession {
+    name: String,
+    priority: u8,
+    last_seen: Instant,
+    lights: Lights,
+}
+
+/// Decides whose LED feedback the controller shows when several hosts send it on the one
+/// input port: the active session with the highest priority (the first to claim among
+/// equals). A session is active until it ends or goes silent for `host_session_timeout_ms`.
+///
+/// The input port can't tell senders apart, so the feedback after a claim counts as the
+/// claiming host's until the next claim. Without any session, all feedback goes through.
+struct Arbiter {
+    sessions: Vec<HostSession>,
+    /// Session the feedback comes from: the last to claim
+    sender: Option<usize>,
+    /// Session on the controller
+    shown: Option<usize>,
+}
+
+// Claims and feedback arrive on the MIDI input thread; the main loop drops silent sessions
+static ARBITER: Mutex<Arbiter> = Mutex::new(Arbiter {
+    sessions: Vec::new(),
+    sender: None,
+    shown: None,
+});
+
+impl Arbiter {
+    fn winner(&self) -> Option<usize> {
+        // max_by_key keeps the last of equals, so look from the back
+        (0..self.sessions.len())
+            .rev()
+            .max_by_key(|&i| self.sessions[i].priority)
+    }
+
+    /// Puts the winning session's lights on the controller if it changed. Returns its name
+    /// (empty for none) when it did.
+    fn show_winner(&mut self, lights: &mut Lights) -> Option<String> {
+        let winner = self.winner();
+        if winner == self.shown {
+            return None;
+        }
+        self.shown = winner;
+        let Some(winner) = winner else {
+            return Some(String::new());
+        };
+        let session = &self.sessions[winner];
+        *lights = session.lights.clone();
+        Some(session.name.clone())
+    }
+
+    fn remove(&mut self, idx: usize) {
+        self.sessions.remove(idx);
+        let shift = |slot: Option<usize>| match slot {
+            Some(i) if i == idx => None,
+            Some(i) if i > idx => Some(i - 1),
+            other => other,
+        };
+        self.sender = shift(self.sender);
+        self.shown = shift(self.shown);
+    }
+}
+
+/// SysEx Session Claim: the host `name` sends the feedback that follows, at `priority`.
+/// Also a heartbeat. Returns the name of the session now shown when that changed.
+pub(crate) fn claim(name: &str, priority: u8, now: Instant, lights: &mut Lights) -> Option<String> {
+    let mut arbiter = ARBITER.lock().unwrap();
+    let idx = match arbiter.sessions.iter().position(|s| s.name == name) {
+        Some(idx) => {
+            let session = &mut arbiter.sessions[idx];
+            session.priority = priority;
+            session.last_seen = now;
+            idx
+        }
+        None => {
+            // Starts from what the controller shows
+            arbiter.sessions.push(HostSession {
+                name: name.to_string(),
+                priority,
+                last_seen: now,
+                lights: lights.clone(),
+            });
+            arbiter.sessions.len() - 1
+        }
+    };
+    arbiter.sender = Some(idx);
+    arbiter.show_winner(lights)
+}
+
+/// SysEx Session End: the host `name` stops sending feedback, the next session shows at
+/// once. Returns the name of the session now shown when that changed.
+pub(crate) fn end(name: &str, lights: &mut Lights) -> Option<String> {
+    let mut arbiter = ARBITER.lock().unwrap();
+    let idx = arbiter.sessions.iter().position(|s| s.name == name)?;
+    arbiter.remove(idx);
+    arbiter.show_winner(lights)
+}
+
+/// Applies LED feedback from the input port with `apply` (true when it changed a light):
+/// to the controller's lights if its sender is shown, else only to the sender's own copy.
+/// True when the controller's lights changed.
+pub(crate) fn feedback(
+    now: Instant,
+    lights: &mut Lights,
+    apply: impl FnOnce(&mut Lights) -> bool,
+) -> bool {
+    let mut arbiter = ARBITER.lock().unwrap();
+    if arbiter.sessions.is_empty() {
+        return apply(lights);
+    }
+    // Feedback from before any claim has no session to go to
+    let Some(sender) = arbiter.sender else {
+        return false;
+    };
+    let shown = arbiter.shown == Some(sender);
+    let session = &mut arbiter.sessions[sender];
+    session.last_seen = now;
+    if !shown {
+        apply(&mut session.lights);
+        return false;
+    }
+    let changed = apply(lights);
+    session.lights = lights.clone();
+    changed
+}
+
+/// Ends the sessions silent for longer than `timeout`. Returns the name of the session
+/// now shown (empty for none) when that changed.
+pub(crate) fn poll(now: Instant, timeout: Duration, lights: &mut Lights) -> Option<String> {
+    let mut arbiter = ARBITER.lock().unwrap();
+    while let Some(idx) = arbiter
+        .sessions
+        .iter()
+        .position(|s| now.saturating_duration_since(s.last_seen) > timeout)
+    {
+        println!("Host session {:?} went silent", arbiter.sessions[idx].name);
+        arbiter.remove(idx);
+    }
+    arbiter.show_winner(lights)
+}
@@ -0,0 +1,170 @@
+use crate::settings::Settings;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+
+/// Scales for the keyboard pad mode, as semitone offsets from the root
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    Dorian,
+    Mixolydian,
+    PentatonicMajor,
+    PentatonicMinor,
+    Blues,
+}
+
+impl Scale {
+    fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::PentatonicMajor => &[0, 2, 4, 7, 9],
+            Scale::PentatonicMinor => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+}
+
+pub(crate) fn parse_scale(s: &str) -> Result<Scale, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "chromatic" => Ok(Scale::Chromatic),
+        "major" => Ok(Scale::Major),
+        "minor" => Ok(Scale::Minor),
+        "dorian" => Ok(Scale::Dorian),
+        "mixolydian" => Ok(Scale::Mixolydian),
+        "pentatonic_major" => Ok(Scale::PentatonicMajor),
+        "pentatonic_minor" => Ok(Scale::PentatonicMinor),
+        "blues" => Ok(Scale::Blues),
+        other => Err(format!(
+            "invalid keyboard_scale={other:?} (expected: \"chromatic\", \"major\", \"minor\", \
+             \"dorian\", \"mixolydian\", \"pentatonic_major\", \"pentatonic_minor\", \"blues\")"
+        )),
+    }
+}
+
+/// Checks the `keyboard_split` settings (for `Settings::validate`)
+pub(crate) fn validate_split(settings: &Settings) -> Result<(), String> {
+    if settings.keyboard_split > 15 {
+        return Err("keyboard_split should be 0 (no split) to 15".to_string());
+    }
+    if settings.keyboard_split_channels.iter().any(|c| *c > 15) {
+        return Err("keyboard_split_channels should be 0 to 15".to_string());
+    }
+    if settings
+        .keyboard_split_octaves
+        .iter()
+        .any(|o| !(-4..=4).contains(o))
+    {
+        return Err("keyboard_split_octaves should be -4 to 4".to_string());
+    }
+    for name in &settings.keyboard_split_colors {
+        if PadColors::from_name(name).is_none() {
+            return Err(format!("keyboard_split_colors: unknown color {name:?}"));
+        }
+    }
+    Ok(())
+}
+
+/// Pads laid out as consecutive scale notes: logical pad 0 (bottom left) is the root,
+/// counting left to right, then up a row.
+pub(crate) struct Keyboard {
+    root: u8,
+    scale: Scale,
+    split: Option<Split>,
+}
+
+/// `keyboard_split`: the pads from `first_upper` on form a second keyboard, each zone
+/// with its own channel, octave and color
+struct Split {
+    first_upper: usize,
+    channels: [u8; 2],
+    octaves: [i8; 2],
+    colors: [PadColors; 2],
+}
+
+impl Keyboard {
+    pub(crate) fn new(root: u8, scale: Scale) -> Self {
+        Self {
+            root,
+            scale,
+            split: None,
+        }
+    }
+
+    /// Splits the pads into a lower and an upper zone as `keyboard_split` says (validated
+    /// by `Settings::validate`); no change with `keyboard_split = 0`
+    pub(crate) fn with_split(mut self, settings: &Settings) -> Self {
+        if settings.keyboard_split == 0 {
+            return self;
+        }
+        let color = |name: &str| PadColors::from_name(name).expect("Invalid keyboard_split_colors");
+        let [lower, upper] = &settings.keyboard_split_colors;
+        self.split = Some(Split {
+            first_upper: settings.keyboard_split,
+            channels: settings.keyboard_split_channels,
+            octaves: settings.keyboard_split_octaves,
+            colors: [color(lower), color(upper)],
+        });
+        self
+    }
+
+    /// The pad's zone (0 lower, 1 upper) and its place in it, from the zone's first pad
+    fn zone(&self, pad: usize) -> Option<(usize, usize)> {
+        let split = self.split.as_ref()?;
+        Some(match pad.checked_sub(split.first_upper) {
+            Some(place) => (1, place),
+            None => (0, pad),
+        })
+    }
+
+    /// MIDI note of the pad, or None if it would be outside 0-127
+    pub(crate) fn note(&self, pad: usize) -> Option<u8> {
+        let (pad, shift) = match (self.zone(pad), &self.split) {
+            (Some((zone, place)), Some(split)) => (place, split.octaves[zone] as i32 * 12),
+            _ => (pad, 0),
+        };
+        let intervals = self.scale.intervals();
+        let octave = pad / intervals.len();
+        let note = self.root as usize + octave * 12 + intervals[pad % intervals.len()] as usize;
+        u8::try_from(note as i32 + shift).ok().filter(|n| *n < 128)
+    }
+
+    /// Scale degree of the pad, 1 being the root of its zone
+    pub(crate) fn degree(&self, pad: usize) -> usize {
+        let pad = self.zone(pad).map_or(pad, |(_, place)| place);
+        pad % self.scale.intervals().len() + 1
+    }
+
+    /// The channel of the pad's zone; None without a split
+    pub(crate) fn channel(&self, pad: usize) -> Option<u8> {
+        let (zone, _) = self.zone(pad)?;
+        self.split.as_ref().map(|split| split.channels[zone])
+    }
+
+    /// Lights each zone's pads dim in its color (`on`), or puts them out again; returns
+    /// whether there was a split to show
+    pub(crate) fn paint_zones(&self, lights: &mut Lights, on: bool) -> bool {
+        let Some(split) = &self.split else {
+            return false;
+        };
+        for pad in 0..16 {
+            let zone = usize::from(pad >= split.first_upper);
+            if on {
+                lights.set_pad(pad, split.colors[zone], Brightness::Dim);
+            } else {
+                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            }
+        }
+        true
+    }
+}
+
+/// Note name with octave, e.g. "D#2" (36 is C1, as in the default notemaps)
+pub(crate) fn note_name(note: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[note as usize % 12], note as i32 / 12 - 2)
+}
@@ -0,0 +1,127 @@
+use crate::settings::Settings;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use midir::os::unix::{VirtualInput, VirtualOutput};
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Launchpad Mini grid width: row r, column c is note 16 * r + c (row 0 at the top)
+const GRID_SIZE: usize = 8;
+
+/// Launchpad note of a Mikro pad: the pads are the top-left 4x4 of the Launchpad grid
+fn pad_note(pad: usize) -> u8 {
+    let (row, col) = (3 - pad / 4, pad % 4);
+    (16 * row + col) as u8
+}
+
+/// Mikro pad showing the Launchpad grid cell, if it's inside the 4x4
+fn cell_pad(row: usize, col: usize) -> Option<usize> {
+    (row < 4 && col < 4).then(|| (3 - row) * 4 + col)
+}
+
+fn note_pad(note: u8) -> Option<usize> {
+    let (row, col) = (note as usize / 16, note as usize % 16);
+    cell_pad(row, col)
+}
+
+/// Launchpad Mini LED velocity: red level in bits 0-1, green in bits 4-5 (bits 2-3 are
+/// buffering flags). Mixed levels become orange, yellow or lime; the brighter of the two
+/// sets the brightness.
+fn led_color(velocity: u8) -> (PadColors, Brightness) {
+    let (red, green) = (velocity & 0x03, (velocity >> 4) & 0x03);
+    let color = match (red, green) {
+        (0, 0) => return (PadColors::Off, Brightness::Off),
+        (_, 0) => PadColors::Red,
+        (0, _) => PadColors::Green,
+        (r, g) if r > g => PadColors::Orange,
+        (r, g) if r < g => PadColors::Lime,
+        _ => PadColors::Yellow,
+    };
+    let brightness = match red.max(green) {
+        1 => Brightness::Dim,
+        2 => Brightness::Normal,
+        _ => Brightness::Bright,
+    };
+    (color, brightness)
+}
+
+/// The Mikro's pads as a Launchpad Mini on their own virtual ports: hits go out as the
+/// Launchpad's grid notes, and LED messages for the top-left 4x4 light the pads.
+/// The ports stay open for as long as this is kept.
+pub(crate) struct Launchpad {
+    out: MidiOutputConnection,
+    _input: MidiInputConnection<()>,
+}
+
+impl Launchpad {
+    pub(crate) fn open(
+        settings: &Settings,
+        lights: Arc<Mutex<Lights>>,
+        lights_dirty: Arc<AtomicBool>,
+    ) -> Result<Self, String> {
+        // Launchpad software looks for the name, so the client gets it too
+        let name = &settings.launchpad_port_name;
+        let out = MidiOutput::new(name)
+            .map_err(|e| e.to_string())?
+            .create_virtual(name)
+            .map_err(|e| e.to_string())?;
+
+        // Next cell of a rapid LED update (channel 3 note ons fill the grid two at a time)
+        let mut rapid = 0;
+        let input = MidiInput::new(name)
+            .map_err(|e| e.to_string())?
+            .create_virtual(
+                name,
+                move |_timestamp, message, _| {
+                    let mut lights = lights.lock().unwrap();
+                    match *message {
+                        [0x92, first, second] => {
+                            for velocity in [first, second] {
+                                let (row, col) = (rapid / GRID_SIZE, rapid % GRID_SIZE);
+                                if let Some(pad) = cell_pad(row, col) {
+                                    let (color, brightness) = led_color(velocity);
+                                    lights.set_pad(pad, color, brightness);
+                                }
+                                rapid += 1;
+                            }
+                        }
+                        [0x90, note, velocity] => {
+                            if let Some(pad) = note_pad(note) {
+                                let (color, brightness) = led_color(velocity);
+                                lights.set_pad(pad, color, brightness);
+                            }
+                        }
+                        [0x80, note, _] => {
+                            if let Some(pad) = note_pad(note) {
+                                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+                            }
+                        }
+                        // Reset: all LEDs off
+                        [0xB0, 0, 0] => {
+                            for pad in 0..16 {
+                                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+                            }
+                        }
+                        _ => {}
+                    }
+                    // Any other message ends a rapid update
+                    if message.first() != Some(&0x92) {
+                        rapid = 0;
+                    }
+                    lights_dirty.store(true, Ordering::SeqCst);
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        println!("Launchpad Mini emulation on \"{name}\"");
+        Ok(Self { out, _input: input })
+    }
+
+    /// Sends a pad press or release the way a Launchpad does: full velocity, released with 0
+    pub(crate) fn pad(&mut self, pad: usize, pressed: bool) {
+        let velocity = if pressed { 127 } else { 0 };
+        if let Err(e) = self.out.send(&[0x90, pad_note(pad), velocity]) {
+            eprintln!("Launchpad output failed: {e}");
+        }
+    }
+}
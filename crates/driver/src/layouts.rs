@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+/// Built-in pad layouts, as `notemaps` (indexed by logical pad, 0-3 is the bottom row)
+const LAYOUTS: [(&str, [u8; 16]); 4] = [
+    // Pads 1-16 = C1-D#2, pad 1 top left (the driver's default notemaps)
+    ("maschine", [48, 49, 50, 51, 44, 45, 46, 47, 40, 41, 42, 43, 36, 37, 38, 39]),
+    // C1 bottom left, chromatic left to right, then up
+    ("chromatic", [36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51]),
+    // Ableton Drum Rack 4x4 (C1 bottom left, like "chromatic")
+    ("ableton", [36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51]),
+    // General MIDI kit: kick/snare/hats at the bottom, toms and cymbals above,
+    // hand percussion on top
+    ("gm_drums", [
+        36, 38, 42, 46, // Kick, Snare, Closed Hi-Hat, Open Hi-Hat
+        41, 45, 48, 50, // Toms: Low Floor, Low, Hi-Mid, High
+        37, 39, 44, 49, // Side Stick, Clap, Pedal Hi-Hat, Crash
+        51, 56, 54, 55, // Ride, Cowbell, Tambourine, Splash
+    ]),
+];
+
+/// Notes the pads play right now (layout switching and octave shift applied), so the MIDI
+/// input callback can light the pad a note belongs to
+static ACTIVE_NOTEMAPS: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+pub(crate) fn set_active_notemaps(notes: &[u8]) {
+    *ACTIVE_NOTEMAPS.lock().unwrap() = notes.to_vec();
+}
+
+/// Logical pad currently playing `note`
+pub(crate) fn pad_of_note(note: u8) -> Option<usize> {
+    ACTIVE_NOTEMAPS.lock().unwrap().iter().position(|&n| n == note)
+}
+
+/// Note logical `pad` plays right now, if it plays one
+pub(crate) fn active_note(pad: usize) -> Option<u8> {
+    ACTIVE_NOTEMAPS.lock().unwrap().get(pad).copied().filter(|&n| n < 128)
+}
+
+/// Name of the built-in layout `delta` steps away from `current` (wrapping around; a
+/// custom layout counts as being before the first one)
+pub(crate) fn step_layout(current: &str, delta: i32) -> &'static str {
+    let n = LAYOUTS.len() as i32;
+    let pos = LAYOUTS.iter().position(|(name, _)| *name == current.trim().to_ascii_lowercase());
+    let next = match pos {
+        Some(pos) => (pos as i32 + delta).rem_euclid(n),
+        None if delta > 0 => delta - 1,
+        None => delta,
+    };
+    LAYOUTS[next.rem_euclid(n) as usize].0
+}
+
+/// Notemaps of the named layout
+pub(crate) fn layout_notemaps(name: &str) -> Result<[u8; 16], String> {
+    LAYOUTS
+        .iter()
+        .find(|(n, _)| *n == name.trim().to_ascii_lowercase())
+        .map(|(_, notes)| *notes)
+        .ok_or_else(|| {
+            let names: Vec<String> = LAYOUTS.iter().map(|(n, _)| format!("{n:?}")).collect();
+            format!("invalid layout={name:?} (expected: {})", names.join(", "))
+        })
+}
@@ -0,0 +1,130 @@
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// One frame of a light show defined in the config
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct LightFrame {
+    /// Pad colors by logical index: one color for all pads, or 16. Empty: pads off.
+    #[serde(default)]
+    pub pads: Vec<String>,
+    /// Pad brightness: "dim", "normal" or "bright"
+    #[serde(default = "default_brightness")]
+    pub brightness: String,
+    /// Brightness of all button LEDs: "off", "dim", "normal" or "bright"
+    #[serde(default = "default_off")]
+    pub buttons: String,
+    /// Slider LEDs lit from the left (0-25)
+    #[serde(default)]
+    pub slider: usize,
+    /// How long the frame stays up
+    pub ms: u64,
+}
+
+fn default_brightness() -> String {
+    "bright".to_string()
+}
+
+fn default_off() -> String {
+    "off".to_string()
+}
+
+fn parse_brightness(s: &str) -> Result<Brightness, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok(Brightness::Off),
+        "dim" => Ok(Brightness::Dim),
+        "normal" => Ok(Brightness::Normal),
+        "bright" => Ok(Brightness::Bright),
+        other => Err(format!(
+            "invalid brightness {other:?} (expected: \"off\", \"dim\", \"normal\", \"bright\")"
+        )),
+    }
+}
+
+impl LightFrame {
+    /// The frame drawn over `base` (which keeps its display options)
+    pub(crate) fn render(&self, base: &Lights) -> Result<Lights, String> {
+        let mut lights = base.clone();
+        lights.reset();
+
+        let colors = self
+            .pads
+            .iter()
+            .map(|name| PadColors::from_name(name).ok_or(format!("unknown pad color {name:?}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let brightness = parse_brightness(&self.brightness)?;
+        match colors.len() {
+            0 => {}
+            1 => (0..16).for_each(|pad| lights.set_pad(pad, colors[0], brightness)),
+            16 => {
+                for (pad, &color) in colors.iter().enumerate() {
+                    lights.set_pad(pad, color, brightness);
+                }
+            }
+            n => return Err(format!("pads should list 1 or 16 colors (found {n})")),
+        }
+
+        let buttons = parse_brightness(&self.buttons)?;
+        for button in (0..Buttons::COUNT).filter_map(num::FromPrimitive::from_usize) {
+            if lights.button_has_light(button) {
+                lights.set_button(button, buttons);
+            }
+        }
+
+        if self.slider > 25 {
+            return Err(format!("slider should be 0 to 25 (found {})", self.slider));
+        }
+        for led in 0..self.slider {
+            lights.set_slider(led, Brightness::Bright);
+        }
+        Ok(lights)
+    }
+}
+
+/// A sequence of light frames played from the main loop, so input keeps flowing meanwhile
+pub(crate) struct LightShow {
+    frames: Vec<(Lights, Duration)>,
+    next: usize,
+    due: Option<Instant>,
+}
+
+/// What the main loop should do with the lights
+pub(crate) enum ShowStep<'a> {
+    /// Write this frame
+    Frame(&'a Lights),
+    /// Keep the current frame up
+    Hold,
+    /// Over: write the regular lights again
+    Done,
+}
+
+impl LightShow {
+    pub(crate) fn new(frames: Vec<(Lights, Duration)>) -> Self {
+        Self {
+            frames,
+            next: 0,
+            due: None,
+        }
+    }
+
+    pub(crate) fn from_config(frames: &[LightFrame], base: &Lights) -> Result<Self, String> {
+        let frames = frames
+            .iter()
+            .map(|f| Ok((f.render(base)?, Duration::from_millis(f.ms))))
+            .collect::<Result<_, String>>()?;
+        Ok(Self::new(frames))
+    }
+
+    pub(crate) fn poll(&mut self, now: Instant) -> ShowStep<'_> {
+        if self.due.is_some_and(|due| now < due) {
+            return ShowStep::Hold;
+        }
+        let Some((lights, duration)) = self.frames.get(self.next) else {
+            return ShowStep::Done;
+        };
+        self.next += 1;
+        self.due = Some(now + *duration);
+        ShowStep::Frame(lights)
+    }
+}
@@ -1,34 +1,170 @@
+mod activity;
+mod aftertouch;
+mod autoconnect;
+mod button_map;
+mod calibrate;
+mod chords;
+mod clock;
+mod config_check;
+mod config_file;
+mod control;
+mod encoder;
+mod encoder_actions;
+mod encoder_hold;
+mod expression;
+mod faults;
+mod desk;
+mod game;
+mod gate;
+mod gestures;
+#[cfg(test)]
+mod hid_fixtures;
+mod host_sessions;
+mod keyboard;
+mod launchpad;
+mod layouts;
+mod light_show;
+mod merge_input;
+mod menu;
+mod metronome;
+mod midi_out;
+mod motion;
+mod note_repeat;
+#[cfg(feature = "mpris")]
+mod now_playing;
+mod output_filter;
+mod pad_banks;
+mod pad_latch;
+mod pad_stats;
+mod palette;
+mod params;
+mod patch_browser;
+mod pitch_bend;
+mod polling;
+mod reload;
+mod rules;
+mod sampler;
+mod scenes;
+mod screen_layers;
 mod self_test;
+mod sequencer;
+mod session;
 mod settings;
-
-use crate::self_test::self_test;
+mod shift_layer;
+mod shutdown;
+mod slider;
+mod slider_zones;
+mod smf_player;
+mod stage_light;
+mod step_record;
+mod stats;
+mod strum;
+mod sync;
+mod text_entry;
+mod timer;
+mod timestamp;
+mod transport;
+mod velocity;
+mod watchdog;
+
+use crate::activity::ActivityIndicator;
+use crate::aftertouch::{
+    AftertouchCcRoute, AftertouchLimiter, AftertouchMode, FxThrow, PressureMeter,
+    parse_aftertouch_mode,
+};
+use crate::autoconnect::{try_autoconnect_virmidi, watch_announcements};
+use crate::button_map::ButtonMap;
+use crate::chords::{ChordMemory, PadChords};
+use crate::faults::{Fault, FaultIndicator};
+use crate::clock::{InternalClock, PPQN, TapTempo};
+use crate::config_file::CliOverrides;
+use crate::control::ControlCommand;
+use crate::desk::Desk;
+use crate::encoder::{Acceleration, parse_relative_encoding};
+use crate::encoder_actions::{EncoderAction, bound_action};
+use crate::encoder_hold::EncoderHold;
+use crate::merge_input::MergedMessage;
+use crate::menu::Menu;
+use crate::metronome::{Metronome, MetronomeMode, parse_metronome_mode};
+use crate::midi_out::MidiOut;
+use crate::motion::MotionRecorder;
+use crate::note_repeat::NoteRepeat;
+use crate::keyboard::{Keyboard, note_name, parse_scale};
+use crate::launchpad::Launchpad;
+use crate::light_show::{LightShow, ShowStep};
+use crate::pad_stats::Heatmap;
+use crate::params::Param;
+use crate::reload::ConfigWatch;
+use crate::sequencer::Sequencer;
+use crate::expression::Expression;
+use crate::gate::Gates;
+use crate::gestures::{ButtonTimers, Gestures};
+use crate::pad_banks::PadBanks;
+use crate::pad_latch::PadLatch;
+use crate::palette::VelocityPalette;
+use crate::patch_browser::{PatchBrowser, load_patch_names};
+use crate::session::Session;
+use crate::pitch_bend::PitchBend;
+use crate::polling::{CpuAudit, Polling};
+use crate::rules::{PadOutcome, Rules};
+use crate::scenes::Scenes;
+use crate::screen_layers::{Layer, ScreenLayers};
+use crate::self_test::{SelfTest, parse_self_test, self_test};
 use crate::settings::Settings;
-use clap::Parser;
-use config::Config;
-use hidapi::{HidDevice, HidResult};
-use maschine_library::controls::{Buttons, PadEventType};
+use crate::shift_layer::{ShiftCommand, ShiftLayer};
+use crate::slider::{
+    SLIDER_RAW_MAX, SLIDER_RAW_MIN, SliderFilter, SliderMode, SoftTakeover, crossfader_volumes,
+    parse_crossfader_curve, parse_slider_curve, parse_slider_mode,
+};
+use crate::slider_zones::SliderZones;
+use crate::step_record::StepRecord;
+use crate::stats::SystemStats;
+use crate::strum::Strum;
+use crate::sync::Linked;
+use crate::text_entry::TextEntry;
+use crate::timer::Timer;
+use crate::timestamp::Timestamp;
+use crate::transport::{BeatFlash, TransportState};
+use crate::velocity::{VelocityMap, VelocitySplits};
+use crate::watchdog::HostWatchdog;
+use clap::{Parser, Subcommand};
+use hidapi::{HidApi, HidDevice, HidError, HidResult};
+use maschine_library::controls::{Buttons, PadEventType, PadTransform};
 use maschine_library::font::Font;
+use maschine_library::hid::HidTransport;
 use maschine_library::lights::{Brightness, Lights, PadColors};
 use maschine_library::screen::Screen;
-use midir::os::unix::{VirtualInput, VirtualOutput};
-use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
-use std::process::Command;
-use std::{thread, time};
+use midir::os::unix::VirtualInput;
+use midir::{MidiInput, MidiInputConnection};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-// MIDI CC assignments for controls
-// Buttons use CC 20-58 (button enum value + 20)
-const BUTTON_CC_OFFSET: u8 = 20;
-// Encoder rotation uses CC 1 (relative mode: 65 = CW, 63 = CCW)
-const ENCODER_CC: u8 = 1;
-// Slider uses CC 9
-const SLIDER_CC: u8 = 9;
+/// Set once at startup from `accessibility`: all status text is drawn at double size
+static LARGE_TEXT: AtomicBool = AtomicBool::new(false);
+
+/// How long "PANIC" stays on the screen
+const PANIC_FLASH: Duration = Duration::from_millis(800);
+/// How long the transport state stays on the screen
+const TRANSPORT_FLASH: Duration = Duration::from_millis(600);
+/// How long the pad layout schematic stays on the screen
+const LAYOUT_FLASH: Duration = Duration::from_millis(1500);
+/// How long the restored session's name stays on the screen after the boot animation
+const SESSION_FLASH: Duration = Duration::from_millis(2000);
+/// How long "CONFIG RELOADED" stays on the screen
+const RELOAD_FLASH: Duration = Duration::from_millis(1500);
+/// How long a recoverable error stays on the screen
+const FAULT_FLASH: Duration = Duration::from_millis(3000);
+/// How long the pads flash on a metronome beat
+const METRONOME_FLASH: Duration = Duration::from_millis(80);
 
 /// Tracks the state of all controls for change detection
 struct ControlState {
     buttons: [bool; 41],
+    timers: ButtonTimers,
     slider_value: u8,
     encoder_pos: Option<u8>, // 4-bit absolute position (0..15)
 }
@@ -37,6 +173,7 @@ impl ControlState {
     fn new() -> Self {
         Self {
             buttons: [false; 41],
+            timers: ButtonTimers::new(),
             slider_value: 0,
             encoder_pos: None,
         }
@@ -50,20 +187,138 @@ impl ControlState {
     author = env!("CARGO_PKG_AUTHORS"),
 )]
 struct Args {
-    #[clap(short, long, help = "Config file (see example_config.toml)")]
+    #[clap(
+        short,
+        long,
+        help = "Config file (see example_config.toml; default: the one in ~/.config or /etc)"
+    )]
     config: Option<String>,
     
     #[clap(short, long, help = "Print text on screen (slides if > 4 chars)")]
     text: Option<String>,
+
+    #[clap(short, long, help = "Pad layout preset, overrides the config (see README.md)")]
+    layout: Option<String>,
+
+    #[clap(long, help = "ALSA client name, overrides the config")]
+    client_name: Option<String>,
+
+    #[clap(long, help = "MIDI output port name, overrides the config")]
+    port_name: Option<String>,
+
+    #[clap(
+        long,
+        value_parser = clap::value_parser!(u8).range(0..=15),
+        help = "MIDI channel (0-15) of the pads, buttons, encoder and slider, overrides the config"
+    )]
+    channel: Option<u8>,
+
+    #[clap(long, help = "Skip the self-test animation on startup")]
+    no_self_test: bool,
+
+    #[clap(long, help = "Don't connect to the virmidi port, overrides the config")]
+    no_autoconnect: bool,
+
+    #[clap(
+        long,
+        value_parser = ["simon"],
+        help = "Play a game on the pads instead of running the driver"
+    )]
+    game: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Commands>,
+}
+
+// Commands sent to an already running driver (over its control socket), or run standalone
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Send All Notes Off / All Sound Off on every channel
+    Panic,
+    /// Print the host transport state seen by the running driver
+    Transport,
+    /// Print per-pad hit counts and velocity distributions of the running driver's session
+    PadStats {
+        /// Clear the statistics instead
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Play a Standard MIDI File out of the virtual port, lighting the pads of its notes
+    Play {
+        /// Path to the .mid file
+        file: String,
+    },
+    /// Forget the running driver's saved session (layout, octave, patch) and go back to the
+    /// config's
+    ResetSession,
+    /// Turn the running driver's stage light on or off: every pad bright white and the
+    /// screen fully lit, e.g. as a music stand light in a blackout
+    StageLight,
+    /// Play the full self-test on the controller (the driver must not be running) and keep
+    /// every LED lit until Enter, to spot dead LEDs
+    SelfTest,
+    /// Validate the config (given with -c, or found in ~/.config or /etc) and preview the
+    /// pad notes and colors it sets up
+    CheckConfig,
+    /// Measure soft/medium/hard hits on every pad and write per-pad velocity scaling into
+    /// the config (given with -c, or found in ~/.config or /etc)
+    Calibrate,
+}
+
+/// Opens the controller. With `wait` set, keeps retrying with backoff until it shows up
+/// (e.g. when started at login before the Mikro is plugged in or enumerated).
+fn open_device(api: &HidApi, wait: bool) -> HidResult<HidDevice> {
+    #[allow(non_snake_case)]
+    let (VID, PID) = (0x17cc, 0x1700);
+    if !wait {
+        return api.open(VID, PID);
+    }
+
+    let mut backoff = Duration::from_millis(500);
+    let mut attempt = 1;
+    loop {
+        match api.open(VID, PID) {
+            Ok(device) => {
+                if attempt > 1 {
+                    println!("Device found after {attempt} attempts");
+                }
+                return Ok(device);
+            }
+            Err(e) => {
+                println!("Waiting for device (attempt {attempt}, retry in {backoff:?}): {e}");
+                let retry_at = Instant::now() + backoff;
+                while Instant::now() < retry_at {
+                    if shutdown::requested() {
+                        return Err(HidError::HidApiError {
+                            message: "stopped while waiting for the device".to_string(),
+                        });
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                backoff = (backoff * 2).min(Duration::from_secs(5));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Which encoder movement to discard right after the encoder is touched
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EncoderTouchFilter {
+    /// Drop only the first small delta inside the window
+    First,
+    /// Drop all movement inside the window
+    All,
+    Off,
 }
 
-fn parse_backlight_brightness(s: &str) -> Result<Brightness, String> {
+fn parse_encoder_touch_filter(s: &str) -> Result<EncoderTouchFilter, String> {
     match s.trim().to_ascii_lowercase().as_str() {
-        "dim" => Ok(Brightness::Dim),
-        "normal" => Ok(Brightness::Normal),
-        "bright" => Ok(Brightness::Bright),
+        "first" => Ok(EncoderTouchFilter::First),
+        "all" => Ok(EncoderTouchFilter::All),
+        "off" => Ok(EncoderTouchFilter::Off),
         other => Err(format!(
-            "invalid backlight_brightness={other:?} (expected: \"dim\", \"normal\", \"bright\")"
+            "invalid encoder_touch_filter={other:?} (expected: \"first\", \"all\", \"off\")"
         )),
     }
 }
@@ -102,10 +357,8 @@ fn display_text(device: &HidDevice, screen: &mut Screen, text: &str) -> HidResul
                 let char_x = x_pos + (i * CHAR_WIDTH) as i32;
                 
                 // Only render characters that are at least partially on screen
-                if char_x >= -(CHAR_WIDTH as i32) && char_x < SCREEN_WIDTH as i32 {
-                    if char_x >= 0 {
-                        Font::write_char(screen, Y_POSITION, char_x as usize, ch, SCALE);
-                    }
+                if char_x >= 0 && char_x < SCREEN_WIDTH as i32 {
+                    Font::write_char(screen, Y_POSITION, char_x as usize, ch, SCALE);
                 }
             }
             
@@ -118,8 +371,39 @@ fn display_text(device: &HidDevice, screen: &mut Screen, text: &str) -> HidResul
 }
 
 fn main() -> HidResult<()> {
+    timestamp::mark_start();
     let args = Args::parse();
 
+    let check_only = matches!(args.command, Some(Commands::CheckConfig));
+    let play_file = match args.command {
+        Some(Commands::Panic) => return run_control_command(ControlCommand::Panic),
+        Some(Commands::Transport) => return run_control_command(ControlCommand::Transport),
+        Some(Commands::PadStats { reset }) => {
+            let cmd = if reset { ControlCommand::PadStatsReset } else { ControlCommand::PadStats };
+            return run_control_command(cmd);
+        }
+        Some(Commands::ResetSession) => return run_control_command(ControlCommand::ResetSession),
+        Some(Commands::StageLight) => return run_control_command(ControlCommand::StageLight),
+        Some(Commands::Play { file }) => Some(file),
+        Some(Commands::CheckConfig) => None,
+        Some(Commands::SelfTest) => {
+            let api = hidapi::HidApi::new()?;
+            let device = open_device(&api, false)?;
+            device.set_blocking_mode(false)?;
+            return self_test::run(&device);
+        }
+        Some(Commands::Calibrate) => {
+            // Like --game, only the controller is needed
+            let api = hidapi::HidApi::new()?;
+            let device = open_device(&api, false)?;
+            device.set_blocking_mode(false)?;
+            // The file the driver would load, found the same way
+            let config_path = config_file::find(args.config.as_deref());
+            return calibrate::run_calibration(&device, config_path.as_deref());
+        }
+        None => None,
+    };
+
     // If --text is provided, just display the text and exit (no MIDI setup needed)
     if let Some(text) = args.text {
         let api = hidapi::HidApi::new()?;
@@ -137,28 +421,73 @@ fn main() -> HidResult<()> {
         return Ok(());
     }
 
-    let mut cfg = Config::builder();
-
-    if let Some(config_fn) = args.config {
-        cfg = cfg.add_source(config::File::with_name(config_fn.as_str()));
+    // Games only need the controller, like --text
+    if args.game.is_some() {
+        let api = hidapi::HidApi::new()?;
+        let device = open_device(&api, false)?;
+        device.set_blocking_mode(false)?;
+        return game::run_simon(&device);
     }
 
-    let cfg = cfg.build().expect("Can't create settings");
-    let settings: Settings = cfg.try_deserialize().expect("Can't parse settings");
+    let config_path = config_file::find(args.config.as_deref());
+    if args.config.is_none()
+        && let Some(path) = &config_path
+    {
+        println!("Using config {path}");
+    }
+    let loaded = config_file::load(config_path.as_deref());
+    let (mut settings, notes) = loaded.unwrap_or_else(|e| {
+        eprintln!("Can't parse config: {e}");
+        std::process::exit(1);
+    });
+    for note in notes {
+        eprintln!("{note}");
+    }
 
+    let overrides = CliOverrides {
+        layout: args.layout.clone(),
+        client_name: args.client_name.clone(),
+        port_name: args.port_name.clone(),
+        channel: args.channel,
+        no_self_test: args.no_self_test,
+        no_autoconnect: args.no_autoconnect,
+    };
+    overrides.apply(&mut settings);
+    if check_only {
+        if let Err(e) = settings.validate() {
+            eprintln!("Invalid config: {e}");
+            std::process::exit(1);
+        }
+        settings.apply_layout();
+        config_check::check_config(&settings);
+        return Ok(());
+    }
     settings.validate().unwrap();
+    settings.apply_layout();
+    activate_settings(&mut settings);
+
+    // Shared immutably by the MIDI input callback, the main loop and background threads;
+    // a config reload swaps in new ones
+    let mut settings = Arc::new(settings);
 
     println!("Running with settings:");
     println!("{settings:?}");
 
+    // Parse the file before creating any ports so a bad file fails fast
+    let smf_messages = play_file.as_ref().map(|file| {
+        smf_player::load_smf(file).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        })
+    });
+
     // Create MIDI output port
-    let output = MidiOutput::new(&settings.client_name).expect("Couldn't open MIDI output");
-    let mut port = output
-        .create_virtual(&settings.port_name)
-        .expect("Couldn't create virtual output port");
+    let mut port = MidiOut::new(Arc::clone(&settings));
 
     // Shared state for lights (needed for MIDI input callback)
     let lights = Arc::new(Mutex::new(Lights::new()));
+    lights.lock().unwrap().set_high_contrast(settings.accessibility);
+    lights.lock().unwrap().set_pad_transform(settings.pad_transform());
     let lights_dirty = Arc::new(AtomicBool::new(false));
     
     // Shared state for screen (needed for MIDI input callback - SysEx messages)
@@ -168,9 +497,11 @@ fn main() -> HidResult<()> {
     // Create MIDI input port
     let midi_input = MidiInput::new(&format!("{} In", settings.client_name))
         .expect("Couldn't open MIDI input");
+    let feedback = Arc::new(Mutex::new(FeedbackMapping::new(Arc::clone(&settings))));
     let _midi_input_connection = create_midi_input(
         midi_input,
-        &settings,
+        &settings.port_name_in,
+        Arc::clone(&feedback),
         Arc::clone(&lights),
         Arc::clone(&lights_dirty),
         Arc::clone(&screen),
@@ -178,281 +509,527 @@ fn main() -> HidResult<()> {
     );
 
     // Now that the virtual MIDI ports exist, optionally wire them to virmidi (what Bitwig enumerates).
-    if settings.autoconnect_virmidi {
-        if let Err(e) = try_autoconnect_virmidi(&settings) {
-            eprintln!("Auto-connect to virmidi failed (continuing): {e}");
-        }
+    if settings.autoconnect_virmidi
+        && let Err(e) = try_autoconnect_virmidi(&settings, true)
+    {
+        eprintln!("Auto-connect to virmidi failed (continuing): {e}");
+    }
+    if settings.autoconnect_virmidi
+        && let Err(e) = watch_announcements(Arc::clone(&settings))
+    {
+        eprintln!("Can't watch for new ALSA clients, virmidi only connects at startup: {e}");
     }
 
-    let api = hidapi::HidApi::new()?;
-    #[allow(non_snake_case)]
-    let (VID, PID) = (0x17cc, 0x1700);
-    let device = api.open(VID, PID)?;
+    let mut api = hidapi::HidApi::new()?;
+    let mut device = open_device(&api, settings.wait_for_device)?;
 
     device.set_blocking_mode(false)?;
 
-    // Run self test with a temporary lock on lights and screen
-    {
-        let mut lights_guard = lights.lock().unwrap();
-        let mut screen_guard = screen.lock().unwrap();
-        self_test(&device, &mut screen_guard, &mut lights_guard)?;
+    if let (Some(file), Some(messages)) = (play_file, smf_messages) {
+        println!("Playing {file} ({} messages)", messages.len());
+        let palette =
+            VelocityPalette::new(&settings).expect("Invalid velocity colors (see README.md)");
+        let transform = settings.pad_transform();
+        let notemaps = &settings.notemaps;
+        smf_player::play_smf(&device, &mut port, notemaps, transform, &palette, &messages)?;
+        println!("Done playing {file}");
+        return Ok(());
     }
 
-    main_loop(&device, lights, lights_dirty, screen, screen_dirty, &mut port, &settings)?;
+    let mut second = if settings.sync_controllers {
+        sync::open_second(&mut api, &device)
+    } else {
+        None
+    };
 
-    Ok(())
-}
+    let mut connections = Connections::open(port, &settings, &lights, &lights_dirty);
+    shutdown::install();
+    reload::install(config_path.as_deref(), overrides);
+    loop {
+        let linked = Linked::new(&device, second.as_ref());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            main_loop(
+                &linked,
+                Arc::clone(&lights),
+                Arc::clone(&lights_dirty),
+                Arc::clone(&screen),
+                Arc::clone(&screen_dirty),
+                &mut connections,
+                Arc::clone(&settings),
+            )
+        }));
+        let result = result.unwrap_or_else(|payload| {
+            shut_down(&linked, &mut connections.port);
+            panic::resume_unwind(payload)
+        });
+        let Err(e) = result else {
+            if let Some(mut reloaded) = reload::take_loaded() {
+                activate_settings(&mut reloaded);
+                let old = std::mem::replace(&mut settings, Arc::new(reloaded));
+                swap_settings(&old, &settings, &lights, &feedback, &mut connections);
+                continue;
+            }
+            shut_down(&linked, &mut connections.port);
+            return Ok(());
+        };
+        // Only a controller that went away is waited for, other errors end the driver
+        api.refresh_devices()?;
+        if !settings.reconnect_device || device_present(&api) {
+            return Err(e);
+        }
 
-#[derive(Debug, Clone)]
-struct SeqPort {
-    client_id: u32,
-    port_id: u32,
-    client_name: String,
-    port_name: String,
-}
-
-fn parse_aconnect_list(output: &str) -> Vec<SeqPort> {
-    // aconnect -l format:
-    // client 128: 'Name' [type=user,pid=...]
-    //     0 'Port name'
-    let mut ports = Vec::new();
-    let mut cur_client_id: Option<u32> = None;
-    let mut cur_client_name: Option<String> = None;
-
-    for line in output.lines() {
-        let line = line.trim_end();
-        if let Some(rest) = line.strip_prefix("client ") {
-            // Parse "128: 'Name' ..."
-            let mut parts = rest.splitn(2, ':');
-            let id_part = parts.next().unwrap_or("").trim();
-            let tail = parts.next().unwrap_or("");
-            let id = id_part.parse::<u32>().ok();
-
-            // Find first quoted string for name.
-            let name = tail
-                .split('\'')
-                .nth(1)
-                .map(|s| s.to_string());
-
-            cur_client_id = id;
-            cur_client_name = name;
-            continue;
+        eprintln!("Device lost ({e}), waiting for it to come back");
+        // Pads held while unplugged never get their release
+        let mut limiter = AftertouchLimiter::new(Duration::ZERO, 0);
+        send_panic(&mut connections.port, Timestamp::now(), &mut limiter, None, None);
+        connections.port.flush();
+
+        // Either controller may come back as the one opened first
+        second = None;
+        device = match open_device(&api, true) {
+            Err(_) if shutdown::requested() => return Ok(()),
+            result => result?,
+        };
+        device.set_blocking_mode(false)?;
+        if settings.sync_controllers {
+            second = sync::open_second(&mut api, &device);
         }
+        // Show what the host set meanwhile; the loop takes it from there
+        let linked = Linked::new(&device, second.as_ref());
+        lights.lock().unwrap().write(&linked)?;
+        screen.lock().unwrap().write(&linked)?;
+        println!("Device reconnected");
+        faults::raise(Fault::DeviceReconnect);
+    }
+}
 
-        // Port lines are indented and start with a number: "0 'Port name'"
-        let l = line.trim_start();
-        let first = l.split_whitespace().next().unwrap_or("");
-        if first.chars().all(|c| c.is_ascii_digit()) && l.contains('\'') {
-            let port_id = first.parse::<u32>().ok();
-            let port_name = l.split('\'').nth(1).map(|s| s.to_string());
-
-            if let (Some(client_id), Some(client_name), Some(port_id), Some(port_name)) = (
-                cur_client_id,
-                cur_client_name.clone(),
-                port_id,
-                port_name,
-            ) {
-                ports.push(SeqPort {
-                    client_id,
-                    port_id,
-                    client_name,
-                    port_name,
-                });
+/// Puts settings into effect beyond the main loop: the notemaps the host's pad feedback
+/// looks notes up in, and accessibility
+fn activate_settings(settings: &mut Settings) {
+    layouts::set_active_notemaps(&settings.notemaps);
+    if settings.accessibility {
+        // A dim backlight would read as lit with every light at full brightness
+        settings.backlight_buttons = false;
+    }
+    LARGE_TEXT.store(settings.accessibility, Ordering::Relaxed);
+}
+
+/// Swaps reloaded settings in while the main loop is stopped: the MIDI input and output
+/// take them at once, and the lights lose the backlight of `old` (the main loop puts up
+/// the new one when it starts over). Notes still held are released, the new loop doesn't
+/// know about them.
+fn swap_settings(
+    old: &Settings,
+    settings: &Arc<Settings>,
+    lights: &Mutex<Lights>,
+    feedback: &Mutex<FeedbackMapping>,
+    connections: &mut Connections,
+) {
+    let mut lights = lights.lock().unwrap();
+    lights.set_high_contrast(settings.accessibility);
+    lights.set_pad_transform(settings.pad_transform());
+    if old.backlight_buttons {
+        for idx in 0..Buttons::COUNT {
+            let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+            let Some(button) = button.filter(|b| lights.button_has_light(*b)) else {
+                continue;
+            };
+            if lights.get_button(button) == old.button_off_level(button) {
+                lights.set_button(button, Brightness::Off);
             }
         }
     }
+    // The MIDI input locks the mapping first
+    drop(lights);
+    *feedback.lock().unwrap() = FeedbackMapping::new(Arc::clone(settings));
+    connections.port.set_settings(Arc::clone(settings));
+
+    let mut limiter = AftertouchLimiter::new(Duration::ZERO, 0);
+    send_panic(&mut connections.port, Timestamp::now(), &mut limiter, None, None);
+    connections.port.flush();
+    reload::reloaded();
+    println!("Config reloaded");
+}
 
-    ports
+/// Leaves things quiet on the way out: All Notes Off / All Sound Off, every light off and
+/// the screen blank
+fn shut_down(device: &impl HidTransport, port: &mut MidiOut) {
+    let mut limiter = AftertouchLimiter::new(Duration::ZERO, 0);
+    send_panic(port, Timestamp::now(), &mut limiter, None, None);
+    port.flush();
+    // Best effort: the controller may be what failed
+    let _ = Lights::new().write(device);
+    let _ = Screen::new().write(device);
+    println!("Driver stopped");
 }
 
-fn run_aconnect(from: &SeqPort, to: &SeqPort) -> Result<(), String> {
-    let status = Command::new("aconnect")
-        .arg(format!("{}:{}", from.client_id, from.port_id))
-        .arg(format!("{}:{}", to.client_id, to.port_id))
-        .status()
-        .map_err(|e| format!("failed to execute aconnect: {e}"))?;
-    if !status.success() {
-        return Err(format!("aconnect exited with {status}"));
-    }
-    Ok(())
+/// Whether the controller is among the enumerated HID devices
+fn device_present(api: &HidApi) -> bool {
+    api.device_list()
+        .any(|d| d.vendor_id() == 0x17cc && d.product_id() == 0x1700)
 }
 
-fn try_autoconnect_virmidi(settings: &Settings) -> Result<(), String> {
-    // Creating the virtual MIDI ports and having them appear in `aconnect -l` can be slightly racy.
-    // Retry a few times before giving up.
-    let mut last_err: Option<String> = None;
-    for _attempt in 0..20 {
-        let output = Command::new("aconnect")
-            .arg("-l")
-            .output()
-            .map_err(|e| format!("failed to run `aconnect -l`: {e}"))?;
-        if !output.status.success() {
-            last_err = Some(format!("`aconnect -l` failed with {}", output.status));
-            thread::sleep(time::Duration::from_millis(50));
-            continue;
-        }
-        let text = String::from_utf8_lossy(&output.stdout);
-        let ports = parse_aconnect_list(&text);
+/// Connections the main loop uses that outlive a controller reconnect, so the ports
+/// other software connected to stay put
+struct Connections {
+    port: MidiOut,
+    control: Option<Receiver<ControlCommand>>,
+    /// Kept alive for as long as the driver runs
+    _merge_connection: Option<MidiInputConnection<()>>,
+    merged: Option<Receiver<MergedMessage>>,
+    launchpad: Option<Launchpad>,
+}
 
-        let driver_out = match ports
-            .iter()
-            .find(|p| p.client_name == settings.client_name && p.port_name == settings.port_name)
-            .cloned()
-        {
-            Some(p) => p,
-            None => {
-                last_err = Some(format!(
-                    "could not find driver output port \"{}\" / \"{}\" in `aconnect -l`",
-                    settings.client_name, settings.port_name
-                ));
-                thread::sleep(time::Duration::from_millis(50));
-                continue;
+impl Connections {
+    fn open(
+        port: MidiOut,
+        settings: &Settings,
+        lights: &Arc<Mutex<Lights>>,
+        lights_dirty: &Arc<AtomicBool>,
+    ) -> Self {
+        let control = match control::listen() {
+            Ok(rx) => Some(rx),
+            Err(e) => {
+                eprintln!("Control socket unavailable (continuing): {e}");
+                None
             }
         };
 
-        let driver_in_client = format!("{} In", settings.client_name);
-        let driver_in = match ports
-            .iter()
-            .find(|p| p.client_name == driver_in_client && p.port_name == settings.port_name_in)
-            .cloned()
-        {
-            Some(p) => p,
-            None => {
-                last_err = Some(format!(
-                    "could not find driver input port \"{}\" / \"{}\" in `aconnect -l`",
-                    driver_in_client, settings.port_name_in
-                ));
-                thread::sleep(time::Duration::from_millis(50));
-                continue;
+        let (_merge_connection, merged) = if settings.merge_input_port.is_empty() {
+            (None, None)
+        } else {
+            match merge_input::open_merge_input(settings) {
+                Ok((conn, rx)) => (Some(conn), Some(rx)),
+                Err(e) => {
+                    eprintln!("MIDI merge input unavailable (continuing): {e}");
+                    (None, None)
+                }
             }
         };
 
-        let virmidi_candidates: Vec<SeqPort> = if settings.virmidi_client_name.trim().is_empty() {
-            ports.iter()
-                .filter(|p| p.client_name.starts_with("Virtual Raw MIDI"))
-                .cloned()
-                .collect()
+        let launchpad = if settings.launchpad_emulation {
+            match Launchpad::open(settings, Arc::clone(lights), Arc::clone(lights_dirty)) {
+                Ok(launchpad) => Some(launchpad),
+                Err(e) => {
+                    eprintln!("Launchpad emulation unavailable (continuing): {e}");
+                    None
+                }
+            }
         } else {
-            ports.iter()
-                .filter(|p| p.client_name == settings.virmidi_client_name)
-                .cloned()
-                .collect()
+            None
         };
 
-        if virmidi_candidates.is_empty() {
-            last_err = Some(
-                "no virmidi ALSA sequencer ports found (is snd-virmidi loaded? did Bitwig open it once?)"
-                    .to_string(),
-            );
-            thread::sleep(time::Duration::from_millis(50));
-            continue;
+        Self {
+            port,
+            control,
+            _merge_connection,
+            merged,
+            launchpad,
         }
-
-        let virmidi_port = match virmidi_candidates
-            .into_iter()
-            .find(|p| p.port_id as usize == settings.virmidi_port)
-        {
-            Some(p) => p,
-            None => {
-                last_err = Some(format!(
-                    "virmidi client found, but no port {} exists",
-                    settings.virmidi_port
-                ));
-                thread::sleep(time::Duration::from_millis(50));
-                continue;
-            }
-        };
-
-        // Driver -> Bitwig (via virmidi rawmidi)
-        run_aconnect(&driver_out, &virmidi_port)?;
-        // Bitwig -> Driver (LEDs), also via virmidi rawmidi
-        run_aconnect(&virmidi_port, &driver_in)?;
-
-        eprintln!(
-            "Auto-connected: {}:{} -> {}:{} and back -> {}:{}",
-            driver_out.client_id,
-            driver_out.port_id,
-            virmidi_port.client_id,
-            virmidi_port.port_id,
-            driver_in.client_id,
-            driver_in.port_id
-        );
-
-        return Ok(());
     }
+}
 
-    Err(last_err.unwrap_or_else(|| "auto-connect failed".to_string()))
+/// Sends a command to the running driver over its control socket and prints the reply
+fn run_control_command(cmd: ControlCommand) -> HidResult<()> {
+    match control::send_command(cmd) {
+        // Replies are single lines, longer ones list their items separated by "; "
+        Ok(reply) => println!("{}", reply.replace("; ", "\n")),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
 }
 
 /// Sends a MIDI CC message
-fn send_cc(port: &mut MidiOutputConnection, cc: u8, value: u8) {
-    // MIDI CC: 0xB0 (CC on channel 0), controller, value
-    let buf = [0xB0, cc, value];
-    port.send(&buf).unwrap();
+fn send_cc(port: &mut MidiOut, at: Timestamp, channel: u8, cc: u8, value: u8) {
+    // MIDI CC: 0xB0 | channel, controller, value
+    let buf = [0xB0 | channel, cc, value];
+    port.send(&buf, at);
+}
+
+/// Sends a 14-bit value (0-16383) as a CC pair: the MSB on `cc`, the LSB on `cc + 32`
+fn send_cc14(port: &mut MidiOut, at: Timestamp, channel: u8, cc: u8, value: u16) {
+    send_cc(port, at, channel, cc, (value >> 7) as u8);
+    send_cc(port, at, channel, cc + 32, (value & 0x7F) as u8);
 }
 
 /// Sends a MIDI Note message
-fn send_note(port: &mut MidiOutputConnection, note: u8, velocity: u8, on: bool) {
-    // MIDI Note: 0x90 (Note On) or 0x80 (Note Off) on channel 0
+fn send_note(port: &mut MidiOut, at: Timestamp, channel: u8, note: u8, velocity: u8, on: bool) {
+    // MIDI Note: 0x90 (Note On) or 0x80 (Note Off) | channel
     let status = if on && velocity > 0 { 0x90 } else { 0x80 };
-    let buf = [status, note, velocity];
-    port.send(&buf).unwrap();
-}
-
-/// Maps a MIDI velocity (0-127) to a pad color
-fn velocity_to_color(velocity: u8) -> PadColors {
-    match velocity {
-        0 => PadColors::Off,
-        1..=7 => PadColors::Red,
-        8..=14 => PadColors::Orange,
-        15..=21 => PadColors::LightOrange,
-        22..=28 => PadColors::WarmYellow,
-        29..=35 => PadColors::Yellow,
-        36..=42 => PadColors::Lime,
-        43..=49 => PadColors::Green,
-        50..=56 => PadColors::Mint,
-        57..=63 => PadColors::Cyan,
-        64..=70 => PadColors::Turquoise,
-        71..=77 => PadColors::Blue,
-        78..=84 => PadColors::Plum,
-        85..=91 => PadColors::Violet,
-        92..=98 => PadColors::Purple,
-        99..=105 => PadColors::Magenta,
-        106..=112 => PadColors::Fuchsia,
-        113..=127 => PadColors::White,
-        _ => PadColors::White,
+    let buf = [status | channel, note, velocity];
+    port.send(&buf, at);
+}
+
+/// Sends a Channel Volume (CC 7) message on the given channel
+fn send_channel_volume(port: &mut MidiOut, at: Timestamp, channel: u8, volume: u8) {
+    let buf = [0xB0 | channel, 7, volume];
+    port.send(&buf, at);
+}
+
+/// Sends a MIDI Polyphonic Key Pressure message
+fn send_poly_aftertouch(port: &mut MidiOut, at: Timestamp, channel: u8, note: u8, pressure: u8) {
+    // MIDI Poly Pressure: 0xA0 | channel, note, pressure
+    let buf = [0xA0 | channel, note, pressure];
+    port.send(&buf, at);
+}
+
+/// Sends a MIDI Channel Pressure message
+fn send_channel_pressure(port: &mut MidiOut, at: Timestamp, channel: u8, pressure: u8) {
+    // MIDI Channel Pressure: 0xD0 | channel, pressure
+    let buf = [0xD0 | channel, pressure];
+    port.send(&buf, at);
+}
+
+/// Sends the pressure CCs (`aftertouch_cc`) fed by the pad: the highest pressure held on
+/// each route's pads
+fn send_aftertouch_ccs(
+    port: &mut MidiOut,
+    at: Timestamp,
+    routes: &[AftertouchCcRoute],
+    pad: usize,
+    aftertouch_limiter: &AftertouchLimiter,
+) {
+    for route in routes.iter().filter(|r| r.matches(pad)) {
+        let value = aftertouch_limiter.route_pressure(route);
+        port.send(&[0xB0 | route.channel, route.cc, value], at);
+    }
+}
+
+/// The note a pad plays: the keyboard's if given, otherwise from `notemaps`, shifted by
+/// `octave` (None if that leaves the MIDI range)
+fn pad_note(keyboard: Option<&Keyboard>, notemaps: &[u8], octave: i8, pad: usize) -> Option<u8> {
+    let note = match keyboard {
+        Some(keyboard) => keyboard.note(pad)?,
+        None => notemaps[pad],
+    };
+    transpose(note, octave)
+}
+
+fn transpose(note: u8, octave: i8) -> Option<u8> {
+    u8::try_from(note as i16 + octave as i16 * 12).ok().filter(|n| *n < 128)
+}
+
+/// Tells the MIDI input callback which notes the pads play now, so pad LEDs follow
+fn publish_notemaps(notemaps: &[u8], octave: i8) {
+    // Pads shifted out of range get a note no message can carry
+    let notes: Vec<u8> =
+        notemaps.iter().map(|&n| transpose(n, octave).unwrap_or(u8::MAX)).collect();
+    layouts::set_active_notemaps(&notes);
+}
+
+/// Puts the lights back to what the driver shows on its own: pads dark (FX throw pads
+/// excepted), buttons off or backlit
+fn standalone_lights(lights: &mut Lights, settings: &Settings) {
+    lights.reset();
+    for idx in 0..41 {
+        let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+        let Some(button) = button else { continue };
+        if lights.button_has_light(button) {
+            lights.set_button(button, settings.button_off_level(button));
+        }
+    }
+    for throw in &settings.fx_throws {
+        paint_fx_throw(lights, throw, 0);
+    }
+}
+
+/// Lights an FX throw pad: dim at rest, brighter the harder it's pressed
+fn paint_fx_throw(lights: &mut Lights, throw: &FxThrow, pressure: u8) {
+    let brightness = match pressure {
+        0..=42 => Brightness::Dim,
+        43..=84 => Brightness::Normal,
+        _ => Brightness::Bright,
+    };
+    lights.set_pad(throw.pad, PadColors::Orange, brightness);
+}
+
+/// Runs a command from the Shift layer or a button gesture
+fn run_command(
+    command: ShiftCommand,
+    port: &mut MidiOut,
+    at: Timestamp,
+    aftertouch_limiter: &mut AftertouchLimiter,
+    note_repeat: Option<&mut NoteRepeat>,
+    pad_latch: Option<(&mut PadLatch, &mut Lights)>,
+    screen_layers: &mut ScreenLayers,
+) {
+    match command {
+        ShiftCommand::Panic => {
+            send_panic(port, at, aftertouch_limiter, note_repeat, pad_latch);
+            let until = Some(at.instant() + PANIC_FLASH);
+            screen_layers.show_text(Layer::Alert, "PANIC", until);
+        }
+        ShiftCommand::ResetSession => session::request_reset(),
+        ShiftCommand::Menu => menu::request_toggle(),
+    }
+}
+
+/// Sends All Notes Off and All Sound Off on every channel and forgets held-note state
+fn send_panic(
+    port: &mut MidiOut,
+    at: Timestamp,
+    aftertouch_limiter: &mut AftertouchLimiter,
+    note_repeat: Option<&mut NoteRepeat>,
+    pad_latch: Option<(&mut PadLatch, &mut Lights)>,
+) {
+    for channel in 0..16u8 {
+        port.send(&[0xB0 | channel, 123, 0], at);
+        port.send(&[0xB0 | channel, 120, 0], at);
+    }
+    for pad in 0..16 {
+        aftertouch_limiter.reset(pad);
+    }
+    // Held pads would bring their notes right back
+    if let Some(repeat) = note_repeat {
+        repeat.clear();
+    }
+    // A latched pad would need two hits to start its note again
+    if let Some((latch, lights)) = pad_latch {
+        latch.clear(lights);
+    }
+    println!("{at} Panic: All Notes Off / All Sound Off sent");
+}
+
+/// A tap on the tap tempo pad or button: sets the clock's tempo and pulls its beat along
+fn tap_clock(
+    clock: &mut InternalClock,
+    tap_tempo: &mut TapTempo,
+    settings: &Settings,
+    now: Timestamp,
+    screen: &Mutex<Screen>,
+    screen_dirty: &AtomicBool,
+) {
+    clock.nudge(now.instant(), settings.tap_tempo_drift_correction);
+    if let Some(bpm) = tap_tempo.tap(now.instant()) {
+        clock.set_bpm(now.instant(), bpm);
+        let status = format!("{bpm} BPM");
+        println!("{} Tap tempo {}", now, status);
+        render_screen_lines(&mut screen.lock().unwrap(), "TAP", &status);
+        screen_dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Turns the backlight of the buttons that are off on or off
+fn switch_backlight(lights: &mut Lights, settings: &Settings, on: bool) {
+    for idx in 0..41 {
+        let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+        let Some(button) = button else { continue };
+        if !lights.button_has_light(button) {
+            continue;
+        }
+        let level = settings.backlight_level(button);
+        let (from, to) = if on { (Brightness::Off, level) } else { (level, Brightness::Off) };
+        if lights.get_button(button) == from {
+            lights.set_button(button, to);
+        }
     }
 }
 
+/// Lights Play while the host plays (dim when paused) and Stop while it doesn't
+fn paint_transport(lights: &mut Lights, state: TransportState, settings: &Settings) {
+    let (play, stop) = match state {
+        TransportState::Playing => (Brightness::Bright, settings.button_off_level(Buttons::Stop)),
+        TransportState::Paused => (Brightness::Dim, Brightness::Normal),
+        TransportState::Stopped => (settings.button_off_level(Buttons::Play), Brightness::Normal),
+    };
+    lights.set_button(Buttons::Play, play);
+    lights.set_button(Buttons::Stop, stop);
+}
+
 // SysEx protocol constants
 // Format: F0 00 21 09 <cmd> <data...> F7
-// Commands: 01 = Screen Text, 02 = Screen Clear
+// Commands: 01 = Screen Text, 02 = Screen Clear, 03 = Host Ping, 04 = Screen Line,
+// 05 = Reset Session, 06 = Pad Color, 07 = Pad Color RGB, 08 = Button Group,
+// 09 = Set Param, 0A = Get Param, 0B = Param Value (sent by the driver), 0C = Session Claim,
+// 0D = Session End
 const SYSEX_MANUFACTURER: [u8; 3] = [0x00, 0x21, 0x09];
 const SYSEX_CMD_TEXT: u8 = 0x01;
 const SYSEX_CMD_CLEAR: u8 = 0x02;
+const SYSEX_CMD_PING: u8 = 0x03;
+const SYSEX_CMD_LINE: u8 = 0x04;
+const SYSEX_CMD_RESET_SESSION: u8 = 0x05;
+const SYSEX_CMD_PAD_COLOR: u8 = 0x06;
+const SYSEX_CMD_PAD_RGB: u8 = 0x07;
+const SYSEX_CMD_BUTTON_GROUP: u8 = 0x08;
+const SYSEX_CMD_SET_PARAM: u8 = 0x09;
+const SYSEX_CMD_GET_PARAM: u8 = 0x0A;
+const SYSEX_CMD_PARAM_VALUE: u8 = 0x0B;
+const SYSEX_CMD_SESSION_CLAIM: u8 = 0x0C;
+const SYSEX_CMD_SESSION_END: u8 = 0x0D;
+/// Get Param for every parameter
+const SYSEX_ALL_PARAMS: u8 = 0x7F;
+
+/// The two lines set with SysEx Screen Line; Screen Text and Screen Clear empty them
+static SYSEX_LINES: Mutex<[String; 2]> = Mutex::new([String::new(), String::new()]);
+
+/// How the MIDI input turns host messages into lights, swapped as a whole on a config
+/// reload
+struct FeedbackMapping {
+    settings: Arc<Settings>,
+    rules: Rules,
+    buttons: ButtonMap,
+    palette: VelocityPalette,
+}
+
+impl FeedbackMapping {
+    fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            rules: Rules::new(&settings.rules).expect("Invalid rules (see README.md)"),
+            buttons: ButtonMap::new(&settings).expect("Invalid button_mappings (see README.md)"),
+            palette: VelocityPalette::new(&settings)
+                .expect("Invalid velocity colors (see README.md)"),
+            settings,
+        }
+    }
+}
 
 /// Creates the MIDI input port with a callback that processes incoming MIDI messages
 fn create_midi_input(
     midi_input: MidiInput,
-    settings: &Settings,
+    port_name_in: &str,
+    feedback: Arc<Mutex<FeedbackMapping>>,
     lights: Arc<Mutex<Lights>>,
     lights_dirty: Arc<AtomicBool>,
     screen: Arc<Mutex<Screen>>,
     screen_dirty: Arc<AtomicBool>,
 ) -> MidiInputConnection<Vec<u8>> {
-    // Clone notemaps for the callback (it needs to be 'static)
-    let notemaps = settings.notemaps.clone();
-    let backlight_enabled = settings.backlight_buttons;
-    let backlight_brightness = parse_backlight_brightness(&settings.backlight_brightness)
-        .expect("Invalid backlight_brightness (see README.md)");
-
     midi_input
         .create_virtual(
-            &settings.port_name_in,
+            port_name_in,
             move |_timestamp, message, _data| {
+                activity::midi_in();
+                let feedback = feedback.lock().unwrap();
+                let FeedbackMapping {
+                    settings,
+                    rules,
+                    buttons,
+                    palette,
+                } = &*feedback;
+
                 // Handle SysEx messages (variable length, starts with 0xF0)
                 if !message.is_empty() && message[0] == 0xF0 {
-                    handle_sysex(message, &screen, &screen_dirty);
+                    handle_sysex(
+                        message,
+                        settings,
+                        &screen,
+                        &screen_dirty,
+                        &lights,
+                        &lights_dirty,
+                    );
+                    return;
+                }
+
+                // Real-time messages: follow the host transport
+                if let [status @ 0xF8..=0xFF] = message {
+                    if let Some(state) = transport::handle_realtime(*status)
+                        && settings.transport_feedback
+                    {
+                        paint_transport(&mut lights.lock().unwrap(), state, settings);
+                        lights_dirty.store(true, Ordering::SeqCst);
+                    }
                     return;
                 }
                 
@@ -466,67 +1043,73 @@ fn create_midi_input(
                 let data1 = message[1];
                 let data2 = message[2];
 
-                // Only process channel 0 (can be extended later)
-                if channel != 0 {
+                // Button LEDs answer to the message their button sends, pad LEDs follow the pad
+                // channel (or the host's feedback channel), rules the button channel
+                let button = buttons.button_of(status, channel, data1);
+                let expected = if status == 0xB0 {
+                    settings.button_channel
+                } else {
+                    params::value(Param::FeedbackChannel, settings)
+                };
+                if button.is_none() && channel != expected {
                     return;
                 }
 
-                let mut lights_guard = lights.lock().unwrap();
-
-                match status {
-                    0x90 => {
-                        // Note On - control pad LEDs
-                        let pad_idx = notemaps.iter().position(|&n| n == data1);
-                        if let Some(idx) = pad_idx {
-                            if data2 > 0 {
-                                let color = velocity_to_color(data2);
-                                lights_guard.set_pad(idx, color, Brightness::Normal);
-                            } else {
-                                lights_guard.set_pad(idx, PadColors::Off, Brightness::Off);
-                            }
-                            lights_dirty.store(true, Ordering::SeqCst);
-                        }
+                let apply = |leds: &mut Lights| match (status, button) {
+                    // Rules take precedence over the button LEDs
+                    (0xB0, _)
+                        if channel == settings.button_channel
+                            && rules.cc_in(data1, data2, leds) =>
+                    {
+                        true
                     }
-                    0x80 => {
-                        // Note Off - turn off pad LED
-                        let pad_idx = notemaps.iter().position(|&n| n == data1);
-                        if let Some(idx) = pad_idx {
-                            lights_guard.set_pad(idx, PadColors::Off, Brightness::Off);
-                            lights_dirty.store(true, Ordering::SeqCst);
+                    // Latching buttons light up by themselves
+                    (_, Some(btn)) if buttons.latches(btn) => false,
+                    (_, Some(btn)) => {
+                        // A button's CC or note - control its LED
+                        if !leds.button_has_light(btn) {
+                            return false;
                         }
+                        let brightness = match data2 {
+                            // Map velocity to brightness
+                            1..=42 if status != 0x80 => Brightness::Dim,
+                            43..=84 if status != 0x80 => Brightness::Normal,
+                            85..=127 if status != 0x80 => Brightness::Bright,
+                            // Off shows as the backlight in night mode
+                            _ => settings.button_off_level(btn),
+                        };
+                        leds.set_button(btn, brightness);
+                        true
                     }
-                    0xB0 => {
-                        // Control Change - control button LEDs
-                        let cc = data1;
-                        let value = data2;
-
-                        // Check if this CC corresponds to a button (CC 20-60)
-                        if cc >= BUTTON_CC_OFFSET && cc < BUTTON_CC_OFFSET + 41 {
-                            let button_idx = (cc - BUTTON_CC_OFFSET) as usize;
-                            let button: Option<Buttons> = num::FromPrimitive::from_usize(button_idx);
-                            if let Some(btn) = button {
-                                if lights_guard.button_has_light(btn) {
-                                    let mut brightness = if value > 0 {
-                                        // Map velocity to brightness
-                                        match value {
-                                            1..=42 => Brightness::Dim,
-                                            43..=84 => Brightness::Normal,
-                                            85..=127 => Brightness::Bright,
-                                            _ => Brightness::Off,
-                                        }
-                                    } else {
-                                        Brightness::Off
-                                    };
-                                    if backlight_enabled && brightness == Brightness::Off {
-                                        brightness = backlight_brightness;
-                                    }
-                                    lights_guard.set_button(btn, brightness);
-                                    lights_dirty.store(true, Ordering::SeqCst);
-                                }
-                            }
+                    (0x90, None) => {
+                        // Note On - control pad LEDs
+                        let Some(idx) = layouts::pad_of_note(data1) else {
+                            return false;
+                        };
+                        if data2 > 0 {
+                            let color = palette.color(data2);
+                            leds.set_pad(idx, color, Brightness::Normal);
+                        } else {
+                            leds.set_pad(idx, PadColors::Off, Brightness::Off);
                         }
+                        true
+                    }
+                    (0x80, None) => {
+                        // Note Off - turn off pad LED
+                        let Some(idx) = layouts::pad_of_note(data1) else {
+                            return false;
+                        };
+                        leds.set_pad(idx, PadColors::Off, Brightness::Off);
+                        true
                     }
-                    _ => {}
+                    _ => false,
+                };
+                // With several hosts sending feedback, only the shown session's reaches the
+                // controller
+                let mut lights_guard = lights.lock().unwrap();
+                let changed = host_sessions::feedback(Instant::now(), &mut lights_guard, apply);
+                if changed {
+                    lights_dirty.store(true, Ordering::SeqCst);
                 }
             },
             Vec::new(),
@@ -534,8 +1117,15 @@ fn create_midi_input(
         .expect("Couldn't create virtual input port")
 }
 
-/// Handle incoming SysEx messages for screen control
-fn handle_sysex(message: &[u8], screen: &Arc<Mutex<Screen>>, screen_dirty: &Arc<AtomicBool>) {
+/// Handle incoming SysEx messages for screen and pad color control
+fn handle_sysex(
+    message: &[u8],
+    settings: &Settings,
+    screen: &Arc<Mutex<Screen>>,
+    screen_dirty: &Arc<AtomicBool>,
+    lights: &Arc<Mutex<Lights>>,
+    lights_dirty: &Arc<AtomicBool>,
+) {
     // Minimum SysEx: F0 <3 bytes mfr> <cmd> F7 = 6 bytes
     if message.len() < 6 {
         return;
@@ -558,75 +1148,606 @@ fn handle_sysex(message: &[u8], screen: &Arc<Mutex<Screen>>, screen_dirty: &Arc<
             let mut screen_guard = screen.lock().unwrap();
             render_screen_text(&mut screen_guard, &text);
             screen_dirty.store(true, Ordering::SeqCst);
+            *SYSEX_LINES.lock().unwrap() = Default::default();
             
             println!("Screen: {}", text);
         }
+        SYSEX_CMD_LINE => {
+            // One line of two: F0 00 21 09 04 <line: 00 top, 01 bottom> <text bytes> F7
+            // The other line keeps what it was last set to
+            let Some(&line) = message.get(5).filter(|l| **l < 2) else {
+                return;
+            };
+            let text_bytes = &message[6.min(message.len() - 1)..message.len() - 1];
+            let mut lines = SYSEX_LINES.lock().unwrap();
+            lines[line as usize] = String::from_utf8_lossy(text_bytes).into_owned();
+
+            let mut screen_guard = screen.lock().unwrap();
+            render_screen_lines(&mut screen_guard, &lines[0], &lines[1]);
+            screen_dirty.store(true, Ordering::SeqCst);
+
+            println!("Screen line {}: {}", line + 1, lines[line as usize]);
+        }
         SYSEX_CMD_CLEAR => {
             // Screen clear: F0 00 21 09 02 F7
+            *SYSEX_LINES.lock().unwrap() = Default::default();
             let mut screen_guard = screen.lock().unwrap();
             screen_guard.reset();
             screen_dirty.store(true, Ordering::SeqCst);
             
             println!("Screen: cleared");
         }
+        SYSEX_CMD_PING => {
+            // Controller script heartbeat: F0 00 21 09 03 F7
+            watchdog::ping(Instant::now());
+        }
+        SYSEX_CMD_RESET_SESSION => {
+            // Back to the config's layout, octave and patch: F0 00 21 09 05 F7
+            session::request_reset();
+        }
+        SYSEX_CMD_PAD_COLOR => {
+            // One pad in a palette color: F0 00 21 09 06 <pad 00-0F> <color 00-11>
+            // <brightness 00 off, 01 dim, 02 normal, 03 bright> F7
+            let &[_, _, _, _, _, pad, color, brightness, 0xF7] = message else {
+                return;
+            };
+            let color: Option<PadColors> = num::FromPrimitive::from_u8(color);
+            let levels = [Brightness::Off, Brightness::Dim, Brightness::Normal, Brightness::Bright];
+            let brightness = levels.get(brightness as usize);
+            let (Some(color), Some(&brightness)) = (color, brightness) else {
+                return;
+            };
+            if pad < 16 {
+                let apply = |leds: &mut Lights| {
+                    leds.set_pad(pad as usize, color, brightness);
+                    true
+                };
+                if host_sessions::feedback(Instant::now(), &mut lights.lock().unwrap(), apply) {
+                    lights_dirty.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        SYSEX_CMD_BUTTON_GROUP => {
+            // A whole group of button LEDs at once:
+            // F0 00 21 09 08 <brightness 00 off, 01 dim, 02 normal, 03 bright> <group name> F7
+            let Some(&level) = message.get(5).filter(|l| **l < 4 && message.len() > 7) else {
+                return;
+            };
+            let name = String::from_utf8_lossy(&message[6..message.len() - 1]);
+            let Some(buttons) = settings.button_group(&name) else {
+                eprintln!("SysEx: unknown button group {name:?}");
+                return;
+            };
+            let levels = [Brightness::Off, Brightness::Dim, Brightness::Normal, Brightness::Bright];
+            let apply = |leds: &mut Lights| {
+                for button in buttons {
+                    if !leds.button_has_light(button) {
+                        continue;
+                    }
+                    // Off shows as the backlight in night mode, like a CC of 0
+                    let brightness = match level {
+                        0 => settings.button_off_level(button),
+                        _ => levels[level as usize],
+                    };
+                    leds.set_button(button, brightness);
+                }
+                true
+            };
+            if host_sessions::feedback(Instant::now(), &mut lights.lock().unwrap(), apply) {
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+        }
+        SYSEX_CMD_PAD_RGB => {
+            // One pad in the palette color closest to an RGB color:
+            // F0 00 21 09 07 <pad 00-0F> <red> <green> <blue> F7, 7 bits each
+            let &[_, _, _, _, _, pad, r, g, b, 0xF7] = message else {
+                return;
+            };
+            if pad < 16 && r < 0x80 && g < 0x80 && b < 0x80 {
+                let widen = |c: u8| (c << 1) | (c >> 6);
+                let (color, brightness) = palette::nearest(widen(r), widen(g), widen(b));
+                let apply = |leds: &mut Lights| {
+                    leds.set_pad(pad as usize, color, brightness);
+                    true
+                };
+                if host_sessions::feedback(Instant::now(), &mut lights.lock().unwrap(), apply) {
+                    lights_dirty.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        SYSEX_CMD_SET_PARAM => {
+            // A runtime parameter: F0 00 21 09 09 <param> <value> F7
+            let &[_, _, _, _, _, id, value, 0xF7] = message else {
+                return;
+            };
+            let Some(param) = Param::from_u8(id) else {
+                eprintln!("SysEx: unknown parameter {id:02X}");
+                return;
+            };
+            if param == Param::Backlight && settings.accessibility {
+                eprintln!("SysEx: the backlight stays off with accessibility");
+                return;
+            }
+            if !params::set(param, value) {
+                eprintln!("SysEx: {} can't be {value}", param.name());
+                return;
+            }
+            println!("Host set {} to {value}", param.name());
+        }
+        SYSEX_CMD_GET_PARAM => {
+            // Asks for a Param Value reply: F0 00 21 09 0A <param, 7F = all> F7
+            let &[_, _, _, _, _, id, 0xF7] = message else {
+                return;
+            };
+            match Param::from_u8(id) {
+                Some(param) => params::request(&[param]),
+                None if id == SYSEX_ALL_PARAMS => params::request(&Param::ALL),
+                None => eprintln!("SysEx: unknown parameter {id:02X}"),
+            }
+        }
+        SYSEX_CMD_SESSION_CLAIM => {
+            // The LED feedback that follows is this host's:
+            // F0 00 21 09 0C <priority 00-7F> <name> F7. Repeat it (at least every
+            // host_session_timeout_ms) to stay active.
+            let Some(&priority) = message.get(5).filter(|_| message.len() > 7) else {
+                return;
+            };
+            let name = String::from_utf8_lossy(&message[6..message.len() - 1]);
+            let mut lights = lights.lock().unwrap();
+            let shown = host_sessions::claim(&name, priority, Instant::now(), &mut lights);
+            if let Some(shown) = shown {
+                println!("Host session {shown:?} shown");
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+        }
+        SYSEX_CMD_SESSION_END => {
+            // The host stops sending feedback, the next session shows at once:
+            // F0 00 21 09 0D <name> F7
+            if message.len() < 7 {
+                return;
+            }
+            let name = String::from_utf8_lossy(&message[5..message.len() - 1]);
+            let mut lights = lights.lock().unwrap();
+            if let Some(shown) = host_sessions::end(&name, &mut lights) {
+                println!("Host session {name:?} ended, {shown:?} shown");
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+        }
         _ => {
             // Unknown command
         }
     }
 }
 
+/// Writes the lights with all pads lit in one color, leaving the shared state untouched
+fn flash_pads(device: &impl HidTransport, lights: &Lights, color: PadColors) -> HidResult<()> {
+    let mut flash = lights.clone();
+    for pad in 0..16 {
+        flash.set_pad(pad, color, Brightness::Bright);
+    }
+    flash.write(device)
+}
+
+/// Writes the lights, with the pressure bar over the slider LEDs while it's up and the
+/// Shift layer over the buttons while Shift is held
+fn write_lights(
+    device: &impl HidTransport,
+    lights: &Lights,
+    meter: Option<&PressureMeter>,
+    shift_layer: Option<&ShiftLayer>,
+    stage_light_on: bool,
+) -> HidResult<()> {
+    let meter = meter.filter(|meter| meter.active());
+    let shift_layer = shift_layer.filter(|layer| layer.active());
+    if stage_light_on {
+        let mut shown = lights.clone();
+        stage_light::paint(&mut shown);
+        return shown.write(device);
+    }
+    if meter.is_none() && shift_layer.is_none() {
+        return lights.write(device);
+    }
+    let mut shown = lights.clone();
+    if let Some(meter) = meter {
+        meter.paint(&mut shown);
+    }
+    if let Some(layer) = shift_layer {
+        layer.paint(&mut shown);
+    }
+    shown.write(device)
+}
+
 /// Render text to the screen buffer (centered)
 fn render_screen_text(screen: &mut Screen, text: &str) {
+    let y = if LARGE_TEXT.load(Ordering::Relaxed) { 8 } else { 12 };
+
+    screen.reset();
+    write_centered(screen, y, text);
+}
+
+/// Render a few characters in large type (e.g. a countdown), centered
+fn render_screen_big(screen: &mut Screen, text: &str) {
     const SCREEN_WIDTH: usize = 128;
+    const SCALE: usize = 4;
+
+    screen.reset();
+    let text_width = text.chars().count() * 8 * SCALE;
+    Font::write_str(screen, 0, SCREEN_WIDTH.saturating_sub(text_width) / 2, text, SCALE);
+}
+
+/// Render two lines of text to the screen buffer (each centered)
+fn render_screen_lines(screen: &mut Screen, top: &str, bottom: &str) {
+    // Large text fills the screen with two lines
+    let (y_top, y_bottom) = if LARGE_TEXT.load(Ordering::Relaxed) { (0, 16) } else { (4, 20) };
+
+    screen.reset();
+    write_centered(screen, y_top, top);
+    write_centered(screen, y_bottom, bottom);
+}
+
+/// Draws a 4x4 schematic of the pads, one label (up to 4 characters) per logical pad,
+/// laid out as on the device: pads 0-3 on the bottom row, then moved by the pad transform
+fn render_pad_grid(screen: &mut Screen, labels: &[String], transform: PadTransform) {
+    const CELL_WIDTH: usize = 32;
     const CHAR_WIDTH: usize = 8;
-    const Y_POSITION: usize = 12;
-    const SCALE: usize = 1;
-    
+
     screen.reset();
-    
-    let text_width = text.chars().count() * CHAR_WIDTH * SCALE;
-    let x_start = if text_width < SCREEN_WIDTH {
-        (SCREEN_WIDTH - text_width) / 2
-    } else {
-        0
-    };
-    
-    Font::write_str(screen, Y_POSITION, x_start, text, SCALE);
+    for (pad, label) in labels.iter().enumerate().take(16) {
+        let pad = transform.apply(pad);
+        let (row, col) = (3 - pad / 4, pad % 4);
+        let label: String = label.chars().take(CELL_WIDTH / CHAR_WIDTH).collect();
+        let x = col * CELL_WIDTH + (CELL_WIDTH - label.chars().count() * CHAR_WIDTH) / 2;
+        Font::write_str(screen, row * 8, x, &label, 1);
+    }
+}
+
+/// Writes one line centered, cut to what fits on the screen
+fn write_centered(screen: &mut Screen, y: usize, text: &str) {
+    const SCREEN_WIDTH: usize = 128;
+    const CHAR_WIDTH: usize = 8;
+
+    let scale = if LARGE_TEXT.load(Ordering::Relaxed) { 2 } else { 1 };
+    let text: String = text.chars().take(SCREEN_WIDTH / (CHAR_WIDTH * scale)).collect();
+    let text_width = text.chars().count() * CHAR_WIDTH * scale;
+    let x_start = (SCREEN_WIDTH - text_width) / 2;
+
+    Font::write_str(screen, y, x_start, &text, scale);
 }
 
 fn main_loop(
-    device: &HidDevice,
+    device: &impl HidTransport,
     lights: Arc<Mutex<Lights>>,
     lights_dirty: Arc<AtomicBool>,
     screen: Arc<Mutex<Screen>>,
     screen_dirty: Arc<AtomicBool>,
-    port: &mut MidiOutputConnection,
-    settings: &Settings,
+    connections: &mut Connections,
+    settings: Arc<Settings>,
 ) -> HidResult<()> {
     let mut buf = [0u8; 64];
     let mut state = ControlState::new();
-    let backlight_enabled = settings.backlight_buttons;
-    let backlight_brightness = parse_backlight_brightness(&settings.backlight_brightness)
-        .expect("Invalid backlight_brightness (see README.md)");
-
-    println!("MIDI CC Mapping:");
-    println!("  Buttons: CC {}-{} (value 127=press, 0=release)", BUTTON_CC_OFFSET, BUTTON_CC_OFFSET + 40);
-    println!("  Encoder: CC {} (relative: 65+=CW, 63-=CCW)", ENCODER_CC);
-    println!("  Slider:  CC {} (0-127)", SLIDER_CC);
-    println!("");
-
-    // Optional "night mode": keep all button LEDs faintly lit, unless explicitly set brighter.
-    if backlight_enabled {
+    let encoder_touch_filter = parse_encoder_touch_filter(&settings.encoder_touch_filter)
+        .expect("Invalid encoder_touch_filter (see README.md)");
+    let encoder_touch_window = Duration::from_millis(settings.encoder_touch_suppress_ms);
+    let mut aftertouch_mode = parse_aftertouch_mode(params::aftertouch(&settings))
+        .expect("Invalid aftertouch (see README.md)");
+    let slider_mode = parse_slider_mode(&settings.slider_mode)
+        .expect("Invalid slider_mode (see README.md)");
+    let crossfader_curve = parse_crossfader_curve(&settings.crossfader_curve)
+        .expect("Invalid crossfader_curve (see README.md)");
+    let slider_curve =
+        parse_slider_curve(&settings.slider_curve).expect("Invalid slider_curve (see README.md)");
+    let encoder_encoding = parse_relative_encoding(&settings.encoder_encoding)
+        .expect("Invalid encoder_encoding (see README.md)");
+    let mut encoder_acceleration = Acceleration::new(settings.encoder_acceleration);
+    let mut slider_filter = SliderFilter::new(settings.slider_median_window, settings.slider_hysteresis);
+    let mut aftertouch_limiter = AftertouchLimiter::new(
+        Duration::from_millis(settings.aftertouch_min_interval_ms),
+        settings.aftertouch_min_change,
+    );
+    let mut pressure_meter = settings.aftertouch_slider_display.then(PressureMeter::default);
+    let mut shift_layer = ShiftLayer::new(&settings.shift_mappings, settings.button_channel);
+    // FX throw pads are rate-limited on their own, they don't count as held notes
+    let mut fx_limiter = AftertouchLimiter::new(
+        Duration::from_millis(settings.aftertouch_min_interval_ms),
+        settings.aftertouch_min_change,
+    );
+    let panic_chord = settings.panic_chord();
+    let stats_chord = settings.stats_chord();
+    let timer_chord = settings.timer_chord();
+    let pad_stats_chord = settings.pad_stats_chord();
+    let layout_toggle_chord = settings.layout_toggle_chord();
+    let stage_light_chord = settings.stage_light_chord();
+    let menu_chord = settings.menu_chord();
+    let mut stage_light_on = false;
+    let tap_tempo_button = Buttons::from_name(&settings.tap_tempo_button);
+    let step_record_button = Buttons::from_name(&settings.step_record_button);
+    let encoder_secondary_button = Buttons::from_name(&settings.encoder_secondary_button);
+    let mut encoder_hold = EncoderHold::new(&settings);
+    let mut gestures = Gestures::new(&settings);
+    if !settings.fx_throws.is_empty() {
         let mut lights_guard = lights.lock().unwrap();
-        let mut changed = false;
-        for idx in 0..41 {
-            let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+        for throw in &settings.fx_throws {
+            paint_fx_throw(&mut lights_guard, throw, 0);
+        }
+        lights_dirty.store(true, Ordering::SeqCst);
+    }
+    let host_session_timeout = Duration::from_millis(settings.host_session_timeout_ms);
+    let mut watchdog = (settings.host_watchdog_secs > 0)
+        .then(|| HostWatchdog::new(Duration::from_secs(settings.host_watchdog_secs)));
+    let rules = Rules::new(&settings.rules).expect("Invalid rules (see README.md)");
+    let mut velocity_map = VelocityMap::with_curve(&settings, params::velocity_curve(&settings))
+        .expect("Invalid velocity curve (see README.md)");
+    // Whether button LEDs that are off show the backlight, as the host may switch it
+    let mut backlight_on = params::value(Param::Backlight, &settings) == 1;
+    let pad_transform = settings.pad_transform();
+    let velocity_splits =
+        VelocitySplits::new(&settings).expect("Invalid velocity_splits (see README.md)");
+    let pad_chords = PadChords::new(&settings).expect("Invalid chords (see README.md)");
+    let mut gates = Some(Gates::new(&settings).expect("Invalid gate times (see README.md)"))
+        .filter(Gates::enabled);
+    let mut midi_activity = settings.midi_activity_indicator.then(ActivityIndicator::new);
+    let mut fault_indicator = FaultIndicator::new(&settings);
+    let mut beat_flash = BeatFlash::new(&settings);
+    // Host tempo last shown on the screen; None: show it once known
+    let mut shown_tempo: Option<f64> = None;
+    let mut scenes = (settings.pad_mode == "scenes").then(|| {
+        let scenes = Scenes::new(settings.scene_channel, &settings.scenes);
+        scenes.paint(&mut lights.lock().unwrap());
+        lights_dirty.store(true, Ordering::SeqCst);
+        scenes
+    });
+    let mut desk = (settings.pad_mode == "desk").then(|| {
+        let desk = Desk::new(&settings.client_name, &settings.desk_pages)
+            .expect("Can't create the desk keyboard (is /dev/uinput writable?)");
+        desk.paint(&mut lights.lock().unwrap());
+        lights_dirty.store(true, Ordering::SeqCst);
+        let [top, bottom] = desk.lines();
+        render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+        screen_dirty.store(true, Ordering::SeqCst);
+        desk
+    });
+    let mut step_record = step_record_button.map(|_| {
+        StepRecord::new(&settings)
+            .expect("Can't create the step record keyboard (is /dev/uinput writable?)")
+    });
+    let mut sequencer = (settings.pad_mode == "sequencer").then(|| {
+        let sequencer = Sequencer::new(&settings);
+        sequencer.paint(&mut lights.lock().unwrap());
+        lights_dirty.store(true, Ordering::SeqCst);
+        sequencer
+    });
+    // Picks up where the last run (or the controller before it was unplugged) left off
+    let session = if settings.session_file.is_empty() {
+        Session::new(&settings)
+    } else {
+        Session::load(&settings.session_file, Session::new(&settings)).unwrap_or_else(|e| {
+            eprintln!("Session not restored: {e}");
+            Session::new(&settings)
+        })
+    };
+    // Set when one of the session's values changes, so it's saved
+    let mut session_changed = false;
+    // The layout toggle switches between notemaps and the keyboard in either pad mode
+    let mut keyboard_active = session.keyboard_active;
+    // The encoder can switch layouts and octaves (`encoder_actions`)
+    let mut notemaps = session.notemaps(&settings);
+    // Bank buttons switch between whole note maps (`bank_buttons`)
+    let mut pad_banks = PadBanks::new(&settings);
+    if let Some(banks) = &mut pad_banks {
+        banks.select(session.pad_bank);
+        if let Some(notes) = banks.notes() {
+            notemaps = notes.to_vec();
+        }
+        banks.paint(&mut lights.lock().unwrap(), &settings);
+        lights_dirty.store(true, Ordering::SeqCst);
+    }
+    // Pads of the `latch_banks` toggle their note
+    let mut pad_latch = PadLatch::new(&settings);
+    // Named on the screen once the boot animation is over
+    let mut restored =
+        (!settings.session_file.is_empty()).then(|| session.lines(pad_banks.is_some()));
+    let mut layout = session.layout;
+    let mut octave = session.octave;
+    publish_notemaps(&notemaps, octave);
+    // The note each pad started with, so its note off matches its note on
+    let mut pad_notes: [Option<u8>; 16] = [None; 16];
+    // And the channel of its keyboard split zone, if it started one
+    let mut pad_zone_channels: [Option<u8>; 16] = [None; 16];
+    let keyboard = matches!(settings.pad_mode.as_str(), "notes" | "keyboard").then(|| {
+        let scale = parse_scale(&settings.keyboard_scale)
+            .expect("Invalid keyboard_scale (see README.md)");
+        Keyboard::new(settings.keyboard_root, scale).with_split(&settings)
+    });
+    if let Some(keyboard) = &keyboard
+        && keyboard_active
+        && keyboard.paint_zones(&mut lights.lock().unwrap(), true)
+    {
+        lights_dirty.store(true, Ordering::SeqCst);
+    }
+    let mut slider_zones = (slider_mode == SliderMode::Zones).then(|| {
+        let zones = SliderZones::new(settings.slider_zones.clone());
+        zones.paint(&mut lights.lock().unwrap());
+        lights_dirty.store(true, Ordering::SeqCst);
+        zones
+    });
+    let mut soft_takeover = (settings.slider_soft_takeover
+        && matches!(slider_mode, SliderMode::Cc | SliderMode::Crossfader))
+    .then(SoftTakeover::new);
+    let mut strum = (slider_mode == SliderMode::Strum).then(|| {
+        // With nothing held, strum the scale's triad over two octaves
+        let fallback = match &keyboard {
+            Some(keyboard) if keyboard_active => [0, 2, 4, 7, 9, 11]
+                .iter()
+                .filter_map(|&pad| keyboard.note(pad))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Strum::new(fallback, settings.pad_channel)
+    });
+    let mut pitch_bend = (slider_mode == SliderMode::PitchBend).then(|| {
+        let return_time = Duration::from_millis(settings.pitchbend_return_ms);
+        PitchBend::new(settings.slider_channel, return_time)
+    });
+    let mut chord_memory = settings.chord_memory.then(|| {
+        ChordMemory::new(
+            Duration::from_millis(settings.chord_capture_window_ms),
+            &settings.chords_file,
+        )
+        .expect("Can't load chords_file")
+    });
+    let mut clock = settings.internal_clock.then(|| InternalClock::new(settings.clock_bpm));
+    let mut note_repeat = settings.note_repeat.then(|| {
+        let rate = note_repeat::parse_rate(&settings.note_repeat_rate)
+            .expect("Invalid note_repeat_rate (see README.md)");
+        NoteRepeat::new(rate, settings.clock_bpm)
+    });
+    let mut tap_tempo = settings.tap_tempo.then(|| TapTempo::new(settings.tap_tempo_taps));
+    let metronome_mode = parse_metronome_mode(&settings.metronome).unwrap();
+    let mut metronome = Metronome::new(
+        metronome_mode,
+        settings.metronome_channel,
+        settings.metronome_notes,
+        settings.metronome_beats_per_bar,
+    );
+    #[cfg(feature = "sampler")]
+    let sampler = if settings.sampler_kit.is_empty() {
+        None
+    } else {
+        match sampler::Sampler::new(&settings.sampler_kit, settings.sampler_volume) {
+            Ok(sampler) => Some(sampler),
+            Err(e) => {
+                eprintln!("Sampler unavailable (continuing): {e}");
+                None
+            }
+        }
+    };
+    #[cfg(not(feature = "sampler"))]
+    if !settings.sampler_kit.is_empty() {
+        eprintln!("sampler_kit is set, but the driver was built without the \"sampler\" feature");
+    }
+    // Patch browsing owns the encoder in its mode; `encoder_actions` may also step banks and
+    // programs
+    let browse_patches = settings.encoder_mode == "patch_browser";
+    let mut expression = (settings.encoder_mode == "expression").then(|| {
+        let smoothing = Duration::from_millis(settings.expression_smoothing_ms);
+        let (channel, cc) = (settings.encoder_channel, settings.expression_cc);
+        let high_resolution = settings.encoder_high_resolution;
+        Expression::new(channel, cc, high_resolution, settings.expression_step, smoothing)
+    });
+    let mut motion = MotionRecorder::new(&settings);
+    let mut patch_browser = if browse_patches || settings.encoder_actions_browse() {
+        let names = if settings.patch_names_file.is_empty() {
+            Default::default()
+        } else {
+            load_patch_names(&settings.patch_names_file).expect("Can't load patch_names_file")
+        };
+        let mut browser = PatchBrowser::new(settings.patch_browser_channel, names);
+        // Restored without sending: pushing the encoder or the next step sends it
+        browser.set_selection(session.bank, session.program);
+        if browse_patches {
+            let [top, bottom] = browser.lines();
+            render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+            screen_dirty.store(true, Ordering::SeqCst);
+        }
+        Some(browser)
+    } else {
+        None
+    };
+    // While set, the encoder types into this text entry; the pad is the scene being named
+    let mut text_entry: Option<(TextEntry, usize)> = None;
+    #[cfg(feature = "mpris")]
+    let mut now_playing = settings.now_playing.then(now_playing::NowPlaying::spawn);
+    #[cfg(not(feature = "mpris"))]
+    if settings.now_playing {
+        eprintln!("now_playing is set, but the driver was built without the \"mpris\" feature");
+    }
+    // When the screen content last changed, for the idle now-playing display
+    #[cfg(feature = "mpris")]
+    let mut screen_updated_at = Instant::now();
+    // While set, the status page covers the screen
+    let mut stats_page: Option<SystemStats> = None;
+    // While set, the timer covers the screen and the pads; Play, Stop and the encoder drive it
+    let mut timer: Option<Timer> = None;
+    // While set, the pads show how often each was hit this session
+    let mut heatmap: Option<Heatmap> = None;
+    let mut menu: Option<Menu> = None;
+    // Set when the loop starts over on a reloaded config, which skips the boot animation
+    let reloaded = reload::take_reloaded();
+    // Startup animation, played while the loop already handles input
+    let mut boot_show = match settings.boot_animation.as_str() {
+        _ if reloaded => None,
+        "off" => None,
+        "frames" => Some(
+            LightShow::from_config(&settings.boot_frames, &lights.lock().unwrap())
+                .expect("Invalid boot_frames (see README.md)"),
+        ),
+        _ => match parse_self_test(&settings.self_test).expect("Invalid self_test") {
+            SelfTest::Off => None,
+            mode => {
+                let quick = mode == SelfTest::Quick;
+                let frames = self_test(&mut screen.lock().unwrap(), &lights.lock().unwrap(), quick);
+                screen_dirty.store(true, Ordering::SeqCst);
+                Some(LightShow::new(frames))
+            }
+        },
+    };
+    // While set, the pads show a metronome flash and light updates are held back
+    let mut lights_flash_until: Option<Instant> = None;
+    // While set, a flash message is on the screen and screen updates are held back
+    let mut screen_layers = ScreenLayers::new();
+    if reloaded {
+        let mut page = Screen::new();
+        render_screen_lines(&mut page, "CONFIG", "RELOADED");
+        let until = Some(Instant::now() + RELOAD_FLASH);
+        screen_layers.show(Layer::Overlay, page, until);
+    }
+
+    let Connections {
+        port,
+        control,
+        merged,
+        launchpad,
+        ..
+    } = connections;
+
+    println!("MIDI CC Mapping:");
+    let buttons = settings.button_cc_range();
+    println!(
+        "  Buttons: CC {}-{} (value 127=press, 0=release)",
+        buttons.start(),
+        buttons.end()
+    );
+    let mut button_map =
+        ButtonMap::new(&settings).expect("Invalid button_mappings (see README.md)");
+    for (button, message) in button_map.remapped(&settings) {
+        let latch = if button_map.latches(button) {
+            ", latching"
+        } else {
+            ""
+        };
+        println!("    {button:?}: {message}{latch}");
+    }
+    println!(
+        "  Encoder: CC {} (relative: {})",
+        settings.encoder_cc,
+        encoder_encoding.describe()
+    );
+    println!("  Slider:  CC {} (0-127)", settings.slider_cc);
+    println!();
+
+    // Optional "night mode": keep all button LEDs faintly lit, unless explicitly set brighter.
+    if settings.backlight_buttons {
+        let mut lights_guard = lights.lock().unwrap();
+        let mut changed = false;
+        for idx in 0..41 {
+            let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
             let Some(button) = button else { continue };
             if !lights_guard.button_has_light(button) {
                 continue;
             }
-            if lights_guard.get_button(button) == Brightness::Off {
-                lights_guard.set_button(button, backlight_brightness);
+            let level = settings.button_off_level(button);
+            if lights_guard.get_button(button) == Brightness::Off && level != Brightness::Off {
+                lights_guard.set_button(button, level);
                 changed = true;
             }
         }
@@ -636,24 +1757,493 @@ fn main_loop(
     }
 
     // Capacitive encoder touch produces a small, spurious delta on this device.
-    // Suppress encoder deltas briefly after EncoderTouch is pressed.
+    // Filter encoder deltas briefly after EncoderTouch is pressed.
     let mut suppress_encoder_until: Option<Instant> = None;
+    // In "first" mode, whether the window still expects its one spurious delta.
+    let mut spurious_delta_pending = false;
+
+    let mut polling = Polling::new(&settings, Instant::now());
+    let mut cpu_audit = settings.cpu_audit.then(|| CpuAudit::new(Instant::now()));
+    let mut config_watch = ConfigWatch::new(&settings, Instant::now());
 
     loop {
-        let size = device.read_timeout(&mut buf, 1)?;
+        if shutdown::requested() {
+            return Ok(());
+        }
+        if let Some(watch) = &mut config_watch {
+            watch.poll(Instant::now());
+        }
+        // A config that loads is swapped in by `run`, which starts the loop over on it
+        if reload::take_request() {
+            match reload::load(&settings) {
+                Ok(reloaded) => {
+                    reload::stash(reloaded);
+                    return Ok(());
+                }
+                Err(e) => {
+                    eprintln!("Config not reloaded: {e}");
+                    faults::raise(Fault::ConfigReload);
+                }
+            }
+        }
+        let timeout = polling.timeout_ms(Instant::now());
+        let size = device.read_timeout(&mut buf, timeout)?;
+        // Everything generated from this report carries the time it was read
+        let now = Timestamp::now();
+        if let Some(audit) = &mut cpu_audit {
+            audit.pass(now.instant(), size < 1, timeout);
+        }
+        // A running clock (or held note repeat, a playing sequencer, beats to flash) needs
+        // every pulse on time, and gates, glides, a bend going back and a looping motion
+        // every step
+        let on_clock = clock.as_ref().is_some_and(|c| c.running())
+            || note_repeat.as_ref().is_some_and(|r| r.active())
+            || sequencer.as_ref().is_some_and(|s| s.playing())
+            || gates.as_ref().is_some_and(|g| g.pending())
+            || expression.as_ref().is_some_and(|e| e.pending())
+            || pitch_bend.as_ref().is_some_and(|b| b.pending())
+            || motion.as_ref().is_some_and(|m| m.playing())
+            || (beat_flash.is_some() && transport::state() == TransportState::Playing)
+            || encoder_hold.as_ref().is_some_and(|h| h.pending())
+            || gestures.as_ref().is_some_and(|g| g.pending(&state.timers));
+        if size > 0 || on_clock {
+            polling.activity(now.instant());
+        }
+
+        if let Some(rx) = &control {
+            while let Ok(cmd) = rx.try_recv() {
+                match cmd {
+                    ControlCommand::Panic => {
+                        let mut leds = lights.lock().unwrap();
+                        let latch = pad_latch.as_mut().map(|latch| (latch, &mut *leds));
+                        send_panic(port, now, &mut aftertouch_limiter, note_repeat.as_mut(), latch);
+                        drop(leds);
+                        lights_dirty.store(true, Ordering::SeqCst);
+                        let until = Some(now.instant() + PANIC_FLASH);
+                        screen_layers.show_text(Layer::Alert, "PANIC", until);
+                    }
+                    ControlCommand::ResetSession => session::request_reset(),
+                    ControlCommand::StageLight => {
+                        stage_light_on = !stage_light_on;
+                        stage_light::show(stage_light_on, &mut screen_layers, now);
+                        // The timer and heatmap write the pads themselves
+                        if stage_light_on && (timer.take().is_some() | heatmap.take().is_some()) {
+                            screen_layers.dismiss(Layer::Page);
+                        }
+                        lights_dirty.store(true, Ordering::SeqCst);
+                    }
+                    // Answered by the control socket itself
+                    ControlCommand::Transport
+                    | ControlCommand::PadStats
+                    | ControlCommand::PadStatsReset => {}
+                }
+            }
+        }
+
+        if session::take_reset() {
+            let fresh = Session::new(&settings);
+            keyboard_active = fresh.keyboard_active;
+            notemaps = fresh.notemaps(&settings);
+            if let Some(banks) = &mut pad_banks {
+                banks.select(fresh.pad_bank);
+                if let Some(notes) = banks.notes() {
+                    notemaps = notes.to_vec();
+                }
+                banks.paint(&mut lights.lock().unwrap(), &settings);
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+            layout = fresh.layout;
+            octave = fresh.octave;
+            publish_notemaps(&notemaps, octave);
+            if let Some(browser) = &mut patch_browser {
+                browser.set_selection(fresh.bank, fresh.program);
+            }
+            session_changed = true;
+            println!("{} Session reset", now);
+            let until = Some(now.instant() + LAYOUT_FLASH);
+            screen_layers.show_text(Layer::Overlay, "SESSION RESET", until);
+        }
+        if std::mem::take(&mut session_changed) {
+            let (bank, program) = patch_browser.as_ref().map_or((0, 0), |b| b.selection());
+            let session = Session {
+                layout: layout.clone(),
+                keyboard_active,
+                pad_bank: pad_banks.as_ref().map_or(0, |b| b.active()),
+                octave,
+                bank,
+                program,
+            };
+            session.save(&settings.session_file);
+        }
+
+        // The settings menu, opened or closed by its chord or a command
+        if menu::take_toggle() {
+            menu = match menu {
+                Some(_) => {
+                    screen_layers.dismiss(Layer::Page);
+                    None
+                }
+                None => {
+                    let menu = Menu::new(pad_banks.is_some());
+                    let page = menu.screen(&settings, pad_banks.as_ref());
+                    screen_layers.show(Layer::Page, page, None);
+                    // The menu takes the page, and the others' pads go back to normal
+                    stats_page = None;
+                    if timer.take().is_some() | heatmap.take().is_some() {
+                        lights_dirty.store(true, Ordering::SeqCst);
+                    }
+                    Some(menu)
+                }
+            };
+            println!(
+                "{} Menu {}",
+                now,
+                if menu.is_some() { "open" } else { "closed" }
+            );
+        }
+
+        // Parameters the host (or the settings menu) set, and the values the host asked for
+        if params::take_changed() {
+            velocity_map = VelocityMap::with_curve(&settings, params::velocity_curve(&settings))
+                .expect("Velocity curves come from a fixed list");
+            aftertouch_mode = parse_aftertouch_mode(params::aftertouch(&settings))
+                .expect("Aftertouch modes come from a fixed list");
+            let backlight = params::value(Param::Backlight, &settings) == 1;
+            if backlight != backlight_on {
+                backlight_on = backlight;
+                switch_backlight(&mut lights.lock().unwrap(), &settings, backlight);
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+        }
+        for param in params::take_requests() {
+            let value = params::value(param, &settings);
+            let [a, b, c] = SYSEX_MANUFACTURER;
+            port.send(&[0xF0, a, b, c, SYSEX_CMD_PARAM_VALUE, param as u8, value, 0xF7], now);
+        }
+
+        if transport::take_changed() {
+            let state = transport::state();
+            println!("{} Transport {}", now, state.name());
+            if settings.transport_feedback {
+                let until = Some(now.instant() + TRANSPORT_FLASH);
+                let name = state.name().to_ascii_uppercase();
+                screen_layers.show_text(Layer::Overlay, &name, until);
+                if let Some(flash) = &mut beat_flash {
+                    flash.transport_painted();
+                }
+            }
+            shown_tempo = None;
+        }
+        // The host tempo, once measured after the transport changed, then on every change
+        if settings.host_tempo_display
+            && transport::state() == TransportState::Playing
+            && let Some(bpm) = transport::bpm()
+            && shown_tempo.is_none_or(|shown| (shown - bpm).abs() >= 0.5)
+        {
+            shown_tempo = Some(bpm);
+            println!("{} Host tempo {:.1} BPM", now, bpm);
+            let until = Some(now.instant() + TRANSPORT_FLASH);
+            screen_layers.show_text(Layer::Overlay, &format!("{bpm:.0} BPM"), until);
+        }
+        if let Some(flash) = &mut beat_flash
+            && flash.due(now.instant())
+            && flash.flash(&mut lights.lock().unwrap(), now.instant())
+        {
+            lights_dirty.store(true, Ordering::SeqCst);
+        }
+
+        // Forward the merged hardware input, stamped with when it was received
+        if let Some(rx) = &merged {
+            while let Ok((at, message)) = rx.try_recv() {
+                // The other controller is being played: keep up with it
+                polling.activity(now.instant());
+                port.send(&message, at);
+                if let Some(chords) = &mut chord_memory
+                    && message.len() == 3
+                    && message[0] & 0xF0 == 0x90
+                    && message[2] > 0
+                {
+                    chords.observe_note(message[1], at.instant());
+                }
+            }
+        }
+
+        // Internal clock pulses past the count-in, for note repeat and the sequencer
+        let mut clock_pulses = None;
+        if let Some(clock) = &mut clock {
+            let count_in = clock.count_in_pulses();
+            let pulses = clock.poll(now.instant());
+            if clock.running() {
+                let past = |pulse: u64| pulse.max(count_in) - count_in;
+                clock_pulses = Some(past(pulses.start)..past(pulses.end));
+            }
+            for pulse in pulses {
+                if pulse < count_in {
+                    if pulse.is_multiple_of(PPQN) {
+                        // Count down the beats of each bar: 4-3-2-1
+                        let beat = pulse / PPQN;
+                        let bar_beats = settings.metronome_beats_per_bar as u64;
+                        let left = bar_beats - beat % bar_beats;
+                        if metronome_mode != MetronomeMode::Off {
+                            metronome.beat(beat, port, now);
+                        }
+                        let color =
+                            if left == bar_beats { PadColors::White } else { PadColors::Red };
+                        flash_pads(device, &lights.lock().unwrap(), color)?;
+                        lights_flash_until = Some(now.instant() + METRONOME_FLASH);
+                        render_screen_big(&mut screen.lock().unwrap(), &left.to_string());
+                        screen_dirty.store(true, Ordering::SeqCst);
+                    }
+                    continue;
+                }
+                if pulse == count_in {
+                    if settings.clock_send_midi {
+                        port.send(&[0xFA], now);
+                    }
+                    if count_in > 0 {
+                        let status = format!("{} BPM", clock.bpm());
+                        render_screen_lines(&mut screen.lock().unwrap(), "CLOCK", &status);
+                        screen_dirty.store(true, Ordering::SeqCst);
+                    }
+                }
+                if settings.clock_send_midi {
+                    port.send(&[0xF8], now);
+                }
+                if pulse.is_multiple_of(PPQN) && metronome_mode != MetronomeMode::Off {
+                    let downbeat = metronome.beat((pulse - count_in) / PPQN, port, now);
+                    if settings.metronome_flash {
+                        let color = if downbeat { PadColors::White } else { PadColors::Blue };
+                        flash_pads(device, &lights.lock().unwrap(), color)?;
+                        lights_flash_until = Some(now.instant() + METRONOME_FLASH);
+                    }
+                }
+            }
+        }
+
+        if let Some(repeat) = &mut note_repeat {
+            repeat.poll(clock_pulses.clone(), port, now);
+        }
+
+        // Step the sequencer, chasing the playhead on the pads
+        if let Some(sequencer) = &mut sequencer
+            && sequencer.poll(clock_pulses, port, now)
+        {
+            sequencer.paint(&mut lights.lock().unwrap());
+            lights_dirty.store(true, Ordering::SeqCst);
+        }
+
+        if let Some(expression) = &mut expression {
+            expression.poll(port, now);
+        }
+
+        // Loop the recorded slider and encoder motion
+        if let Some(motion) = &mut motion {
+            motion.poll(port, now);
+        }
+
+        // Buttons held long enough for their long press
+        if let Some(gestures) = &mut gestures {
+            while let Some(command) = gestures.poll(&state.timers, port, now) {
+                let (limiter, repeat) = (&mut aftertouch_limiter, note_repeat.as_mut());
+                let mut leds = lights.lock().unwrap();
+                let latch = pad_latch.as_mut().map(|latch| (latch, &mut *leds));
+                run_command(command, port, now, limiter, repeat, latch, &mut screen_layers);
+                lights_dirty.store(true, Ordering::SeqCst);
+            }
+        }
+
+        // A push on the encoder held long enough becomes a hold
+        if let Some(hold) = &mut encoder_hold {
+            hold.poll(port, now);
+        }
+
+        // Note offs held back to the minimum gate, or ending notes at the maximum
+        if let Some(gates) = &mut gates {
+            gates.poll(port, now);
+        }
+
+        // Glide the pitch bend back to center after the strip was let go
+        if let Some(pitch_bend) = &mut pitch_bend
+            && let Some(raw) = pitch_bend.poll(port, now)
+        {
+            slider::paint_bipolar(&mut lights.lock().unwrap(), raw);
+            lights_dirty.store(true, Ordering::SeqCst);
+        }
+
+        if let Some(chords) = &mut chord_memory
+            && let Some([top, bottom]) = chords.poll(now.instant())
+        {
+            render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+            screen_dirty.store(true, Ordering::SeqCst);
+        }
 
         // Check if MIDI input callback flagged lights or screen as dirty
-        let lights_changed = lights_dirty.swap(false, Ordering::SeqCst);
-        let screen_changed = screen_dirty.swap(false, Ordering::SeqCst);
+        let mut lights_changed = lights_dirty.swap(false, Ordering::SeqCst);
+        if let Some(until) = lights_flash_until {
+            if now.instant() < until {
+                // Keep the flash up; the shared lights are written once it's over
+                lights_changed = false;
+            } else {
+                lights_flash_until = None;
+                lights_changed = true;
+            }
+        }
+        if let Some(show) = &mut boot_show {
+            // The regular lights come back once the show is over
+            match show.poll(now.instant()) {
+                ShowStep::Frame(frame) => {
+                    frame.write(device)?;
+                    lights_changed = false;
+                }
+                ShowStep::Hold => lights_changed = false,
+                ShowStep::Done => {
+                    boot_show = None;
+                    lights_changed = true;
+                }
+            }
+        }
+        if boot_show.is_none()
+            && let Some([top, bottom]) = restored.take()
+        {
+            println!("{} Session: {} {}", now, top, bottom);
+            let mut page = Screen::new();
+            render_screen_lines(&mut page, &top, &bottom);
+            let until = Some(now.instant() + SESSION_FLASH);
+            screen_layers.show(Layer::Overlay, page, until);
+        }
+        // The shared screen only reaches the device while no layer covers it
+        let mut screen_changed = screen_dirty.swap(false, Ordering::SeqCst);
+        // The host is sending feedback
+        if lights_changed || screen_changed {
+            polling.activity(now.instant());
+        }
+        if let Some(stats) = &mut stats_page
+            && let Some([top, bottom]) = stats.poll(now.instant())
+        {
+            let mut page = Screen::new();
+            render_screen_lines(&mut page, &top, &bottom);
+            screen_layers.show(Layer::Page, page, None);
+        }
+        if let Some(timer) = &mut timer {
+            // The regular lights are written once the timer is closed
+            lights_changed = false;
+            let mut overlay = lights.lock().unwrap().clone();
+            if let Some([top, bottom]) = timer.poll(now.instant(), &mut overlay) {
+                overlay.write(device)?;
+                let mut page = Screen::new();
+                render_screen_lines(&mut page, &top, &bottom);
+                screen_layers.show(Layer::Page, page, None);
+            }
+        }
+        if let Some(heatmap) = &mut heatmap {
+            lights_changed = false;
+            let mut overlay = lights.lock().unwrap().clone();
+            if let Some([top, bottom]) = heatmap.poll(&mut overlay) {
+                overlay.write(device)?;
+                let mut page = Screen::new();
+                render_screen_lines(&mut page, &top, &bottom);
+                screen_layers.show(Layer::Page, page, None);
+            }
+        }
+        // Nothing new on the screen for a while: show what the desktop is playing
+        #[cfg(feature = "mpris")]
+        if let Some(now_playing) = &mut now_playing
+            && stats_page.is_none()
+            && timer.is_none()
+            && heatmap.is_none()
+        {
+            let idle = Duration::from_secs(settings.now_playing_idle_secs);
+            if screen_changed {
+                screen_updated_at = now.instant();
+                now_playing.hide();
+                screen_layers.dismiss(Layer::Page);
+            } else if !matches!(screen_layers.top(), Some(Layer::Overlay | Layer::Alert))
+                && now.instant() >= screen_updated_at + idle
+                && let Some(line) = now_playing.frame(now.instant())
+            {
+                if line.is_empty() {
+                    // Playback stopped: bring back the regular content
+                    screen_layers.dismiss(Layer::Page);
+                } else {
+                    screen_layers.show_text(Layer::Page, &line, None);
+                }
+            }
+        }
+
+        // The controller script stopped pinging: say so, and drop the lights it left behind
+        if let Some(watchdog) = &mut watchdog {
+            match watchdog.poll(now.instant()) {
+                Some(true) => {
+                    println!("{} Host lost (no ping for {} s)", now, settings.host_watchdog_secs);
+                    standalone_lights(&mut lights.lock().unwrap(), &settings);
+                    lights_changed = true;
+                    // The warning stays until pings come back
+                    screen_layers.show_text(Layer::Alert, "HOST LOST", None);
+                }
+                Some(false) => {
+                    println!("{} Host back", now);
+                    screen_layers.dismiss(Layer::Alert);
+                }
+                None => {}
+            }
+        }
+        // A host session went silent: the next one's lights show
+        let shown = {
+            let mut lights = lights.lock().unwrap();
+            host_sessions::poll(now.instant(), host_session_timeout, &mut lights)
+        };
+        if let Some(shown) = shown {
+            println!("{} Host session {:?} shown", now, shown);
+            lights_changed = true;
+        }
+
+        // Recoverable errors: their code on the screen, and the fault button blinking
+        for fault in fault_indicator.take(now.instant()) {
+            if settings.fault_display {
+                let mut fault_screen = Screen::new();
+                let code = format!("ERROR {}", fault.code());
+                render_screen_lines(&mut fault_screen, &code, fault.label());
+                screen_layers.show(Layer::Overlay, fault_screen, Some(now.instant() + FAULT_FLASH));
+            }
+        }
+        if fault_indicator.blinking()
+            && fault_indicator.blink(&mut lights.lock().unwrap(), now.instant())
+        {
+            lights_changed = true;
+        }
+
+        // Blink the MIDI activity corner of the shared screen
+        if let Some(activity) = &mut midi_activity
+            && activity.poll(now.instant())
+        {
+            screen_changed = true;
+        }
+        if screen_layers.poll(now.instant()) {
+            screen_changed = true;
+        }
+        if screen_layers.covered() {
+            screen_changed = false;
+        }
+        screen_layers.flush(device)?;
 
         if size < 1 {
+            port.flush();
             // No HID data, but still write lights/screen if MIDI input changed them
             if lights_changed {
                 let lights_guard = lights.lock().unwrap();
-                lights_guard.write(device)?;
+                write_lights(
+                    device,
+                    &lights_guard,
+                    pressure_meter.as_ref(),
+                    shift_layer.as_ref(),
+                    stage_light_on,
+                )?;
             }
             if screen_changed {
-                let screen_guard = screen.lock().unwrap();
+                let mut screen_guard = screen.lock().unwrap();
+                if let Some(activity) = &midi_activity {
+                    activity.draw(&mut screen_guard);
+                }
                 screen_guard.write(device)?;
             }
             continue;
@@ -681,14 +2271,359 @@ fn main_loop(
                     // Detect state change
                     if is_pressed != was_pressed {
                         state.buttons[idx] = is_pressed;
+                        state.timers.button(button, is_pressed, now.instant());
+                        // Gestures go off on top of whatever the button does
+                        if let Some(gestures) = &mut gestures
+                            && let Some(command) =
+                                gestures.button(&mut state.timers, button, is_pressed, port, now)
+                        {
+                            let (limiter, repeat) = (&mut aftertouch_limiter, note_repeat.as_mut());
+                            let latch = pad_latch.as_mut().map(|latch| (latch, &mut *lights_guard));
+                            let layers = &mut screen_layers;
+                            run_command(command, port, now, limiter, repeat, latch, layers);
+                            changed_lights = true;
+                        }
+
+                        // Send the button's MIDI CC (or its mapped note)
+                        let message = button_map.message(button);
+                        if let Some(menu) = &mut menu
+                            && button == Buttons::EncoderPress
+                        {
+                            // Turning the encoder pushed edits the menu page's value
+                            menu.set_editing(is_pressed);
+                            let page = menu.screen(&settings, pad_banks.as_ref());
+                            screen_layers.show(Layer::Page, page, None);
+                            continue;
+                        }
+                        if let Some((entry, pad)) = &mut text_entry
+                            && button == Buttons::EncoderPress
+                        {
+                            // Pushing the encoder types (Shift + push deletes)
+                            if is_pressed {
+                                let lines = if state.buttons[Buttons::Shift as usize] {
+                                    entry.delete();
+                                    entry.lines()
+                                } else if let Some(name) = entry.push() {
+                                    let scenes = scenes.as_mut().unwrap();
+                                    scenes.set_name(*pad, name);
+                                    let lines = scenes.lines(*pad);
+                                    text_entry = None;
+                                    lines
+                                } else {
+                                    entry.lines()
+                                };
+                                let [top, bottom] = lines;
+                                render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                                screen_dirty.store(true, Ordering::SeqCst);
+                            }
+                            continue;
+                        }
+                        if let Some(browser) = &patch_browser
+                            && browse_patches
+                            && button == Buttons::EncoderPress
+                        {
+                            // Pushing the encoder confirms the patch instead
+                            if is_pressed {
+                                browser.select(port, now);
+                            }
+                            continue;
+                        }
+                        if let Some(banks) = &mut pad_banks
+                            && let Some(bank) = banks.bank_of(button)
+                            && !state.buttons[Buttons::Shift as usize]
+                        {
+                            // Bank buttons only switch banks (Shift + button works as usual)
+                            if is_pressed && banks.select(bank) {
+                                notemaps = match banks.notes() {
+                                    Some(notes) => notes.to_vec(),
+                                    None => session::layout_notes(&layout, &settings),
+                                };
+                                publish_notemaps(&notemaps, octave);
+                                banks.paint(&mut lights_guard, &settings);
+                                changed_lights = true;
+                                session_changed = true;
+                                let name = format!("BANK {}", PadBanks::name(bank));
+                                println!("{} Pad {}", now, name.to_ascii_lowercase());
+                                let until = Some(now.instant() + LAYOUT_FLASH);
+                                screen_layers.show_text(Layer::Overlay, &name, until);
+                            }
+                            continue;
+                        }
+                        if let Some(timer) = &mut timer
+                            && matches!(button, Buttons::Play | Buttons::Stop)
+                            && !state.buttons[Buttons::Shift as usize]
+                        {
+                            // Play starts/pauses the timer, Stop resets it (Shift + Stop still
+                            // panics)
+                            if is_pressed && button == Buttons::Play {
+                                timer.toggle(now.instant());
+                            } else if is_pressed {
+                                timer.reset();
+                            }
+                            continue;
+                        }
+                        if let Some(clock) = &mut clock
+                            && button == Buttons::Play
+                        {
+                            // Play starts/stops the internal clock instead
+                            if is_pressed {
+                                // Start goes out once the count-in is over
+                                let status = if clock.running() {
+                                    if settings.clock_send_midi && clock.past_count_in() {
+                                        port.send(&[0xFC], now);
+                                    }
+                                    clock.stop();
+                                    metronome.release(port, now);
+                                    "STOP".to_string()
+                                } else {
+                                    let count_in = settings.count_in_bars as u64
+                                        * settings.metronome_beats_per_bar as u64;
+                                    clock.start(now.instant(), count_in);
+                                    format!("{} BPM", clock.bpm())
+                                };
+                                println!("{} Internal clock {}", now, status);
+                                render_screen_lines(&mut screen.lock().unwrap(), "CLOCK", &status);
+                                screen_dirty.store(true, Ordering::SeqCst);
+                            }
+                            continue;
+                        }
+                        if let Some(desk) = &mut desk
+                            && button == Buttons::Group
+                        {
+                            // Group steps through the macro pages (Shift + Group goes back)
+                            if is_pressed {
+                                let back = state.buttons[Buttons::Shift as usize];
+                                desk.switch_page(if back { -1 } else { 1 });
+                                desk.paint(&mut lights_guard);
+                                changed_lights = true;
+                                let [top, bottom] = desk.lines();
+                                render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                                screen_dirty.store(true, Ordering::SeqCst);
+                            }
+                            continue;
+                        }
+                        if let Some(chords) = &mut chord_memory
+                            && button == Buttons::Chords
+                        {
+                            // Chords arms (or cancels) chord capture instead
+                            if is_pressed {
+                                let [top, bottom] = chords.toggle_capture();
+                                render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                                screen_dirty.store(true, Ordering::SeqCst);
+                            }
+                            continue;
+                        }
+                        if let Some(tap_tempo) = &mut tap_tempo
+                            && let Some(clock) = &mut clock
+                            && tap_tempo_button == Some(button)
+                        {
+                            // The tap button sets the clock's tempo instead
+                            if is_pressed {
+                                tap_clock(clock, tap_tempo, &settings, now, &screen, &screen_dirty);
+                            }
+                            continue;
+                        }
+                        if let Some(step_record) = &mut step_record
+                            && step_record_button == Some(button)
+                        {
+                            // The step record button arms step recording instead
+                            if is_pressed {
+                                step_record.toggle();
+                                let armed = step_record.armed();
+                                let status = if armed { "on" } else { "off" };
+                                println!("{} Step record {}", now, status);
+                                if armed {
+                                    lights_guard.set_button(button, Brightness::Bright);
+                                    let [top, bottom] = step_record.lines();
+                                    let mut page = Screen::new();
+                                    render_screen_lines(&mut page, &top, &bottom);
+                                    screen_layers.show(Layer::Page, page, None);
+                                } else {
+                                    let off = settings.button_off_level(button);
+                                    lights_guard.set_button(button, off);
+                                    screen_layers.dismiss(Layer::Page);
+                                }
+                                changed_lights = true;
+                            }
+                            continue;
+                        }
+                        if let Some(repeat) = &mut note_repeat
+                            && button == Buttons::NoteRepeat
+                        {
+                            // Note Repeat retriggers the held pads while it's down instead
+                            repeat.set_active(is_pressed, now.instant());
+                            let status = if is_pressed { repeat.rate_name() } else { "OFF" };
+                            println!("{} Note repeat {}", now, status);
+                            render_screen_lines(&mut screen.lock().unwrap(), "REPEAT", status);
+                            screen_dirty.store(true, Ordering::SeqCst);
+                            continue;
+                        }
+                        if let Some(layer) = &mut shift_layer {
+                            if button == Buttons::Shift && layer.set_active(is_pressed) {
+                                changed_lights = true;
+                            }
+                            if let Some(command) = layer.button(button, is_pressed, port, now) {
+                                // The Shift layer sends this one instead of its CC
+                                if let Some(command) = command {
+                                    let limiter = &mut aftertouch_limiter;
+                                    let repeat = note_repeat.as_mut();
+                                    let latch =
+                                        pad_latch.as_mut().map(|latch| (latch, &mut *lights_guard));
+                                    let layers = &mut screen_layers;
+                                    run_command(command, port, now, limiter, repeat, latch, layers);
+                                }
+                                changed_lights = true;
+                                continue;
+                            }
+                        }
+                        let muted = settings.output_suppress_buttons
+                            || (button == Buttons::EncoderTouch
+                                && settings.output_suppress_encoder_touch);
+                        // With encoder_hold_ms the push counts when it's let go, if it was
+                        // a click
+                        let presses: &[bool] = match &mut encoder_hold {
+                            Some(hold) if button == Buttons::EncoderPress => {
+                                if is_pressed {
+                                    hold.push(now.instant());
+                                    &[]
+                                } else if hold.release(port, now) {
+                                    &[true, false]
+                                } else {
+                                    &[]
+                                }
+                            }
+                            _ if is_pressed => &[true],
+                            _ => &[false],
+                        };
+                        for &pressed in presses {
+                            let on = button_map.press(button, pressed);
+                            if let Some(on) = on
+                                && button_map.latches(button)
+                            {
+                                // A latching button's LED shows whether it's on
+                                let level = if on {
+                                    Brightness::Bright
+                                } else {
+                                    settings.button_off_level(button)
+                                };
+                                lights_guard.set_button(button, level);
+                                changed_lights = true;
+                            }
+                            if !muted && let Some(on) = on {
+                                port.send(&message.bytes(on), now);
+                                if pressed {
+                                    println!("{} Button {:?} pressed -> {}", now, button, message);
+                                }
+                            }
+                        }
+
+                        if is_pressed
+                            && panic_chord.contains(&button)
+                            && panic_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            let repeat = note_repeat.as_mut();
+                            let latch = pad_latch.as_mut().map(|latch| (latch, &mut *lights_guard));
+                            send_panic(port, now, &mut aftertouch_limiter, repeat, latch);
+                            changed_lights = true;
+                            let until = Some(now.instant() + PANIC_FLASH);
+                            screen_layers.show_text(Layer::Alert, "PANIC", until);
+                        }
+
+                        if is_pressed
+                            && stats_chord.contains(&button)
+                            && stats_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            stats_page = match stats_page {
+                                Some(_) => {
+                                    screen_layers.dismiss(Layer::Page);
+                                    None
+                                }
+                                None => Some(SystemStats::new(now.instant())),
+                            };
+                            menu = None;
+                            // Both paint the pads: bring the regular lights back
+                            let closed_timer = timer.take().is_some();
+                            if heatmap.take().is_some() || closed_timer {
+                                lights_dirty.store(true, Ordering::SeqCst);
+                            }
+                        }
+
+                        if is_pressed
+                            && timer_chord.contains(&button)
+                            && timer_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            timer = match timer {
+                                Some(_) => {
+                                    screen_layers.dismiss(Layer::Page);
+                                    lights_dirty.store(true, Ordering::SeqCst);
+                                    None
+                                }
+                                None => Some(Timer::new(settings.timer_minutes)),
+                            };
+                            stats_page = None;
+                            heatmap = None;
+                            menu = None;
+                        }
+
+                        if is_pressed
+                            && pad_stats_chord.contains(&button)
+                            && pad_stats_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            if heatmap.take().is_some() {
+                                screen_layers.dismiss(Layer::Page);
+                            } else {
+                                heatmap = Some(Heatmap::new());
+                            }
+                            lights_dirty.store(true, Ordering::SeqCst);
+                            stats_page = None;
+                            timer = None;
+                            menu = None;
+                        }
 
-                        // Send MIDI CC for button
-                        let cc = BUTTON_CC_OFFSET + idx as u8;
-                        let value = if is_pressed { 127 } else { 0 };
-                        send_cc(port, cc, value);
+                        if is_pressed
+                            && menu_chord.contains(&button)
+                            && menu_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            menu::request_toggle();
+                        }
 
-                        if is_pressed {
-                            println!("Button {:?} pressed -> CC {} = 127", button, cc);
+                        if is_pressed
+                            && stage_light_chord.contains(&button)
+                            && stage_light_chord.iter().all(|b| state.buttons[*b as usize])
+                        {
+                            stage_light_on = !stage_light_on;
+                            stage_light::show(stage_light_on, &mut screen_layers, now);
+                            if stage_light_on && (timer.take().is_some() | heatmap.take().is_some())
+                            {
+                                screen_layers.dismiss(Layer::Page);
+                            }
+                            changed_lights = true;
+                        }
+
+                        if is_pressed
+                            && layout_toggle_chord.contains(&button)
+                            && layout_toggle_chord.iter().all(|b| state.buttons[*b as usize])
+                            && let Some(keyboard) = &keyboard
+                        {
+                            // Show what the pads play now before anything gets hit
+                            keyboard_active = !keyboard_active;
+                            session_changed = true;
+                            changed_lights |=
+                                keyboard.paint_zones(&mut lights_guard, keyboard_active);
+                            let labels: Vec<String> = (0..16)
+                                .map(|pad| {
+                                    let keyboard = Some(keyboard).filter(|_| keyboard_active);
+                                    pad_note(keyboard, &notemaps, octave, pad)
+                                        .map(note_name)
+                                        .unwrap_or_default()
+                                })
+                                .collect();
+                            let name = if keyboard_active { "keyboard" } else { "notes" };
+                            println!("{} Pad layout {}", now, name);
+                            let mut grid = Screen::new();
+                            render_pad_grid(&mut grid, &labels, pad_transform);
+                            let until = Some(now.instant() + LAYOUT_FLASH);
+                            screen_layers.show(Layer::Overlay, grid, until);
                         }
 
                         // Encoder touch can produce a spurious encoder delta in the same HID packet.
@@ -703,8 +2638,9 @@ fn main_loop(
                 }
             }
 
-            if encoder_touch_just_pressed {
-                suppress_encoder_until = Some(Instant::now() + Duration::from_millis(120));
+            if encoder_touch_just_pressed && encoder_touch_filter != EncoderTouchFilter::Off {
+                suppress_encoder_until = Some(now.instant() + encoder_touch_window);
+                spurious_delta_pending = true;
             }
 
             // Encoder
@@ -712,94 +2648,669 @@ fn main_loop(
             // IMPORTANT: `buf[7] & 0x0f` is an absolute 4-bit position (0..15), not a delta.
             // We compute delta with wrap-around, mapping to [-8..+7].
             let encoder_raw = buf[7];
-            let suppressed = suppress_encoder_until
-                .map(|until| Instant::now() < until)
+            let in_touch_window = suppress_encoder_until
+                .map(|until| now.instant() < until)
                 .unwrap_or(false);
             let cur_pos = encoder_raw & 0x0f;
 
-            // If touch just engaged or we're in the suppression window, just resync position
-            // and do not emit CC movement.
-            if encoder_touch_just_pressed || suppressed {
-                state.encoder_pos = Some(cur_pos);
-            } else if let Some(prev_pos) = state.encoder_pos {
+            if let Some(prev_pos) = state.encoder_pos {
                 let diff = cur_pos.wrapping_sub(prev_pos) & 0x0f; // 0..15
                 // Map 0..15 to signed -8..+7
                 let delta: i8 = if diff < 8 { diff as i8 } else { (diff as i8) - 16 };
-                if delta != 0 {
-                    // Convert to relative MIDI CC: 64 + delta (centered at 64)
-                    let cc_value = (64i16 + delta as i16).clamp(0, 127) as u8;
-                    send_cc(port, ENCODER_CC, cc_value);
-                    println!("Encoder turn {} -> CC {} = {}", delta, ENCODER_CC, cc_value);
+
+                // Inside the touch window either drop everything ("all"), or only the first
+                // movement if it is small enough to be the touch artifact ("first").
+                // A larger first turn is intentional, so it ends the window early.
+                let suppressed = delta != 0
+                    && in_touch_window
+                    && match encoder_touch_filter {
+                        EncoderTouchFilter::All => true,
+                        EncoderTouchFilter::First if spurious_delta_pending => {
+                            spurious_delta_pending = false;
+                            delta.unsigned_abs() <= settings.encoder_touch_max_delta
+                        }
+                        _ => false,
+                    };
+
+                if delta != 0
+                    && !suppressed
+                    && let Some(hold) = &mut encoder_hold
+                {
+                    hold.turned();
+                }
+                if delta != 0 && !suppressed && let Some(menu) = &mut menu {
+                    let bank = menu.turn(delta as i32, &settings, pad_banks.as_ref());
+                    if let Some(bank) = bank
+                        && let Some(banks) = &mut pad_banks
+                        && banks.select(bank)
+                    {
+                        notemaps = match banks.notes() {
+                            Some(notes) => notes.to_vec(),
+                            None => session::layout_notes(&layout, &settings),
+                        };
+                        publish_notemaps(&notemaps, octave);
+                        banks.paint(&mut lights_guard, &settings);
+                        changed_lights = true;
+                        session_changed = true;
+                    }
+                    let page = menu.screen(&settings, pad_banks.as_ref());
+                    screen_layers.show(Layer::Page, page, None);
+                } else if delta != 0 && !suppressed && let Some(timer) = &mut timer {
+                    timer.adjust(delta as i32);
+                } else if delta != 0
+                    && !suppressed
+                    && let Some(repeat) = &mut note_repeat
+                    && repeat.active()
+                {
+                    // Turning while Note Repeat is held picks the rate
+                    let rate = repeat.turn(delta as i32);
+                    println!("{} Note repeat rate {}", now, rate);
+                    render_screen_lines(&mut screen.lock().unwrap(), "REPEAT", rate);
+                    screen_dirty.store(true, Ordering::SeqCst);
+                } else if delta != 0 && !suppressed && let Some((entry, _)) = &mut text_entry {
+                    entry.turn(delta as i32);
+                    let [top, bottom] = entry.lines();
+                    render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                    screen_dirty.store(true, Ordering::SeqCst);
+                } else if delta != 0
+                    && !suppressed
+                    && let Some(action) = bound_action(&settings.encoder_actions, |b| {
+                        state.buttons[b as usize]
+                    })
+                    && action != EncoderAction::Cc
+                {
+                    session_changed = true;
+                    let lines = match action {
+                        EncoderAction::Octave => {
+                            octave = (octave as i32 + delta as i32).clamp(-4, 4) as i8;
+                            publish_notemaps(&notemaps, octave);
+                            ["OCTAVE".to_string(), format!("{octave:+}")]
+                        }
+                        EncoderAction::Layout => {
+                            layout = layouts::step_layout(&layout, delta as i32).to_string();
+                            notemaps = layouts::layout_notemaps(&layout).unwrap().to_vec();
+                            publish_notemaps(&notemaps, octave);
+                            ["LAYOUT".to_string(), layout.to_ascii_uppercase()]
+                        }
+                        EncoderAction::Bank | EncoderAction::Program => {
+                            let browser = patch_browser.as_mut().unwrap();
+                            if action == EncoderAction::Bank {
+                                browser.scroll_bank(delta as i32);
+                            } else {
+                                browser.scroll_program(delta as i32);
+                            }
+                            browser.select(port, now);
+                            browser.lines()
+                        }
+                        EncoderAction::Cc => unreachable!(),
+                    };
+                    println!("{} Encoder {:?} {} -> {}", now, action, delta, lines.join(" "));
+                    render_screen_lines(&mut screen.lock().unwrap(), &lines[0], &lines[1]);
+                    screen_dirty.store(true, Ordering::SeqCst);
+                } else if delta != 0
+                    && !suppressed
+                    && browse_patches
+                    && let Some(browser) = &mut patch_browser
+                {
+                    if state.buttons[Buttons::Shift as usize] {
+                        browser.scroll_bank(delta as i32);
+                    } else {
+                        browser.scroll_program(delta as i32);
+                    }
+                    session_changed = true;
+                    let [top, bottom] = browser.lines();
+                    render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                    screen_dirty.store(true, Ordering::SeqCst);
+                } else if delta != 0 && !suppressed && let Some(expression) = &mut expression {
+                    // Only a hand on the encoder moves the pedal, a brushed knob doesn't
+                    if state.buttons[Buttons::EncoderTouch as usize] {
+                        let level = expression.turn(delta as i32);
+                        println!("{} Encoder expression {} -> {}", now, delta, level);
+                        let value = level.to_string();
+                        render_screen_lines(&mut screen.lock().unwrap(), "EXPRESSION", &value);
+                        screen_dirty.store(true, Ordering::SeqCst);
+                    }
+                } else if delta != 0 && !suppressed && !settings.output_suppress_encoder {
+                    let delta = encoder_acceleration.apply(delta as i32, now.instant());
+                    let cc_value = encoder_encoding.encode(delta);
+                    // Holding the secondary button turns a second parameter
+                    let cc = match encoder_secondary_button {
+                        Some(button) if state.buttons[button as usize] => {
+                            settings.encoder_secondary_cc
+                        }
+                        _ => settings.encoder_cc,
+                    };
+                    send_cc(port, now, settings.encoder_channel, cc, cc_value);
+                    if let Some(motion) = &mut motion {
+                        let message = [0xB0 | settings.encoder_channel, cc, cc_value];
+                        motion.observe_cc(&state.buttons, message, now.instant());
+                    }
+                    println!("{} Encoder turn {} -> CC {} = {}", now, delta, cc, cc_value);
                 }
-                state.encoder_pos = Some(cur_pos);
-            } else {
-                // First observation; just initialize.
-                state.encoder_pos = Some(cur_pos);
+            }
+            // Always resync, so suppressed movement doesn't resurface later.
+            state.encoder_pos = Some(cur_pos);
+
+            // The motion button was let go: the recording is ready to loop
+            if let Some(motion) = &mut motion
+                && let Some([top, bottom]) = motion.follow_button(&state.buttons, now.instant())
+            {
+                println!("{} Motion recorded, {}", now, bottom);
+                render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                screen_dirty.store(true, Ordering::SeqCst);
             }
 
-            // Slider - absolute position
-            let slider_raw = buf[10];
-            if slider_raw != 0 && slider_raw != state.slider_value {
+            // Slider - absolute position (a suppressed slider counts as never touched)
+            let slider_raw = if settings.output_suppress_slider { 0 } else { buf[10] };
+            let slider_pos = if slider_raw == 0 {
+                slider_filter.reset();
+                if let Some(takeover) = &mut soft_takeover {
+                    takeover.release();
+                }
+                None
+            } else {
+                slider_filter.filter(slider_raw)
+            };
+            if let Some(strum) = &mut strum {
+                match slider_pos {
+                    Some(pos) => strum.touch(pos, port, now),
+                    None if slider_raw == 0 => strum.release(port, now),
+                    None => {}
+                }
+            }
+            if let Some(pitch_bend) = &mut pitch_bend {
+                match slider_pos {
+                    Some(pos) => pitch_bend.touch(pos, port, now),
+                    None if slider_raw == 0 => pitch_bend.release(now.instant()),
+                    None => {}
+                }
+            }
+            if let Some(zones) = &mut slider_zones {
+                let zone_changed = match slider_pos {
+                    Some(pos) => zones.touch(pos, port, now),
+                    None if slider_raw == 0 => zones.release(port, now),
+                    None => false,
+                };
+                if zone_changed {
+                    zones.paint(&mut lights_guard);
+                    changed_lights = true;
+                }
+            }
+            if let Some(slider_raw) = slider_pos
+                && slider_raw != state.slider_value
+            {
                 state.slider_value = slider_raw;
                 // Scale from 1-201 range to 0-127
-                let cc_value = ((slider_raw as u16 - 1) * 127 / 200).min(127) as u8;
-                send_cc(port, SLIDER_CC, cc_value);
-                println!("Slider {} -> CC {} = {}", slider_raw, SLIDER_CC, cc_value);
+                let cc_value = ((slider_raw - SLIDER_RAW_MIN) as u16 * 127
+                    / (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as u16)
+                    .min(127);
+                // The CC mode follows the slider curve
+                let shaped = slider_mode == SliderMode::Cc;
+                let cc_value = if shaped {
+                    slider_curve.shape(cc_value, 127, settings.slider_invert) as u8
+                } else {
+                    cc_value as u8
+                };
+                // Pages are the pad layouts, each keeps its own slider value
+                let picked_up = soft_takeover.as_mut().is_none_or(|takeover| {
+                    let page = if keyboard_active { "keyboard" } else { layout.as_str() };
+                    takeover.pick_up(page, cc_value)
+                });
+                match slider_mode {
+                    _ if !picked_up => {
+                        let target = soft_takeover.as_ref().and_then(|t| t.pending());
+                        println!("{} Slider {} held back, picks up at {:?}", now, cc_value, target);
+                    }
+                    SliderMode::Crossfader if settings.crossfader_output == "dual" => {
+                        let (a, b) = crossfader_volumes(cc_value, crossfader_curve);
+                        let [ch_a, ch_b] = settings.crossfader_channels;
+                        send_channel_volume(port, now, ch_a, a);
+                        send_channel_volume(port, now, ch_b, b);
+                        if let Some(scenes) = &mut scenes {
+                            scenes.observe_cc(0xB0 | ch_a, 7, a);
+                            scenes.observe_cc(0xB0 | ch_b, 7, b);
+                        }
+                        println!("{} Crossfader {} -> A = {}, B = {}", now, slider_raw, a, b);
+                    }
+                    // Notes and pitch bend were already sent above
+                    SliderMode::Strum | SliderMode::Zones | SliderMode::PitchBend => {}
+                    _ if settings.slider_high_resolution => {
+                        let (channel, cc) = (settings.slider_channel, settings.slider_cc);
+                        // Every one of the strip's positions gets its own value
+                        let value = (slider_raw - SLIDER_RAW_MIN) as u32 * 16383
+                            / (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as u32;
+                        let value = value.min(16383) as u16;
+                        let value = if shaped {
+                            slider_curve.shape(value, 16383, settings.slider_invert)
+                        } else {
+                            value
+                        };
+                        send_cc14(port, now, channel, cc, value);
+                        if let Some(scenes) = &mut scenes {
+                            scenes.observe_cc(0xB0 | channel, cc, (value >> 7) as u8);
+                            scenes.observe_cc(0xB0 | channel, cc + 32, (value & 0x7F) as u8);
+                        }
+                        if let Some(motion) = &mut motion {
+                            let (msb, lsb) = ((value >> 7) as u8, (value & 0x7F) as u8);
+                            let at = now.instant();
+                            motion.observe_cc(&state.buttons, [0xB0 | channel, cc, msb], at);
+                            motion.observe_cc(&state.buttons, [0xB0 | channel, cc + 32, lsb], at);
+                        }
+                        let lsb = cc + 32;
+                        println!("{} Slider {} -> CC {}/{} = {}", now, slider_raw, cc, lsb, value);
+                    }
+                    _ => {
+                        let channel = settings.slider_channel;
+                        send_cc(port, now, channel, settings.slider_cc, cc_value);
+                        if let Some(scenes) = &mut scenes {
+                            scenes.observe_cc(0xB0 | channel, settings.slider_cc, cc_value);
+                        }
+                        if let Some(motion) = &mut motion {
+                            let message = [0xB0 | channel, settings.slider_cc, cc_value];
+                            motion.observe_cc(&state.buttons, message, now.instant());
+                        }
+                        println!(
+                            "{} Slider {} -> CC {} = {}",
+                            now, slider_raw, settings.slider_cc, cc_value
+                        );
+                    }
+                }
 
                 // Update slider LEDs
-                let cnt = (slider_raw as i32 - 1 + 5) * 25 / 200 - 1;
-                for i in 0..25 {
-                    let b = match cnt - i {
-                        0 => Brightness::Normal,
-                        1..=25 => Brightness::Dim,
-                        _ => Brightness::Off,
-                    };
-                    lights_guard.set_slider(i as usize, b);
+                match slider_mode {
+                    SliderMode::Cc | SliderMode::Strum => {
+                        slider::paint_position(&mut lights_guard, slider_raw)
+                    }
+                    SliderMode::Crossfader => slider::paint_bipolar(&mut lights_guard, slider_raw),
+                    // Painted with the zones above, and by the pitch bend's poll
+                    SliderMode::Zones | SliderMode::PitchBend => {}
                 }
                 changed_lights = true;
             }
         } else if buf[0] == 0x02 {
             // Pad mode
             for i in (1..buf.len()).step_by(3) {
-                let idx = buf[i];
                 let evt = buf[i + 1] & 0xf0;
                 let val = ((buf[i + 1] as u16 & 0x0f) << 8) + buf[i + 2] as u16;
-                if i > 1 && idx == 0 && evt == 0 && val == 0 {
+                if i > 1 && buf[i] == 0 && evt == 0 && val == 0 {
                     break;
                 }
+                // Everything from here on works with logical pads
+                let idx = pad_transform.apply(buf[i] as usize) as u8;
                 let pad_evt: PadEventType = num::FromPrimitive::from_u8(evt).unwrap();
 
+                // The emulated Launchpad sees every press, whatever the pad does here
+                if let Some(launchpad) = launchpad.as_mut() {
+                    match pad_evt {
+                        PadEventType::NoteOn | PadEventType::PressOn => {
+                            launchpad.pad(idx as usize, true)
+                        }
+                        PadEventType::NoteOff | PadEventType::PressOff => {
+                            launchpad.pad(idx as usize, false)
+                        }
+                        PadEventType::Aftertouch => {}
+                    }
+                }
+
                 // REMOVED: Automatic blue LED feedback on pad touch
                 // This was conflicting with MIDI-based LED control from Bitwig
                 // Now LEDs are controlled exclusively via MIDI Note On/Off messages
                 // from the controller script, allowing proper step sequencer LED states
 
-                let note = settings.notemaps[idx as usize];
+                // Tap tempo: the tap pad sets the clock's tempo and pulls its beat along
+                if let Some(tap_tempo) = &mut tap_tempo
+                    && let Some(clock) = &mut clock
+                    && tap_tempo_button.is_none()
+                    && idx == settings.tap_tempo_pad
+                {
+                    if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn) {
+                        tap_clock(clock, tap_tempo, &settings, now, &screen, &screen_dirty);
+                    }
+                    continue;
+                }
+
+                // Motion pad: loops the recorded motion, the next hit stops it
+                if let Some(motion) = &mut motion
+                    && idx as usize == motion.pad()
+                {
+                    if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn)
+                        && let Some([top, bottom]) = motion.hit(now.instant(), &mut lights_guard)
+                    {
+                        println!("{} Motion {}", now, bottom);
+                        render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                        screen_dirty.store(true, Ordering::SeqCst);
+                        changed_lights = true;
+                    }
+                    continue;
+                }
+
+                // FX throw: the pad's pressure drives a CC, release snaps it back to 0
+                if let Some(throw) = settings.fx_throws.iter().find(|t| t.pad == idx as usize) {
+                    let pad = idx as usize;
+                    let pressure = match pad_evt {
+                        PadEventType::NoteOn | PadEventType::PressOn => {
+                            fx_limiter.reset(pad);
+                            fx_limiter.filter(pad, (val >> 5) as u8, now.instant())
+                        }
+                        PadEventType::Aftertouch => {
+                            fx_limiter.filter(pad, (val >> 5) as u8, now.instant())
+                        }
+                        PadEventType::NoteOff | PadEventType::PressOff => {
+                            fx_limiter.reset(pad);
+                            Some(0)
+                        }
+                    };
+                    if let Some(pressure) = pressure {
+                        let value = throw.value(pressure);
+                        port.send(&[0xB0 | throw.channel, throw.cc, value], now);
+                        paint_fx_throw(&mut lights_guard, throw, pressure);
+                        changed_lights = true;
+                    }
+                    continue;
+                }
+
+                // Scene mode: pads recall CC snapshots (Shift + pad stores one)
+                if let Some(scenes) = &mut scenes {
+                    if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn) {
+                        let pad = idx as usize;
+                        if state.buttons[Buttons::Shift as usize] {
+                            // Store, then name the scene with the encoder
+                            scenes.store(pad);
+                            let title = format!("NAME SCENE {pad}");
+                            let entry = TextEntry::new(&title, scenes.name(pad));
+                            let [top, bottom] = entry.lines();
+                            render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                            screen_dirty.store(true, Ordering::SeqCst);
+                            text_entry = Some((entry, pad));
+                        } else if scenes.recall(pad, port, now) {
+                            let [top, bottom] = scenes.lines(pad);
+                            render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                            screen_dirty.store(true, Ordering::SeqCst);
+                        }
+                        scenes.paint(&mut lights_guard);
+                        changed_lights = true;
+                    }
+                    continue;
+                }
+
+                // Sequencer mode: pads toggle steps (Shift + pad picks the sound to edit)
+                if let Some(sequencer) = &mut sequencer {
+                    if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn) {
+                        let pad = idx as usize;
+                        if state.buttons[Buttons::Shift as usize] {
+                            let [top, bottom] = sequencer.select(pad);
+                            render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                            screen_dirty.store(true, Ordering::SeqCst);
+                        } else {
+                            sequencer.toggle(pad, velocity_map.velocity(pad, val));
+                        }
+                        sequencer.paint(&mut lights_guard);
+                        changed_lights = true;
+                    }
+                    continue;
+                }
+
+                // Desk mode: pads type on the virtual keyboard, nothing goes out as MIDI
+                if let Some(desk) = &mut desk {
+                    match pad_evt {
+                        PadEventType::NoteOn | PadEventType::PressOn => {
+                            desk.pad(idx as usize, true)
+                        }
+                        PadEventType::NoteOff | PadEventType::PressOff => {
+                            desk.pad(idx as usize, false)
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                // Chord capture: the next pad hit picks where the chord goes (Shift clears it)
+                if let Some(chords) = &mut chord_memory
+                    && chords.choosing_pad()
+                {
+                    if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn) {
+                        let [top, bottom] = if state.buttons[Buttons::Shift as usize] {
+                            chords.clear(idx as usize)
+                        } else {
+                            chords.choose_pad(idx as usize)
+                        };
+                        render_screen_lines(&mut screen.lock().unwrap(), &top, &bottom);
+                        screen_dirty.store(true, Ordering::SeqCst);
+                    }
+                    continue;
+                }
+
+                // Latching bank: a hit turns the pad's note on, the next one off. A pad held
+                // since before the switch still releases the note it started.
+                let bank = pad_banks.as_ref().map_or(0, PadBanks::active);
+                let hit = matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn);
+                if let Some(latch) = &mut pad_latch
+                    && latch.latches(bank)
+                    && (hit || pad_notes[idx as usize].is_none())
+                {
+                    let pad = idx as usize;
+                    pad_notes[pad] = None;
+                    let keyboard = keyboard.as_ref().filter(|_| keyboard_active);
+                    if hit
+                        && !settings.pad_below_threshold(pad, val)
+                        && let Some(note) = pad_note(keyboard, &notemaps, octave, pad)
+                    {
+                        let velocity = velocity_map.velocity(pad, val);
+                        pad_stats::record(pad, velocity);
+                        let channel = keyboard
+                            .and_then(|k| k.channel(pad))
+                            .unwrap_or_else(|| params::value(Param::PadChannel, &settings));
+                        latch.hit(pad, (channel, note), velocity, port, now, &mut lights_guard);
+                        changed_lights = true;
+                    }
+                    continue;
+                }
+
+                if matches!(pad_evt, PadEventType::NoteOn | PadEventType::PressOn) {
+                    // Hits under the calibrated threshold don't play (nor release) anything
+                    if settings.pad_below_threshold(idx as usize, val) {
+                        pad_notes[idx as usize] = None;
+                        continue;
+                    }
+                    let keyboard = keyboard.as_ref().filter(|_| keyboard_active);
+                    pad_notes[idx as usize] = pad_note(keyboard, &notemaps, octave, idx as usize);
+                    pad_zone_channels[idx as usize] =
+                        keyboard.and_then(|k| k.channel(idx as usize));
+                    // A split pad picks its note by how hard it was hit, shifted with the octave
+                    let velocity = velocity_map.velocity(idx as usize, val);
+                    if let Some(note) = velocity_splits.note(idx as usize, velocity) {
+                        pad_notes[idx as usize] = transpose(note, octave);
+                    }
+                    if let Some(note) = pad_notes[idx as usize] {
+                        match rules.pad_hit(idx as usize, &state.buttons, note) {
+                            Some(PadOutcome::Note(note)) => pad_notes[idx as usize] = Some(note),
+                            Some(PadOutcome::Cc { cc, value }) => {
+                                let channel = params::value(Param::PadChannel, &settings);
+                                send_cc(port, now, channel, cc, value);
+                                pad_notes[idx as usize] = None;
+                                continue;
+                            }
+                            None => {}
+                        }
+                    }
+                }
+                let Some(note) = pad_notes[idx as usize] else {
+                    continue;
+                };
+                // A pad with a captured or configured chord plays all its notes instead
+                let notes = match chord_memory.as_ref().and_then(|c| c.chord(idx as usize)) {
+                    Some(chord) => chord.to_vec(),
+                    None => pad_chords.notes(idx as usize, note).unwrap_or_else(|| vec![note]),
+                };
                 let mut velocity = (val >> 5) as u8;
                 if val > 0 && velocity == 0 {
                     velocity = 1;
                 }
+                let channel = pad_zone_channels[idx as usize]
+                    .unwrap_or_else(|| params::value(Param::PadChannel, &settings));
 
                 match pad_evt {
                     PadEventType::NoteOn | PadEventType::PressOn => {
-                        send_note(port, note, velocity, true);
-                        println!("Pad {} Note On {} vel {}", idx, note, velocity);
+                        let velocity = velocity_map.velocity(idx as usize, val);
+                        pad_stats::record(idx as usize, velocity);
+                        aftertouch_limiter.reset(idx as usize);
+                        if let Some(strum) = &mut strum {
+                            strum.hold(&notes);
+                        }
+                        #[cfg(feature = "sampler")]
+                        if let Some(sampler) = &sampler {
+                            sampler.play(idx as usize, velocity);
+                        }
+                        if let Some(gates) = &mut gates {
+                            gates.note_on(idx as usize, channel, &notes, port, now);
+                        }
+                        if let Some(repeat) = &mut note_repeat {
+                            repeat.hold(idx as usize, channel, &notes, velocity);
+                        }
+                        if let Some(step_record) = &mut step_record {
+                            step_record.hit(idx as usize);
+                        }
+                        if let Some(meter) = &mut pressure_meter {
+                            meter.hold(idx as usize, true);
+                        }
+                        for &note in &notes {
+                            send_note(port, now, channel, note, velocity, true);
+                            if let Some(chords) = &mut chord_memory {
+                                chords.observe_note(note, now.instant());
+                            }
+                        }
+                        if notes.len() > 1 {
+                            println!("{} Pad {} Chord On {:?} vel {}", now, idx, notes, velocity);
+                        } else {
+                            println!("{} Pad {} Note On {} vel {}", now, idx, note, velocity);
+                        }
+                        if let Some(keyboard) = &keyboard
+                            && keyboard_active
+                        {
+                            // Note name and scale degree of the last pad hit
+                            let name = note_name(note);
+                            let degree = format!("DEG {}", keyboard.degree(idx as usize));
+                            render_screen_lines(&mut screen.lock().unwrap(), &name, &degree);
+                            screen_dirty.store(true, Ordering::SeqCst);
+                        }
                     }
                     PadEventType::NoteOff | PadEventType::PressOff => {
-                        send_note(port, note, velocity, false);
+                        let had_pressure = aftertouch_limiter.pressure(idx as usize) > 0;
+                        aftertouch_limiter.reset(idx as usize);
+                        if let Some(meter) = &mut pressure_meter {
+                            meter.hold(idx as usize, false);
+                        }
+                        if let Some(strum) = &mut strum {
+                            strum.unhold(&notes);
+                        }
+                        if let Some(repeat) = &mut note_repeat {
+                            repeat.release(idx as usize);
+                        }
+                        // The gates may hold the note offs back, or have sent them already
+                        let release = gates
+                            .as_mut()
+                            .is_none_or(|g| g.note_off(idx as usize, velocity, now.instant()));
+                        for &note in notes.iter().filter(|_| release) {
+                            send_note(port, now, channel, note, velocity, false);
+                        }
+                        // The step's last pad came up: on to the next step
+                        if let Some(step_record) = &mut step_record
+                            && step_record.release(idx as usize, port, now)
+                        {
+                            let [top, bottom] = step_record.lines();
+                            println!("{} Step record at {}", now, bottom);
+                            let mut page = Screen::new();
+                            render_screen_lines(&mut page, &top, &bottom);
+                            screen_layers.show(Layer::Page, page, None);
+                        }
+                        if aftertouch_mode == AftertouchMode::Channel && had_pressure {
+                            // Fall back to whatever the remaining held pads press
+                            let pressure = aftertouch_limiter.max_pressure();
+                            send_channel_pressure(port, now, channel, pressure);
+                        }
+                        if had_pressure {
+                            send_aftertouch_ccs(
+                                port,
+                                now,
+                                &settings.aftertouch_cc,
+                                idx as usize,
+                                &aftertouch_limiter,
+                            );
+                        }
+                    }
+                    PadEventType::Aftertouch => {
+                        if aftertouch_mode == AftertouchMode::Off
+                            && settings.aftertouch_cc.is_empty()
+                            && pressure_meter.is_none()
+                        {
+                            continue;
+                        }
+                        let Some(pressure) =
+                            aftertouch_limiter.filter(idx as usize, velocity, now.instant())
+                        else {
+                            continue;
+                        };
+                        if let Some(meter) = &mut pressure_meter {
+                            meter.set_pressure(aftertouch_limiter.max_pressure());
+                        }
+                        match aftertouch_mode {
+                            AftertouchMode::Poly => {
+                                for &note in &notes {
+                                    send_poly_aftertouch(port, now, channel, note, pressure);
+                                }
+                            }
+                            AftertouchMode::Channel => {
+                                let pressure = aftertouch_limiter.max_pressure();
+                                send_channel_pressure(port, now, channel, pressure)
+                            }
+                            AftertouchMode::Off => {}
+                        }
+                        send_aftertouch_ccs(
+                            port,
+                            now,
+                            &settings.aftertouch_cc,
+                            idx as usize,
+                            &aftertouch_limiter,
+                        );
                     }
-                    _ => {}
                 }
             }
         }
-        if changed_lights || lights_changed {
-            lights_guard.write(device)?;
+        // Notes latched on a bank that was switched away from are let go
+        if let Some(latch) = &mut pad_latch {
+            let bank = pad_banks.as_ref().map_or(0, PadBanks::active);
+            changed_lights |= latch.follow_bank(bank, port, now, &mut lights_guard);
+        }
+
+        // Send everything generated from this report back-to-back
+        port.flush();
+
+        let meter_changed = pressure_meter.as_mut().is_some_and(PressureMeter::take_changed);
+        if changed_lights || lights_changed || meter_changed {
+            write_lights(
+                device,
+                &lights_guard,
+                pressure_meter.as_ref(),
+                shift_layer.as_ref(),
+                stage_light_on,
+            )?;
+            if let Some(timer) = &mut timer {
+                // The slider LEDs went out with the shared pad colors
+                timer.redraw();
+            }
+            if let Some(heatmap) = &mut heatmap {
+                heatmap.redraw();
+            }
         }
         
-        // Write screen if changed by MIDI callback
-        if screen_changed {
-            let screen_guard = screen.lock().unwrap();
+        // Write screen if changed by MIDI callback (and not covered since)
+        if screen_changed && !screen_layers.covered() {
+            let mut screen_guard = screen.lock().unwrap();
+            if let Some(activity) = &midi_activity {
+                activity.draw(&mut screen_guard);
+            }
             screen_guard.write(device)?;
         }
+        screen_layers.flush(device)?;
     }
 }
@@ -0,0 +1,129 @@
+use crate::pad_banks::{MAX_BANKS, PadBanks};
+use crate::params::{self, Param};
+use crate::render_screen_lines;
+use crate::settings::Settings;
+use maschine_library::screen::Screen;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static TOGGLE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the main loop to open the settings menu, or close it
+pub(crate) fn request_toggle() {
+    TOGGLE_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether the menu was asked to open or close since the last call
+pub(crate) fn take_toggle() -> bool {
+    TOGGLE_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// A page of the menu: one setting
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Page {
+    Channel,
+    Velocity,
+    Backlight,
+    Bank,
+}
+
+impl Page {
+    fn title(self) -> &'static str {
+        match self {
+            Page::Channel => "CHANNEL",
+            Page::Velocity => "VELOCITY",
+            Page::Backlight => "BACKLIGHT",
+            Page::Bank => "PAD BANK",
+        }
+    }
+
+    /// The runtime parameter the page edits; the pad bank isn't one
+    fn param(self) -> Option<Param> {
+        match self {
+            Page::Channel => Some(Param::PadChannel),
+            Page::Velocity => Some(Param::VelocityCurve),
+            Page::Backlight => Some(Param::Backlight),
+            Page::Bank => None,
+        }
+    }
+}
+
+/// The settings menu: turning the encoder goes through the pages, turning it pushed
+/// changes the page's value. Values are runtime parameters (as if set by the host), so
+/// they apply at once and last until the driver restarts; the pad bank is switched as
+/// with its button.
+pub(crate) struct Menu {
+    pages: Vec<Page>,
+    page: usize,
+    /// Whether the encoder is pushed, so turns edit
+    editing: bool,
+}
+
+impl Menu {
+    /// The bank page is there with pad banks only
+    pub(crate) fn new(banks: bool) -> Self {
+        let mut pages = vec![Page::Channel, Page::Velocity, Page::Backlight];
+        if banks {
+            pages.push(Page::Bank);
+        }
+        Self {
+            pages,
+            page: 0,
+            editing: false,
+        }
+    }
+
+    pub(crate) fn set_editing(&mut self, pushed: bool) {
+        self.editing = pushed;
+    }
+
+    /// The encoder turned: on to another page, or (pushed) a new value. Returns the pad bank
+    /// to switch to, if that's what changed.
+    pub(crate) fn turn(
+        &mut self,
+        delta: i32,
+        settings: &Settings,
+        banks: Option<&PadBanks>,
+    ) -> Option<usize> {
+        if !self.editing {
+            let last = self.pages.len() as i32 - 1;
+            self.page = (self.page as i32 + delta).clamp(0, last) as usize;
+            return None;
+        }
+        let page = self.pages[self.page];
+        if let Some(param) = page.param() {
+            let value = params::value(param, settings) as i32 + delta;
+            params::set(param, value.clamp(0, param.max() as i32) as u8);
+            return None;
+        }
+        // The banks without notes can't be picked
+        let banks = banks?;
+        let selectable: Vec<usize> = (0..MAX_BANKS).filter(|b| banks.selectable(*b)).collect();
+        let current = selectable.iter().position(|b| *b == banks.active())?;
+        let next = (current as i32 + delta).clamp(0, selectable.len() as i32 - 1);
+        Some(selectable[next as usize]).filter(|bank| *bank != banks.active())
+    }
+
+    /// The page as shown: its name and number on top, its value below, in brackets while
+    /// the encoder is pushed
+    pub(crate) fn screen(&self, settings: &Settings, banks: Option<&PadBanks>) -> Screen {
+        let page = self.pages[self.page];
+        let top = format!("{}/{} {}", self.page + 1, self.pages.len(), page.title());
+        let value = match page {
+            Page::Channel => format!("CH {}", params::value(Param::PadChannel, settings) + 1),
+            Page::Velocity => params::velocity_curve(settings).to_ascii_uppercase(),
+            Page::Backlight if params::value(Param::Backlight, settings) == 1 => "ON".to_string(),
+            Page::Backlight => "OFF".to_string(),
+            Page::Bank => banks
+                .map(|banks| PadBanks::name(banks.active()).to_string())
+                .unwrap_or_default(),
+        };
+        let bottom = if self.editing {
+            format!("[{value}]")
+        } else {
+            value
+        };
+        let mut screen = Screen::new();
+        render_screen_lines(&mut screen, &top, &bottom);
+        screen
+    }
+}
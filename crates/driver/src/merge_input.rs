@@ -0,0 +1,46 @@
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use std::sync::mpsc::{self, Receiver};
+
+/// A MIDI message received on the merged hardware input
+pub(crate) type MergedMessage = (Timestamp, Vec<u8>);
+
+/// Connects to an existing MIDI input (e.g. a USB keyboard) whose port name contains
+/// `merge_input_port`. Its messages are delivered to the returned receiver, which the main
+/// loop drains into the driver's own output, so both appear as one device to the DAW.
+///
+/// The connection stops delivering when dropped.
+pub(crate) fn open_merge_input(
+    settings: &Settings,
+) -> Result<(MidiInputConnection<()>, Receiver<MergedMessage>), String> {
+    let mut input = MidiInput::new(&format!("{} Merge", settings.client_name))
+        .map_err(|e| e.to_string())?;
+    input.ignore(Ignore::ActiveSense);
+
+    let wanted = &settings.merge_input_port;
+    let port = input
+        .ports()
+        .into_iter()
+        .filter_map(|p| input.port_name(&p).ok().map(|name| (p, name)))
+        // Never merge our own ports back into the output
+        .filter(|(_, name)| !name.starts_with(&settings.client_name))
+        .find(|(_, name)| name.contains(wanted.as_str()));
+    let Some((port, name)) = port else {
+        return Err(format!("no MIDI input port matching {wanted:?}"));
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let conn = input
+        .connect(
+            &port,
+            "merge",
+            move |_timestamp, message, _| {
+                let _ = tx.send((Timestamp::now(), message.to_vec()));
+            },
+            (),
+        )
+        .map_err(|e| format!("can't connect to {name}: {e}"))?;
+    println!("Merging MIDI input from {name}");
+    Ok((conn, rx))
+}
@@ -0,0 +1,101 @@
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+
+/// How the metronome clicks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MetronomeMode {
+    Off,
+    /// Notes on a MIDI channel (e.g. a GM rim click / wood block)
+    Notes,
+    /// Generated click on the default audio output (needs the "sampler" feature)
+    Audio,
+}
+
+pub(crate) fn parse_metronome_mode(s: &str) -> Result<MetronomeMode, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok(MetronomeMode::Off),
+        "notes" => Ok(MetronomeMode::Notes),
+        "audio" => Ok(MetronomeMode::Audio),
+        other => Err(format!(
+            "invalid metronome={other:?} (expected: \"off\", \"notes\", \"audio\")"
+        )),
+    }
+}
+
+/// Clicks on every beat of the internal clock, accenting the first beat of each bar
+pub(crate) struct Metronome {
+    mode: MetronomeMode,
+    channel: u8,
+    /// Downbeat note, other beats note
+    notes: [u8; 2],
+    beats_per_bar: u64,
+    sounding: Option<u8>,
+    #[cfg(feature = "sampler")]
+    audio: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
+}
+
+impl Metronome {
+    pub(crate) fn new(mode: MetronomeMode, channel: u8, notes: [u8; 2], beats_per_bar: u8) -> Self {
+        #[cfg(feature = "sampler")]
+        let audio = (mode == MetronomeMode::Audio)
+            .then(|| match rodio::OutputStream::try_default() {
+                Ok(stream) => Some(stream),
+                Err(e) => {
+                    eprintln!("Metronome: no audio output (continuing): {e}");
+                    None
+                }
+            })
+            .flatten();
+        #[cfg(not(feature = "sampler"))]
+        if mode == MetronomeMode::Audio {
+            eprintln!("metronome = \"audio\" needs the driver built with the \"sampler\" feature");
+        }
+        Self {
+            mode,
+            channel,
+            notes,
+            beats_per_bar: beats_per_bar.max(1) as u64,
+            sounding: None,
+            #[cfg(feature = "sampler")]
+            audio,
+        }
+    }
+
+    /// Clicks beat number `beat` (counted from the start); returns whether it is a downbeat
+    pub(crate) fn beat(&mut self, beat: u64, port: &mut MidiOut, at: Timestamp) -> bool {
+        let accent = beat.is_multiple_of(self.beats_per_bar);
+        match self.mode {
+            MetronomeMode::Off => {}
+            MetronomeMode::Notes => {
+                self.release(port, at);
+                let note = self.notes[if accent { 0 } else { 1 }];
+                let velocity = if accent { 127 } else { 90 };
+                port.send(&[0x90 | self.channel, note, velocity], at);
+                self.sounding = Some(note);
+            }
+            MetronomeMode::Audio => {
+                #[cfg(feature = "sampler")]
+                if let Some((_, handle)) = &self.audio {
+                    use rodio::Source;
+                    use std::time::Duration;
+
+                    let (freq, gain) = if accent { (1600.0, 0.6) } else { (1000.0, 0.4) };
+                    let click = rodio::source::SineWave::new(freq)
+                        .take_duration(Duration::from_millis(25))
+                        .amplify(gain);
+                    if let Err(e) = handle.play_raw(click) {
+                        eprintln!("Metronome: can't play click: {e}");
+                    }
+                }
+            }
+        }
+        accent
+    }
+
+    /// Ends the last click note (on stop)
+    pub(crate) fn release(&mut self, port: &mut MidiOut, at: Timestamp) {
+        if let Some(note) = self.sounding.take() {
+            port.send(&[0x80 | self.channel, note, 0], at);
+        }
+    }
+}
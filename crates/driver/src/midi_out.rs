@@ -0,0 +1,256 @@
+use crate::activity;
+use crate::autoconnect::try_autoconnect_virmidi;
+use crate::faults::{self, Fault};
+use crate::output_filter::filter_output;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use midir::os::unix::VirtualOutput;
+use midir::{MidiOutput, MidiOutputConnection};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(250);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+/// Sending this long after the input event happened is worth a warning
+const LATE_SEND_WARN: Duration = Duration::from_millis(5);
+/// How often a lost echo port is looked for again
+const ECHO_RETRY: Duration = Duration::from_secs(5);
+
+/// The driver's virtual MIDI output port.
+///
+/// Messages are queued while a HID report is being parsed and sent back-to-back by
+/// `flush`, so the notes of one report leave together instead of being interleaved with
+/// parsing and logging. (midir's ALSA and JACK backends take one message per send, so they
+/// can't go out as a single buffer.)
+///
+/// A failed send doesn't take the driver down: the error is logged, the port is dropped and
+/// recreated in the background (re-running autoconnect if enabled). Messages generated while
+/// the port is being recreated are dropped.
+///
+/// With `echo_output_port` set, every message is also sent to that existing port (e.g. a
+/// USB MIDI interface). Losing it only stops the echo; it's looked for again periodically.
+pub(crate) struct MidiOut {
+    conn: Option<MidiOutputConnection>,
+    /// Queued message bytes, back to back
+    pending: Vec<u8>,
+    /// Length and event time of each queued message
+    pending_msgs: Vec<(usize, Timestamp)>,
+    reconnect: Option<Receiver<MidiOutputConnection>>,
+    echo: Option<MidiOutputConnection>,
+    /// When to look for a missing echo port again
+    echo_retry_at: Option<Instant>,
+    /// Set on a port that only records what would be sent (see `capture`)
+    captured: Option<Vec<Vec<u8>>>,
+    settings: Arc<Settings>,
+}
+
+impl MidiOut {
+    pub(crate) fn new(settings: Arc<Settings>) -> Self {
+        let output = MidiOutput::new(&settings.client_name).expect("Couldn't open MIDI output");
+        let conn = output
+            .create_virtual(&settings.port_name)
+            .expect("Couldn't create virtual output port");
+        let mut out = Self {
+            conn: Some(conn),
+            pending: Vec::with_capacity(256),
+            pending_msgs: Vec::with_capacity(32),
+            reconnect: None,
+            echo: None,
+            echo_retry_at: None,
+            captured: None,
+            settings,
+        };
+        if !out.settings.echo_output_port.is_empty() {
+            out.reopen_echo();
+        }
+        out
+    }
+
+    /// Uses reloaded settings from now on: the output filters, and the echo port, which is
+    /// opened anew if it changed. The virtual port stays as it is.
+    pub(crate) fn set_settings(&mut self, settings: Arc<Settings>) {
+        let echo_changed = settings.echo_output_port != self.settings.echo_output_port;
+        self.settings = settings;
+        if echo_changed {
+            self.echo = None;
+            self.echo_retry_at = None;
+            if !self.settings.echo_output_port.is_empty() {
+                self.reopen_echo();
+            }
+        }
+    }
+
+    /// Queues a message generated from the input event read at `at`, after the output
+    /// filters (which may change or drop it)
+    pub(crate) fn send(&mut self, message: &[u8], at: Timestamp) {
+        let start = self.pending.len();
+        self.pending.extend_from_slice(message);
+        if !filter_output(&self.settings, &mut self.pending[start..]) {
+            self.pending.truncate(start);
+            return;
+        }
+        self.pending_msgs.push((message.len(), at));
+    }
+
+    /// A port that creates no MIDI port and keeps the messages it's sent instead, for
+    /// checking what the driver sends
+    #[cfg(test)]
+    pub(crate) fn capture(settings: Arc<Settings>) -> Self {
+        Self {
+            conn: None,
+            pending: Vec::new(),
+            pending_msgs: Vec::new(),
+            reconnect: None,
+            echo: None,
+            echo_retry_at: None,
+            captured: Some(Vec::new()),
+            settings,
+        }
+    }
+
+    /// Messages sent through a `capture` port so far, after the output filters
+    #[cfg(test)]
+    pub(crate) fn captured(&self) -> &[Vec<u8>] {
+        self.captured.as_deref().unwrap_or_default()
+    }
+
+    /// Sends all queued messages
+    pub(crate) fn flush(&mut self) {
+        if self.pending_msgs.is_empty() {
+            return;
+        }
+        if let Some(captured) = &mut self.captured {
+            let mut offset = 0;
+            for &(len, _) in &self.pending_msgs {
+                captured.push(self.pending[offset..offset + len].to_vec());
+                offset += len;
+            }
+            self.pending.clear();
+            self.pending_msgs.clear();
+            return;
+        }
+        if self.conn.is_none() {
+            self.poll_reconnect();
+        }
+        if self.echo_retry_at.is_some_and(|at| Instant::now() >= at) {
+            self.reopen_echo();
+        }
+
+        activity::midi_out();
+        let mut offset = 0;
+        for &(len, at) in &self.pending_msgs {
+            let message = &self.pending[offset..offset + len];
+            offset += len;
+            if let Some(echo) = self.echo.as_mut()
+                && let Err(e) = echo.send(message)
+            {
+                eprintln!("MIDI echo send failed ({e}), retrying in {ECHO_RETRY:?}");
+                faults::raise(Fault::MidiEcho);
+                self.echo = None;
+                self.echo_retry_at = Some(Instant::now() + ECHO_RETRY);
+            }
+            let Some(conn) = self.conn.as_mut() else {
+                break;
+            };
+            let latency = at.elapsed();
+            if latency > LATE_SEND_WARN {
+                eprintln!("{at} MIDI send {latency:?} after the event was read");
+            }
+            if let Err(e) = conn.send(message) {
+                eprintln!("MIDI send failed ({e}), recreating output port");
+                faults::raise(Fault::MidiSend);
+                // Drop the old port first so the new one can take over its name
+                self.conn = None;
+            }
+        }
+        self.pending.clear();
+        self.pending_msgs.clear();
+
+        if self.conn.is_none() {
+            self.start_reconnect();
+        }
+    }
+
+    fn reopen_echo(&mut self) {
+        match open_echo_output(&self.settings) {
+            Ok(echo) => {
+                self.echo = Some(echo);
+                self.echo_retry_at = None;
+            }
+            Err(e) => {
+                eprintln!("MIDI echo unavailable ({e}), retrying in {ECHO_RETRY:?}");
+                self.echo_retry_at = Some(Instant::now() + ECHO_RETRY);
+            }
+        }
+    }
+
+    fn poll_reconnect(&mut self) {
+        let Some(rx) = &self.reconnect else { return };
+        if let Ok(conn) = rx.try_recv() {
+            self.conn = Some(conn);
+            self.reconnect = None;
+        }
+    }
+
+    fn start_reconnect(&mut self) {
+        if self.reconnect.is_some() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let settings = Arc::clone(&self.settings);
+        thread::spawn(move || {
+            let mut backoff = RECONNECT_BACKOFF_MIN;
+            loop {
+                match open_virtual_output(&settings) {
+                    Ok(conn) => {
+                        eprintln!("MIDI output port recreated");
+                        if settings.autoconnect_virmidi
+                            && let Err(e) = try_autoconnect_virmidi(&settings, false)
+                        {
+                            eprintln!("Auto-connect to virmidi failed (continuing): {e}");
+                        }
+                        let _ = tx.send(conn);
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("Recreating MIDI output port failed ({e}), retrying in {backoff:?}");
+                        thread::sleep(backoff);
+                        backoff = (backoff * 2).min(RECONNECT_BACKOFF_MAX);
+                    }
+                }
+            }
+        });
+        self.reconnect = Some(rx);
+    }
+}
+
+fn open_virtual_output(settings: &Settings) -> Result<MidiOutputConnection, String> {
+    let output = MidiOutput::new(&settings.client_name).map_err(|e| e.to_string())?;
+    output
+        .create_virtual(&settings.port_name)
+        .map_err(|e| e.to_string())
+}
+
+/// Connects to the existing output whose port name contains `echo_output_port`
+fn open_echo_output(settings: &Settings) -> Result<MidiOutputConnection, String> {
+    let output = MidiOutput::new(&format!("{} Echo", settings.client_name))
+        .map_err(|e| e.to_string())?;
+    let wanted = &settings.echo_output_port;
+    let port = output
+        .ports()
+        .into_iter()
+        .filter_map(|p| output.port_name(&p).ok().map(|name| (p, name)))
+        // Echoing into our own ports would loop
+        .filter(|(_, name)| !name.starts_with(&settings.client_name))
+        .find(|(_, name)| name.contains(wanted.as_str()));
+    let Some((port, name)) = port else {
+        return Err(format!("no MIDI output port matching {wanted:?}"));
+    };
+    let conn = output
+        .connect(&port, "echo")
+        .map_err(|e| format!("can't connect to {name}: {e}"))?;
+    println!("Echoing MIDI output to {name}");
+    Ok(conn)
+}
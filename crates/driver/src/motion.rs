@@ -0,0 +1,127 @@
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use std::time::{Duration, Instant};
+
+/// Shortest loop, so a single twitch doesn't replay as fast as the main loop can go
+const MIN_LENGTH: Duration = Duration::from_millis(100);
+
+/// Motion recorder: moving the slider or the encoder while `motion_button` is held records
+/// the CCs they send, up to `motion_max_secs`. Hitting `motion_pad` then replays them in a
+/// loop, as long as the recording took, until the next hit: an LFO drawn by hand.
+pub(crate) struct MotionRecorder {
+    button: Buttons,
+    pad: usize,
+    max: Duration,
+    /// CC messages and when they went out, from the start of the recording
+    events: Vec<(Duration, [u8; 3])>,
+    /// The recording being made, and when its first move was
+    take: Vec<(Duration, [u8; 3])>,
+    recording: Option<Instant>,
+    /// From the first move until the button was let go
+    length: Duration,
+    /// While looping: when the current lap started and the next event in it
+    playing: Option<(Instant, usize)>,
+}
+
+impl MotionRecorder {
+    /// None without `motion_button` (validated by `Settings::validate`)
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        let button = Buttons::from_name(&settings.motion_button)?;
+        Some(Self {
+            button,
+            pad: settings.motion_pad as usize,
+            max: Duration::from_secs(settings.motion_max_secs),
+            events: Vec::new(),
+            take: Vec::new(),
+            recording: None,
+            length: Duration::ZERO,
+            playing: None,
+        })
+    }
+
+    /// The pad that loops the motion
+    pub(crate) fn pad(&self) -> usize {
+        self.pad
+    }
+
+    /// Whether a motion is looping, so the main loop should poll on time
+    pub(crate) fn playing(&self) -> bool {
+        self.playing.is_some()
+    }
+
+    /// A CC the slider or encoder sent: recorded while the button is held, the first one
+    /// starting a new recording
+    pub(crate) fn observe_cc(&mut self, buttons: &[bool], message: [u8; 3], now: Instant) {
+        if !buttons[self.button as usize] {
+            return;
+        }
+        let started = *self.recording.get_or_insert(now);
+        let at = now - started;
+        if at < self.max {
+            self.take.push((at, message));
+        }
+    }
+
+    /// Ends the recording once the button is let go. It replaces the last one, and a loop
+    /// that's running goes on with it. Returns the lines for the screen.
+    pub(crate) fn follow_button(&mut self, buttons: &[bool], now: Instant) -> Option<[String; 2]> {
+        if buttons[self.button as usize] {
+            return None;
+        }
+        let started = self.recording.take()?;
+        self.events = std::mem::take(&mut self.take);
+        self.length = (now - started).clamp(MIN_LENGTH, self.max);
+        if self.playing.is_some() {
+            self.playing = Some((now, 0));
+        }
+        let secs = format!("{:.1} S", self.length.as_secs_f32());
+        Some(["MOTION".to_string(), secs])
+    }
+
+    /// A hit on the motion pad: starts or stops the loop and lights the pad while it runs.
+    /// Returns the lines for the screen, None with nothing recorded.
+    pub(crate) fn hit(&mut self, now: Instant, lights: &mut Lights) -> Option<[String; 2]> {
+        if self.events.is_empty() {
+            return None;
+        }
+        let state = if self.playing.take().is_some() {
+            lights.set_pad(self.pad, PadColors::Off, Brightness::Off);
+            "STOP"
+        } else {
+            self.playing = Some((now, 0));
+            lights.set_pad(self.pad, PadColors::Cyan, Brightness::Bright);
+            "LOOP"
+        };
+        Some(["MOTION".to_string(), state.to_string()])
+    }
+
+    /// Sends the recorded CCs that are due, starting the next lap at the end of one
+    pub(crate) fn poll(&mut self, port: &mut MidiOut, at: Timestamp) {
+        let Some((mut lap, mut next)) = self.playing else {
+            return;
+        };
+        let now = at.instant();
+        loop {
+            match self.events.get(next) {
+                Some(&(offset, message)) if lap + offset <= now => {
+                    port.send(&message, at);
+                    next += 1;
+                }
+                Some(_) => break,
+                None if lap + self.length <= now => {
+                    lap += self.length;
+                    // A stall doesn't replay the laps it missed
+                    if lap + self.length <= now {
+                        lap = now;
+                    }
+                    next = 0;
+                }
+                None => break,
+            }
+        }
+        self.playing = Some((lap, next));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::clock::{InternalClock, PPQN};
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+use crate::transport;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Repeat rates, slowest first, and their length in clock pulses
+const RATES: [(&str, u64); 8] = [
+    ("1/4", PPQN),
+    ("1/4T", PPQN * 2 / 3),
+    ("1/8", PPQN / 2),
+    ("1/8T", PPQN / 3),
+    ("1/16", PPQN / 4),
+    ("1/16T", PPQN / 6),
+    ("1/32", PPQN / 8),
+    ("1/32T", PPQN / 12),
+];
+
+/// The host clock counts as gone this long after its last pulse (a pulse is 21 ms at 120 BPM,
+/// 42 ms at 60 BPM)
+const HOST_CLOCK_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Index of a rate name in `RATES`
+pub(crate) fn parse_rate(s: &str) -> Result<usize, String> {
+    let s = s.trim().to_ascii_uppercase();
+    RATES
+        .iter()
+        .position(|(name, _)| *name == s)
+        .ok_or_else(|| {
+            let names: Vec<String> = RATES.iter().map(|(name, _)| format!("{name:?}")).collect();
+            format!(
+                "invalid note_repeat_rate={s:?} (expected: {})",
+                names.join(", ")
+            )
+        })
+}
+
+/// A pad held while repeat is available
+struct HeldPad {
+    notes: Vec<u8>,
+    channel: u8,
+    velocity: u8,
+    /// Whether its notes are on (they're off in the second half of each step)
+    sounding: bool,
+}
+
+/// Note repeat: while the Note Repeat button is held, held pads retrigger their notes on
+/// a grid of `RATES`, each note sounding for half a step.
+///
+/// The grid follows the host's MIDI clock while pulses arrive, the internal clock while it
+/// runs, and otherwise a clock of its own at `clock_bpm`, started when the button goes down.
+pub(crate) struct NoteRepeat {
+    rate: usize,
+    /// The Note Repeat button is down
+    active: bool,
+    pads: [Option<HeldPad>; 16],
+    fallback: InternalClock,
+    /// Host clock pulses already seen, and when the last new one came in
+    host_pulses: u64,
+    host_seen: Option<Instant>,
+}
+
+impl NoteRepeat {
+    pub(crate) fn new(rate: usize, bpm: f64) -> Self {
+        Self {
+            rate,
+            active: false,
+            pads: Default::default(),
+            fallback: InternalClock::new(bpm),
+            host_pulses: transport::pulses(),
+            host_seen: None,
+        }
+    }
+
+    /// Name of the current rate, as on the screen
+    pub(crate) fn rate_name(&self) -> &'static str {
+        RATES[self.rate].0
+    }
+
+    /// Steps the rate by `delta` (positive: faster)
+    pub(crate) fn turn(&mut self, delta: i32) -> &'static str {
+        self.rate = (self.rate as i32 + delta).clamp(0, RATES.len() as i32 - 1) as usize;
+        self.rate_name()
+    }
+
+    /// The Note Repeat button went down or up
+    pub(crate) fn set_active(&mut self, active: bool, now: Instant) {
+        self.active = active;
+        if active {
+            // Without a clock to follow, the first step is right away
+            self.fallback.start(now, 0);
+        } else {
+            self.fallback.stop();
+        }
+    }
+
+    pub(crate) fn active(&self) -> bool {
+        self.active
+    }
+
+    /// A pad started `notes` (its hit is played as usual, repeats follow on the grid)
+    pub(crate) fn hold(&mut self, pad: usize, channel: u8, notes: &[u8], velocity: u8) {
+        self.pads[pad] = Some(HeldPad {
+            notes: notes.to_vec(),
+            channel,
+            velocity,
+            sounding: true,
+        });
+    }
+
+    /// The pad was released; its note offs are sent as usual
+    pub(crate) fn release(&mut self, pad: usize) {
+        self.pads[pad] = None;
+    }
+
+    /// Forgets every held pad, e.g. after a panic silenced their notes
+    pub(crate) fn clear(&mut self) {
+        self.pads = Default::default();
+    }
+
+    /// Retriggers the held pads on the pulses that became due. `internal` are the internal
+    /// clock's pulses since the count-in ended, if it's running.
+    pub(crate) fn poll(&mut self, internal: Option<Range<u64>>, port: &mut MidiOut, at: Timestamp) {
+        let now = at.instant();
+        let host = self.host_pulses(now);
+        let pulses = match (host, internal) {
+            (Some(pulses), _) | (None, Some(pulses)) => pulses,
+            (None, None) => self.fallback.poll(now),
+        };
+        if !self.active {
+            return;
+        }
+        let step = RATES[self.rate].1;
+        for pulse in pulses {
+            match pulse % step {
+                0 => self.retrigger(port, at),
+                phase if phase == step / 2 => self.release_all(port, at),
+                _ => {}
+            }
+        }
+    }
+
+    /// Host clock pulses since the last poll, or None while the host sends no clock
+    fn host_pulses(&mut self, now: Instant) -> Option<Range<u64>> {
+        let received = transport::pulses();
+        // A Start puts the count back to zero
+        let seen = if received < self.host_pulses {
+            0
+        } else {
+            self.host_pulses
+        };
+        self.host_pulses = received;
+        if received > seen {
+            self.host_seen = Some(now);
+        }
+        let running = self
+            .host_seen
+            .is_some_and(|at| now - at < HOST_CLOCK_TIMEOUT);
+        // Pulse n is the n-th since Start, counted from zero
+        running.then_some(seen..received)
+    }
+
+    fn retrigger(&mut self, port: &mut MidiOut, at: Timestamp) {
+        self.release_all(port, at);
+        for pad in self.pads.iter_mut().flatten() {
+            for &note in &pad.notes {
+                port.send(&[0x90 | pad.channel, note, pad.velocity], at);
+            }
+            pad.sounding = true;
+        }
+    }
+
+    fn release_all(&mut self, port: &mut MidiOut, at: Timestamp) {
+        for pad in self.pads.iter_mut().flatten().filter(|pad| pad.sounding) {
+            for &note in &pad.notes {
+                port.send(&[0x80 | pad.channel, note, 0], at);
+            }
+            pad.sounding = false;
+        }
+    }
+}
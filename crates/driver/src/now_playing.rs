@@ -0,0 +1,103 @@
+use mpris::{PlaybackStatus, PlayerFinder};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the player's metadata is read
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Scroll speed of text wider than the screen
+const SCROLL_STEP: Duration = Duration::from_millis(300);
+/// Characters fitting on one line of the screen
+const SCREEN_CHARS: usize = 16;
+/// Blank characters between the end of the text and its next pass
+const SCROLL_GAP: usize = 4;
+
+/// "Artist - Title" of the desktop's active MPRIS player, scrolled across the screen.
+///
+/// Metadata is read on a background thread, as D-Bus calls can take a while when a
+/// player hangs; the main loop only asks for the next frame.
+pub(crate) struct NowPlaying {
+    current: Arc<Mutex<Option<String>>>,
+    shown: Option<String>,
+    offset: usize,
+    next_step: Instant,
+}
+
+impl NowPlaying {
+    pub(crate) fn spawn() -> Self {
+        let current = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&current);
+        thread::spawn(move || {
+            let finder = match PlayerFinder::new() {
+                Ok(finder) => finder,
+                Err(e) => {
+                    eprintln!("Now playing: no D-Bus session ({e})");
+                    return;
+                }
+            };
+            // Until the main loop that started it is gone (reconnect, config reload)
+            while Arc::strong_count(&shared) > 1 {
+                *shared.lock().unwrap() = read_now_playing(&finder);
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Self {
+            current,
+            shown: None,
+            offset: 0,
+            next_step: Instant::now(),
+        }
+    }
+
+    /// Next line to put on the screen, if it changed; an empty line means nothing plays
+    /// anymore and the regular screen content should come back.
+    pub(crate) fn frame(&mut self, now: Instant) -> Option<String> {
+        let current = self.current.lock().unwrap().clone();
+        if current != self.shown {
+            let was_shown = self.shown.is_some();
+            self.shown = current;
+            self.offset = 0;
+            self.next_step = now + SCROLL_STEP;
+            return match &self.shown {
+                Some(text) => Some(window(text, 0)),
+                None if was_shown => Some(String::new()),
+                None => None,
+            };
+        }
+        let text = self.shown.as_ref()?;
+        if text.chars().count() <= SCREEN_CHARS || now < self.next_step {
+            return None;
+        }
+        self.offset = (self.offset + 1) % (text.chars().count() + SCROLL_GAP);
+        self.next_step = now + SCROLL_STEP;
+        Some(window(text, self.offset))
+    }
+
+    /// Something else took over the screen: start over when it goes idle again
+    pub(crate) fn hide(&mut self) {
+        self.shown = None;
+    }
+}
+
+fn read_now_playing(finder: &PlayerFinder) -> Option<String> {
+    let player = finder.find_active().ok()?;
+    if player.get_playback_status().ok()? != PlaybackStatus::Playing {
+        return None;
+    }
+    let metadata = player.get_metadata().ok()?;
+    let title = metadata.title().unwrap_or("");
+    match metadata.artists().filter(|a| !a.is_empty()) {
+        Some(artists) => Some(format!("{} - {title}", artists.join(", "))),
+        None if !title.is_empty() => Some(title.to_string()),
+        None => None,
+    }
+}
+
+/// The part of `text` visible at `offset`, wrapping around with a gap
+fn window(text: &str, offset: usize) -> String {
+    if text.chars().count() <= SCREEN_CHARS {
+        return text.to_string();
+    }
+    let looped: Vec<char> = text.chars().chain(std::iter::repeat_n(' ', SCROLL_GAP)).collect();
+    (0..SCREEN_CHARS).map(|i| looped[(offset + i) % looped.len()]).collect()
+}
@@ -0,0 +1,34 @@
+use crate::settings::Settings;
+
+/// Applies the `output_*` filters to a message about to be sent, in place.
+///
+/// Returns false when the message should be dropped. Only channel voice messages are
+/// touched; SysEx, clock and other system messages always go through.
+pub(crate) fn filter_output(settings: &Settings, message: &mut [u8]) -> bool {
+    let Some(&status) = message.first() else {
+        return false;
+    };
+    if !(0x80..0xF0).contains(&status) {
+        return true;
+    }
+    let channel = status & 0x0F;
+    if !settings.output_channels.is_empty() && !settings.output_channels.contains(&channel) {
+        return false;
+    }
+    match (status & 0xF0, message) {
+        (0xA0 | 0xD0, _) if settings.output_suppress_aftertouch => false,
+        (0x80, [_, _, velocity]) => {
+            if settings.output_suppress_release_velocity {
+                *velocity = 0;
+            }
+            true
+        }
+        // Velocity 0 is a note off, it isn't clamped
+        (0x90, [_, _, velocity]) if *velocity > 0 => {
+            let (min, max) = (settings.output_velocity_min, settings.output_velocity_max);
+            *velocity = (*velocity).clamp(min, max);
+            true
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,77 @@
+use crate::settings::Settings;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+
+/// Most banks there are buttons for: A-D
+pub(crate) const MAX_BANKS: usize = 4;
+
+/// Pad banks: up to four 16-note maps (`bank_notemaps`), one per bank button.
+/// Bank A plays the pad layout (`notemaps`, `layout`) unless given its own map.
+pub(crate) struct PadBanks {
+    /// Notes of each bank; B-D without a map can't be selected
+    banks: [Option<Vec<u8>>; MAX_BANKS],
+    buttons: Vec<Buttons>,
+    active: usize,
+}
+
+impl PadBanks {
+    /// The banks the settings define, or None without `bank_buttons`
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        let buttons = settings.bank_buttons();
+        if buttons.is_empty() {
+            return None;
+        }
+        let mut banks: [Option<Vec<u8>>; MAX_BANKS] = Default::default();
+        for (bank, notes) in banks.iter_mut().zip(&settings.bank_notemaps) {
+            *bank = (!notes.is_empty()).then(|| notes.to_vec());
+        }
+        Some(Self { banks, buttons, active: 0 })
+    }
+
+    /// Bank a button selects, if it's a bank button
+    pub(crate) fn bank_of(&self, button: Buttons) -> Option<usize> {
+        self.buttons.iter().position(|b| *b == button)
+    }
+
+    /// Whether `bank` can be made active: bank A, or a bank with a map
+    pub(crate) fn selectable(&self, bank: usize) -> bool {
+        bank == 0 || self.banks.get(bank).is_some_and(|b| b.is_some())
+    }
+
+    /// Makes `bank` the active one, unless it's a bank without a map
+    pub(crate) fn select(&mut self, bank: usize) -> bool {
+        let selectable = self.selectable(bank);
+        if selectable {
+            self.active = bank;
+        }
+        selectable
+    }
+
+    /// Notes of the active bank; None for bank A playing the pad layout
+    pub(crate) fn notes(&self) -> Option<&[u8]> {
+        self.banks[self.active].as_deref()
+    }
+
+    pub(crate) fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Letter of a bank, as on the screen
+    pub(crate) fn name(bank: usize) -> char {
+        (b'A' + bank as u8) as char
+    }
+
+    /// Lights the active bank's button bright and the other selectable ones dim
+    pub(crate) fn paint(&self, lights: &mut Lights, settings: &Settings) {
+        for (bank, &button) in self.buttons.iter().enumerate() {
+            let brightness = if bank == self.active {
+                Brightness::Bright
+            } else if bank == 0 || self.banks[bank].is_some() {
+                Brightness::Dim
+            } else {
+                settings.button_off_level(button)
+            };
+            lights.set_button(button, brightness);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::midi_out::MidiOut;
+use crate::pad_banks::{MAX_BANKS, PadBanks};
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+
+/// Checks `latch_banks` and `latch_color` (for `Settings::validate`)
+pub(crate) fn validate_latch(settings: &Settings) -> Result<(), String> {
+    for name in &settings.latch_banks {
+        bank_index(name).ok_or_else(|| format!("latch_banks: unknown bank {name:?}"))?;
+    }
+    if PadColors::from_name(&settings.latch_color).is_none() {
+        return Err(format!(
+            "latch_color: unknown color {:?}",
+            settings.latch_color
+        ));
+    }
+    Ok(())
+}
+
+/// Bank "A" to "D" as its index
+fn bank_index(name: &str) -> Option<usize> {
+    (0..MAX_BANKS).find(|bank| name.eq_ignore_ascii_case(&PadBanks::name(*bank).to_string()))
+}
+
+/// Latching pads on the banks in `latch_banks`: a hit sends Note On and lights the pad in
+/// `latch_color`, the next hit on it sends the Note Off and puts the light out. Releasing
+/// the pad does nothing, as a session view's clip launcher expects.
+pub(crate) struct PadLatch {
+    banks: [bool; MAX_BANKS],
+    color: PadColors,
+    /// The bank the latched notes were played on
+    bank: usize,
+    /// Channel and note each pad latched on
+    latched: [Option<(u8, u8)>; 16],
+}
+
+impl PadLatch {
+    /// None without `latch_banks` (validated by `Settings::validate`)
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        if settings.latch_banks.is_empty() {
+            return None;
+        }
+        let mut banks = [false; MAX_BANKS];
+        for name in &settings.latch_banks {
+            banks[bank_index(name).expect("Invalid latch_banks")] = true;
+        }
+        Some(Self {
+            banks,
+            color: PadColors::from_name(&settings.latch_color).expect("Invalid latch_color"),
+            bank: 0,
+            latched: [None; 16],
+        })
+    }
+
+    /// Whether the pads latch on `bank` (0 without pad banks)
+    pub(crate) fn latches(&self, bank: usize) -> bool {
+        self.banks[bank]
+    }
+
+    /// A hit on a latching pad that plays `(channel, note)`: turns the note on, or the note
+    /// it latched off
+    pub(crate) fn hit(
+        &mut self,
+        pad: usize,
+        (channel, note): (u8, u8),
+        velocity: u8,
+        port: &mut MidiOut,
+        at: Timestamp,
+        lights: &mut Lights,
+    ) {
+        if let Some((channel, note)) = self.latched[pad].take() {
+            println!("{} Pad {} Note Off {} (latch)", at, pad, note);
+            port.send(&[0x80 | channel, note, 0], at);
+            lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            return;
+        }
+        println!(
+            "{} Pad {} Note On {} vel {} (latch)",
+            at, pad, note, velocity
+        );
+        port.send(&[0x90 | channel, note, velocity.max(1)], at);
+        lights.set_pad(pad, self.color, Brightness::Bright);
+        self.latched[pad] = Some((channel, note));
+    }
+
+    /// Forgets the latched notes and puts their pads out, e.g. after a panic silenced them,
+    /// so the next hit starts the note again
+    pub(crate) fn clear(&mut self, lights: &mut Lights) {
+        for (pad, latched) in self.latched.iter_mut().enumerate() {
+            if latched.take().is_some() {
+                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            }
+        }
+    }
+
+    /// Lets go of the latched notes once another bank is picked, so none is left on
+    /// without its pad to turn it off; returns whether lights changed
+    pub(crate) fn follow_bank(
+        &mut self,
+        bank: usize,
+        port: &mut MidiOut,
+        at: Timestamp,
+        lights: &mut Lights,
+    ) -> bool {
+        if bank == self.bank {
+            return false;
+        }
+        self.bank = bank;
+        let mut changed = false;
+        for (pad, latched) in self.latched.iter_mut().enumerate() {
+            if let Some((channel, note)) = latched.take() {
+                port.send(&[0x80 | channel, note, 0], at);
+                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,117 @@
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use std::sync::Mutex;
+
+/// Velocity histogram bins per pad (16 velocities each)
+const BINS: usize = 8;
+
+// Recorded by the main loop, read by the control socket
+static PAD_STATS: Mutex<PadStats> = Mutex::new(PadStats::new());
+
+/// Hit counts and velocity distribution of every pad since start (or the last reset)
+struct PadStats {
+    hits: [u32; 16],
+    velocity_sum: [u64; 16],
+    min: [u8; 16],
+    max: [u8; 16],
+    histogram: [[u32; BINS]; 16],
+}
+
+impl PadStats {
+    const fn new() -> Self {
+        Self {
+            hits: [0; 16],
+            velocity_sum: [0; 16],
+            min: [127; 16],
+            max: [0; 16],
+            histogram: [[0; BINS]; 16],
+        }
+    }
+
+    fn average(&self, pad: usize) -> Option<u64> {
+        (self.hits[pad] > 0).then(|| self.velocity_sum[pad] / self.hits[pad] as u64)
+    }
+}
+
+/// Counts a hit on `pad` at `velocity`
+pub(crate) fn record(pad: usize, velocity: u8) {
+    let mut stats = PAD_STATS.lock().unwrap();
+    stats.hits[pad] += 1;
+    stats.velocity_sum[pad] += velocity as u64;
+    stats.min[pad] = stats.min[pad].min(velocity);
+    stats.max[pad] = stats.max[pad].max(velocity);
+    stats.histogram[pad][velocity as usize * BINS / 128] += 1;
+}
+
+pub(crate) fn reset() {
+    *PAD_STATS.lock().unwrap() = PadStats::new();
+}
+
+/// One line per pad, joined with "; " (control socket replies are single lines):
+/// hits, velocity range and average, and the histogram from soft to hard
+pub(crate) fn report() -> String {
+    let stats = PAD_STATS.lock().unwrap();
+    (0..16)
+        .map(|pad| match stats.average(pad) {
+            Some(average) => {
+                let histogram: Vec<String> =
+                    stats.histogram[pad].iter().map(|n| n.to_string()).collect();
+                format!(
+                    "pad {pad}: {} hits, velocity {}-{} avg {average}, histogram {}",
+                    stats.hits[pad],
+                    stats.min[pad],
+                    stats.max[pad],
+                    histogram.join(" ")
+                )
+            }
+            None => format!("pad {pad}: no hits"),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Heatmap page: pads colored by how often they were hit (blue = rarely, red = most),
+/// the screen shows the total hits and the spread of the pads' average velocities.
+pub(crate) struct Heatmap {
+    /// Total hits when last drawn
+    drawn: Option<u32>,
+}
+
+impl Heatmap {
+    pub(crate) fn new() -> Self {
+        Self { drawn: None }
+    }
+
+    pub(crate) fn redraw(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Screen lines and pad colors, when they changed since the last call
+    pub(crate) fn poll(&mut self, lights: &mut Lights) -> Option<[String; 2]> {
+        let stats = PAD_STATS.lock().unwrap();
+        let total: u32 = stats.hits.iter().sum();
+        if self.drawn == Some(total) {
+            return None;
+        }
+        self.drawn = Some(total);
+
+        let most = stats.hits.iter().copied().max().unwrap_or(0).max(1);
+        for (pad, &hits) in stats.hits.iter().enumerate() {
+            let color = match hits * 4 / most {
+                _ if hits == 0 => PadColors::Off,
+                0 => PadColors::Blue,
+                1 => PadColors::Green,
+                2 => PadColors::Yellow,
+                _ => PadColors::Red,
+            };
+            let brightness = if hits == 0 { Brightness::Off } else { Brightness::Normal };
+            lights.set_pad(pad, color, brightness);
+        }
+
+        let averages: Vec<u64> = (0..16).filter_map(|pad| stats.average(pad)).collect();
+        let bottom = match (averages.iter().min(), averages.iter().max()) {
+            (Some(min), Some(max)) => format!("VEL {min}-{max}"),
+            _ => "NO HITS".to_string(),
+        };
+        Some([format!("HITS {total}"), bottom])
+    }
+}
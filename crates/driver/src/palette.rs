@@ -0,0 +1,128 @@
+use crate::settings::Settings;
+use maschine_library::lights::{Brightness, PadColors};
+use serde::Deserialize;
+
+/// Approximate RGB of the pad colors at full brightness
+pub(crate) fn rgb(color: PadColors) -> (u8, u8, u8) {
+    match color {
+        PadColors::Off => (0, 0, 0),
+        PadColors::Red => (255, 0, 0),
+        PadColors::Orange => (255, 96, 0),
+        PadColors::LightOrange => (255, 150, 40),
+        PadColors::WarmYellow => (255, 190, 40),
+        PadColors::Yellow => (255, 240, 0),
+        PadColors::Lime => (160, 255, 0),
+        PadColors::Green => (0, 220, 0),
+        PadColors::Mint => (60, 255, 150),
+        PadColors::Cyan => (0, 230, 230),
+        PadColors::Turquoise => (0, 170, 200),
+        PadColors::Blue => (0, 60, 255),
+        PadColors::Plum => (110, 60, 200),
+        PadColors::Violet => (150, 40, 255),
+        PadColors::Purple => (190, 0, 230),
+        PadColors::Magenta => (255, 0, 200),
+        PadColors::Fuchsia => (255, 0, 120),
+        PadColors::White => (255, 255, 255),
+    }
+}
+
+/// How bright a level looks next to Bright, roughly
+pub(crate) fn level(brightness: Brightness) -> f32 {
+    match brightness {
+        Brightness::Off => 0.0,
+        Brightness::Dim => 0.3,
+        Brightness::Normal => 0.65,
+        Brightness::Bright => 1.0,
+    }
+}
+
+/// Pad color and brightness looking closest to an RGB color (8 bits per channel)
+pub(crate) fn nearest(r: u8, g: u8, b: u8) -> (PadColors, Brightness) {
+    let brightnesses = [Brightness::Dim, Brightness::Normal, Brightness::Bright];
+    let distance = |(color, brightness): &(PadColors, Brightness)| {
+        let (cr, cg, cb) = rgb(*color);
+        let scale = level(*brightness);
+        [(cr, r), (cg, g), (cb, b)]
+            .iter()
+            .map(|&(c, want)| (c as f32 * scale - want as f32).powi(2))
+            .sum::<f32>()
+    };
+    let lit = (PadColors::Red as u8..=PadColors::White as u8)
+        .filter_map(num::FromPrimitive::from_u8)
+        .flat_map(|color| brightnesses.map(|brightness| (color, brightness)));
+    std::iter::once((PadColors::Off, Brightness::Off))
+        .chain(lit)
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap()
+}
+
+/// The built-in velocity colors: a rainbow from red (soft) to white (full)
+fn default_color(velocity: u8) -> PadColors {
+    match velocity {
+        0 => PadColors::Off,
+        1..=7 => PadColors::Red,
+        8..=14 => PadColors::Orange,
+        15..=21 => PadColors::LightOrange,
+        22..=28 => PadColors::WarmYellow,
+        29..=35 => PadColors::Yellow,
+        36..=42 => PadColors::Lime,
+        43..=49 => PadColors::Green,
+        50..=56 => PadColors::Mint,
+        57..=63 => PadColors::Cyan,
+        64..=70 => PadColors::Turquoise,
+        71..=77 => PadColors::Blue,
+        78..=84 => PadColors::Plum,
+        85..=91 => PadColors::Violet,
+        92..=98 => PadColors::Purple,
+        99..=105 => PadColors::Magenta,
+        106..=112 => PadColors::Fuchsia,
+        113..=127 => PadColors::White,
+        _ => PadColors::White,
+    }
+}
+
+/// A range of note velocities lighting the pad in one color:
+/// `{ min = 1, max = 63, color = "Blue" }`
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct VelocityColor {
+    pub min: u8,
+    pub max: u8,
+    pub color: String,
+}
+
+/// Pad colors of incoming note velocities (`velocity_colors`, `velocity_on_color`), looked
+/// up by the MIDI input callback
+#[derive(Clone)]
+pub(crate) struct VelocityPalette {
+    colors: [PadColors; 128],
+}
+
+impl VelocityPalette {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let color = |name: &str, what: &str| {
+            PadColors::from_name(name).ok_or_else(|| format!("{what}: unknown color {name:?}"))
+        };
+        let mut colors: [PadColors; 128] = std::array::from_fn(|v| default_color(v as u8));
+        if !settings.velocity_on_color.is_empty() {
+            let on = color(&settings.velocity_on_color, "velocity_on_color")?;
+            colors[1..].fill(on);
+        }
+        // The first range listed wins where they overlap
+        for range in settings.velocity_colors.iter().rev() {
+            if !(1 <= range.min && range.min <= range.max && range.max <= 127) {
+                return Err(format!(
+                    "velocity_colors: range {}-{} should be within 1 to 127, min <= max",
+                    range.min, range.max
+                ));
+            }
+            let c = color(&range.color, "velocity_colors")?;
+            colors[range.min as usize..=range.max as usize].fill(c);
+        }
+        Ok(Self { colors })
+    }
+
+    /// Pad color of a note-on velocity (0 is off)
+    pub(crate) fn color(&self, velocity: u8) -> PadColors {
+        self.colors[velocity.min(127) as usize]
+    }
+}
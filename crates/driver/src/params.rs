@@ -0,0 +1,128 @@
+use crate::settings::Settings;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Velocity curves, by parameter value
+const VELOCITY_CURVES: [&str; 6] = ["linear", "soft", "hard", "exp", "log", "fixed"];
+/// Aftertouch modes, by parameter value
+const AFTERTOUCH_MODES: [&str; 3] = ["off", "poly", "channel"];
+/// Stored for a parameter the host hasn't set
+const UNSET: u8 = 0xFF;
+
+/// Settings a controller script can change while the driver runs (SysEx Set Param) and
+/// read back (SysEx Get Param). What the host sets overrides the config until the driver
+/// restarts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Param {
+    /// `velocity_curve`: 0 linear, 1 soft, 2 hard, 3 exp, 4 log, 5 fixed
+    VelocityCurve = 1,
+    /// `aftertouch`: 0 off, 1 poly, 2 channel
+    Aftertouch = 2,
+    /// Channel (0-15) of the notes that light the pads, the pad channel by default
+    FeedbackChannel = 3,
+    /// `backlight_buttons`: 0 off, 1 on
+    Backlight = 4,
+    /// `pad_channel`: channel (0-15) of the pads' notes
+    PadChannel = 5,
+}
+
+impl Param {
+    pub(crate) const ALL: [Param; 5] = [
+        Param::VelocityCurve,
+        Param::Aftertouch,
+        Param::FeedbackChannel,
+        Param::Backlight,
+        Param::PadChannel,
+    ];
+
+    pub(crate) fn from_u8(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|param| *param as u8 == id)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Param::VelocityCurve => "velocity_curve",
+            Param::Aftertouch => "aftertouch",
+            Param::FeedbackChannel => "feedback_channel",
+            Param::Backlight => "backlight_buttons",
+            Param::PadChannel => "pad_channel",
+        }
+    }
+
+    pub(crate) fn max(self) -> u8 {
+        match self {
+            Param::VelocityCurve => VELOCITY_CURVES.len() as u8 - 1,
+            Param::Aftertouch => AFTERTOUCH_MODES.len() as u8 - 1,
+            Param::FeedbackChannel | Param::PadChannel => 15,
+            Param::Backlight => 1,
+        }
+    }
+}
+
+// Set by the MIDI input callback (or the settings menu); the main loop applies the changes
+// and sends the replies
+static VALUES: [AtomicU8; 6] = [const { AtomicU8::new(UNSET) }; 6];
+static CHANGED: AtomicBool = AtomicBool::new(false);
+static REQUESTED: Mutex<Vec<Param>> = Mutex::new(Vec::new());
+
+/// Sets a parameter from the host or the settings menu; false if the value is out of range
+pub(crate) fn set(param: Param, value: u8) -> bool {
+    if value > param.max() {
+        return false;
+    }
+    VALUES[param as usize].store(value, Ordering::SeqCst);
+    CHANGED.store(true, Ordering::SeqCst);
+    true
+}
+
+/// The value the host set, if it did
+pub(crate) fn get(param: Param) -> Option<u8> {
+    Some(VALUES[param as usize].load(Ordering::SeqCst)).filter(|v| *v != UNSET)
+}
+
+/// The value in effect: the host's, or the config's
+pub(crate) fn value(param: Param, settings: &Settings) -> u8 {
+    let position = |names: &[&str], name: &str| {
+        let name = name.trim().to_ascii_lowercase();
+        names.iter().position(|n| *n == name).unwrap_or(0) as u8
+    };
+    get(param).unwrap_or_else(|| match param {
+        Param::VelocityCurve => position(&VELOCITY_CURVES, &settings.velocity_curve),
+        Param::Aftertouch => position(&AFTERTOUCH_MODES, &settings.aftertouch),
+        Param::FeedbackChannel => value(Param::PadChannel, settings),
+        Param::Backlight => settings.backlight_buttons as u8,
+        Param::PadChannel => settings.pad_channel,
+    })
+}
+
+pub(crate) fn velocity_curve(settings: &Settings) -> &'static str {
+    VELOCITY_CURVES[value(Param::VelocityCurve, settings) as usize]
+}
+
+pub(crate) fn aftertouch(settings: &Settings) -> &'static str {
+    AFTERTOUCH_MODES[value(Param::Aftertouch, settings) as usize]
+}
+
+/// Forgets what was set, so every parameter is the config's again
+#[cfg(test)]
+pub(crate) fn reset() {
+    for value in &VALUES {
+        value.store(UNSET, Ordering::SeqCst);
+    }
+    CHANGED.store(false, Ordering::SeqCst);
+}
+
+/// Whether a parameter changed since the last call
+pub(crate) fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Asks for the values of `params` to be sent to the host
+pub(crate) fn request(params: &[Param]) {
+    REQUESTED.lock().unwrap().extend_from_slice(params);
+}
+
+/// Parameters whose values the host asked for since the last call
+pub(crate) fn take_requests() -> Vec<Param> {
+    std::mem::take(&mut *REQUESTED.lock().unwrap())
+}
@@ -0,0 +1,107 @@
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+use std::collections::HashMap;
+
+/// Highest bank reachable with Bank Select MSB/LSB (14 bits)
+const MAX_BANK: u16 = 0x3fff;
+
+/// Encoder-driven program browser for hardware synths.
+///
+/// Turning scrolls through programs (rolling over into the next/previous bank), Shift+turn
+/// steps banks, and pushing the encoder sends Bank Select (CC 0/32) + Program Change.
+pub(crate) struct PatchBrowser {
+    channel: u8,
+    bank: u16,
+    program: u8,
+    names: HashMap<(u16, u8), String>,
+}
+
+impl PatchBrowser {
+    pub(crate) fn new(channel: u8, names: HashMap<(u16, u8), String>) -> Self {
+        Self {
+            channel,
+            bank: 0,
+            program: 0,
+            names,
+        }
+    }
+
+    /// Moves by `delta` programs, rolling over bank boundaries
+    pub(crate) fn scroll_program(&mut self, delta: i32) {
+        let max = (MAX_BANK as i32 + 1) * 128 - 1;
+        let pos = (self.bank as i32 * 128 + self.program as i32 + delta).clamp(0, max);
+        self.bank = (pos / 128) as u16;
+        self.program = (pos % 128) as u8;
+    }
+
+    /// Moves by `delta` banks, keeping the program number
+    pub(crate) fn scroll_bank(&mut self, delta: i32) {
+        self.bank = (self.bank as i32 + delta).clamp(0, MAX_BANK as i32) as u16;
+    }
+
+    /// Current bank and program (0-127)
+    pub(crate) fn selection(&self) -> (u16, u8) {
+        (self.bank, self.program)
+    }
+
+    /// Moves to a bank and program without sending anything
+    pub(crate) fn set_selection(&mut self, bank: u16, program: u8) {
+        self.bank = bank.min(MAX_BANK);
+        self.program = program.min(127);
+    }
+
+    /// Sends Bank Select MSB/LSB and Program Change for the current selection
+    pub(crate) fn select(&self, port: &mut MidiOut, at: Timestamp) {
+        let cc = 0xB0 | self.channel;
+        port.send(&[cc, 0, (self.bank >> 7) as u8], at);
+        port.send(&[cc, 32, (self.bank & 0x7f) as u8], at);
+        port.send(&[0xC0 | self.channel, self.program], at);
+        println!(
+            "{at} Patch browser: bank {} program {} {}",
+            self.bank,
+            self.program + 1,
+            self.name().unwrap_or("")
+        );
+    }
+
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.names.get(&(self.bank, self.program)).map(|s| s.as_str())
+    }
+
+    /// Screen lines: bank/program and the patch name
+    pub(crate) fn lines(&self) -> [String; 2] {
+        [
+            format!("B{:03} P{:03}", self.bank, self.program + 1),
+            self.name().unwrap_or("").to_string(),
+        ]
+    }
+}
+
+/// Loads patch names from a text file.
+///
+/// One patch per line: `<program> <name>` or `<bank>:<program> <name>`, programs 1-128
+/// as printed on most synths. Empty lines and lines starting with `#` are ignored.
+pub(crate) fn load_patch_names(path: &str) -> Result<HashMap<(u16, u8), String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let mut names = HashMap::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = || format!("{path}:{}: expected \"[bank:]program name\"", lineno + 1);
+        let (number, name) = line.split_once(char::is_whitespace).ok_or_else(err)?;
+        let (bank, program) = match number.split_once(':') {
+            Some((bank, program)) => (bank.parse::<u16>().ok(), program.parse::<u8>().ok()),
+            None => (Some(0), number.parse::<u8>().ok()),
+        };
+        let (Some(bank), Some(program)) = (bank, program) else {
+            return Err(err());
+        };
+        if bank > MAX_BANK || !(1..=128).contains(&program) {
+            return Err(err());
+        }
+        names.insert((bank, program - 1), name.trim().to_string());
+    }
+    Ok(names)
+}
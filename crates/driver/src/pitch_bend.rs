@@ -0,0 +1,91 @@
+use crate::midi_out::MidiOut;
+use crate::slider::{SLIDER_RAW_MAX, SLIDER_RAW_MIN};
+use crate::timestamp::Timestamp;
+use std::time::{Duration, Instant};
+
+/// 14-bit pitch bend at rest
+const CENTER: u16 = 8192;
+const MAX: u16 = 16383;
+
+/// The touch strip as a pitch strip: the touched position bends (center = no bend) and
+/// letting go glides back to center over the return time, or snaps back with none.
+pub(crate) struct PitchBend {
+    channel: u8,
+    return_time: Duration,
+    /// Last value sent
+    value: u16,
+    /// Value and time the finger was lifted at, while gliding back
+    returning: Option<(u16, Instant)>,
+    /// A value went out that the strip LEDs don't show yet
+    moved: bool,
+}
+
+impl PitchBend {
+    pub(crate) fn new(channel: u8, return_time: Duration) -> Self {
+        Self {
+            channel,
+            return_time,
+            value: CENTER,
+            returning: None,
+            moved: false,
+        }
+    }
+
+    /// Bends to the touched position
+    pub(crate) fn touch(&mut self, raw: u8, port: &mut MidiOut, at: Timestamp) {
+        self.returning = None;
+        let pos = raw.clamp(SLIDER_RAW_MIN, SLIDER_RAW_MAX) - SLIDER_RAW_MIN;
+        let range = (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as u32;
+        // The middle of the strip is exactly center, both halves scale to the ends
+        let half = range / 2;
+        let value = if pos as u32 <= half {
+            pos as u32 * CENTER as u32 / half
+        } else {
+            CENTER as u32 + (pos as u32 - half) * (MAX - CENTER) as u32 / (range - half)
+        };
+        self.send(value as u16, port, at);
+    }
+
+    /// Finger lifted: start gliding back to center
+    pub(crate) fn release(&mut self, now: Instant) {
+        if self.value != CENTER {
+            self.returning = Some((self.value, now));
+        }
+    }
+
+    /// Whether the bend is gliding back, so the main loop should poll on time
+    pub(crate) fn pending(&self) -> bool {
+        self.returning.is_some()
+    }
+
+    /// Sends the next value of a glide back; returns the slider position to show on the
+    /// strip LEDs whenever the bend changed since the last call.
+    pub(crate) fn poll(&mut self, port: &mut MidiOut, at: Timestamp) -> Option<u8> {
+        if let Some((from, since)) = self.returning {
+            let elapsed = at.instant().saturating_duration_since(since);
+            let value = if elapsed >= self.return_time {
+                self.returning = None;
+                CENTER
+            } else {
+                let left = 1.0 - elapsed.as_secs_f32() / self.return_time.as_secs_f32();
+                (CENTER as f32 + (from as f32 - CENTER as f32) * left).round() as u16
+            };
+            self.send(value, port, at);
+        }
+        if !std::mem::take(&mut self.moved) {
+            return None;
+        }
+        let range = (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as u32;
+        Some(SLIDER_RAW_MIN + (self.value as u32 * range / MAX as u32) as u8)
+    }
+
+    fn send(&mut self, value: u16, port: &mut MidiOut, at: Timestamp) {
+        if value == self.value {
+            return;
+        }
+        self.value = value;
+        self.moved = true;
+        let (lsb, msb) = ((value & 0x7F) as u8, (value >> 7) as u8);
+        port.send(&[0xE0 | self.channel, lsb, msb], at);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::settings::Settings;
+use std::time::{Duration, Instant};
+
+/// How long the main loop waits for a HID report while anything is going on
+const BUSY_TIMEOUT_MS: i32 = 1;
+/// How often the CPU audit reports
+const AUDIT_EVERY: Duration = Duration::from_secs(10);
+
+/// How long the main loop waits for a HID report. Normally 1 ms; with `adaptive_polling`
+/// the wait grows to `idle_poll_ms` once nothing has happened for `idle_after_ms`, and
+/// drops back on the next report, host message or running clock.
+pub(crate) struct Polling {
+    /// Wait once idle; None keeps the busy wait all the time
+    idle_timeout_ms: Option<i32>,
+    idle_after: Duration,
+    last_activity: Instant,
+}
+
+impl Polling {
+    pub(crate) fn new(settings: &Settings, now: Instant) -> Self {
+        Self {
+            idle_timeout_ms: settings.adaptive_polling.then_some(settings.idle_poll_ms as i32),
+            idle_after: Duration::from_millis(settings.idle_after_ms),
+            last_activity: now,
+        }
+    }
+
+    /// Something happened that may be followed by more: poll fast for a while
+    pub(crate) fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Read timeout (ms) for the next pass
+    pub(crate) fn timeout_ms(&self, now: Instant) -> i32 {
+        match self.idle_timeout_ms {
+            Some(idle) if now.duration_since(self.last_activity) >= self.idle_after => idle,
+            _ => BUSY_TIMEOUT_MS,
+        }
+    }
+}
+
+/// CPU time of the calling thread and of the whole process
+fn cpu_times() -> (Duration, Duration) {
+    let usage = |who| {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+            return Duration::ZERO;
+        }
+        let time = |t: libc::timeval| {
+            Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64)
+        };
+        time(usage.ru_utime) + time(usage.ru_stime)
+    };
+    (usage(libc::RUSAGE_THREAD), usage(libc::RUSAGE_SELF))
+}
+
+/// `cpu_audit`: every 10 s, logs how much CPU the main loop (the thread it runs on) and the
+/// whole driver used, how many passes the loop made and how many of them were idle
+/// (no HID report), to see what polling costs on a laptop.
+pub(crate) struct CpuAudit {
+    since: Instant,
+    cpu_since: (Duration, Duration),
+    passes: u64,
+    idle_passes: u64,
+}
+
+impl CpuAudit {
+    /// Call from the main loop's thread
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            since: now,
+            cpu_since: cpu_times(),
+            passes: 0,
+            idle_passes: 0,
+        }
+    }
+
+    /// Counts a pass of the main loop, reporting once per interval
+    pub(crate) fn pass(&mut self, now: Instant, idle: bool, timeout_ms: i32) {
+        self.passes += 1;
+        self.idle_passes += idle as u64;
+        let elapsed = now.duration_since(self.since);
+        if elapsed < AUDIT_EVERY {
+            return;
+        }
+        let (thread, process) = cpu_times();
+        let percent = |used: Duration, before: Duration| {
+            (used.saturating_sub(before)).as_secs_f64() * 100.0 / elapsed.as_secs_f64()
+        };
+        println!(
+            "CPU audit: main loop {:.2}%, driver {:.2}% of one core; {:.0} passes/s \
+             ({:.0}% without a report), read timeout {} ms",
+            percent(thread, self.cpu_since.0),
+            percent(process, self.cpu_since.1),
+            self.passes as f64 / elapsed.as_secs_f64(),
+            self.idle_passes as f64 * 100.0 / self.passes as f64,
+            timeout_ms
+        );
+        *self = Self::new(now);
+    }
+}
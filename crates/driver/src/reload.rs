@@ -0,0 +1,158 @@
+use crate::config_file::{self, CliOverrides};
+use crate::settings::Settings;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+// Set by SIGHUP and the config watch, taken by the main loop
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+// Set once reloaded settings are in use, so the main loop says so instead of replaying
+// the boot animation
+static RELOADED: AtomicBool = AtomicBool::new(false);
+/// Settings read and validated by the main loop, for `run` to swap in
+static LOADED: Mutex<Option<Settings>> = Mutex::new(None);
+static SOURCE: OnceLock<Source> = OnceLock::new();
+
+/// How often `watch_config` looks at the file
+const WATCH_EVERY: Duration = Duration::from_secs(1);
+
+/// Where the settings came from: the config file and what the command line overrides
+struct Source {
+    path: Option<String>,
+    overrides: CliOverrides,
+}
+
+extern "C" fn on_hangup(_signal: libc::c_int) {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Makes SIGHUP reload the config file in use (the command line flags given with it
+/// still win)
+pub(crate) fn install(path: Option<&str>, overrides: CliOverrides) {
+    let _ = SOURCE.set(Source {
+        path: path.map(str::to_string),
+        overrides,
+    });
+    let handler = on_hangup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGHUP, handler);
+    }
+}
+
+/// Whether a reload was asked for since the last call
+pub(crate) fn take_request() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Reads the config file again and checks it, as on startup. Settings that only take
+/// effect on startup are logged if they changed, and otherwise ignored.
+pub(crate) fn load(current: &Settings) -> Result<Settings, String> {
+    let source = SOURCE.get().ok_or("no config file")?;
+    let path = source
+        .path
+        .as_deref()
+        .ok_or("started without a config file")?;
+    let (mut settings, notes) = config_file::load(Some(path))?;
+    for note in notes {
+        eprintln!("{note}");
+    }
+    source.overrides.apply(&mut settings);
+    settings.validate()?;
+    settings.apply_layout();
+    let changed = startup_only_changes(current, &settings);
+    if !changed.is_empty() {
+        eprintln!("Restart the driver to apply {}", changed.join(", "));
+    }
+    Ok(settings)
+}
+
+/// Settings `run` sets up once (ports, threads, the second controller), changed from
+/// `current` to `new`
+fn startup_only_changes(current: &Settings, new: &Settings) -> Vec<&'static str> {
+    let checks = [
+        ("client_name", current.client_name != new.client_name),
+        ("port_name", current.port_name != new.port_name),
+        ("port_name_in", current.port_name_in != new.port_name_in),
+        (
+            "merge_input_port",
+            current.merge_input_port != new.merge_input_port,
+        ),
+        (
+            "launchpad_emulation",
+            current.launchpad_emulation != new.launchpad_emulation,
+        ),
+        (
+            "autoconnect_virmidi",
+            current.autoconnect_virmidi != new.autoconnect_virmidi,
+        ),
+        (
+            "sync_controllers",
+            current.sync_controllers != new.sync_controllers,
+        ),
+    ];
+    checks
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Hands reloaded settings from the main loop to `run`
+pub(crate) fn stash(settings: Settings) {
+    *LOADED.lock().unwrap() = Some(settings);
+}
+
+/// Reloaded settings waiting to be swapped in
+pub(crate) fn take_loaded() -> Option<Settings> {
+    LOADED.lock().unwrap().take()
+}
+
+/// Records that reloaded settings are in use
+pub(crate) fn reloaded() {
+    RELOADED.store(true, Ordering::SeqCst);
+}
+
+/// Whether the main loop starts over on reloaded settings
+pub(crate) fn take_reloaded() -> bool {
+    RELOADED.swap(false, Ordering::SeqCst)
+}
+
+/// `watch_config`: asks for a reload whenever the config file's modification time
+/// changes (saving it in an editor, copying a new one over it)
+pub(crate) struct ConfigWatch {
+    path: String,
+    modified: Option<SystemTime>,
+    check_at: Instant,
+}
+
+impl ConfigWatch {
+    /// None unless `watch_config` is on and there is a config file
+    pub(crate) fn new(settings: &Settings, now: Instant) -> Option<Self> {
+        let path = SOURCE.get()?.path.clone()?;
+        if !settings.watch_config {
+            return None;
+        }
+        Some(Self {
+            modified: modified(&path),
+            path,
+            check_at: now + WATCH_EVERY,
+        })
+    }
+
+    pub(crate) fn poll(&mut self, now: Instant) {
+        if now < self.check_at {
+            return;
+        }
+        self.check_at = now + WATCH_EVERY;
+        let modified = modified(&self.path);
+        // A file being replaced may be missing for a moment
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            REQUESTED.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
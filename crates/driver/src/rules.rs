@@ -0,0 +1,166 @@
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use serde::Deserialize;
+
+/// One `[[rules]]` entry: a condition and what to do when it matches, both as short
+/// phrases, e.g. `when = "held Shift and pad 5"`, `then = "note +12"`.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct RuleConfig {
+    pub when: String,
+    pub then: String,
+}
+
+/// The event a rule reacts to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Trigger {
+    /// A pad hit, on one pad or any
+    Pad(Option<usize>),
+    /// A CC received from the host on the input port
+    CcIn(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    /// Play the pad's note shifted by this many semitones
+    NoteOffset(i8),
+    /// Play this note instead of the pad's
+    Note(u8),
+    /// Send a CC instead of the pad's note
+    Cc { cc: u8, value: u8 },
+    /// Light a pad in a color while the incoming CC is non-zero
+    PadColor { pad: usize, color: PadColors },
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    held: Vec<Buttons>,
+    trigger: Trigger,
+    action: Action,
+}
+
+/// What a pad hit does under the rules
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PadOutcome {
+    Note(u8),
+    Cc { cc: u8, value: u8 },
+}
+
+/// The `[[rules]]` of the config, checked in order; the first matching rule wins.
+///
+/// `when` joins conditions with "and": `held <Button>` (any number), then exactly one
+/// trigger, `pad <0-15>`, `pad` (any pad) or `cc <0-127>` (received from the host).
+/// `then` is one action: `note +N` / `note -N` (transpose the pad's note), `note N`,
+/// `cc N V` (pad triggers), or `pad N <color>` (CC triggers).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub(crate) fn new(configs: &[RuleConfig]) -> Result<Self, String> {
+        let rules = configs
+            .iter()
+            .enumerate()
+            .map(|(i, config)| parse_rule(config).map_err(|e| format!("rules: rule {i}: {e}")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Outcome of hitting `pad` (which would play `note`) with the `held` button states,
+    /// or None when no rule matches
+    pub(crate) fn pad_hit(&self, pad: usize, held: &[bool], note: u8) -> Option<PadOutcome> {
+        let rule = self.rules.iter().find(|rule| {
+            matches!(rule.trigger, Trigger::Pad(p) if p.is_none_or(|p| p == pad))
+                && rule.held.iter().all(|b| held[*b as usize])
+        })?;
+        match rule.action {
+            Action::NoteOffset(offset) => {
+                let shifted = (note as i16 + offset as i16).clamp(0, 127);
+                Some(PadOutcome::Note(shifted as u8))
+            }
+            Action::Note(note) => Some(PadOutcome::Note(note)),
+            Action::Cc { cc, value } => Some(PadOutcome::Cc { cc, value }),
+            Action::PadColor { .. } => None,
+        }
+    }
+
+    /// Applies the rules for a CC received from the host; true if one matched (and the CC
+    /// shouldn't be handled further). Button conditions don't apply here.
+    pub(crate) fn cc_in(&self, cc: u8, value: u8, lights: &mut Lights) -> bool {
+        let mut matched = false;
+        for rule in self.rules.iter().filter(|r| r.trigger == Trigger::CcIn(cc)) {
+            if let Action::PadColor { pad, color } = rule.action {
+                if value > 0 {
+                    lights.set_pad(pad, color, Brightness::Normal);
+                } else {
+                    lights.set_pad(pad, PadColors::Off, Brightness::Off);
+                }
+                matched = true;
+            }
+        }
+        matched
+    }
+}
+
+fn parse_rule(config: &RuleConfig) -> Result<Rule, String> {
+    let mut held = Vec::new();
+    let mut trigger = None;
+    for condition in config.when.split(" and ") {
+        let words: Vec<&str> = condition.split_whitespace().collect();
+        let parsed = match words.as_slice() {
+            ["held", name] => {
+                let button =
+                    Buttons::from_name(name).ok_or(format!("unknown button {name:?}"))?;
+                held.push(button);
+                continue;
+            }
+            ["pad"] => Trigger::Pad(None),
+            ["pad", pad] => Trigger::Pad(Some(parse_number(pad, 15)? as usize)),
+            ["cc", cc] => Trigger::CcIn(parse_number(cc, 127)?),
+            _ => return Err(format!("can't understand condition {condition:?}")),
+        };
+        if trigger.replace(parsed).is_some() {
+            return Err("only one pad or cc trigger per rule".to_string());
+        }
+    }
+    let trigger = trigger.ok_or("`when` needs a pad or cc trigger")?;
+
+    let words: Vec<&str> = config.then.split_whitespace().collect();
+    let action = match words.as_slice() {
+        ["note", n] if n.starts_with(['+', '-']) => {
+            let offset: i8 = n.parse().map_err(|_| format!("invalid note offset {n:?}"))?;
+            Action::NoteOffset(offset)
+        }
+        ["note", n] => Action::Note(parse_number(n, 127)?),
+        ["cc", cc, value] => Action::Cc {
+            cc: parse_number(cc, 127)?,
+            value: parse_number(value, 127)?,
+        },
+        ["pad", pad, color] => Action::PadColor {
+            pad: parse_number(pad, 15)? as usize,
+            color: PadColors::from_name(color).ok_or(format!("unknown color {color:?}"))?,
+        },
+        _ => return Err(format!("can't understand action {:?}", config.then)),
+    };
+
+    let lights_pad = matches!(action, Action::PadColor { .. });
+    match trigger {
+        Trigger::Pad(_) if lights_pad => Err("pad triggers can't light pads".to_string()),
+        Trigger::CcIn(_) if !lights_pad => Err("cc triggers can only light pads".to_string()),
+        Trigger::CcIn(_) if !held.is_empty() => {
+            Err("cc triggers can't depend on held buttons".to_string())
+        }
+        _ => Ok(Rule {
+            held,
+            trigger,
+            action,
+        }),
+    }
+}
+
+fn parse_number(s: &str, max: u8) -> Result<u8, String> {
+    s.parse()
+        .ok()
+        .filter(|n| *n <= max)
+        .ok_or(format!("{s:?} should be a number from 0 to {max}"))
+}
@@ -0,0 +1,71 @@
+use serde::Deserialize;
+
+/// A kit entry in the config: the WAV file played when `pad` is hit
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct KitSample {
+    /// Logical pad index (0-15)
+    pub pad: usize,
+    pub file: String,
+}
+
+#[cfg(feature = "sampler")]
+pub(crate) use engine::Sampler;
+
+#[cfg(feature = "sampler")]
+mod engine {
+    use super::KitSample;
+    use rodio::source::Buffered;
+    use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    type Sample = Buffered<Decoder<BufReader<File>>>;
+
+    /// Plays a WAV per pad on the default audio output, without any DAW.
+    ///
+    /// Gain follows the square of the velocity, which sounds closer to how drum
+    /// machines respond than a linear scale.
+    pub(crate) struct Sampler {
+        // Audio stops when the stream is dropped
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+        samples: [Option<Sample>; 16],
+        volume: f32,
+    }
+
+    impl Sampler {
+        pub(crate) fn new(kit: &[KitSample], volume: f32) -> Result<Self, String> {
+            let (stream, handle) =
+                OutputStream::try_default().map_err(|e| format!("no audio output: {e}"))?;
+            let mut samples: [Option<Sample>; 16] = Default::default();
+            for entry in kit {
+                let file = File::open(&entry.file)
+                    .map_err(|e| format!("can't open {}: {e}", entry.file))?;
+                let sample = Decoder::new(BufReader::new(file))
+                    .map_err(|e| format!("can't decode {}: {e}", entry.file))?
+                    .buffered();
+                // Decode up front so the first hit doesn't
+                sample.clone().for_each(drop);
+                samples[entry.pad] = Some(sample);
+            }
+            Ok(Self {
+                _stream: stream,
+                handle,
+                samples,
+                volume,
+            })
+        }
+
+        /// Plays the pad's sample, if any, over whatever is still sounding
+        pub(crate) fn play(&self, pad: usize, velocity: u8) {
+            let Some(sample) = &self.samples[pad] else {
+                return;
+            };
+            let gain = self.volume * (velocity as f32 / 127.0).powi(2);
+            let source = sample.clone().amplify(gain).convert_samples::<f32>();
+            if let Err(e) = self.handle.play_raw(source) {
+                eprintln!("Sampler: can't play pad {pad}: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A scene defined in the config: CC values sent when `pad` is hit in scene mode
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct SceneConfig {
+    /// Logical pad index (0-15)
+    pub pad: usize,
+    /// `[cc, value]` pairs
+    pub ccs: Vec<[u8; 2]>,
+    /// Shown on the screen when recalled
+    #[serde(default)]
+    pub name: String,
+}
+
+/// CC snapshots stored on pads and recalled as a burst.
+///
+/// Snapshots capture the last value of every absolute CC the driver has sent (e.g. the
+/// slider); relative encoder output has no value to capture and is left out.
+pub(crate) struct Scenes {
+    slots: [Option<Vec<[u8; 3]>>; 16],
+    names: [String; 16],
+    /// Last value sent per (status byte, CC)
+    last_values: BTreeMap<(u8, u8), u8>,
+    recalled: Option<usize>,
+}
+
+impl Scenes {
+    pub(crate) fn new(channel: u8, configured: &[SceneConfig]) -> Self {
+        let mut slots: [Option<Vec<[u8; 3]>>; 16] = Default::default();
+        let mut names: [String; 16] = Default::default();
+        for scene in configured {
+            names[scene.pad] = scene.name.clone();
+            let msgs = scene
+                .ccs
+                .iter()
+                .map(|[cc, value]| [0xB0 | channel, *cc, *value])
+                .collect();
+            slots[scene.pad] = Some(msgs);
+        }
+        Self {
+            slots,
+            names,
+            last_values: BTreeMap::new(),
+            recalled: None,
+        }
+    }
+
+    /// Remembers an absolute CC the driver sent, for the next snapshot
+    pub(crate) fn observe_cc(&mut self, status: u8, cc: u8, value: u8) {
+        self.last_values.insert((status, cc), value);
+    }
+
+    /// Stores the current CC values on the pad
+    pub(crate) fn store(&mut self, pad: usize) {
+        let msgs: Vec<[u8; 3]> = self
+            .last_values
+            .iter()
+            .map(|(&(status, cc), &value)| [status, cc, value])
+            .collect();
+        println!("Scene {pad} stored ({} CCs)", msgs.len());
+        self.slots[pad] = Some(msgs);
+    }
+
+    pub(crate) fn name(&self, pad: usize) -> &str {
+        &self.names[pad]
+    }
+
+    pub(crate) fn set_name(&mut self, pad: usize, name: String) {
+        println!("Scene {pad} named {name:?}");
+        self.names[pad] = name;
+    }
+
+    /// Screen lines for the pad's scene: its number and name
+    pub(crate) fn lines(&self, pad: usize) -> [String; 2] {
+        [format!("SCENE {pad}"), self.names[pad].clone()]
+    }
+
+    /// Sends the pad's snapshot, if any; returns whether there was one
+    pub(crate) fn recall(&mut self, pad: usize, port: &mut MidiOut, at: Timestamp) -> bool {
+        let Some(msgs) = &self.slots[pad] else {
+            return false;
+        };
+        for msg in msgs {
+            port.send(msg, at);
+            self.last_values.insert((msg[0], msg[1]), msg[2]);
+        }
+        self.recalled = Some(pad);
+        println!("{at} Scene {pad} recalled ({} CCs)", msgs.len());
+        true
+    }
+
+    /// Pad colors: stored scenes blue, the last recalled one white, empty pads off
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        for (pad, slot) in self.slots.iter().enumerate() {
+            match slot {
+                _ if self.recalled == Some(pad) => {
+                    lights.set_pad(pad, PadColors::White, Brightness::Bright)
+                }
+                Some(_) => lights.set_pad(pad, PadColors::Blue, Brightness::Normal),
+                None => lights.set_pad(pad, PadColors::Off, Brightness::Off),
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::render_screen_text;
+use hidapi::HidResult;
+use maschine_library::hid::HidTransport;
+use maschine_library::screen::Screen;
+use std::time::Instant;
+
+/// Screens stacked over the shared one (host SysEx and the driver's regular content).
+/// A higher layer hides the lower ones while it's up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Layer {
+    /// Pages the user opened (status page, practice timer) or the now playing ticker
+    Page,
+    /// Short-lived driver messages: transport state, layout map
+    Overlay,
+    /// Alerts: PANIC, HOST LOST
+    Alert,
+    /// The stage light's fully lit screen, over everything while it's on
+    StageLight,
+}
+
+const LAYERS: usize = Layer::StageLight as usize + 1;
+
+struct Shown {
+    screen: Screen,
+    /// None stays until dismissed
+    until: Option<Instant>,
+}
+
+/// Decides which screen reaches the device, so subsystems don't overwrite each other.
+///
+/// Each layer holds at most one screen, optionally dismissed after a timeout. The topmost
+/// one is written by `flush`; with all layers empty, the shared screen shows through and
+/// the caller writes it as before.
+pub(crate) struct ScreenLayers {
+    layers: [Option<Shown>; LAYERS],
+    /// The topmost screen changed since the last `flush`
+    dirty: bool,
+    /// The last layer went away since the last `poll`
+    revealed: bool,
+}
+
+impl ScreenLayers {
+    pub(crate) fn new() -> Self {
+        Self {
+            layers: [const { None }; LAYERS],
+            dirty: false,
+            revealed: false,
+        }
+    }
+
+    /// Puts `screen` on `layer`, replacing what was there
+    pub(crate) fn show(&mut self, layer: Layer, screen: Screen, until: Option<Instant>) {
+        self.layers[layer as usize] = Some(Shown { screen, until });
+        self.dirty |= self.top() == Some(layer);
+    }
+
+    /// Shows `text` centered on `layer`
+    pub(crate) fn show_text(&mut self, layer: Layer, text: &str, until: Option<Instant>) {
+        let mut screen = Screen::new();
+        render_screen_text(&mut screen, text);
+        self.show(layer, screen, until);
+    }
+
+    pub(crate) fn dismiss(&mut self, layer: Layer) {
+        let was_top = self.top() == Some(layer);
+        if self.layers[layer as usize].take().is_some() && was_top {
+            // Either a lower layer or the shared screen comes back
+            if self.covered() {
+                self.dirty = true;
+            } else {
+                self.revealed = true;
+            }
+        }
+    }
+
+    /// Whether any layer hides the shared screen
+    pub(crate) fn covered(&self) -> bool {
+        self.top().is_some()
+    }
+
+    /// Dismisses the layers whose time is up. True when the shared screen shows again
+    /// (after a timeout or a `dismiss`) and needs writing.
+    pub(crate) fn poll(&mut self, now: Instant) -> bool {
+        for layer in [Layer::StageLight, Layer::Alert, Layer::Overlay, Layer::Page] {
+            let expired = self.layers[layer as usize]
+                .as_ref()
+                .is_some_and(|shown| shown.until.is_some_and(|until| now >= until));
+            if expired {
+                self.dismiss(layer);
+            }
+        }
+        std::mem::take(&mut self.revealed) && !self.covered()
+    }
+
+    /// Writes the topmost layer if it changed
+    pub(crate) fn flush(&mut self, device: &impl HidTransport) -> HidResult<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+        match self.layers.iter().rev().flatten().next() {
+            Some(shown) => shown.screen.write(device),
+            None => Ok(()),
+        }
+    }
+
+    /// The highest layer shown
+    pub(crate) fn top(&self) -> Option<Layer> {
+        [Layer::StageLight, Layer::Alert, Layer::Overlay, Layer::Page]
+            .into_iter()
+            .find(|l| self.layers[*l as usize].is_some())
+    }
+}
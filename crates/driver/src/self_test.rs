@@ -1,16 +1,47 @@
-use hidapi::{HidDevice, HidResult};
+use hidapi::HidResult;
 use maschine_library::font::Font;
+use maschine_library::hid::HidTransport;
 use maschine_library::lights::{Brightness, Lights, PadColors};
 use maschine_library::screen::Screen;
-use std::{thread, time};
+use std::thread;
+use std::time::Duration;
 
+/// How much of the self-test the default startup animation plays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SelfTest {
+    /// Every pad, button and slider LED in turn (about 2.2 s)
+    Full,
+    /// The pads, then every LED at once (under a second)
+    Quick,
+    Off,
+}
+
+pub(crate) fn parse_self_test(s: &str) -> Result<SelfTest, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "full" => Ok(SelfTest::Full),
+        "quick" => Ok(SelfTest::Quick),
+        "off" => Ok(SelfTest::Off),
+        other => Err(format!(
+            "invalid self_test={other:?} (expected: \"full\", \"quick\", \"off\")"
+        )),
+    }
+}
+
+/// The default startup animation: "LAVA" on the screen, then frames for `LightShow`
+/// drawn over `base`. `quick` lights the buttons and the slider all at once instead of
+/// one by one.
 pub(crate) fn self_test(
-    device: &HidDevice,
     screen: &mut Screen,
-    lights: &mut Lights,
-) -> HidResult<()> {
+    base: &Lights,
+    quick: bool,
+) -> Vec<(Lights, Duration)> {
     Font::write_str(screen, 0, 0, "LAVA", 4);
-    screen.write(device)?;
+
+    let mut frames = Vec::new();
+    let mut lights = base.clone();
+    lights.reset();
+    let mut push =
+        |lights: &Lights, ms: u64| frames.push((lights.clone(), Duration::from_millis(ms)));
 
     // Rainbow colors for funky cycling
     let rainbow = [
@@ -33,8 +64,23 @@ pub(crate) fn self_test(
             let color_idx = (i + frame * 2) % rainbow.len();
             lights.set_pad(i, rainbow[color_idx], Brightness::Bright);
         }
-        lights.write(device)?;
-        thread::sleep(time::Duration::from_millis(50));
+        push(&lights, 50);
+    }
+
+    if quick {
+        // Every button and slider LED at once (300ms), then the final flash
+        for i in 0..39 {
+            lights.set_button(num::FromPrimitive::from_u32(i).unwrap(), Brightness::Bright);
+        }
+        for i in 0..25 {
+            lights.set_slider(i, Brightness::Bright);
+        }
+        push(&lights, 300);
+        for i in 0..16 {
+            lights.set_pad(i, PadColors::White, Brightness::Bright);
+        }
+        push(&lights, 200);
+        return frames;
     }
 
     // Spinning rainbow on pads (6 rotations, ~40ms each = 240ms)
@@ -43,15 +89,13 @@ pub(crate) fn self_test(
             let color_idx = (i + rotation * 3) % rainbow.len();
             lights.set_pad(i, rainbow[color_idx], Brightness::Bright);
         }
-        lights.write(device)?;
-        thread::sleep(time::Duration::from_millis(40));
+        push(&lights, 40);
     }
 
     // Cascade buttons from left to right (39 buttons, ~15ms each = 585ms)
     for i in 0..39 {
         lights.set_button(num::FromPrimitive::from_u32(i).unwrap(), Brightness::Bright);
-        lights.write(device)?;
-        thread::sleep(time::Duration::from_millis(15));
+        push(&lights, 15);
     }
 
     // Slider chase effect (25 positions × 2 passes × 15ms = 750ms)
@@ -61,8 +105,7 @@ pub(crate) fn self_test(
             if i > 0 {
                 lights.set_slider(i - 1, Brightness::Dim);
             }
-            lights.write(device)?;
-            thread::sleep(time::Duration::from_millis(15));
+            push(&lights, 15);
         }
     }
 
@@ -70,12 +113,35 @@ pub(crate) fn self_test(
     for i in 0..16 {
         lights.set_pad(i, PadColors::White, Brightness::Bright);
     }
-    lights.write(device)?;
-    thread::sleep(time::Duration::from_millis(200));
+    push(&lights, 200);
 
-    // Fade to off
-    lights.reset();
+    frames
+}
+
+/// `self-test`: plays the full self-test on the controller for checking the hardware, with
+/// the last frame (every LED lit) held until Enter is pressed
+pub(crate) fn run(device: &impl HidTransport) -> HidResult<()> {
+    let mut screen = Screen::new();
+    let frames = self_test(&mut screen, &Lights::new(), false);
+    screen.write(device)?;
+    println!("Self-test: pads, buttons, then the slider; every LED should light up");
+    for (lights, duration) in &frames {
+        lights.write(device)?;
+        thread::sleep(*duration);
+    }
+    // All pads white, all buttons bright: a dark LED or a wrong color stands out
+    let mut lights = frames
+        .last()
+        .map_or_else(Lights::new, |(lights, _)| lights.clone());
+    for i in 0..25 {
+        lights.set_slider(i, Brightness::Bright);
+    }
     lights.write(device)?;
+    println!("Everything is lit now; press Enter to finish");
+    let _ = std::io::stdin().read_line(&mut String::new());
 
-    Ok(())
+    screen.reset();
+    screen.write(device)?;
+    lights.reset();
+    lights.write(device)
 }
@@ -0,0 +1,194 @@
+use crate::clock::PPQN;
+use crate::keyboard::note_name;
+use crate::layouts;
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use crate::transport::{self, TransportState};
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Steps in a pattern, one per pad
+pub(crate) const STEPS: usize = 16;
+/// Steps are 16th notes
+const STEP_PULSES: u64 = PPQN / 4;
+/// Full swing pushes every second 16th to the last third of its 8th (a triplet feel)
+const MAX_SWING_PULSES: f64 = STEP_PULSES as f64 / 3.0;
+
+/// Step sequencer (`pad_mode = "sequencer"`): each pad's sound (the note it plays) has a
+/// pattern of 16th-note steps, and the pads show and toggle the steps of the selected one.
+/// All patterns play together, on the host's MIDI clock while it plays, or on the
+/// internal clock while that runs.
+pub(crate) struct Sequencer {
+    /// Velocity of each step, per sound; None: the step is off
+    patterns: [[Option<u8>; STEPS]; 16],
+    /// Sound whose steps are on the pads
+    sound: usize,
+    length: usize,
+    /// 0-1 of `MAX_SWING_PULSES`
+    swing: f64,
+    channel: u8,
+    /// Step playing; None while stopped
+    playhead: Option<usize>,
+    /// Host clock pulses already seen
+    host_pulses: u64,
+    /// Time per pulse, measured, to turn swing into a delay
+    pulse_period: Duration,
+    last_pulses: Option<Instant>,
+    /// A swung step waiting for its time, and the pulse it belongs to
+    pending: Option<(Instant, usize, u64)>,
+    /// Notes on, with the pulse they end at
+    sounding: Vec<(u8, u64)>,
+}
+
+impl Sequencer {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        Self {
+            patterns: [[None; STEPS]; 16],
+            sound: 0,
+            length: settings.sequencer_length,
+            swing: settings.sequencer_swing as f64 / 100.0,
+            channel: settings.pad_channel,
+            playhead: None,
+            host_pulses: transport::pulses(),
+            // 120 BPM until measured
+            pulse_period: Duration::from_secs_f64(60.0 / 120.0 / PPQN as f64),
+            last_pulses: None,
+            pending: None,
+            sounding: Vec::new(),
+        }
+    }
+
+    /// Whether the pattern runs
+    pub(crate) fn playing(&self) -> bool {
+        self.playhead.is_some()
+    }
+
+    /// Turns a step of the selected sound on (at `velocity`) or off
+    pub(crate) fn toggle(&mut self, step: usize, velocity: u8) {
+        let step = &mut self.patterns[self.sound][step];
+        *step = match step {
+            Some(_) => None,
+            None => Some(velocity),
+        };
+    }
+
+    /// Puts the steps of the sound `pad` plays on the pads. Returns the screen lines to show.
+    pub(crate) fn select(&mut self, pad: usize) -> [String; 2] {
+        self.sound = pad;
+        let steps = self.patterns[pad].iter().flatten().count();
+        let note = layouts::active_note(pad).map_or("NO NOTE".to_string(), note_name);
+        [
+            format!("SOUND {} {note}", pad + 1),
+            format!("{steps} STEPS"),
+        ]
+    }
+
+    /// Steps through the pulses that became due. `internal` are the internal clock's pulses
+    /// since the count-in ended, if it's running. True when the playhead moved.
+    pub(crate) fn poll(
+        &mut self,
+        internal: Option<Range<u64>>,
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) -> bool {
+        let now = at.instant();
+        let pulses = match (self.host_pulses(), internal) {
+            (Some(pulses), _) | (None, Some(pulses)) => pulses,
+            (None, None) => {
+                // Stopped: nothing may keep sounding
+                self.pending = None;
+                self.release(u64::MAX, port, at);
+                return self.playhead.take().is_some();
+            }
+        };
+        if !pulses.is_empty() {
+            if let Some(last) = self.last_pulses {
+                let count = (pulses.end - pulses.start) as u32;
+                self.pulse_period = now.saturating_duration_since(last) / count;
+            }
+            self.last_pulses = Some(now);
+        }
+
+        let mut moved = false;
+        for pulse in pulses {
+            self.release(pulse, port, at);
+            if !pulse.is_multiple_of(STEP_PULSES) {
+                continue;
+            }
+            let step = (pulse / STEP_PULSES) as usize % self.length;
+            self.playhead = Some(step);
+            moved = true;
+            // A step still waiting goes out before the next one
+            if let Some((_, step, pulse)) = self.pending.take() {
+                self.trigger(step, pulse, port, at);
+            }
+            if step % 2 == 1 && self.swing > 0.0 {
+                let delay = self.pulse_period.mul_f64(self.swing * MAX_SWING_PULSES);
+                self.pending = Some((now + delay, step, pulse));
+            } else {
+                self.trigger(step, pulse, port, at);
+            }
+        }
+        if let Some((due, step, pulse)) = self.pending
+            && now >= due
+        {
+            self.pending = None;
+            self.trigger(step, pulse, port, at);
+        }
+        moved
+    }
+
+    /// Host clock pulses since the last poll, or None unless the host plays
+    fn host_pulses(&mut self) -> Option<Range<u64>> {
+        let received = transport::pulses();
+        // A Start puts the count back to zero; Continue carries on from where it was
+        let seen = if received < self.host_pulses {
+            0
+        } else {
+            self.host_pulses
+        };
+        self.host_pulses = received;
+        // Pulse n is the n-th since Start, counted from zero
+        (transport::state() == TransportState::Playing).then_some(seen..received)
+    }
+
+    /// Plays every sound with the step on
+    fn trigger(&mut self, step: usize, pulse: u64, port: &mut MidiOut, at: Timestamp) {
+        for (sound, pattern) in self.patterns.iter().enumerate() {
+            let (Some(velocity), Some(note)) = (pattern[step], layouts::active_note(sound)) else {
+                continue;
+            };
+            port.send(&[0x90 | self.channel, note, velocity], at);
+            // Ends just before the next step
+            self.sounding.push((note, pulse + STEP_PULSES - 1));
+        }
+    }
+
+    /// Sends the note offs due at `pulse`
+    fn release(&mut self, pulse: u64, port: &mut MidiOut, at: Timestamp) {
+        let channel = self.channel;
+        self.sounding.retain(|&(note, until)| {
+            if until > pulse {
+                return true;
+            }
+            port.send(&[0x80 | channel, note, 0], at);
+            false
+        });
+    }
+
+    /// Lights the selected sound's steps: set steps orange, empty ones dim blue, steps past
+    /// the pattern length dark, the playhead white
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        for (step, velocity) in self.patterns[self.sound].iter().enumerate() {
+            let (color, brightness) = match velocity {
+                _ if self.playhead == Some(step) => (PadColors::White, Brightness::Bright),
+                _ if step >= self.length => (PadColors::Off, Brightness::Off),
+                Some(_) => (PadColors::Orange, Brightness::Normal),
+                None => (PadColors::Blue, Brightness::Dim),
+            };
+            lights.set_pad(step, color, brightness);
+        }
+    }
+}
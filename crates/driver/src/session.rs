@@ -0,0 +1,142 @@
+use crate::layouts::layout_notemaps;
+use crate::pad_banks::PadBanks;
+use crate::settings::Settings;
+use std::fs::File;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by the SysEx Reset Session command (MIDI input thread) and `driver reset-session`
+static RESET_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the main loop to forget the session and go back to the config's defaults
+pub(crate) fn request_reset() {
+    RESET_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Whether a reset was asked for since the last call
+pub(crate) fn take_reset() -> bool {
+    RESET_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Pad notes of a layout preset, or the config's `notemaps` for none
+pub(crate) fn layout_notes(layout: &str, settings: &Settings) -> Vec<u8> {
+    match layout_notemaps(layout) {
+        Ok(notes) if !layout.is_empty() => notes.to_vec(),
+        _ => settings.notemaps.clone(),
+    }
+}
+
+/// What the player changed on the controller during a session and gets back after a
+/// restart or a reconnect: pad layout, keyboard/notes toggle, pad bank, octave and patch
+/// browser position. Saved to `session_file` whenever it changes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Session {
+    /// Layout preset picked with the encoder; empty keeps the config's pads
+    pub layout: String,
+    /// Whether the layout toggle switched the pads to keyboard mode
+    pub keyboard_active: bool,
+    /// Pad bank (0 = A)
+    pub pad_bank: usize,
+    pub octave: i8,
+    pub bank: u16,
+    pub program: u8,
+}
+
+impl Session {
+    /// The session a fresh start gets: what the config sets up
+    pub(crate) fn new(settings: &Settings) -> Self {
+        Self {
+            layout: settings.layout.clone(),
+            keyboard_active: settings.pad_mode == "keyboard",
+            ..Default::default()
+        }
+    }
+
+    /// Pad notes of the session's layout (the config's `notemaps` without one)
+    pub(crate) fn notemaps(&self, settings: &Settings) -> Vec<u8> {
+        layout_notes(&self.layout, settings)
+    }
+
+    /// Loads the session saved by the driver: one `<key> = <value>` per line. A missing file
+    /// is a fresh session; unknown keys are skipped so older files keep loading.
+    pub(crate) fn load(path: &str, defaults: Session) -> Result<Self, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(defaults),
+            Err(e) => return Err(format!("can't read {path}: {e}")),
+        };
+        let mut session = defaults;
+        for (lineno, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = || format!("{path}:{}: expected \"key = value\"", lineno + 1);
+            let (key, value) = line.split_once('=').ok_or_else(err)?;
+            let value = value.trim();
+            match key.trim() {
+                "layout" => {
+                    if !value.is_empty() {
+                        layout_notemaps(value).map_err(|e| format!("{path}: {e}"))?;
+                    }
+                    session.layout = value.to_string();
+                }
+                "keyboard_active" => session.keyboard_active = value.parse().map_err(|_| err())?,
+                "pad_bank" => session.pad_bank = value.parse().map_err(|_| err())?,
+                "octave" => session.octave = value.parse().map_err(|_| err())?,
+                "bank" => session.bank = value.parse().map_err(|_| err())?,
+                "program" => session.program = value.parse().map_err(|_| err())?,
+                _ => {}
+            }
+        }
+        session.octave = session.octave.clamp(-4, 4);
+        session.program = session.program.min(127);
+        Ok(session)
+    }
+
+    pub(crate) fn save(&self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        let text = format!(
+            "# Session state (written by the driver)\n\
+             layout = {}\nkeyboard_active = {}\npad_bank = {}\noctave = {}\nbank = {}\n\
+             program = {}\n",
+            self.layout,
+            self.keyboard_active,
+            self.pad_bank,
+            self.octave,
+            self.bank,
+            self.program
+        );
+        // Written aside, synced and moved over, so a power cut can't leave half a file
+        let partial = format!("{path}.tmp");
+        let saved = File::create(&partial)
+            .and_then(|mut file| {
+                file.write_all(text.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|()| std::fs::rename(&partial, path));
+        if let Err(e) = saved {
+            eprintln!("Can't save session to {path}: {e}");
+        }
+    }
+
+    /// Screen lines naming the session: the pad layout, then the pad bank (with `banks`)
+    /// and octave
+    pub(crate) fn lines(&self, banks: bool) -> [String; 2] {
+        let layout = match self.layout.as_str() {
+            _ if self.keyboard_active => "KEYBOARD".to_string(),
+            "" => "NOTES".to_string(),
+            layout => layout.to_ascii_uppercase(),
+        };
+        let mut details = Vec::new();
+        if banks {
+            details.push(format!("BANK {}", PadBanks::name(self.pad_bank)));
+        }
+        if self.octave != 0 {
+            details.push(format!("OCT {:+}", self.octave));
+        }
+        [layout, details.join(" ")]
+    }
+}
@@ -1,18 +1,162 @@
+use crate::aftertouch::{AftertouchCcRoute, FxThrow};
+use crate::button_map::{ButtonMap, ButtonMapping};
+use crate::chords::{ChordConfig, PadChords};
+use crate::encoder_hold::HOLD_MAX_MS;
+use crate::keyboard::{parse_scale, validate_split};
+use crate::gate::Gates;
+use crate::gestures::{ButtonGestures, validate_gestures};
+use crate::pad_banks::{MAX_BANKS, PadBanks};
+use crate::pad_latch::validate_latch;
+use crate::palette::{VelocityColor, VelocityPalette};
+use crate::params::{self, Param};
+use crate::layouts::layout_notemaps;
+use crate::light_show::LightFrame;
+use crate::metronome::parse_metronome_mode;
+use crate::note_repeat::parse_rate;
+use crate::sampler::KitSample;
+use crate::desk::{DeskPage, validate_combo, validate_pages};
+use crate::encoder::parse_relative_encoding;
+use crate::encoder_actions::{EncoderAction, EncoderBinding, parse_encoder_action};
+use crate::rules::{RuleConfig, Rules};
+use crate::scenes::SceneConfig;
+use crate::sequencer::STEPS;
+use crate::self_test::parse_self_test;
+use crate::shift_layer::{ShiftMapping, validate_mappings};
+use crate::slider::{SliderMode, parse_crossfader_curve, parse_slider_curve, parse_slider_mode};
+use crate::slider_zones::SliderZone;
+use crate::velocity::{VelocityMap, VelocitySplit, VelocitySplits};
+use maschine_library::controls::{Buttons, PadTransform};
+use maschine_library::lights::{Brightness, Lights};
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A button backlit at a different level than `backlight_brightness`
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct BacklightOverride {
+    /// Button name as in `Buttons`
+    pub button: String,
+    /// "dim", "normal" or "bright"
+    pub level: String,
+}
+
+/// Buttons a SysEx Button Group message lights together: `{ name = "mixer", buttons = [...] }`
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ButtonGroup {
+    pub name: String,
+    /// Button names as in `Buttons`
+    pub buttons: Vec<String>,
+}
+
+/// Groups a SysEx Button Group message can name without any `button_groups` in the config
+const BUILTIN_BUTTON_GROUPS: [(&str, &[Buttons]); 3] = [
+    (
+        "transport",
+        &[
+            Buttons::Restart,
+            Buttons::Erase,
+            Buttons::Tap,
+            Buttons::Follow,
+            Buttons::Play,
+            Buttons::Rec,
+            Buttons::Stop,
+        ],
+    ),
+    (
+        "pad_mode",
+        &[Buttons::FixedVol, Buttons::PadMode, Buttons::Keyboard, Buttons::Chords, Buttons::Step],
+    ),
+    (
+        "pattern",
+        &[
+            Buttons::Scene,
+            Buttons::Pattern,
+            Buttons::Events,
+            Buttons::Variation,
+            Buttons::Duplicate,
+            Buttons::Select,
+            Buttons::Solo,
+            Buttons::Mute,
+        ],
+    ),
+];
 
 #[derive(Deserialize, Debug)]
 #[serde(default)]
 pub(crate) struct Settings {
     pub notemaps: Vec<u8>,
+    /// Built-in pad layout replacing `notemaps`: "maschine", "chromatic", "ableton",
+    /// "gm_drums". Empty uses `notemaps`.
+    pub layout: String,
+    /// How the pad grid is turned, for left-handed players or a rotated controller:
+    /// "identity", "mirror_h", "mirror_v", "rotate_180". Notes, lights and animations follow.
+    pub pad_layout_transform: String,
     pub client_name: String,
     pub port_name: String,
     pub port_name_in: String,
+    /// MIDI channels (0-15) of the pads (notes, pressure, also their LEDs), the buttons
+    /// (also their LEDs), the encoder and the slider
+    pub pad_channel: u8,
+    pub button_channel: u8,
+    pub encoder_channel: u8,
+    pub slider_channel: u8,
+    /// CC of the first button (the others follow in `Buttons` order, 41 CCs in all)
+    pub button_cc_offset: u8,
+    /// Buttons sending their own CC or note instead, by button name:
+    /// `play = { type = "cc", number = 115 }`, `rec = { type = "note", number = 95 }`
+    /// (`channel` defaults to `button_channel`). The host lights them with the same message.
+    /// `latch = true` makes a button a toggle that lights up by itself.
+    pub button_mappings: BTreeMap<String, ButtonMapping>,
+    /// CC of the encoder's relative turns (65+ = CW, 63- = CCW with the default
+    /// `encoder_encoding`)
+    pub encoder_cc: u8,
+    /// CC of the slider's absolute position
+    pub slider_cc: u8,
+    /// Send the slider as a 14-bit CC pair: `slider_cc` (0-31) and `slider_cc + 32`
+    pub slider_high_resolution: bool,
+    /// Response of `slider_cc` to the finger position (`slider_mode = "cc"`): "linear",
+    /// "exponential", "logarithmic" or "s_curve".
+    pub slider_curve: String,
+    /// Send `slider_cc` from the right end of the strip (127) to the left (0).
+    pub slider_invert: bool,
+    /// Send the "expression" encoder's CC as a 14-bit pair: `expression_cc` (0-31) and
+    /// `expression_cc + 32`
+    pub encoder_high_resolution: bool,
     /// If true, treat "LED Off" for buttons as a low backlight instead.
     /// Useful as a "night mode" so you can see buttons in the dark.
     pub backlight_buttons: bool,
     /// Backlight level for buttons when `backlight_buttons = true`.
     /// Valid values: "dim", "normal", "bright".
     pub backlight_brightness: String,
+    /// Buttons that stay dark in backlight mode, e.g. ["Play", "Rec", "Stop"].
+    pub backlight_exclude: Vec<String>,
+    /// Buttons backlit at their own level: `{ button = "Shift", level = "normal" }`.
+    pub backlight_overrides: Vec<BacklightOverride>,
+    /// Named sets of buttons the SysEx Button Group command lights in one message, next to
+    /// the built-in "all", "transport", "pad_mode" and "pattern" (a same-named group here
+    /// replaces the built-in one).
+    pub button_groups: Vec<ButtonGroup>,
+    /// What buttons send while Shift is held, by button name: `Play = { cc = 100 }`,
+    /// `Rec = { note = 60, channel = 1 }` or `Stop = { command = "panic" }`.
+    pub shift_mappings: BTreeMap<String, ShiftMapping>,
+    /// Long press and double tap gestures by button name, each a CC, note or command as in
+    /// `shift_mappings`: `Rec = { long_press = { cc = 100 }, double_tap = { note = 60 } }`.
+    /// `long_press_ms`/`double_tap_ms` in an entry override the ones below.
+    pub button_gestures: BTreeMap<String, ButtonGestures>,
+    /// How long (ms) a button is held before its long press goes off
+    pub long_press_ms: u64,
+    /// Longest time (ms) from one press to the next for a double tap
+    pub double_tap_ms: u64,
+    /// Startup light show: "default" (built-in rainbow), "off", or "frames" (`boot_frames`).
+    pub boot_animation: String,
+    /// How much of the built-in rainbow, a test of every LED, plays: "full" (about 2 s),
+    /// "quick" (under 1 s) or "off".
+    pub self_test: String,
+    /// Frames of the "frames" startup light show:
+    /// `{ pads = ["red"], buttons = "dim", slider = 25, ms = 100 }`.
+    pub boot_frames: Vec<LightFrame>,
+    /// Accessibility: status text at double size, every light that is on at full
+    /// brightness (no Dim states). Turns `backlight_buttons` off.
+    pub accessibility: bool,
     /// If true, try to connect the driver's ALSA sequencer ports to a kernel rawmidi
     /// device exposed via snd-virmidi (what Bitwig enumerates as "Virtual Raw MIDI ...").
     pub autoconnect_virmidi: bool,
@@ -21,6 +165,326 @@ pub(crate) struct Settings {
     pub virmidi_client_name: String,
     /// Port number on the virmidi client (usually 0).
     pub virmidi_port: usize,
+    /// Existing MIDI input (e.g. a USB keyboard) merged into the driver's output, matched
+    /// by a part of its port name. Empty disables merging.
+    pub merge_input_port: String,
+    /// Existing MIDI output (e.g. a USB MIDI interface) that gets a copy of everything the
+    /// driver sends, matched by a part of its port name. Empty disables the echo.
+    pub echo_output_port: String,
+    /// Also present the pads as a Launchpad Mini (its top-left 4x4) on their own virtual
+    /// ports, for software that supports Launchpads.
+    pub launchpad_emulation: bool,
+    /// Client and port name of the emulated Launchpad.
+    pub launchpad_port_name: String,
+    /// Output filters, applied to everything the driver sends: drop poly/channel pressure,
+    /// send note offs with release velocity 0, only pass these channels (0-15, empty = all),
+    /// clamp note-on velocities.
+    pub output_suppress_aftertouch: bool,
+    pub output_suppress_release_velocity: bool,
+    pub output_channels: Vec<u8>,
+    pub output_velocity_min: u8,
+    pub output_velocity_max: u8,
+    /// Controls that send nothing: button CCs (the driver's own button functions still
+    /// work), just the EncoderTouch CC, the encoder's CC (other encoder modes still work),
+    /// and everything the slider does.
+    pub output_suppress_buttons: bool,
+    pub output_suppress_encoder_touch: bool,
+    pub output_suppress_encoder: bool,
+    pub output_suppress_slider: bool,
+    /// Scroll "Artist - Title" of the desktop's media player (MPRIS) once the screen has
+    /// been idle for `now_playing_idle_secs`. Needs the "mpris" feature.
+    pub now_playing: bool,
+    pub now_playing_idle_secs: u64,
+    /// If true, the main loop waits longer for the controller (`idle_poll_ms`) once nothing
+    /// has happened for `idle_after_ms`, saving power when the driver runs all day.
+    pub adaptive_polling: bool,
+    pub idle_poll_ms: u64,
+    pub idle_after_ms: u64,
+    /// If true, log the main loop's CPU use and pass rate every 10 seconds.
+    pub cpu_audit: bool,
+    /// If true, keep retrying (with backoff) until the controller shows up instead of
+    /// exiting right away when it isn't connected at startup.
+    pub wait_for_device: bool,
+    /// If true, wait for the controller to come back when it's unplugged while running
+    /// (the MIDI ports stay open) instead of exiting.
+    pub reconnect_device: bool,
+    /// If true and a second Mikro is plugged in, run both as one controller: the same
+    /// lights and screen on both, and either's controls through the one mapping.
+    pub sync_controllers: bool,
+    /// If true, reload the config file whenever it changes, as on SIGHUP.
+    pub watch_config: bool,
+    /// File the pad layout, layout toggle, pad bank, octave and patch browser position are
+    /// saved to whenever they change, and restored from on startup (and named on the screen
+    /// after the boot animation). Empty: every start is fresh.
+    pub session_file: String,
+    /// How long (ms) after touching the encoder its movement is considered suspect.
+    /// Capacitive touch produces a small, spurious delta on this device.
+    pub encoder_touch_suppress_ms: u64,
+    /// What to discard within the touch window.
+    /// Valid values: "first" (only the first small delta), "all" (any movement), "off".
+    pub encoder_touch_filter: String,
+    /// Largest delta (in detents) that "first" treats as spurious; bigger turns pass through.
+    pub encoder_touch_max_delta: u8,
+    /// What the encoder does: "cc" (relative CC), "patch_browser" (scroll programs,
+    /// Shift+turn for banks, push to send Bank Select + Program Change) or "expression"
+    /// (turning while touching it glides an absolute CC, like an expression pedal).
+    pub encoder_mode: String,
+    /// How relative encoder turns are written into `encoder_cc`: "offset" (64 + delta),
+    /// "twos_complement" (1 = CW, 127 = CCW) or "sign_magnitude" (1 = CW, 65 = CCW).
+    pub encoder_encoding: String,
+    /// How much fast encoder turns are sped up (0 = off, up to 10): above 10 detents a
+    /// second, each detent counts for more, up to 63 per message.
+    pub encoder_acceleration: f64,
+    /// While this button is held, e.g. "EncoderPress" or "EncoderTouch", the encoder's turns
+    /// go to `encoder_secondary_cc` instead of `encoder_cc` ("" = off).
+    pub encoder_secondary_button: String,
+    /// CC (on `encoder_channel`) of the turns made holding `encoder_secondary_button`
+    pub encoder_secondary_cc: u8,
+    /// Above 0, tells a click on the encoder from a hold: a push let go within this many ms
+    /// without turning sends EncoderPress's message then, press and release together;
+    /// longer pushes and push-turns send none of it and only modify the turns.
+    pub encoder_hold_ms: u64,
+    /// CC (on `button_channel`) a hold sends, 127 once it's held `encoder_hold_ms` without
+    /// turning and 0 when it's let go (unset = none).
+    pub encoder_hold_cc: Option<u8>,
+    /// CC (on `encoder_channel`) the "expression" encoder mode sends.
+    pub expression_cc: u8,
+    /// Expression levels per encoder detent (1-32).
+    pub expression_step: u8,
+    /// How long (ms) the expression CC takes to glide most of the way to a new level
+    /// (0 = jump in detent steps).
+    pub expression_smoothing_ms: u64,
+    /// Encoder bindings to discrete actions, first match wins:
+    /// `{ modifier = "Shift", action = "octave" }`. Actions: "cc", "octave", "layout",
+    /// "bank", "program". An empty modifier binds plain turns.
+    pub encoder_actions: Vec<EncoderBinding>,
+    /// MIDI channel (0-15) for patch browser Bank Select / Program Change.
+    pub patch_browser_channel: u8,
+    /// Optional text file with patch names shown by the patch browser
+    /// (one `[bank:]program name` per line, programs 1-128).
+    pub patch_names_file: String,
+    /// What the pads do: "notes" (play notemaps), "keyboard" (play a scale from
+    /// `keyboard_root`, showing the note name on screen), "scenes" (recall CC snapshots,
+    /// Shift + pad stores the current CC values on that pad), "desk" (type keys on a
+    /// virtual keyboard, see `desk_pages`) or "sequencer" (pads toggle the 16 steps of the
+    /// sound picked with Shift + pad).
+    pub pad_mode: String,
+    /// Steps (1-16) the sequencer plays before starting over.
+    pub sequencer_length: usize,
+    /// Sequencer swing in percent: 0 plays straight 16ths, 100 a triplet feel.
+    pub sequencer_swing: u8,
+    /// MIDI note of the bottom left pad in keyboard mode.
+    pub keyboard_root: u8,
+    /// Scale laid out on the pads in keyboard mode, e.g. "major", "minor", "chromatic".
+    pub keyboard_scale: String,
+    /// Splits the keyboard in two: pads below this one (logical index, 0 = no split) play
+    /// the lower zone, the others the upper one, each laying out the scale from
+    /// `keyboard_root` on its own.
+    pub keyboard_split: usize,
+    /// MIDI channels (0-15) of the lower and upper zone.
+    pub keyboard_split_channels: [u8; 2],
+    /// Octaves (-4 to 4) of the lower and upper zone, relative to `keyboard_root`.
+    pub keyboard_split_octaves: [i8; 2],
+    /// Colors the lower and upper zone's pads are lit in while the keyboard is on.
+    pub keyboard_split_colors: [String; 2],
+    /// If true, pads can hold chords: the Chords button arms capture, the next pad hit picks
+    /// the pad (Shift + pad clears it), then the notes played form the chord.
+    pub chord_memory: bool,
+    /// How long (ms) after the first captured note further notes join the chord.
+    pub chord_capture_window_ms: u64,
+    /// File captured chords are saved to and loaded from on startup. Empty: not saved.
+    pub chords_file: String,
+    /// Chords played by pads, with or without `chord_memory`: `{ pad = 0, notes = [48, 52, 55] }`
+    /// or a chord type on a root, `{ pad = 1, chord = "min7", root = 45 }` (no root: the pad's
+    /// note).
+    pub chords: Vec<ChordConfig>,
+    /// Samples played by the built-in sampler (needs the "sampler" feature):
+    /// `{ pad = 12, file = "kick.wav" }`. Empty: off.
+    pub sampler_kit: Vec<KitSample>,
+    /// Sampler output gain (0.0-1.0) at full velocity.
+    pub sampler_volume: f32,
+    /// Play starts/stops the driver's own clock instead of sending its CC.
+    pub internal_clock: bool,
+    /// Tempo of the internal clock.
+    pub clock_bpm: f64,
+    /// Send MIDI clock and Start/Stop from the internal clock on the output port.
+    pub clock_send_midi: bool,
+    /// Bars (0-2) counted in, with a big countdown on the screen, before the clock starts.
+    pub count_in_bars: u8,
+    /// Tapping `tap_tempo_pad` (or `tap_tempo_button`) sets the internal clock's tempo
+    /// (needs `internal_clock`).
+    pub tap_tempo: bool,
+    /// Logical pad index (0-15) used for tapping; it sends no note.
+    pub tap_tempo_pad: u8,
+    /// Button used for tapping instead of the pad, e.g. "Tap"; it sends no CC ("" = the pad).
+    pub tap_tempo_button: String,
+    /// Number of tap intervals (1-16) averaged into the tempo.
+    pub tap_tempo_taps: usize,
+    /// How far (0-1) each tap pulls the running clock's beat toward it; 0 only sets the tempo.
+    pub tap_tempo_drift_correction: f64,
+    /// Holding Note Repeat retriggers held pads on the host's MIDI clock (or the internal
+    /// clock, or `clock_bpm`) instead of sending its CC; the encoder picks the rate meanwhile.
+    pub note_repeat: bool,
+    /// Starting repeat rate: "1/4", "1/4T", "1/8", "1/8T", "1/16", "1/16T", "1/32", "1/32T".
+    pub note_repeat_rate: String,
+    /// Metronome on the internal clock: "off", "notes" or "audio" (needs the "sampler" feature).
+    pub metronome: String,
+    /// MIDI channel (0-15) of the metronome notes.
+    pub metronome_channel: u8,
+    /// Metronome notes: downbeat, other beats.
+    pub metronome_notes: [u8; 2],
+    pub metronome_beats_per_bar: u8,
+    /// Flash the pads on every metronome beat.
+    pub metronome_flash: bool,
+    /// Button that arms step recording into the DAW, e.g. "Step"; it sends no CC ("" = off).
+    /// Each hit on the pads while the host doesn't play moves a position counter on.
+    pub step_record_button: String,
+    /// Steps per beat (1-8) the step record counter shows, 4 = 16th notes.
+    pub step_record_steps_per_beat: u8,
+    /// CC (on `button_channel`) sent as 127 then 0 after every recorded step, to move the
+    /// DAW's step input on (unset = none).
+    pub step_record_nudge_cc: Option<u8>,
+    /// Key combo typed after every recorded step, e.g. "right" ("" = none).
+    pub step_record_nudge_keys: String,
+    /// Seconds without the controller script's SysEx ping after which the screen shows
+    /// "HOST LOST" and the lights go back to standalone (0 = off). Armed by the first ping.
+    pub host_watchdog_secs: u64,
+    /// How long (ms) a host session (SysEx Session Claim) may go without a claim or LED
+    /// feedback before the next one's lights show.
+    pub host_session_timeout_ms: u64,
+    /// Follow the host transport (MIDI Start/Stop/Continue on the input port): light
+    /// Play/Stop and flash the state on the screen.
+    pub transport_feedback: bool,
+    /// Show the host's tempo, measured from the MIDI clock on the input port, on the screen
+    /// when the host starts playing and whenever it changes.
+    pub host_tempo_display: bool,
+    /// Button that flashes on every beat of the host clock while the host plays ("" = none).
+    pub beat_flash_button: String,
+    /// Blink two small blocks in the top right corner of the screen on incoming (left) and
+    /// outgoing (right) MIDI.
+    pub midi_activity_indicator: bool,
+    /// Show recoverable errors (MIDI output lost, controller reconnected) on the screen as a
+    /// short code, e.g. "ERROR E1" / "MIDI OUT".
+    pub fault_display: bool,
+    /// Button that blinks after a recoverable error ("" = none).
+    pub fault_button: String,
+    /// How long (s) `fault_button` blinks after the last error.
+    pub fault_blink_secs: u64,
+    /// MIDI channel (0-15) for scenes defined in `scenes`.
+    pub scene_channel: u8,
+    /// Predefined scenes: `{ pad = 0, ccs = [[74, 20], [71, 100]] }`.
+    pub scenes: Vec<SceneConfig>,
+    /// Macro pages for desk mode, after the built-in numpad page:
+    /// `{ name = "Edit", keys = ["ctrl+z", "ctrl+x", "ctrl+c", "ctrl+v"] }`.
+    pub desk_pages: Vec<DeskPage>,
+    /// What the touch strip sends: "cc", "crossfader", "strum", "zones" or "pitchbend".
+    pub slider_mode: String,
+    /// Time in ms the pitch bend glides back to center after letting go of the strip
+    /// (`slider_mode = "pitchbend"`, 0 = snap back, up to 5000).
+    pub pitchbend_return_ms: u64,
+    /// Trigger zones (2-8) for `slider_mode = "zones"`, left to right:
+    /// `{ note = 60 }` or `{ cc = 102, channel = 1 }`.
+    pub slider_zones: Vec<SliderZone>,
+    /// Crossfader curve: "linear", "smooth" (constant power) or "sharp" (scratch cut).
+    pub crossfader_curve: String,
+    /// Crossfader output: "single" (position on the slider CC) or "dual"
+    /// (complementary Channel Volume, CC 7, on `crossfader_channels`).
+    pub crossfader_output: String,
+    /// MIDI channels (0-15) of the A and B sides for `crossfader_output = "dual"`.
+    pub crossfader_channels: [u8; 2],
+    /// Number of slider reports in the running median (1 = off, max 9).
+    pub slider_median_window: usize,
+    /// Slider movement (raw steps, strip has 200) ignored around the last position.
+    pub slider_hysteresis: u8,
+    /// Soft takeover: after switching pad layouts, the slider (cc and crossfader modes)
+    /// stays silent until it crosses the value last sent on that layout.
+    pub slider_soft_takeover: bool,
+    /// Buttons that, held together, send All Notes Off / All Sound Off on every channel.
+    /// Names as in `Buttons`, e.g. ["Shift", "Stop"]. Empty disables the chord.
+    pub panic_buttons: Vec<String>,
+    /// Buttons that, held together, show (or hide) the CPU/memory status page.
+    /// Empty (the default) disables the chord.
+    pub stats_buttons: Vec<String>,
+    /// Buttons that, held together, open (or close) the practice timer. Empty disables it.
+    pub timer_buttons: Vec<String>,
+    /// Initial timer duration in minutes (1-99).
+    pub timer_minutes: u64,
+    /// Buttons that, held together, show (or hide) the pad hit heatmap. Empty (the default)
+    /// disables it.
+    pub pad_stats_buttons: Vec<String>,
+    /// Buttons that, held together, switch the pads between the drum layout (`notemaps`) and
+    /// keyboard mode, showing the new layout on the screen. Empty (the default) disables it.
+    pub layout_toggle_buttons: Vec<String>,
+    /// Buttons that, held together, turn the stage light on (or off): every pad bright
+    /// white, every LED and the whole screen lit. Empty disables the chord.
+    pub stage_light_buttons: Vec<String>,
+    /// Buttons that, held together, open (or close) the settings menu. Empty (the default)
+    /// disables the chord; a `"menu"` command in `shift_mappings` or `button_gestures` opens it too.
+    pub menu_buttons: Vec<String>,
+    /// Buttons selecting pad banks A, B, C, D in that order (up to 4). Empty disables banks.
+    pub bank_buttons: Vec<String>,
+    /// 16 notes per pad bank, bank A first (up to 4); empty banks can't be selected, bank A
+    /// defaults to `notemaps`.
+    pub bank_notemaps: Vec<Vec<u8>>,
+    /// Pad banks ("A"-"D") whose pads latch: a hit sends Note On, the next one Note Off,
+    /// e.g. for launching clips. Bank A is all pads without `bank_buttons`. Empty: off.
+    pub latch_banks: Vec<String>,
+    /// Color a latched pad is lit in.
+    pub latch_color: String,
+    /// How pad pressure is sent: "off", "poly" (polyphonic key pressure), "channel".
+    pub aftertouch: String,
+    /// Minimum time (ms) between two pressure updates of the same pad.
+    pub aftertouch_min_interval_ms: u64,
+    /// Minimum pressure change (0-127 scale) before a new update is sent.
+    pub aftertouch_min_change: u8,
+    /// Pad pressure routed to CCs, independently of `aftertouch`:
+    /// `{ cc = 74, channel = 0, pads = [] }` (empty `pads` means all pads).
+    pub aftertouch_cc: Vec<AftertouchCcRoute>,
+    /// While a pad is held, show its pressure as a bar on the slider LEDs.
+    pub aftertouch_slider_display: bool,
+    /// Pads that play no note but throw an effect: pressure sends a CC, release snaps it
+    /// back to 0, e.g. `{ pad = 15, cc = 12, max = 100 }`.
+    pub fx_throws: Vec<FxThrow>,
+    /// Motion recorder: moving the slider or encoder while holding this button ("" = off)
+    /// records their CCs, up to `motion_max_secs` (1-30); hitting `motion_pad` loops the
+    /// recording, the next hit stops it.
+    pub motion_button: String,
+    pub motion_pad: u8,
+    pub motion_max_secs: u64,
+    /// Pad colors of incoming note velocities, first match wins:
+    /// `{ min = 1, max = 63, color = "Blue" }`. Empty: the built-in rainbow.
+    pub velocity_colors: Vec<VelocityColor>,
+    /// Color of velocities no `velocity_colors` range covers ("" = the built-in rainbow).
+    pub velocity_on_color: String,
+    /// How hard a pad has to be hit for a given velocity: "linear", "soft" (soft hits come
+    /// out louder), "hard", "exp", "log" (easiest to play loud) or "fixed" (always
+    /// `velocity_max`).
+    pub velocity_curve: String,
+    /// Per-pad curves overriding `velocity_curve`, 16 values ("" = global; empty = none).
+    pub pad_velocity_curves: Vec<String>,
+    /// Range the velocity curve is scaled into (1-127): the softest hit plays `velocity_min`,
+    /// the hardest `velocity_max`.
+    pub velocity_min: u8,
+    pub velocity_max: u8,
+    /// Pads playing a different note depending on the velocity (after the curve):
+    /// `{ pad = 2, thresholds = [90], notes = [42, 46] }`.
+    pub velocity_splits: Vec<VelocitySplit>,
+    /// Shortest time (ms) a pad note sounds: releasing sooner delays the note off (0 = off).
+    pub gate_min_ms: u64,
+    /// Longest time (ms) a pad note sounds: the driver ends notes held longer (0 = off).
+    pub gate_max_ms: u64,
+    /// Per-pad gates replacing `gate_min_ms`/`gate_max_ms`, 16 values each (empty = none).
+    pub pad_gate_min_ms: Vec<u64>,
+    pub pad_gate_max_ms: Vec<u64>,
+    /// Per-pad velocity gain, 16 values (empty = 1.0 everywhere). Written by
+    /// `driver calibrate`.
+    pub pad_gain: Vec<f64>,
+    /// Per-pad raw hit threshold (0-4095), 16 values: softer hits are ignored. Written by
+    /// `driver calibrate`.
+    pub pad_threshold: Vec<u16>,
+    /// `[[rules]]`: small "when ... then ..." transformations, e.g.
+    /// `{ when = "held Shift and pad 5", then = "note +12" }` (see README.md).
+    pub rules: Vec<RuleConfig>,
 }
 
 impl Default for Settings {
@@ -35,19 +499,290 @@ impl Default for Settings {
                 40, 41, 42, 43,  // Logical 8-11 (physical row 5-8): E1, F1, F#1, G1
                 36, 37, 38, 39,  // Logical 12-15 (physical top row 1-4): C1, C#1, D1, D#1
             ],
+            layout: "".to_string(),
+            pad_layout_transform: "identity".to_string(),
             client_name: "Maschine Mikro MK3".to_string(),
             port_name: "Maschine Mikro MK3 MIDI Out".to_string(),
             port_name_in: "Maschine Mikro MK3 MIDI In".to_string(),
+            pad_channel: 0,
+            button_channel: 0,
+            encoder_channel: 0,
+            slider_channel: 0,
+            button_cc_offset: 20,
+            button_mappings: BTreeMap::new(),
+            encoder_cc: 1,
+            slider_cc: 9,
+            slider_high_resolution: false,
+            slider_curve: "linear".to_string(),
+            slider_invert: false,
+            encoder_high_resolution: false,
             backlight_buttons: false,
             backlight_brightness: "dim".to_string(),
+            backlight_exclude: Vec::new(),
+            backlight_overrides: Vec::new(),
+            button_groups: Vec::new(),
+            shift_mappings: BTreeMap::new(),
+            button_gestures: BTreeMap::new(),
+            long_press_ms: 500,
+            double_tap_ms: 300,
+            boot_animation: "default".to_string(),
+            self_test: "full".to_string(),
+            boot_frames: Vec::new(),
+            accessibility: false,
             autoconnect_virmidi: true,
             virmidi_client_name: "".to_string(),
             virmidi_port: 0,
+            merge_input_port: "".to_string(),
+            echo_output_port: "".to_string(),
+            launchpad_emulation: false,
+            launchpad_port_name: "Launchpad Mini".to_string(),
+            output_suppress_aftertouch: false,
+            output_suppress_release_velocity: false,
+            output_channels: Vec::new(),
+            output_suppress_buttons: false,
+            output_suppress_encoder_touch: false,
+            output_suppress_encoder: false,
+            output_suppress_slider: false,
+            output_velocity_min: 1,
+            output_velocity_max: 127,
+            now_playing: false,
+            now_playing_idle_secs: 10,
+            adaptive_polling: false,
+            idle_poll_ms: 20,
+            idle_after_ms: 3000,
+            cpu_audit: false,
+            wait_for_device: false,
+            reconnect_device: true,
+            sync_controllers: false,
+            watch_config: false,
+            session_file: "".to_string(),
+            encoder_touch_suppress_ms: 120,
+            encoder_touch_filter: "first".to_string(),
+            encoder_touch_max_delta: 1,
+            encoder_mode: "cc".to_string(),
+            encoder_encoding: "offset".to_string(),
+            encoder_acceleration: 0.0,
+            encoder_secondary_button: String::new(),
+            encoder_secondary_cc: 2,
+            encoder_hold_ms: 0,
+            encoder_hold_cc: None,
+            expression_cc: 11,
+            expression_step: 4,
+            expression_smoothing_ms: 60,
+            encoder_actions: Vec::new(),
+            patch_browser_channel: 0,
+            patch_names_file: "".to_string(),
+            pad_mode: "notes".to_string(),
+            sequencer_length: 16,
+            sequencer_swing: 0,
+            keyboard_root: 48,
+            keyboard_scale: "major".to_string(),
+            keyboard_split: 0,
+            keyboard_split_channels: [1, 0],
+            keyboard_split_octaves: [-1, 0],
+            keyboard_split_colors: ["Blue".to_string(), "Orange".to_string()],
+            chord_memory: false,
+            chord_capture_window_ms: 1500,
+            chords_file: "".to_string(),
+            chords: Vec::new(),
+            sampler_kit: Vec::new(),
+            sampler_volume: 1.0,
+            internal_clock: false,
+            clock_bpm: 120.0,
+            clock_send_midi: true,
+            count_in_bars: 0,
+            tap_tempo: false,
+            tap_tempo_pad: 0,
+            tap_tempo_button: String::new(),
+            step_record_button: String::new(),
+            step_record_steps_per_beat: 4,
+            step_record_nudge_cc: None,
+            step_record_nudge_keys: String::new(),
+            tap_tempo_taps: 4,
+            tap_tempo_drift_correction: 0.5,
+            note_repeat: false,
+            note_repeat_rate: "1/16".to_string(),
+            metronome: "off".to_string(),
+            metronome_channel: 9,
+            metronome_notes: [76, 77],
+            metronome_beats_per_bar: 4,
+            metronome_flash: true,
+            host_watchdog_secs: 0,
+            host_session_timeout_ms: 2000,
+            transport_feedback: false,
+            host_tempo_display: false,
+            beat_flash_button: String::new(),
+            midi_activity_indicator: false,
+            fault_display: true,
+            fault_button: String::new(),
+            fault_blink_secs: 10,
+            scene_channel: 0,
+            scenes: Vec::new(),
+            desk_pages: Vec::new(),
+            slider_mode: "cc".to_string(),
+            pitchbend_return_ms: 150,
+            slider_zones: Vec::new(),
+            crossfader_curve: "smooth".to_string(),
+            crossfader_output: "single".to_string(),
+            crossfader_channels: [0, 1],
+            slider_median_window: 3,
+            slider_hysteresis: 1,
+            slider_soft_takeover: false,
+            panic_buttons: vec!["Shift".to_string(), "Stop".to_string()],
+            stats_buttons: Vec::new(),
+            timer_buttons: vec!["Shift".to_string(), "Tempo".to_string()],
+            timer_minutes: 25,
+            pad_stats_buttons: Vec::new(),
+            layout_toggle_buttons: Vec::new(),
+            stage_light_buttons: vec!["Shift".to_string(), "Maschine".to_string()],
+            menu_buttons: Vec::new(),
+            bank_buttons: Vec::new(),
+            bank_notemaps: Vec::new(),
+            latch_banks: Vec::new(),
+            latch_color: "Green".to_string(),
+            aftertouch: "off".to_string(),
+            aftertouch_min_interval_ms: 10,
+            aftertouch_min_change: 2,
+            aftertouch_cc: Vec::new(),
+            aftertouch_slider_display: false,
+            fx_throws: Vec::new(),
+            motion_button: String::new(),
+            motion_pad: 15,
+            motion_max_secs: 4,
+            velocity_colors: Vec::new(),
+            velocity_on_color: "".to_string(),
+            velocity_curve: "linear".to_string(),
+            pad_velocity_curves: Vec::new(),
+            velocity_min: 1,
+            velocity_max: 127,
+            velocity_splits: Vec::new(),
+            gate_min_ms: 0,
+            gate_max_ms: 0,
+            pad_gate_min_ms: Vec::new(),
+            pad_gate_max_ms: Vec::new(),
+            pad_gain: Vec::new(),
+            pad_threshold: Vec::new(),
+            rules: Vec::new(),
         }
     }
 }
 
 impl Settings {
+    /// CCs of the buttons, `button_cc_offset` to `button_cc_offset + 40`
+    pub(crate) fn button_cc_range(&self) -> std::ops::RangeInclusive<u8> {
+        self.button_cc_offset..=self.button_cc_offset.saturating_add(40)
+    }
+
+    /// How the pad grid is turned (validated by `validate`)
+    pub(crate) fn pad_transform(&self) -> PadTransform {
+        PadTransform::from_name(&self.pad_layout_transform).unwrap_or_default()
+    }
+
+    /// Buttons of the panic chord (validated by `validate`)
+    pub(crate) fn panic_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.panic_buttons)
+    }
+
+    /// Buttons of the status page chord (validated by `validate`)
+    pub(crate) fn stats_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.stats_buttons)
+    }
+
+    /// Buttons of the timer chord (validated by `validate`)
+    pub(crate) fn timer_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.timer_buttons)
+    }
+
+    /// Buttons of the pad heatmap chord (validated by `validate`)
+    pub(crate) fn pad_stats_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.pad_stats_buttons)
+    }
+
+    /// Buttons of the stage light chord (validated by `validate`)
+    pub(crate) fn stage_light_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.stage_light_buttons)
+    }
+
+    /// Buttons of the settings menu chord (validated by `validate`)
+    pub(crate) fn menu_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.menu_buttons)
+    }
+
+    /// Buttons of the layout toggle chord (validated by `validate`)
+    pub(crate) fn layout_toggle_chord(&self) -> Vec<Buttons> {
+        button_chord(&self.layout_toggle_buttons)
+    }
+
+    /// Bank select buttons, bank A first (validated by `validate`)
+    pub(crate) fn bank_buttons(&self) -> Vec<Buttons> {
+        button_chord(&self.bank_buttons)
+    }
+
+    /// What a button LED shows when it's off: its backlight level if `backlight_buttons` is
+    /// set (or the host turned the backlight on over SysEx) and the button isn't excluded,
+    /// otherwise Off (validated by `validate`)
+    pub(crate) fn button_off_level(&self, button: Buttons) -> Brightness {
+        let backlight = params::get(Param::Backlight).map_or(self.backlight_buttons, |on| on == 1);
+        if !backlight {
+            return Brightness::Off;
+        }
+        self.backlight_level(button)
+    }
+
+    /// A button's backlight level while the backlight is on
+    pub(crate) fn backlight_level(&self, button: Buttons) -> Brightness {
+        if button_chord(&self.backlight_exclude).contains(&button) {
+            return Brightness::Off;
+        }
+        let level = self
+            .backlight_overrides
+            .iter()
+            .find(|o| Buttons::from_name(&o.button) == Some(button))
+            .map_or(&self.backlight_brightness, |o| &o.level);
+        parse_backlight_brightness(level).expect("Invalid backlight level (see README.md)")
+    }
+
+    /// Buttons of a group named in a SysEx Button Group message: the config's
+    /// `button_groups` first, then the built-in ones (validated by `validate`)
+    pub(crate) fn button_group(&self, name: &str) -> Option<Vec<Buttons>> {
+        if let Some(group) = self.button_groups.iter().find(|g| g.name == name) {
+            return Some(button_chord(&group.buttons));
+        }
+        if name == "all" {
+            return Some((0..41).filter_map(num::FromPrimitive::from_usize).collect());
+        }
+        BUILTIN_BUTTON_GROUPS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, buttons)| buttons.to_vec())
+    }
+
+    /// Whether an `encoder_actions` binding steps banks or programs (validated by `validate`)
+    pub(crate) fn encoder_actions_browse(&self) -> bool {
+        self.encoder_actions.iter().any(|b| {
+            matches!(
+                parse_encoder_action(&b.action),
+                Ok(EncoderAction::Bank | EncoderAction::Program)
+            )
+        })
+    }
+
+    /// Whether a raw pad hit is softer than the pad's calibrated threshold. Calibration
+    /// belongs to the pad under the finger, so it doesn't move with `pad_layout_transform`.
+    pub(crate) fn pad_below_threshold(&self, pad: usize, raw: u16) -> bool {
+        let sensor = self.pad_transform().apply(pad);
+        self.pad_threshold.get(sensor).is_some_and(|&threshold| raw < threshold)
+    }
+
+    /// Replaces `notemaps` with the `layout` preset, if one is set (validated by `validate`)
+    pub(crate) fn apply_layout(&mut self) {
+        if !self.layout.is_empty() {
+            self.notemaps = layout_notemaps(&self.layout)
+                .expect("Invalid layout (see README.md)")
+                .to_vec();
+        }
+    }
+
     pub(crate) fn validate(&self) -> Result<(), String> {
         // todo: is there a better way to do it that doesn't bring too many new useless dependencies?
 
@@ -59,6 +794,67 @@ impl Settings {
         if self.notemaps.iter().any(|x| *x >= 128) {
             return Err("MIDI notes should be 0 to 127".to_string());
         }
+        let channels = [
+            self.pad_channel,
+            self.button_channel,
+            self.encoder_channel,
+            self.slider_channel,
+        ];
+        if channels.iter().any(|c| *c > 15) {
+            return Err("pad/button/encoder/slider_channel should be 0 to 15".to_string());
+        }
+        if self.button_cc_offset > 127 - 40 {
+            return Err("button_cc_offset should be 0 to 87 (41 buttons)".to_string());
+        }
+        let buttons = ButtonMap::new(self)?;
+        // CCs only collide on the same channel
+        let controls = [
+            ("encoder_cc", self.encoder_cc, self.encoder_channel),
+            ("slider_cc", self.slider_cc, self.slider_channel),
+        ];
+        for (name, cc, channel) in controls {
+            if cc > 127 {
+                return Err(format!("{name} should be 0 to 127"));
+            }
+            if buttons.has_cc(channel, cc) {
+                return Err(format!("{name} = {cc} collides with the button CCs"));
+            }
+        }
+        if self.encoder_cc == self.slider_cc && self.encoder_channel == self.slider_channel {
+            return Err("encoder_cc and slider_cc should differ".to_string());
+        }
+        // 14-bit pairs take the CC 32 above for the LSB
+        let expression = self.encoder_high_resolution && self.encoder_mode == "expression";
+        let pairs = [
+            ("slider_cc", self.slider_high_resolution, self.slider_cc, self.slider_channel),
+            ("expression_cc", expression, self.expression_cc, self.encoder_channel),
+        ];
+        for (name, high_resolution, cc, channel) in pairs {
+            if !high_resolution {
+                continue;
+            }
+            if cc > 31 {
+                return Err(format!("{name} should be 0 to 31 for a 14-bit CC pair"));
+            }
+            let lsb = cc + 32;
+            if buttons.has_cc(channel, lsb) {
+                return Err(format!("{name} LSB (CC {lsb}) collides with the button CCs"));
+            }
+            if lsb == self.encoder_cc && channel == self.encoder_channel {
+                return Err(format!("{name} LSB (CC {lsb}) collides with encoder_cc"));
+            }
+        }
+
+        if !self.layout.is_empty() {
+            layout_notemaps(&self.layout)?;
+        }
+        if PadTransform::from_name(&self.pad_layout_transform).is_none() {
+            return Err(format!(
+                "invalid pad_layout_transform={:?} (expected: \"identity\", \"mirror_h\", \
+                 \"mirror_v\", \"rotate_180\")",
+                self.pad_layout_transform
+            ));
+        }
 
         if self.client_name.is_empty() {
             return Err("Client name must not be empty".to_string());
@@ -72,14 +868,429 @@ impl Settings {
             return Err("Input port name must not be empty".to_string());
         }
 
-        let bb = self.backlight_brightness.trim().to_ascii_lowercase();
-        let bb_ok = matches!(bb.as_str(), "dim" | "normal" | "bright");
-        if !bb_ok {
+        parse_backlight_brightness(&self.backlight_brightness)?;
+        if let Some(name) = self.backlight_exclude.iter().find(|n| Buttons::from_name(n).is_none())
+        {
+            return Err(format!("backlight_exclude: unknown button {name:?}"));
+        }
+        for group in &self.button_groups {
+            if group.name.is_empty() || !group.name.is_ascii() {
+                return Err("button_groups: names should be non-empty ASCII".to_string());
+            }
+            if let Some(name) = group.buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+                return Err(format!("button_groups ({}): unknown button {name:?}", group.name));
+            }
+        }
+        validate_mappings(&self.shift_mappings, self.button_channel, &buttons)?;
+        validate_gestures(self, &buttons)?;
+        for o in &self.backlight_overrides {
+            if Buttons::from_name(&o.button).is_none() {
+                return Err(format!("backlight_overrides: unknown button {:?}", o.button));
+            }
+            parse_backlight_brightness(&o.level)
+                .map_err(|e| format!("backlight_overrides ({}): {e}", o.button))?;
+        }
+
+        if !matches!(self.boot_animation.as_str(), "default" | "off" | "frames") {
             return Err(
-                "backlight_brightness must be one of: \"dim\", \"normal\", \"bright\"".to_string(),
+                "boot_animation must be one of: \"default\", \"off\", \"frames\"".to_string(),
             );
         }
 
+        if self.boot_animation == "frames" && self.boot_frames.is_empty() {
+            return Err("boot_animation = \"frames\" needs boot_frames".to_string());
+        }
+        parse_self_test(&self.self_test)?;
+
+        for (i, frame) in self.boot_frames.iter().enumerate() {
+            frame
+                .render(&Lights::new())
+                .map_err(|e| format!("boot_frames[{i}]: {e}"))?;
+        }
+
+        let etf = self.encoder_touch_filter.trim().to_ascii_lowercase();
+        if !matches!(etf.as_str(), "first" | "all" | "off") {
+            return Err(
+                "encoder_touch_filter must be one of: \"first\", \"all\", \"off\"".to_string(),
+            );
+        }
+
+        if self.encoder_touch_max_delta == 0 || self.encoder_touch_max_delta > 7 {
+            return Err("encoder_touch_max_delta should be 1 to 7".to_string());
+        }
+
+        if !matches!(self.encoder_mode.as_str(), "cc" | "patch_browser" | "expression") {
+            return Err(
+                "encoder_mode must be one of: \"cc\", \"patch_browser\", \"expression\""
+                    .to_string(),
+            );
+        }
+        parse_relative_encoding(&self.encoder_encoding)?;
+        if !(0.0..=10.0).contains(&self.encoder_acceleration) {
+            return Err("encoder_acceleration should be 0 to 10".to_string());
+        }
+        if !self.encoder_secondary_button.is_empty() {
+            if Buttons::from_name(&self.encoder_secondary_button).is_none() {
+                let name = &self.encoder_secondary_button;
+                return Err(format!("encoder_secondary_button: unknown button {name:?}"));
+            }
+            let cc = self.encoder_secondary_cc;
+            if cc > 127 {
+                return Err("encoder_secondary_cc should be 0 to 127".to_string());
+            }
+            if buttons.has_cc(self.encoder_channel, cc) {
+                return Err(format!("encoder_secondary_cc = {cc} collides with the button CCs"));
+            }
+            if cc == self.encoder_cc {
+                return Err("encoder_cc and encoder_secondary_cc should differ".to_string());
+            }
+            if cc == self.slider_cc && self.encoder_channel == self.slider_channel {
+                return Err("slider_cc and encoder_secondary_cc should differ".to_string());
+            }
+        }
+        if self.encoder_hold_ms > HOLD_MAX_MS {
+            return Err(format!("encoder_hold_ms should be 0 to {HOLD_MAX_MS}"));
+        }
+        if let Some(cc) = self.encoder_hold_cc {
+            if cc > 127 {
+                return Err("encoder_hold_cc should be 0 to 127".to_string());
+            }
+            if buttons.has_cc(self.button_channel, cc) {
+                return Err(format!("encoder_hold_cc = {cc} collides with the button CCs"));
+            }
+        }
+        if self.expression_cc > 127 {
+            return Err("expression_cc should be 0 to 127".to_string());
+        }
+        if self.encoder_mode == "expression"
+            && buttons.has_cc(self.encoder_channel, self.expression_cc)
+        {
+            let cc = self.expression_cc;
+            return Err(format!("expression_cc = {cc} collides with the button CCs"));
+        }
+        if !(1..=32).contains(&self.expression_step) {
+            return Err("expression_step should be 1 to 32".to_string());
+        }
+        if self.expression_smoothing_ms > 2000 {
+            return Err("expression_smoothing_ms should be 0 to 2000".to_string());
+        }
+
+        for binding in &self.encoder_actions {
+            binding.validate()?;
+        }
+
+        if self.patch_browser_channel > 15 {
+            return Err("patch_browser_channel should be 0 to 15".to_string());
+        }
+
+        if !matches!(
+            self.pad_mode.as_str(),
+            "notes" | "keyboard" | "scenes" | "desk" | "sequencer"
+        ) {
+            return Err("pad_mode must be one of: \"notes\", \"keyboard\", \"scenes\", \
+                 \"desk\", \"sequencer\""
+                .to_string());
+        }
+        if !(1..=STEPS).contains(&self.sequencer_length) {
+            return Err(format!("sequencer_length should be 1 to {STEPS}"));
+        }
+        if self.sequencer_swing > 100 {
+            return Err("sequencer_swing should be 0 to 100".to_string());
+        }
+
+        if self.keyboard_root > 127 {
+            return Err("keyboard_root should be 0 to 127".to_string());
+        }
+
+        parse_scale(&self.keyboard_scale)?;
+        validate_split(self)?;
+
+        PadChords::new(self)?;
+
+        for sample in &self.sampler_kit {
+            if sample.pad >= 16 {
+                return Err(format!("sampler_kit: pad should be 0 to 15 (found {})", sample.pad));
+            }
+            if sample.file.is_empty() {
+                return Err(format!("sampler_kit: file must not be empty (pad {})", sample.pad));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.sampler_volume) {
+            return Err("sampler_volume should be 0.0 to 1.0".to_string());
+        }
+
+        if !(20.0..=300.0).contains(&self.clock_bpm) {
+            return Err("clock_bpm should be 20 to 300".to_string());
+        }
+
+        if self.count_in_bars > 2 {
+            return Err("count_in_bars should be 0 to 2".to_string());
+        }
+
+        if self.tap_tempo && !self.internal_clock {
+            return Err("tap_tempo needs internal_clock = true".to_string());
+        }
+        if self.tap_tempo_pad >= 16 {
+            return Err("tap_tempo_pad should be 0 to 15".to_string());
+        }
+        if !self.tap_tempo_button.is_empty() && Buttons::from_name(&self.tap_tempo_button).is_none()
+        {
+            return Err(format!("tap_tempo_button: unknown button {:?}", self.tap_tempo_button));
+        }
+        if !self.step_record_button.is_empty()
+            && Buttons::from_name(&self.step_record_button).is_none()
+        {
+            return Err(format!("step_record_button: unknown button {:?}", self.step_record_button));
+        }
+        if !(1..=8).contains(&self.step_record_steps_per_beat) {
+            return Err("step_record_steps_per_beat should be 1 to 8".to_string());
+        }
+        if self.step_record_nudge_cc.is_some_and(|cc| cc > 127) {
+            return Err("step_record_nudge_cc should be 0 to 127".to_string());
+        }
+        if !self.step_record_nudge_keys.is_empty() {
+            validate_combo(&self.step_record_nudge_keys)
+                .map_err(|e| format!("step_record_nudge_keys: {e}"))?;
+        }
+        if !(1..=16).contains(&self.tap_tempo_taps) {
+            return Err("tap_tempo_taps should be 1 to 16".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.tap_tempo_drift_correction) {
+            return Err("tap_tempo_drift_correction should be 0 to 1".to_string());
+        }
+
+        parse_rate(&self.note_repeat_rate)?;
+
+        parse_metronome_mode(&self.metronome)?;
+
+        if self.metronome_channel > 15 {
+            return Err("metronome_channel should be 0 to 15".to_string());
+        }
+
+        if self.metronome_notes.iter().any(|n| *n >= 128) {
+            return Err("metronome_notes should be 0 to 127".to_string());
+        }
+
+        if !(1..=16).contains(&self.metronome_beats_per_bar) {
+            return Err("metronome_beats_per_bar should be 1 to 16".to_string());
+        }
+
+        if self.scene_channel > 15 {
+            return Err("scene_channel should be 0 to 15".to_string());
+        }
+
+        for scene in &self.scenes {
+            if scene.pad >= 16 {
+                return Err(format!("scenes: pad should be 0 to 15 (found {})", scene.pad));
+            }
+            if scene.ccs.iter().flatten().any(|x| *x >= 128) {
+                return Err(format!("scenes: CC numbers and values should be 0 to 127 (pad {})", scene.pad));
+            }
+        }
+
+        validate_pages(&self.desk_pages)?;
+
+        if parse_slider_mode(&self.slider_mode)? == SliderMode::Zones
+            && !(2..=8).contains(&self.slider_zones.len())
+        {
+            return Err("slider_mode = \"zones\" needs 2 to 8 slider_zones".to_string());
+        }
+        if !(2..=100).contains(&self.idle_poll_ms) {
+            return Err("idle_poll_ms should be 2 to 100".to_string());
+        }
+        if self.pitchbend_return_ms > 5000 {
+            return Err("pitchbend_return_ms should be 0 to 5000".to_string());
+        }
+        if !(100..=60000).contains(&self.host_session_timeout_ms) {
+            return Err("host_session_timeout_ms should be 100 to 60000".to_string());
+        }
+        for (i, zone) in self.slider_zones.iter().enumerate() {
+            if zone.note.is_some() == zone.cc.is_some() {
+                return Err(format!("slider_zones: zone {i} needs either a note or a cc"));
+            }
+            if zone.note.or(zone.cc).is_some_and(|n| n > 127) || zone.channel > 15 {
+                return Err(format!(
+                    "slider_zones: zone {i}: note/CC should be 0 to 127, channel 0 to 15"
+                ));
+            }
+        }
+        parse_crossfader_curve(&self.crossfader_curve)?;
+        parse_slider_curve(&self.slider_curve)?;
+
+        if !matches!(self.crossfader_output.as_str(), "single" | "dual") {
+            return Err("crossfader_output must be one of: \"single\", \"dual\"".to_string());
+        }
+
+        if self.crossfader_channels.iter().any(|c| *c > 15) {
+            return Err("crossfader_channels should be 0 to 15".to_string());
+        }
+
+        if self.slider_median_window == 0 || self.slider_median_window > 9 {
+            return Err("slider_median_window should be 1 to 9".to_string());
+        }
+
+        if self.slider_hysteresis > 20 {
+            return Err("slider_hysteresis should be 0 to 20".to_string());
+        }
+
+        if let Some(name) = self.panic_buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+            return Err(format!("panic_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) = self.stats_buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+            return Err(format!("stats_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) = self.timer_buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+            return Err(format!("timer_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) =
+            self.pad_stats_buttons.iter().find(|n| Buttons::from_name(n).is_none())
+        {
+            return Err(format!("pad_stats_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) =
+            self.stage_light_buttons.iter().find(|n| Buttons::from_name(n).is_none())
+        {
+            return Err(format!("stage_light_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) = self.menu_buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+            return Err(format!("menu_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) =
+            self.layout_toggle_buttons.iter().find(|n| Buttons::from_name(n).is_none())
+        {
+            return Err(format!("layout_toggle_buttons: unknown button {name:?}"));
+        }
+
+        if let Some(name) = self.bank_buttons.iter().find(|n| Buttons::from_name(n).is_none()) {
+            return Err(format!("bank_buttons: unknown button {name:?}"));
+        }
+        if self.bank_buttons.len() > MAX_BANKS {
+            return Err(format!("bank_buttons: at most {MAX_BANKS} banks"));
+        }
+        if self.bank_notemaps.len() > MAX_BANKS {
+            return Err(format!("bank_notemaps: at most {MAX_BANKS} banks"));
+        }
+        for (bank, notes) in self.bank_notemaps.iter().enumerate() {
+            let name = PadBanks::name(bank);
+            if !notes.is_empty() && notes.len() != 16 {
+                return Err(format!("bank_notemaps: bank {name} needs 16 notes (or none)"));
+            }
+            if notes.iter().any(|&n| n > 127) {
+                return Err(format!("bank_notemaps: bank {name}: MIDI notes should be 0 to 127"));
+            }
+        }
+        validate_latch(self)?;
+
+        if !self.fault_button.is_empty() && Buttons::from_name(&self.fault_button).is_none() {
+            return Err(format!("fault_button: unknown button {:?}", self.fault_button));
+        }
+        if self.fault_blink_secs > 3600 {
+            return Err("fault_blink_secs should be 0 to 3600".to_string());
+        }
+        if !self.beat_flash_button.is_empty()
+            && Buttons::from_name(&self.beat_flash_button).is_none()
+        {
+            return Err(format!("beat_flash_button: unknown button {:?}", self.beat_flash_button));
+        }
+
+        if !(1..=99).contains(&self.timer_minutes) {
+            return Err("timer_minutes should be 1 to 99".to_string());
+        }
+
+        let at = self.aftertouch.trim().to_ascii_lowercase();
+        if !matches!(at.as_str(), "off" | "poly" | "channel") {
+            return Err("aftertouch must be one of: \"off\", \"poly\", \"channel\"".to_string());
+        }
+
+        if self.aftertouch_min_change > 127 {
+            return Err("aftertouch_min_change should be 0 to 127".to_string());
+        }
+
+        for route in &self.aftertouch_cc {
+            if route.cc > 127 {
+                return Err(format!("aftertouch_cc: CC should be 0 to 127 (found {})", route.cc));
+            }
+            if route.channel > 15 {
+                return Err(format!("aftertouch_cc: channel should be 0 to 15 (CC {})", route.cc));
+            }
+            if route.pads.iter().any(|p| *p >= 16) {
+                return Err(format!("aftertouch_cc: pads should be 0 to 15 (CC {})", route.cc));
+            }
+        }
+
+        for throw in &self.fx_throws {
+            if throw.pad >= 16 {
+                return Err(format!("fx_throws: pad should be 0 to 15 (found {})", throw.pad));
+            }
+            if throw.cc > 127 || throw.max > 127 {
+                return Err(format!("fx_throws: CC and max should be 0 to 127 (pad {})", throw.pad));
+            }
+            if throw.channel > 15 {
+                return Err(format!("fx_throws: channel should be 0 to 15 (pad {})", throw.pad));
+            }
+        }
+
+        if !self.motion_button.is_empty() && Buttons::from_name(&self.motion_button).is_none() {
+            return Err(format!(
+                "motion_button: unknown button {:?}",
+                self.motion_button
+            ));
+        }
+        if self.motion_pad >= 16 {
+            return Err("motion_pad should be 0 to 15".to_string());
+        }
+        if !(1..=30).contains(&self.motion_max_secs) {
+            return Err("motion_max_secs should be 1 to 30".to_string());
+        }
+
+        Rules::new(&self.rules)?;
+
+        if self.output_channels.iter().any(|c| *c > 15) {
+            return Err("output_channels should be 0 to 15".to_string());
+        }
+        if !(1 <= self.output_velocity_min
+            && self.output_velocity_min <= self.output_velocity_max
+            && self.output_velocity_max <= 127)
+        {
+            return Err("output_velocity_min/max should be 1 to 127, min <= max".to_string());
+        }
+
+        if !matches!(self.pad_gain.len(), 0 | 16) || !matches!(self.pad_threshold.len(), 0 | 16) {
+            return Err("pad_gain and pad_threshold need 16 values each (or none)".to_string());
+        }
+        if self.pad_gain.iter().any(|g| !(g.is_finite() && *g > 0.0)) {
+            return Err("pad_gain values should be positive".to_string());
+        }
+        if self.pad_threshold.iter().any(|t| *t > 4095) {
+            return Err("pad_threshold values should be 0 to 4095".to_string());
+        }
+        VelocityMap::new(self)?;
+        VelocitySplits::new(self)?;
+        Gates::new(self)?;
+        VelocityPalette::new(self)?;
+
         Ok(())
     }
 }
+
+fn button_chord(names: &[String]) -> Vec<Buttons> {
+    names.iter().filter_map(|n| Buttons::from_name(n)).collect()
+}
+
+fn parse_backlight_brightness(s: &str) -> Result<Brightness, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "dim" => Ok(Brightness::Dim),
+        "normal" => Ok(Brightness::Normal),
+        "bright" => Ok(Brightness::Bright),
+        other => Err(format!(
+            "invalid backlight_brightness={other:?} (expected: \"dim\", \"normal\", \"bright\")"
+        )),
+    }
+}
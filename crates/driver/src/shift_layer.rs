@@ -0,0 +1,196 @@
+use crate::button_map::ButtonMap;
+use crate::midi_out::MidiOut;
+use crate::timestamp::Timestamp;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// What a button sends while Shift is held, from the `[shift_mappings]` table: exactly one
+/// of `cc`, `note` or `command`
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ShiftMapping {
+    /// CC sent as 127 on press, 0 on release
+    pub cc: Option<u8>,
+    /// Note held while the button is
+    pub note: Option<u8>,
+    /// Driver command run on press: "panic", "reset_session" or "menu"
+    pub command: Option<String>,
+    /// MIDI channel (0-15); `button_channel` if left out
+    pub channel: Option<u8>,
+}
+
+/// A driver command a shifted button (or a button gesture) can run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ShiftCommand {
+    /// All Notes Off / All Sound Off everywhere, as with the panic chord
+    Panic,
+    /// Back to the config's layout, octave and patch
+    ResetSession,
+    /// Opens or closes the settings menu
+    Menu,
+}
+
+fn parse_command(s: &str) -> Result<ShiftCommand, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "panic" => Ok(ShiftCommand::Panic),
+        "reset_session" => Ok(ShiftCommand::ResetSession),
+        "menu" => Ok(ShiftCommand::Menu),
+        other => Err(format!(
+            "invalid command {other:?} (expected: \"panic\", \"reset_session\", \"menu\")"
+        )),
+    }
+}
+
+/// A checked `ShiftMapping`
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Action {
+    Cc(u8, u8),
+    Note(u8, u8),
+    Command(ShiftCommand),
+}
+
+impl Action {
+    /// Sends the press or release; returns the command to run, on press
+    pub(crate) fn fire(
+        self,
+        pressed: bool,
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) -> Option<ShiftCommand> {
+        match self {
+            Action::Cc(channel, cc) => {
+                let value = if pressed { 127 } else { 0 };
+                port.send(&[0xB0 | channel, cc, value], at);
+                None
+            }
+            Action::Note(channel, note) if pressed => {
+                port.send(&[0x90 | channel, note, 127], at);
+                None
+            }
+            Action::Note(channel, note) => {
+                port.send(&[0x80 | channel, note, 0], at);
+                None
+            }
+            Action::Command(command) => Some(command).filter(|_| pressed),
+        }
+    }
+}
+
+impl ShiftMapping {
+    pub(crate) fn action(&self, button_channel: u8) -> Result<Action, String> {
+        let channel = self.channel.unwrap_or(button_channel);
+        if channel > 15 {
+            return Err("channel should be 0 to 15".to_string());
+        }
+        match (self.cc, self.note, &self.command) {
+            (Some(cc), None, None) if cc < 128 => Ok(Action::Cc(channel, cc)),
+            (None, Some(note), None) if note < 128 => Ok(Action::Note(channel, note)),
+            (None, None, Some(command)) => parse_command(command).map(Action::Command),
+            (Some(_), None, None) | (None, Some(_), None) => {
+                Err("cc and note should be 0 to 127".to_string())
+            }
+            _ => Err("set one of cc, note or command".to_string()),
+        }
+    }
+}
+
+/// Checks `shift_mappings` (for `Settings::validate`). Shifted CCs may not reuse the
+/// buttons' own CCs on their channel.
+pub(crate) fn validate_mappings(
+    mappings: &BTreeMap<String, ShiftMapping>,
+    button_channel: u8,
+    buttons: &ButtonMap,
+) -> Result<(), String> {
+    for (name, mapping) in mappings {
+        let err = |e: String| format!("shift_mappings.{name}: {e}");
+        match Buttons::from_name(name) {
+            None => return Err(err("unknown button".to_string())),
+            Some(Buttons::Shift) => return Err(err("Shift can't be shifted".to_string())),
+            Some(_) => {}
+        }
+        if let Action::Cc(channel, cc) = mapping.action(button_channel).map_err(err)?
+            && buttons.has_cc(channel, cc)
+        {
+            return Err(err(format!("CC {cc} collides with the button CCs")));
+        }
+    }
+    Ok(())
+}
+
+/// The Shift layer: while Shift is held, buttons with a mapping in `[shift_mappings]` send
+/// that instead of their CC, and the button LEDs show the layer
+pub(crate) struct ShiftLayer {
+    actions: [Option<Action>; 41],
+    /// Buttons pressed as part of the layer, released there even if Shift went up first
+    held: [bool; 41],
+    /// Whether Shift is held
+    active: bool,
+}
+
+impl ShiftLayer {
+    /// None without any mappings (validated by `Settings::validate`)
+    pub(crate) fn new(
+        mappings: &BTreeMap<String, ShiftMapping>,
+        button_channel: u8,
+    ) -> Option<Self> {
+        if mappings.is_empty() {
+            return None;
+        }
+        let mut actions = [None; 41];
+        for (name, mapping) in mappings {
+            let button = Buttons::from_name(name).expect("Invalid shift_mappings");
+            actions[button as usize] = Some(mapping.action(button_channel).ok()?);
+        }
+        Some(Self {
+            actions,
+            held: [false; 41],
+            active: false,
+        })
+    }
+
+    pub(crate) fn active(&self) -> bool {
+        self.active
+    }
+
+    /// Follows Shift; true when the layer came up or went down
+    pub(crate) fn set_active(&mut self, shift_held: bool) -> bool {
+        std::mem::replace(&mut self.active, shift_held) != shift_held
+    }
+
+    /// A button went down or up. Returns None if the layer doesn't take it (the button
+    /// sends its own CC), else the command to run, if any.
+    pub(crate) fn button(
+        &mut self,
+        button: Buttons,
+        pressed: bool,
+        port: &mut MidiOut,
+        at: Timestamp,
+    ) -> Option<Option<ShiftCommand>> {
+        let idx = button as usize;
+        if pressed {
+            self.actions[idx].filter(|_| self.active)?;
+            self.held[idx] = true;
+        } else if !std::mem::take(&mut self.held[idx]) {
+            return None;
+        }
+        Some(self.actions[idx]?.fire(pressed, port, at))
+    }
+
+    /// Draws the layer over `lights`: Shift and the held buttons
+    /// bright, the other mapped buttons dim, the rest off
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        for (idx, action) in self.actions.iter().enumerate() {
+            let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+            let Some(button) = button.filter(|b| lights.button_has_light(*b)) else {
+                continue;
+            };
+            let brightness = match action {
+                _ if button == Buttons::Shift || self.held[idx] => Brightness::Bright,
+                Some(_) => Brightness::Dim,
+                None => Brightness::Off,
+            };
+            lights.set_button(button, brightness);
+        }
+    }
+}
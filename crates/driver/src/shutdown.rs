@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by SIGINT/SIGTERM and by a panic on any thread; the main loop checks it
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_signal: libc::c_int) {
+    // A second Ctrl+C gets out even if cleaning up hangs
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(1) };
+    }
+}
+
+/// Makes SIGINT, SIGTERM and panics ask the main loop to stop, so it can leave the
+/// controller dark and the synths silent instead of exiting mid-note.
+pub(crate) fn install() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+
+    // A background thread that dies would leave the driver half working
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        REQUESTED.store(true, Ordering::SeqCst);
+    }));
+}
+
+/// Whether the driver should shut down
+pub(crate) fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,266 @@
+use maschine_library::lights::{Brightness, Lights};
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+/// Raw slider positions reported while touched (0 means not touched)
+pub(crate) const SLIDER_RAW_MIN: u8 = 1;
+pub(crate) const SLIDER_RAW_MAX: u8 = 201;
+
+pub(crate) const SLIDER_LEDS: i32 = 25;
+const SLIDER_LED_CENTER: i32 = SLIDER_LEDS / 2;
+
+/// What the touch strip sends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SliderMode {
+    /// Absolute position on the slider CC
+    Cc,
+    /// DJ crossfader: position on the slider CC, or complementary volumes on two channels
+    Crossfader,
+    /// Swiping strums the held pad notes (or the keyboard scale's chord)
+    Strum,
+    /// Equal zones acting as momentary note/CC buttons (`slider_zones`)
+    Zones,
+    /// 14-bit pitch bend from the center, back to center on release
+    PitchBend,
+}
+
+pub(crate) fn parse_slider_mode(s: &str) -> Result<SliderMode, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "cc" => Ok(SliderMode::Cc),
+        "crossfader" => Ok(SliderMode::Crossfader),
+        "strum" => Ok(SliderMode::Strum),
+        "zones" => Ok(SliderMode::Zones),
+        "pitchbend" => Ok(SliderMode::PitchBend),
+        other => Err(format!(
+            "invalid slider_mode={other:?} \
+             (expected: \"cc\", \"crossfader\", \"strum\", \"zones\", \"pitchbend\")"
+        )),
+    }
+}
+
+/// Crossfader gain curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CrossfaderCurve {
+    Linear,
+    /// Constant power: no volume dip in the middle
+    Smooth,
+    /// Scratch cut: both sides at full volume except at the very ends
+    Sharp,
+}
+
+pub(crate) fn parse_crossfader_curve(s: &str) -> Result<CrossfaderCurve, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "linear" => Ok(CrossfaderCurve::Linear),
+        "smooth" => Ok(CrossfaderCurve::Smooth),
+        "sharp" => Ok(CrossfaderCurve::Sharp),
+        other => Err(format!(
+            "invalid crossfader_curve={other:?} (expected: \"linear\", \"smooth\", \"sharp\")"
+        )),
+    }
+}
+
+/// Channel volumes (0-127) of the A and B sides for a 7-bit crossfader position
+pub(crate) fn crossfader_volumes(position: u8, curve: CrossfaderCurve) -> (u8, u8) {
+    // Width of the fade zone at each end for the sharp curve
+    const CUT: f32 = 0.08;
+
+    let x = position.min(127) as f32 / 127.0;
+    let (a, b) = match curve {
+        CrossfaderCurve::Linear => (1.0 - x, x),
+        CrossfaderCurve::Smooth => ((x * FRAC_PI_2).cos(), (x * FRAC_PI_2).sin()),
+        CrossfaderCurve::Sharp => (((1.0 - x) / CUT).min(1.0), (x / CUT).min(1.0)),
+    };
+    ((a * 127.0).round() as u8, (b * 127.0).round() as u8)
+}
+
+/// Response of the slider CC to the finger position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SliderCurve {
+    Linear,
+    /// Slow start, most of the range at the right end: fine control of low values
+    Exponential,
+    /// Fast start, the right end fine-tunes high values
+    Logarithmic,
+    /// Fine at both ends, fast through the middle
+    SCurve,
+}
+
+pub(crate) fn parse_slider_curve(s: &str) -> Result<SliderCurve, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "linear" => Ok(SliderCurve::Linear),
+        "exponential" => Ok(SliderCurve::Exponential),
+        "logarithmic" => Ok(SliderCurve::Logarithmic),
+        "s_curve" => Ok(SliderCurve::SCurve),
+        other => Err(format!(
+            "invalid slider_curve={other:?} \
+             (expected: \"linear\", \"exponential\", \"logarithmic\", \"s_curve\")"
+        )),
+    }
+}
+
+impl SliderCurve {
+    /// Shapes a value out of `max` (127, or 16383 for 14 bits), right to left if `invert`.
+    /// Linear without invert leaves it as it is.
+    pub(crate) fn shape(self, value: u16, max: u16, invert: bool) -> u16 {
+        // Steepness of the exponential and logarithmic curves
+        const K: f32 = 4.0;
+
+        let x = value.min(max) as f32 / max as f32;
+        let x = if invert { 1.0 - x } else { x };
+        let y = match self {
+            SliderCurve::Linear => x,
+            SliderCurve::Exponential => ((K * x).exp() - 1.0) / (K.exp() - 1.0),
+            SliderCurve::Logarithmic => (1.0 + (K.exp() - 1.0) * x).ln() / K,
+            SliderCurve::SCurve => x * x * (3.0 - 2.0 * x),
+        };
+        (y.clamp(0.0, 1.0) * max as f32).round() as u16
+    }
+}
+
+/// LED index (0..25) under a raw slider position
+fn led_index(raw: u8) -> i32 {
+    (raw as i32 - 1 + 5) * SLIDER_LEDS / 200 - 1
+}
+
+/// Lights the strip up to the touched position
+pub(crate) fn paint_position(lights: &mut Lights, raw: u8) {
+    let cnt = led_index(raw);
+    for i in 0..SLIDER_LEDS {
+        let b = match cnt - i {
+            0 => Brightness::Normal,
+            1..=SLIDER_LEDS => Brightness::Dim,
+            _ => Brightness::Off,
+        };
+        lights.set_slider(i as usize, b);
+    }
+}
+
+/// Bipolar display: lights from the center of the strip towards the touched position
+pub(crate) fn paint_bipolar(lights: &mut Lights, raw: u8) {
+    let cnt = led_index(raw).clamp(0, SLIDER_LEDS - 1);
+    let (lo, hi) = (cnt.min(SLIDER_LED_CENTER), cnt.max(SLIDER_LED_CENTER));
+    for i in 0..SLIDER_LEDS {
+        let b = if i == cnt {
+            Brightness::Bright
+        } else if i == SLIDER_LED_CENTER || (lo..=hi).contains(&i) {
+            Brightness::Dim
+        } else {
+            Brightness::Off
+        };
+        lights.set_slider(i as usize, b);
+    }
+}
+
+const MAX_MEDIAN_WINDOW: usize = 9;
+
+/// Removes jitter from the raw touch strip position while a finger rests on it.
+///
+/// A running median over the last few reports drops single-report spikes, then hysteresis
+/// ignores movement of up to `hysteresis` raw steps from the last accepted position.
+/// The ends of the strip are always reachable.
+pub(crate) struct SliderFilter {
+    history: [u8; MAX_MEDIAN_WINDOW],
+    len: usize,
+    next: usize,
+    window: usize,
+    hysteresis: u8,
+    last: Option<u8>,
+}
+
+impl SliderFilter {
+    pub(crate) fn new(median_window: usize, hysteresis: u8) -> Self {
+        Self {
+            history: [0; MAX_MEDIAN_WINDOW],
+            len: 0,
+            next: 0,
+            window: median_window.clamp(1, MAX_MEDIAN_WINDOW),
+            hysteresis,
+            last: None,
+        }
+    }
+
+    /// Feeds a raw (non-zero) position; returns the filtered position if it moved
+    pub(crate) fn filter(&mut self, raw: u8) -> Option<u8> {
+        self.history[self.next] = raw;
+        self.next = (self.next + 1) % self.window;
+        self.len = (self.len + 1).min(self.window);
+
+        let mut sorted = self.history;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable();
+        let median = sorted[self.len / 2];
+
+        if let Some(last) = self.last {
+            let at_end = median == SLIDER_RAW_MIN || median >= SLIDER_RAW_MAX;
+            if median == last || (!at_end && median.abs_diff(last) <= self.hysteresis) {
+                return None;
+            }
+        }
+        self.last = Some(median);
+        Some(median)
+    }
+
+    /// Finger lifted: start fresh on the next touch
+    pub(crate) fn reset(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.last = None;
+    }
+}
+
+/// Values this close to the one to pick up count as caught, so a slow finger can't skip it
+const TAKEOVER_TOLERANCE: u8 = 2;
+
+/// Soft takeover for the absolute slider output.
+///
+/// The last value sent is remembered per page (pad layout). After switching pages the
+/// slider's position usually doesn't match what the parameter had there, so nothing is
+/// sent until the finger crosses (or lands on) the remembered value. Lifting the finger
+/// forgets the position, so tapping elsewhere doesn't count as crossing.
+pub(crate) struct SoftTakeover {
+    values: HashMap<String, u8>,
+    page: String,
+    caught: bool,
+    last_position: Option<u8>,
+}
+
+impl SoftTakeover {
+    pub(crate) fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            page: String::new(),
+            caught: true,
+            last_position: None,
+        }
+    }
+
+    /// Whether `value` may be sent on `page`; remembers it if so
+    pub(crate) fn pick_up(&mut self, page: &str, value: u8) -> bool {
+        if page != self.page {
+            self.page = page.to_string();
+            // A page the slider was never used on has nothing to pick up
+            self.caught = !self.values.contains_key(page);
+        }
+        let position = self.last_position.replace(value);
+        if !self.caught {
+            let target = self.values[page];
+            let crossed = position.is_some_and(|p| (p < target) != (value < target));
+            if !crossed && value.abs_diff(target) > TAKEOVER_TOLERANCE {
+                return false;
+            }
+            self.caught = true;
+        }
+        self.values.insert(page.to_string(), value);
+        true
+    }
+
+    /// Finger lifted
+    pub(crate) fn release(&mut self) {
+        self.last_position = None;
+    }
+
+    /// The value the slider has to be brought to, while it hasn't picked it up yet
+    pub(crate) fn pending(&self) -> Option<u8> {
+        (!self.caught).then(|| self.values[&self.page])
+    }
+}
@@ -0,0 +1,83 @@
+use crate::midi_out::MidiOut;
+use crate::slider::{SLIDER_LEDS, SLIDER_RAW_MAX, SLIDER_RAW_MIN};
+use crate::timestamp::Timestamp;
+use maschine_library::lights::{Brightness, Lights};
+use serde::Deserialize;
+
+/// One trigger zone of the touch strip (configured in `slider_zones`): a note or a CC
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct SliderZone {
+    /// Note sent while the zone is touched (velocity 127)
+    pub note: Option<u8>,
+    /// CC sent as 127 while the zone is touched, 0 when released
+    pub cc: Option<u8>,
+    /// MIDI channel (0-15)
+    #[serde(default)]
+    pub channel: u8,
+}
+
+impl SliderZone {
+    fn send(&self, port: &mut MidiOut, at: Timestamp, on: bool) {
+        match (self.note, self.cc) {
+            (Some(note), _) if on => port.send(&[0x90 | self.channel, note, 127], at),
+            (Some(note), _) => port.send(&[0x80 | self.channel, note, 0], at),
+            (None, Some(cc)) => port.send(&[0xB0 | self.channel, cc, if on { 127 } else { 0 }], at),
+            (None, None) => {}
+        }
+    }
+}
+
+/// The touch strip split into equal zones, each a momentary button.
+///
+/// Touching a zone triggers it, sliding into the next one releases the first, lifting the
+/// finger releases whatever is held.
+pub(crate) struct SliderZones {
+    zones: Vec<SliderZone>,
+    active: Option<usize>,
+}
+
+impl SliderZones {
+    pub(crate) fn new(zones: Vec<SliderZone>) -> Self {
+        Self { zones, active: None }
+    }
+
+    /// The finger is at `raw`; returns whether the active zone changed
+    pub(crate) fn touch(&mut self, raw: u8, port: &mut MidiOut, at: Timestamp) -> bool {
+        let span = (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as usize + 1;
+        let offset = raw.clamp(SLIDER_RAW_MIN, SLIDER_RAW_MAX) - SLIDER_RAW_MIN;
+        let zone = offset as usize * self.zones.len() / span;
+        if self.active == Some(zone) {
+            return false;
+        }
+        self.release(port, at);
+        self.zones[zone].send(port, at, true);
+        self.active = Some(zone);
+        println!("{at} Slider zone {zone} on");
+        true
+    }
+
+    /// The finger was lifted; returns whether a zone was released
+    pub(crate) fn release(&mut self, port: &mut MidiOut, at: Timestamp) -> bool {
+        let Some(zone) = self.active.take() else {
+            return false;
+        };
+        self.zones[zone].send(port, at, false);
+        println!("{at} Slider zone {zone} off");
+        true
+    }
+
+    /// Dim zones with a dark LED between them, the touched one bright
+    pub(crate) fn paint(&self, lights: &mut Lights) {
+        let n = self.zones.len();
+        for led in 0..SLIDER_LEDS as usize {
+            let zone = led * n / SLIDER_LEDS as usize;
+            let first = led == zone * SLIDER_LEDS as usize / n;
+            let b = match self.active {
+                _ if first && zone > 0 => Brightness::Off,
+                Some(active) if active == zone => Brightness::Bright,
+                _ => Brightness::Dim,
+            };
+            lights.set_slider(led, b);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::midi_out::MidiOut;
+use crate::palette::VelocityPalette;
+use crate::timestamp::Timestamp;
+use hidapi::{HidDevice, HidResult};
+use maschine_library::controls::PadTransform;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use midly::live::LiveEvent;
+use midly::{MetaMessage, Smf, Timing, TrackEventKind};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Tempo until the file sets one (120 BPM)
+const DEFAULT_US_PER_BEAT: f64 = 500_000.0;
+
+/// A channel message of a MIDI file and when it plays, relative to the start
+pub(crate) struct TimedMessage {
+    pub at: Duration,
+    pub bytes: Vec<u8>,
+}
+
+/// Reads a Standard MIDI File and flattens all its tracks into one time-ordered stream of
+/// channel messages, following the file's tempo changes.
+///
+/// Meta events other than tempo and SysEx are left out.
+pub(crate) fn load_smf(path: &str) -> Result<Vec<TimedMessage>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let smf = Smf::parse(&data).map_err(|e| format!("can't parse {path}: {e}"))?;
+
+    let mut events = Vec::new();
+    for track in &smf.tracks {
+        let mut tick = 0u64;
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            events.push((tick, event.kind));
+        }
+    }
+    // Stable: events on the same tick keep their track order (tempo track first)
+    events.sort_by_key(|(tick, _)| *tick);
+
+    let mut messages = Vec::new();
+    let mut us_per_beat = DEFAULT_US_PER_BEAT;
+    let mut elapsed_us = 0.0;
+    let mut last_tick = 0;
+    for (tick, kind) in events {
+        let us_per_tick = match smf.header.timing {
+            Timing::Metrical(ticks_per_beat) => us_per_beat / ticks_per_beat.as_int() as f64,
+            Timing::Timecode(fps, subframes) => {
+                1_000_000.0 / (fps.as_f32() as f64 * subframes as f64)
+            }
+        };
+        elapsed_us += (tick - last_tick) as f64 * us_per_tick;
+        last_tick = tick;
+
+        match kind {
+            TrackEventKind::Meta(MetaMessage::Tempo(tempo)) => {
+                us_per_beat = tempo.as_int() as f64;
+            }
+            TrackEventKind::Midi { channel, message } => {
+                let mut bytes = Vec::with_capacity(3);
+                LiveEvent::Midi { channel, message }
+                    .write_std(&mut bytes)
+                    .map_err(|e| e.to_string())?;
+                messages.push(TimedMessage {
+                    at: Duration::from_micros(elapsed_us as u64),
+                    bytes,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(messages)
+}
+
+/// Streams the messages out of the port in real time, lighting the pad mapped to each
+/// playing note (notemaps in reverse). Notes still sounding at the end are released.
+pub(crate) fn play_smf(
+    device: &HidDevice,
+    port: &mut MidiOut,
+    notemaps: &[u8],
+    transform: PadTransform,
+    palette: &VelocityPalette,
+    messages: &[TimedMessage],
+) -> HidResult<()> {
+    let mut lights = Lights::new();
+    lights.set_pad_transform(transform);
+    lights.write(device)?;
+    let mut sounding = [[false; 128]; 16];
+    let mut changed_lights = false;
+
+    let start = Instant::now();
+    for (i, msg) in messages.iter().enumerate() {
+        if let Some(wait) = (start + msg.at).checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        let now = Timestamp::now();
+        port.send(&msg.bytes, now);
+
+        let status = msg.bytes[0] & 0xF0;
+        if matches!(status, 0x80 | 0x90) {
+            let channel = (msg.bytes[0] & 0x0F) as usize;
+            let (note, velocity) = (msg.bytes[1], msg.bytes[2]);
+            let on = status == 0x90 && velocity > 0;
+            sounding[channel][note as usize] = on;
+            if let Some(pad) = notemaps.iter().position(|&n| n == note) {
+                if on {
+                    let color = palette.color(velocity);
+                    lights.set_pad(pad, color, Brightness::Normal);
+                } else {
+                    lights.set_pad(pad, PadColors::Off, Brightness::Off);
+                }
+                changed_lights = true;
+            }
+        }
+
+        // Send everything due at the same time back-to-back
+        if messages.get(i + 1).is_some_and(|next| next.at == msg.at) {
+            continue;
+        }
+        port.flush();
+        if changed_lights {
+            lights.write(device)?;
+            changed_lights = false;
+        }
+    }
+
+    let now = Timestamp::now();
+    for (channel, notes) in sounding.iter().enumerate() {
+        for note in (0..128u8).filter(|&n| notes[n as usize]) {
+            port.send(&[0x80 | channel as u8, note, 0], now);
+        }
+    }
+    port.flush();
+    lights.reset();
+    lights.write(device)
+}
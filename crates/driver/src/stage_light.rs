@@ -0,0 +1,48 @@
+use crate::screen_layers::{Layer, ScreenLayers};
+use crate::slider::SLIDER_LEDS;
+use crate::timestamp::Timestamp;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use maschine_library::screen::Screen;
+
+/// Screen size in pixels
+const SCREEN_ROWS: usize = 32;
+const SCREEN_COLUMNS: usize = 128;
+
+/// Every light at full brightness, the pads white: drawn over the lights while the stage
+/// light is on, so the previous state comes back as it was
+pub(crate) fn paint(lights: &mut Lights) {
+    for pad in 0..16 {
+        lights.set_pad(pad, PadColors::White, Brightness::Bright);
+    }
+    for idx in 0..Buttons::COUNT {
+        let button: Option<Buttons> = num::FromPrimitive::from_usize(idx);
+        if let Some(button) = button.filter(|b| lights.button_has_light(*b)) {
+            lights.set_button(button, Brightness::Bright);
+        }
+    }
+    for led in 0..SLIDER_LEDS as usize {
+        lights.set_slider(led, Brightness::Bright);
+    }
+}
+
+/// The screen with every pixel on
+pub(crate) fn screen() -> Screen {
+    let mut screen = Screen::new();
+    for row in 0..SCREEN_ROWS {
+        for column in 0..SCREEN_COLUMNS {
+            screen.set(row, column, true);
+        }
+    }
+    screen
+}
+
+/// Puts the lit screen up over everything, or takes it down
+pub(crate) fn show(on: bool, screen_layers: &mut ScreenLayers, at: Timestamp) {
+    println!("{} Stage light {}", at, if on { "on" } else { "off" });
+    if on {
+        screen_layers.show(Layer::StageLight, screen(), None);
+    } else {
+        screen_layers.dismiss(Layer::StageLight);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// Status page with the machine's CPU load and memory use, read from /proc.
+///
+/// CPU load is measured between two refreshes, so the first page only shows memory.
+/// Audio xruns aren't shown: neither JACK nor PipeWire exposes a counter that can be
+/// read without joining the audio graph.
+pub(crate) struct SystemStats {
+    /// Idle and total CPU time at the last refresh
+    last_cpu: Option<(u64, u64)>,
+    next_refresh: Instant,
+}
+
+impl SystemStats {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            last_cpu: None,
+            next_refresh: now,
+        }
+    }
+
+    /// Screen lines, once per refresh interval
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<[String; 2]> {
+        if now < self.next_refresh {
+            return None;
+        }
+        self.next_refresh = now + REFRESH;
+
+        let cpu = read_cpu_times();
+        let load = match (self.last_cpu, cpu) {
+            (Some((idle0, total0)), Some((idle1, total1))) if total1 > total0 => {
+                let busy = (total1 - total0).saturating_sub(idle1 - idle0);
+                format!("CPU {}%", busy * 100 / (total1 - total0))
+            }
+            _ => "CPU -".to_string(),
+        };
+        self.last_cpu = cpu;
+
+        let memory = match read_memory_kb() {
+            Some((total, available)) => {
+                let gib = |kb: u64| kb as f64 / (1024.0 * 1024.0);
+                format!("RAM {:.1}/{:.1}G", gib(total - available), gib(total))
+            }
+            None => "RAM -".to_string(),
+        };
+        Some([load, memory])
+    }
+}
+
+/// Idle (incl. iowait) and total jiffies of all CPUs
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let times: Vec<u64> = stat
+        .lines()
+        .next()?
+        .strip_prefix("cpu ")?
+        .split_whitespace()
+        .take(8)
+        .map(|t| t.parse().ok())
+        .collect::<Option<_>>()?;
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((idle, times.iter().sum()))
+}
+
+/// MemTotal and MemAvailable in kB
+fn read_memory_kb() -> Option<(u64, u64)> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix(name))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()
+    };
+    Some((field("MemTotal:")?, field("MemAvailable:")?))
+}
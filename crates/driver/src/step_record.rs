@@ -0,0 +1,93 @@
+use crate::desk::KeyMacro;
+use crate::midi_out::MidiOut;
+use crate::settings::Settings;
+use crate::timestamp::Timestamp;
+use crate::transport::{self, TransportState};
+
+/// Step recording into the DAW (`step_record_button`): while the host doesn't play, every
+/// hit on the pads (a chord counts once, when its last pad is released) moves a position
+/// counter on the screen one step on, and can nudge the DAW's step input along with a CC
+/// and/or a key combo. The notes themselves go out as usual.
+pub(crate) struct StepRecord {
+    armed: bool,
+    /// Steps entered since arming
+    steps: u32,
+    steps_per_beat: u32,
+    beats_per_bar: u32,
+    /// Pads held down as part of the current step
+    held: [bool; 16],
+    channel: u8,
+    nudge_cc: Option<u8>,
+    nudge_keys: Option<KeyMacro>,
+}
+
+impl StepRecord {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let name = format!("{} Step", settings.client_name);
+        let nudge_keys = match settings.step_record_nudge_keys.as_str() {
+            "" => None,
+            combo => Some(KeyMacro::new(&name, combo)?),
+        };
+        Ok(Self {
+            armed: false,
+            steps: 0,
+            steps_per_beat: settings.step_record_steps_per_beat as u32,
+            beats_per_bar: settings.metronome_beats_per_bar as u32,
+            held: [false; 16],
+            channel: settings.button_channel,
+            nudge_cc: settings.step_record_nudge_cc,
+            nudge_keys,
+        })
+    }
+
+    pub(crate) fn armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Arms step recording from the first step, or disarms it
+    pub(crate) fn toggle(&mut self) {
+        self.armed = !self.armed;
+        self.steps = 0;
+        self.held = [false; 16];
+    }
+
+    /// A pad went down: it belongs to the step being entered
+    pub(crate) fn hit(&mut self, pad: usize) {
+        // While the host plays, it records in real time
+        if self.armed && transport::state() != TransportState::Playing {
+            self.held[pad] = true;
+        }
+    }
+
+    /// A pad came up. When it was the step's last, moves on and nudges the DAW; true then.
+    pub(crate) fn release(&mut self, pad: usize, port: &mut MidiOut, at: Timestamp) -> bool {
+        if !std::mem::take(&mut self.held[pad]) || self.held.contains(&true) {
+            return false;
+        }
+        self.steps += 1;
+        if let Some(cc) = self.nudge_cc {
+            // Like a button: press, then release
+            port.send(&[0xB0 | self.channel, cc, 127], at);
+            port.send(&[0xB0 | self.channel, cc, 0], at);
+        }
+        if let Some(keys) = &mut self.nudge_keys {
+            keys.tap();
+        }
+        true
+    }
+
+    /// Screen lines: mode, and the position of the next step as bar.beat.step
+    pub(crate) fn lines(&self) -> [String; 2] {
+        let beat = self.steps / self.steps_per_beat;
+        let position = format!(
+            "{}.{}.{}",
+            beat / self.beats_per_bar + 1,
+            beat % self.beats_per_bar + 1,
+            self.steps % self.steps_per_beat + 1
+        );
+        [
+            "STEP REC".to_string(),
+            format!("{position} STEP {}", self.steps + 1),
+        ]
+    }
+}
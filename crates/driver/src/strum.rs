@@ -0,0 +1,111 @@
+use crate::midi_out::MidiOut;
+use crate::slider::{SLIDER_RAW_MAX, SLIDER_RAW_MIN};
+use crate::timestamp::Timestamp;
+
+/// Velocity of a string plucked by touching it without swiping
+const TAP_VELOCITY: u8 = 80;
+/// Velocity = VELOCITY_BASE + swipe speed (raw steps per ms) * VELOCITY_PER_SPEED
+const VELOCITY_BASE: f32 = 30.0;
+const VELOCITY_PER_SPEED: f32 = 50.0;
+
+/// Strums across the touch strip, like Maschine's Smart Strip.
+///
+/// The strip is split into one zone ("string") per note, low to high. Swiping across a zone
+/// boundary plays that note, with velocity derived from the swipe speed; lifting the finger
+/// releases all strummed notes. The notes are those of the held pads, or if none are held,
+/// the fallback chord (the keyboard scale's triad over two octaves).
+pub(crate) struct Strum {
+    fallback: Vec<u8>,
+    /// Channel of the strummed notes (the pads')
+    channel: u8,
+    held: Vec<u8>,
+    /// Last position and when it was read, while touched
+    last: Option<(u8, Timestamp)>,
+    sounding: Vec<u8>,
+}
+
+impl Strum {
+    pub(crate) fn new(fallback: Vec<u8>, channel: u8) -> Self {
+        Self {
+            fallback,
+            channel,
+            held: Vec::new(),
+            last: None,
+            sounding: Vec::new(),
+        }
+    }
+
+    /// A pad playing `notes` went down
+    pub(crate) fn hold(&mut self, notes: &[u8]) {
+        self.held.extend_from_slice(notes);
+    }
+
+    /// A pad playing `notes` was released
+    pub(crate) fn unhold(&mut self, notes: &[u8]) {
+        for note in notes {
+            if let Some(i) = self.held.iter().position(|n| n == note) {
+                self.held.remove(i);
+            }
+        }
+    }
+
+    fn strings(&self) -> Vec<u8> {
+        let mut strings = if self.held.is_empty() {
+            self.fallback.clone()
+        } else {
+            self.held.clone()
+        };
+        strings.sort_unstable();
+        strings.dedup();
+        strings
+    }
+
+    /// The finger is on the strip at `raw` (filtered, non-zero)
+    pub(crate) fn touch(&mut self, raw: u8, port: &mut MidiOut, at: Timestamp) {
+        let strings = self.strings();
+        let last = self.last.replace((raw, at));
+        if strings.is_empty() {
+            return;
+        }
+
+        let span = (SLIDER_RAW_MAX - SLIDER_RAW_MIN) as usize + 1;
+        let zone = |raw: u8| {
+            ((raw.max(SLIDER_RAW_MIN) - SLIDER_RAW_MIN) as usize * strings.len() / span)
+                .min(strings.len() - 1)
+        };
+        let to = zone(raw);
+        let (zones, velocity): (Vec<usize>, u8) = match last {
+            None => (vec![to], TAP_VELOCITY),
+            Some((last_raw, last_at)) => {
+                let from = zone(last_raw);
+                let zones = if to > from {
+                    (from + 1..=to).collect()
+                } else {
+                    (to..from).rev().collect()
+                };
+                let ms = at.instant().duration_since(last_at.instant()).as_secs_f32() * 1000.0;
+                let speed = raw.abs_diff(last_raw) as f32 / ms.max(1.0);
+                let velocity = (VELOCITY_BASE + speed * VELOCITY_PER_SPEED).clamp(1.0, 127.0);
+                (zones, velocity as u8)
+            }
+        };
+
+        for note in zones.into_iter().map(|z| strings[z]) {
+            if self.sounding.contains(&note) {
+                port.send(&[0x80 | self.channel, note, 0], at);
+            } else {
+                self.sounding.push(note);
+            }
+            port.send(&[0x90 | self.channel, note, velocity], at);
+            println!("{at} Strum {note} vel {velocity}");
+        }
+    }
+
+    /// The finger left the strip: release everything strummed
+    pub(crate) fn release(&mut self, port: &mut MidiOut, at: Timestamp) {
+        self.last = None;
+        for note in self.sounding.drain(..) {
+            port.send(&[0x80 | self.channel, note, 0], at);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::faults::{self, Fault};
+use hidapi::{HidApi, HidDevice, HidResult};
+use maschine_library::hid::HidTransport;
+use std::cell::{Cell, RefCell};
+
+/// Bytes of the button bitmap in a button report, after the report ID
+const BUTTON_BYTES: std::ops::Range<usize> = 1..7;
+const ENCODER_BYTE: usize = 7;
+const SLIDER_BYTE: usize = 10;
+
+/// With `sync_controllers`, opens a second Mikro besides `main`, if one is plugged in
+pub(crate) fn open_second(api: &mut HidApi, main: &HidDevice) -> Option<HidDevice> {
+    let opened = api.refresh_devices().and_then(|_| main.get_device_info());
+    let main_info = match opened {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Can't look for a second controller (continuing with one): {e}");
+            return None;
+        }
+    };
+    let other = api.device_list().find(|d| {
+        d.vendor_id() == 0x17cc && d.product_id() == 0x1700 && d.path() != main_info.path()
+    })?;
+    match other.open_device(api).and_then(|device| {
+        device.set_blocking_mode(false)?;
+        Ok(device)
+    }) {
+        Ok(device) => {
+            println!("Second controller found, running both as one");
+            Some(device)
+        }
+        Err(e) => {
+            eprintln!("Can't open the second controller (continuing with one): {e}");
+            None
+        }
+    }
+}
+
+/// What each controller last reported, to give the main loop one controller's worth
+#[derive(Default)]
+struct Merge {
+    buttons: [[u8; 6]; 2],
+    /// Raw 4-bit encoder positions; None until the controller's first report
+    encoders: [Option<u8>; 2],
+    /// The position shown to the main loop, moved by both encoders
+    encoder: u8,
+    sliders: [u8; 2],
+    /// The controller whose slider moved last
+    slider_from: usize,
+}
+
+impl Merge {
+    /// Rewrites a button report from controller `from` (0 or 1): a button is down while
+    /// it's held on either, turning either encoder moves the one position, and the slider
+    /// follows whichever was touched last. Pad reports go through as they are.
+    fn report(&mut self, from: usize, buf: &mut [u8]) {
+        if buf.len() <= SLIDER_BYTE || buf[0] != 0x01 {
+            return;
+        }
+        self.buttons[from].copy_from_slice(&buf[BUTTON_BYTES]);
+        for (byte, merged) in buf[BUTTON_BYTES].iter_mut().enumerate() {
+            *merged = self.buttons[0][byte] | self.buttons[1][byte];
+        }
+
+        let pos = buf[ENCODER_BYTE] & 0x0f;
+        if let Some(prev) = self.encoders[from].replace(pos) {
+            self.encoder = self.encoder.wrapping_add(pos.wrapping_sub(prev)) & 0x0f;
+        }
+        buf[ENCODER_BYTE] = (buf[ENCODER_BYTE] & 0xf0) | self.encoder;
+
+        if buf[SLIDER_BYTE] != self.sliders[from] {
+            self.sliders[from] = buf[SLIDER_BYTE];
+            self.slider_from = from;
+        }
+        buf[SLIDER_BYTE] = self.sliders[self.slider_from];
+    }
+
+    /// A controller went away: nothing is held on it any more
+    fn forget(&mut self, from: usize) {
+        self.buttons[from] = [0; 6];
+        self.sliders[from] = 0;
+    }
+}
+
+/// The controllers the main loop works with: the one it opened, and with
+/// `sync_controllers` a second one. Both show the same lights and screen, and their
+/// controls go through the one mapping: the second doubles the first (its pad 1 plays
+/// what pad 1 does) rather than adding 16 more pads.
+pub(crate) struct Linked<'a, T: HidTransport> {
+    main: &'a T,
+    second: Option<&'a T>,
+    /// Set once the second stopped answering; the driver carries on with the main one
+    second_lost: Cell<bool>,
+    merge: RefCell<Merge>,
+    /// Which one is read first on the next pass, taking turns so neither is starved
+    second_first: Cell<bool>,
+}
+
+impl<'a, T: HidTransport> Linked<'a, T> {
+    pub(crate) fn new(main: &'a T, second: Option<&'a T>) -> Self {
+        Self {
+            main,
+            second,
+            second_lost: Cell::new(false),
+            merge: RefCell::new(Merge::default()),
+            second_first: Cell::new(false),
+        }
+    }
+
+    fn second(&self) -> Option<&'a T> {
+        self.second.filter(|_| !self.second_lost.get())
+    }
+
+    /// The second controller failed: log it, show it, and go on without it
+    fn lose_second(&self, e: impl std::fmt::Display) {
+        eprintln!("Second controller lost ({e}), continuing with one");
+        self.second_lost.set(true);
+        self.merge.borrow_mut().forget(1);
+        faults::raise(Fault::SyncLost);
+    }
+
+    /// Reads the second controller; its errors only take it out
+    fn read_second(&self, second: &T, buf: &mut [u8], timeout: i32) -> usize {
+        match second.read_timeout(buf, timeout) {
+            Ok(size) => size,
+            Err(e) => {
+                self.lose_second(e);
+                0
+            }
+        }
+    }
+}
+
+impl<T: HidTransport> HidTransport for Linked<'_, T> {
+    /// Waits up to `timeout` ms for either controller. The one read first is only checked,
+    /// the wait is spent on the other.
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        let Some(second) = self.second() else {
+            let size = self.main.read_timeout(buf, timeout)?;
+            // Still one controller's worth after the second went away
+            if size > 0 && self.second.is_some() {
+                self.merge.borrow_mut().report(0, &mut buf[..size]);
+            }
+            return Ok(size);
+        };
+        let second_first = self.second_first.replace(!self.second_first.get());
+        let (from, size) = if second_first {
+            match self.read_second(second, buf, 0) {
+                0 => (0, self.main.read_timeout(buf, timeout)?),
+                size => (1, size),
+            }
+        } else {
+            match self.main.read_timeout(buf, 0)? {
+                0 => (1, self.read_second(second, buf, timeout)),
+                size => (0, size),
+            }
+        };
+        if size > 0 {
+            self.merge.borrow_mut().report(from, &mut buf[..size]);
+        }
+        Ok(size)
+    }
+
+    /// Writes to both; only the main controller's errors count
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        let written = self.main.write(data)?;
+        if let Some(second) = self.second()
+            && let Err(e) = second.write(data)
+        {
+            self.lose_second(e);
+        }
+        Ok(written)
+    }
+}
@@ -0,0 +1,78 @@
+/// Characters offered by the text entry, in scroll order
+const CHARSET: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R',
+    'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+    ' ', '-', '#',
+];
+/// Screen stand-ins for the candidate at the cursor that have no visible glyph
+const SPACE_MARK: char = '_';
+const DONE_MARK: char = '>';
+/// One line of the screen, minus the candidate character
+const MAX_LEN: usize = 15;
+
+/// On-screen text entry driven by the encoder.
+///
+/// Turning scrolls the candidate character at the cursor, pushing appends it, and
+/// Shift + push deletes the last character. One step past the end of the character set
+/// is "done" (shown as `>`): pushing it finishes the entry.
+pub(crate) struct TextEntry {
+    title: String,
+    text: String,
+    /// Index into CHARSET, CHARSET.len() being "done"
+    candidate: usize,
+}
+
+impl TextEntry {
+    pub(crate) fn new(title: &str, initial: &str) -> Self {
+        let text: String = initial
+            .to_ascii_uppercase()
+            .chars()
+            .filter(|c| CHARSET.contains(c))
+            .take(MAX_LEN)
+            .collect();
+        // Editing an existing text most likely means confirming it
+        let candidate = if text.is_empty() { 0 } else { CHARSET.len() };
+        Self {
+            title: title.to_string(),
+            text,
+            candidate,
+        }
+    }
+
+    /// Scrolls the candidate character, wrapping around
+    pub(crate) fn turn(&mut self, delta: i32) {
+        let n = CHARSET.len() as i32 + 1;
+        self.candidate = (self.candidate as i32 + delta).rem_euclid(n) as usize;
+    }
+
+    /// Appends the candidate; returns the finished text when "done" is pushed
+    pub(crate) fn push(&mut self) -> Option<String> {
+        match CHARSET.get(self.candidate) {
+            None => Some(self.text.trim_end().to_string()),
+            Some(&c) => {
+                if self.text.len() < MAX_LEN {
+                    self.text.push(c);
+                }
+                if self.text.len() == MAX_LEN {
+                    self.candidate = CHARSET.len();
+                }
+                None
+            }
+        }
+    }
+
+    /// Removes the last character
+    pub(crate) fn delete(&mut self) {
+        self.text.pop();
+    }
+
+    /// Screen lines: the title, and the text followed by the candidate
+    pub(crate) fn lines(&self) -> [String; 2] {
+        let candidate = match CHARSET.get(self.candidate) {
+            Some(' ') => SPACE_MARK,
+            Some(&c) => c,
+            None => DONE_MARK,
+        };
+        [self.title.clone(), format!("{}{candidate}", self.text)]
+    }
+}
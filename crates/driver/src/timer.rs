@@ -0,0 +1,103 @@
+use maschine_library::lights::{Brightness, Lights, PadColors};
+use std::time::{Duration, Instant};
+
+const MAX_MINUTES: u64 = 99;
+
+/// Practice countdown timer shown on the screen, with the pads as a progress bar.
+///
+/// Play starts/pauses, Stop resets, the encoder sets the duration in minutes. The pads
+/// go out one by one as time runs, shifting from green to yellow to red, and all turn
+/// red when the time is up.
+pub(crate) struct Timer {
+    duration: Duration,
+    /// Time run before the last pause
+    elapsed: Duration,
+    started: Option<Instant>,
+    /// Whole seconds left when last drawn, None to force a redraw
+    drawn: Option<u64>,
+}
+
+impl Timer {
+    pub(crate) fn new(minutes: u64) -> Self {
+        Self {
+            duration: Duration::from_secs(minutes.clamp(1, MAX_MINUTES) * 60),
+            elapsed: Duration::ZERO,
+            started: None,
+            drawn: None,
+        }
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        let run = self.started.map_or(Duration::ZERO, |s| now.duration_since(s));
+        self.duration.saturating_sub(self.elapsed + run)
+    }
+
+    /// Play: start or pause
+    pub(crate) fn toggle(&mut self, now: Instant) {
+        match self.started.take() {
+            Some(started) => self.elapsed += now.duration_since(started),
+            None if !self.remaining(now).is_zero() => self.started = Some(now),
+            None => {}
+        }
+        self.redraw();
+    }
+
+    /// Stop: back to the full duration, paused
+    pub(crate) fn reset(&mut self) {
+        self.started = None;
+        self.elapsed = Duration::ZERO;
+        self.redraw();
+    }
+
+    /// Encoder: change the duration by whole minutes
+    pub(crate) fn adjust(&mut self, delta: i32) {
+        let minutes = (self.duration.as_secs() / 60) as i64 + delta as i64;
+        self.duration = Duration::from_secs(minutes.clamp(1, MAX_MINUTES as i64) as u64 * 60);
+        self.redraw();
+    }
+
+    /// Something else was written over the display
+    pub(crate) fn redraw(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Screen lines and pad colors, when they changed since the last call
+    pub(crate) fn poll(&mut self, now: Instant, lights: &mut Lights) -> Option<[String; 2]> {
+        let remaining = self.remaining(now);
+        // Round up, so the display reaches 00:00 exactly when time is up
+        let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+        if self.drawn == Some(secs) {
+            return None;
+        }
+        self.drawn = Some(secs);
+        if secs == 0 {
+            self.started = None;
+        }
+
+        let fraction = remaining.as_secs_f32() / self.duration.as_secs_f32();
+        let lit = (fraction * 16.0).ceil() as usize;
+        let color = match fraction {
+            f if f > 0.5 => PadColors::Green,
+            f if f > 0.2 => PadColors::Yellow,
+            _ => PadColors::Red,
+        };
+        for pad in 0..16 {
+            if secs == 0 {
+                lights.set_pad(pad, PadColors::Red, Brightness::Bright);
+            } else if pad < lit {
+                lights.set_pad(pad, color, Brightness::Normal);
+            } else {
+                lights.set_pad(pad, PadColors::Off, Brightness::Off);
+            }
+        }
+
+        let status = if secs == 0 {
+            "TIME UP"
+        } else if self.started.is_some() {
+            "TIMER"
+        } else {
+            "PAUSED"
+        };
+        Some([status.to_string(), format!("{:02}:{:02}", secs / 60, secs % 60)])
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Marks the driver start; logged timestamps are relative to it
+pub(crate) fn mark_start() {
+    START.get_or_init(Instant::now);
+}
+
+/// Monotonic time at which an input event happened, taken when its HID report was read
+/// and carried along with everything generated from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Timestamp(Instant);
+
+impl Timestamp {
+    pub(crate) fn now() -> Self {
+        Self(Instant::now())
+    }
+
+    pub(crate) fn instant(self) -> Instant {
+        self.0
+    }
+
+    /// Time since the event happened
+    pub(crate) fn elapsed(self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = *START.get_or_init(|| self.0);
+        let secs = self.0.saturating_duration_since(start).as_secs_f64();
+        write!(f, "[{secs:>11.6}]")
+    }
+}
@@ -0,0 +1,192 @@
+use crate::clock::PPQN;
+use crate::settings::Settings;
+use maschine_library::controls::Buttons;
+use maschine_library::lights::{Brightness, Lights};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long `beat_flash_button` stays flashed on a beat
+const BEAT_FLASH: Duration = Duration::from_millis(100);
+
+/// Host transport as seen through MIDI real-time messages on the input port
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TransportState {
+    /// Nothing received yet, or stopped before any clock pulse moved the song
+    Stopped,
+    Playing,
+    /// Stopped mid-song: Continue resumes, Start plays from the top
+    Paused,
+}
+
+impl TransportState {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Stopped => "stopped",
+            Self::Playing => "playing",
+            Self::Paused => "paused",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Playing,
+            2 => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+}
+
+// Written by the MIDI input callback, read by the main loop and the control socket
+static STATE: AtomicU8 = AtomicU8::new(TransportState::Stopped as u8);
+static CHANGED: AtomicBool = AtomicBool::new(false);
+/// Whether clock pulses arrived since the last Start, which tells a pause from a stop
+static MOVED: AtomicBool = AtomicBool::new(false);
+/// Clock pulses received since the last Start (or since the driver started)
+static PULSES: AtomicU64 = AtomicU64::new(0);
+/// Beats (every `PPQN` pulses from Start) received since the driver started
+static BEATS: AtomicU64 = AtomicU64::new(0);
+/// Host tempo in hundredths of a BPM, measured over the last beat; 0 until known
+static TEMPO: AtomicU32 = AtomicU32::new(0);
+/// When the last beat's first pulse came in; None after Start, Stop or Continue, where the
+/// time to the next beat says nothing about the tempo
+static LAST_BEAT: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub(crate) fn state() -> TransportState {
+    TransportState::from_u8(STATE.load(Ordering::SeqCst))
+}
+
+/// Feeds a real-time status byte (Start, Continue, Stop, Clock); returns the new state if
+/// the message changed it
+pub(crate) fn handle_realtime(status: u8) -> Option<TransportState> {
+    let old = state();
+    if matches!(status, 0xFA..=0xFC) {
+        *LAST_BEAT.lock().unwrap() = None;
+        // The host may come back at another tempo
+        TEMPO.store(0, Ordering::SeqCst);
+    }
+    let new = match status {
+        0xFA => {
+            MOVED.store(false, Ordering::SeqCst);
+            PULSES.store(0, Ordering::SeqCst);
+            TransportState::Playing
+        }
+        0xFB => TransportState::Playing,
+        0xFC if old == TransportState::Playing && MOVED.load(Ordering::SeqCst) => {
+            TransportState::Paused
+        }
+        0xFC => TransportState::Stopped,
+        0xF8 => {
+            if PULSES.fetch_add(1, Ordering::SeqCst).is_multiple_of(PPQN) {
+                beat(Instant::now());
+            }
+            if old == TransportState::Playing {
+                MOVED.store(true, Ordering::SeqCst);
+            }
+            return None;
+        }
+        _ => return None,
+    };
+    if new == old {
+        return None;
+    }
+    STATE.store(new as u8, Ordering::SeqCst);
+    CHANGED.store(true, Ordering::SeqCst);
+    Some(new)
+}
+
+/// A beat's first pulse came in: measures the tempo from the previous one
+fn beat(now: Instant) {
+    BEATS.fetch_add(1, Ordering::SeqCst);
+    let last = LAST_BEAT.lock().unwrap().replace(now);
+    if let Some(last) = last {
+        let secs = now.duration_since(last).as_secs_f64();
+        if secs > 0.0 {
+            TEMPO.store((6000.0 / secs).round() as u32, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Host tempo from the MIDI clock, once two beats came in since the last Start, Stop or
+/// Continue
+pub(crate) fn bpm() -> Option<f64> {
+    let tempo = TEMPO.load(Ordering::SeqCst);
+    (tempo > 0).then(|| tempo as f64 / 100.0)
+}
+
+/// Host beats received since the driver started
+pub(crate) fn beats() -> u64 {
+    BEATS.load(Ordering::SeqCst)
+}
+
+/// Host clock pulses since the last Start: the song position, in pulses, while playing
+pub(crate) fn pulses() -> u64 {
+    PULSES.load(Ordering::SeqCst)
+}
+
+/// Whether the state changed since the last call
+pub(crate) fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Flashes `beat_flash_button` on every beat of the host clock while the host plays. A
+/// button that's already fully lit goes dark for the flash instead.
+pub(crate) struct BeatFlash {
+    button: Buttons,
+    /// Host beats already flashed
+    seen: u64,
+    /// While flashing: the button's light to go back to, and when
+    restore: Option<(Brightness, Instant)>,
+}
+
+impl BeatFlash {
+    pub(crate) fn new(settings: &Settings) -> Option<Self> {
+        Buttons::from_name(&settings.beat_flash_button).map(|button| Self {
+            button,
+            seen: beats(),
+            restore: None,
+        })
+    }
+
+    /// Whether `flash` has something to do
+    pub(crate) fn due(&self, now: Instant) -> bool {
+        beats() != self.seen || self.restore.is_some_and(|(_, until)| now >= until)
+    }
+
+    /// Starts a flash on a new beat, ends it after `BEAT_FLASH`; true when the light changed
+    pub(crate) fn flash(&mut self, lights: &mut Lights, now: Instant) -> bool {
+        let beats = beats();
+        if beats != self.seen {
+            self.seen = beats;
+            if state() == TransportState::Playing {
+                let level = match self.restore {
+                    Some((level, _)) => level,
+                    None => lights.get_button(self.button),
+                };
+                let flash = if level == Brightness::Bright {
+                    Brightness::Off
+                } else {
+                    Brightness::Bright
+                };
+                lights.set_button(self.button, flash);
+                self.restore = Some((level, now + BEAT_FLASH));
+                return true;
+            }
+        }
+        match self.restore {
+            Some((level, until)) if now >= until => {
+                self.restore = None;
+                lights.set_button(self.button, level);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The transport feedback repainted Play and Stop; a flash on one of them ends with that
+    pub(crate) fn transport_painted(&mut self) {
+        if matches!(self.button, Buttons::Play | Buttons::Stop) {
+            self.restore = None;
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::settings::Settings;
+use serde::Deserialize;
+
+/// Full scale of the 12-bit pad values
+const RAW_MAX: f64 = 4095.0;
+
+/// Shape of the pad value to velocity mapping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum VelocityCurve {
+    Linear,
+    /// Soft hits come out louder (square root)
+    Soft,
+    /// Takes harder hits to get loud (square)
+    Hard,
+    /// Flat at first, steep at the top
+    Exponential,
+    /// Steep at first, flat at the top: easiest to play loud
+    Log,
+    /// Every hit at `velocity_max`
+    Fixed,
+}
+
+pub(crate) fn parse_velocity_curve(s: &str) -> Result<VelocityCurve, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "linear" => Ok(VelocityCurve::Linear),
+        "soft" => Ok(VelocityCurve::Soft),
+        "hard" => Ok(VelocityCurve::Hard),
+        "exp" => Ok(VelocityCurve::Exponential),
+        "log" => Ok(VelocityCurve::Log),
+        "fixed" => Ok(VelocityCurve::Fixed),
+        other => Err(format!(
+            "invalid velocity curve {other:?} \
+             (expected: \"linear\", \"soft\", \"hard\", \"exp\", \"log\", \"fixed\")"
+        )),
+    }
+}
+
+impl VelocityCurve {
+    /// Maps a hit strength in 0-1 onto 0-1
+    fn apply(self, x: f64) -> f64 {
+        match self {
+            Self::Linear => x,
+            Self::Soft => x.sqrt(),
+            Self::Hard => x * x,
+            Self::Exponential => (3.0 * x).exp_m1() / 3f64.exp_m1(),
+            Self::Log => (20.0 * x).ln_1p() / 20f64.ln_1p(),
+            Self::Fixed => 1.0,
+        }
+    }
+}
+
+/// Turns raw pad values into note-on velocities: the calibrated gain (`pad_gain`), then the
+/// pad's curve, scaled into `velocity_min`-`velocity_max`.
+#[derive(Debug, Clone)]
+pub(crate) struct VelocityMap {
+    curves: [VelocityCurve; 16],
+    gain: [f64; 16],
+    min: u8,
+    max: u8,
+}
+
+impl VelocityMap {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        Self::with_curve(settings, &settings.velocity_curve)
+    }
+
+    /// The map with `curve` in place of `velocity_curve` (per-pad curves still win)
+    pub(crate) fn with_curve(settings: &Settings, curve: &str) -> Result<Self, String> {
+        let global = parse_velocity_curve(curve).map_err(|e| format!("velocity_curve: {e}"))?;
+        let mut curves = [global; 16];
+        match settings.pad_velocity_curves.len() {
+            0 => {}
+            16 => {
+                for (pad, name) in settings.pad_velocity_curves.iter().enumerate() {
+                    // An empty entry keeps the global curve
+                    if !name.trim().is_empty() {
+                        curves[pad] = parse_velocity_curve(name)
+                            .map_err(|e| format!("pad_velocity_curves: pad {pad}: {e}"))?;
+                    }
+                }
+            }
+            _ => return Err("pad_velocity_curves needs 16 values (or none)".to_string()),
+        }
+
+        // Calibrated per physical pad, whatever logical pad it is after the transform
+        let transform = settings.pad_transform();
+        let mut gain = [1.0; 16];
+        for (pad, g) in settings.pad_gain.iter().enumerate().take(16) {
+            gain[transform.apply(pad)] = *g;
+        }
+
+        let (min, max) = (settings.velocity_min, settings.velocity_max);
+        if !(1 <= min && min <= max && max <= 127) {
+            return Err("velocity_min/max should be 1 to 127, min <= max".to_string());
+        }
+        Ok(Self {
+            curves,
+            gain,
+            min,
+            max,
+        })
+    }
+
+    /// Note-on velocity for a raw 12-bit hit on `pad`
+    pub(crate) fn velocity(&self, pad: usize, raw: u16) -> u8 {
+        let x = (f64::from(raw) / RAW_MAX * self.gain[pad]).clamp(0.0, 1.0);
+        let y = self.curves[pad].apply(x);
+        let (min, max) = (f64::from(self.min), f64::from(self.max));
+        (min + y * (max - min)).round() as u8
+    }
+}
+
+/// A pad playing different notes depending on how hard it's hit:
+/// `{ pad = 2, thresholds = [90], notes = [42, 46] }` plays 42 below velocity 90 and 46 from
+/// 90 up. One note more than thresholds.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct VelocitySplit {
+    /// Logical pad index (0-15)
+    pub pad: usize,
+    /// Velocities (after the curve) where the next note takes over, ascending
+    pub thresholds: Vec<u8>,
+    pub notes: Vec<u8>,
+}
+
+/// The `velocity_splits` by pad
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VelocitySplits {
+    pads: [Option<VelocitySplit>; 16],
+}
+
+impl VelocitySplits {
+    pub(crate) fn new(settings: &Settings) -> Result<Self, String> {
+        let mut splits = Self::default();
+        for split in &settings.velocity_splits {
+            let err = |what: &str| format!("velocity_splits: pad {}: {what}", split.pad);
+            if split.pad >= 16 {
+                return Err(err("pad should be 0 to 15"));
+            }
+            if split.notes.len() != split.thresholds.len() + 1 {
+                return Err(err("needs one note more than thresholds"));
+            }
+            if split.notes.iter().any(|&n| n > 127) {
+                return Err(err("notes should be 0 to 127"));
+            }
+            let ascending = split.thresholds.windows(2).all(|w| w[0] < w[1]);
+            if !ascending || split.thresholds.iter().any(|&t| !(2..=127).contains(&t)) {
+                return Err(err("thresholds should be ascending, 2 to 127"));
+            }
+            if splits.pads[split.pad].replace(split.clone()).is_some() {
+                return Err(err("split twice"));
+            }
+        }
+        Ok(splits)
+    }
+
+    /// Note a hit on `pad` plays at `velocity`, if the pad is split
+    pub(crate) fn note(&self, pad: usize, velocity: u8) -> Option<u8> {
+        let split = self.pads[pad].as_ref()?;
+        let zone = split.thresholds.iter().take_while(|&&t| velocity >= t).count();
+        Some(split.notes[zone])
+    }
+}
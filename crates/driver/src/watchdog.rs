@@ -0,0 +1,38 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Set by the SysEx handler on the MIDI input thread
+static LAST_PING: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Records a heartbeat from the controller script
+pub(crate) fn ping(now: Instant) {
+    *LAST_PING.lock().unwrap() = Some(now);
+}
+
+/// Notices when the controller script stops pinging (a crashed script or DAW).
+///
+/// Only armed by the first ping, so running without the script never counts as lost.
+pub(crate) struct HostWatchdog {
+    timeout: Duration,
+    lost: bool,
+}
+
+impl HostWatchdog {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            lost: false,
+        }
+    }
+
+    /// Some(true) when the host was just lost, Some(false) when it just came back
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<bool> {
+        let last = (*LAST_PING.lock().unwrap())?;
+        let lost = now.saturating_duration_since(last) > self.timeout;
+        if lost == self.lost {
+            return None;
+        }
+        self.lost = lost;
+        Some(lost)
+    }
+}
@@ -55,6 +55,22 @@ pub enum Buttons {
     EncoderTouch = 40,
 }
 
+impl Buttons {
+    pub const COUNT: usize = 41;
+
+    /// Looks a button up by name, e.g. "NoteRepeat" or "note_repeat" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Buttons> {
+        let wanted: String = name
+            .chars()
+            .filter(|c| *c != '_' && *c != '-' && *c != ' ')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        (0..Self::COUNT)
+            .filter_map(num::FromPrimitive::from_usize)
+            .find(|b: &Buttons| format!("{b:?}").to_ascii_lowercase() == wanted)
+    }
+}
+
 #[derive(FromPrimitive, Debug, Clone, Copy, PartialEq)]
 pub enum PadEventType {
     NoteOn = 0x10,
@@ -63,3 +79,44 @@ pub enum PadEventType {
     PressOff = 0x20,
     PressOn = 0x00,
 }
+
+/// How the 4x4 pad grid is turned relative to the pad numbers the device reports, for
+/// left-handed players and controllers mounted the other way round. Pad numbers count from
+/// the bottom left, left to right, then up a row.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PadTransform {
+    #[default]
+    Identity,
+    /// Left and right swapped
+    MirrorH,
+    /// Top and bottom swapped
+    MirrorV,
+    /// Upside down
+    Rotate180,
+}
+
+impl PadTransform {
+    /// Looks a transform up by name: "identity", "mirror_h", "mirror_v" or "rotate_180"
+    pub fn from_name(name: &str) -> Option<PadTransform> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "identity" => Some(PadTransform::Identity),
+            "mirror_h" => Some(PadTransform::MirrorH),
+            "mirror_v" => Some(PadTransform::MirrorV),
+            "rotate_180" => Some(PadTransform::Rotate180),
+            _ => None,
+        }
+    }
+
+    /// The pad at the position of `pad` after the transform. Every transform is its own
+    /// inverse, so this maps device pads to logical ones and back.
+    pub fn apply(self, pad: usize) -> usize {
+        let (row, col) = (pad / 4, pad % 4);
+        let (row, col) = match self {
+            PadTransform::Identity => (row, col),
+            PadTransform::MirrorH => (row, 3 - col),
+            PadTransform::MirrorV => (3 - row, col),
+            PadTransform::Rotate180 => (3 - row, 3 - col),
+        };
+        row * 4 + col
+    }
+}
@@ -404,6 +404,114 @@ const LETTERS: [Glyph; 26] = [
     ],
 ];
 
+/// Punctuation glyphs, looked up by character
+const SYMBOLS: [(char, Glyph); 8] = [
+    (
+        '#',
+        [
+            b"  x  x  ",
+            b"  x  x  ",
+            b" xxxxxx ",
+            b"  x  x  ",
+            b"  x  x  ",
+            b" xxxxxx ",
+            b"  x  x  ",
+            b"  x  x  ",
+        ],
+    ),
+    (
+        '-',
+        [
+            b"        ",
+            b"        ",
+            b"        ",
+            b" xxxxxx ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+        ],
+    ),
+    (
+        '_',
+        [
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b" xxxxxx ",
+        ],
+    ),
+    (
+        '>',
+        [
+            b"        ",
+            b"  x     ",
+            b"   x    ",
+            b"    x   ",
+            b"     x  ",
+            b"    x   ",
+            b"   x    ",
+            b"  x     ",
+        ],
+    ),
+    (
+        '.',
+        [
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"        ",
+            b"   xx   ",
+            b"   xx   ",
+        ],
+    ),
+    (
+        '/',
+        [
+            b"      x ",
+            b"     x  ",
+            b"     x  ",
+            b"    x   ",
+            b"   x    ",
+            b"  x     ",
+            b"  x     ",
+            b" x      ",
+        ],
+    ),
+    (
+        '%',
+        [
+            b" xx   x ",
+            b" xx  x  ",
+            b"    x   ",
+            b"   x    ",
+            b"  x     ",
+            b" x  xx  ",
+            b"x   xx  ",
+            b"        ",
+        ],
+    ),
+    (
+        ':',
+        [
+            b"        ",
+            b"   xx   ",
+            b"   xx   ",
+            b"        ",
+            b"        ",
+            b"   xx   ",
+            b"   xx   ",
+            b"        ",
+        ],
+    ),
+];
+
 pub struct Font {}
 
 impl Font {
@@ -425,7 +533,10 @@ impl Font {
             '0'..='9' => &DIGITS[(ch as usize) - ('0' as usize)],
             'A'..='Z' => &LETTERS[(ch as usize) - ('A' as usize)],
             'a'..='z' => &LETTERS[(ch as usize) - ('a' as usize)],
-            _ => return, // unsupported character, skip
+            _ => match SYMBOLS.iter().find(|(c, _)| *c == ch) {
+                Some((_, glyph)) => glyph,
+                None => return, // unsupported character, skip
+            },
         };
         Self::write_glyph(s, y, x, glyph, scale);
     }
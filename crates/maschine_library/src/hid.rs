@@ -0,0 +1,63 @@
+use hidapi::{HidDevice, HidError, HidResult};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// What the controller is reached through: the HID device, or a `MockTransport` standing in
+/// for it
+pub trait HidTransport {
+    /// Reads one input report into `buf`, waiting up to `timeout` ms; 0 when none came
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize>;
+    /// Writes one output report (lights, screen)
+    fn write(&self, data: &[u8]) -> HidResult<usize>;
+}
+
+impl HidTransport for HidDevice {
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        HidDevice::read_timeout(self, buf, timeout)
+    }
+
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        HidDevice::write(self, data)
+    }
+}
+
+/// A controller that plays back recorded input reports, one per read, and keeps the
+/// reports written to it. Once the recording is used up, reads fail the way they do when
+/// the controller is unplugged.
+#[derive(Default)]
+pub struct MockTransport {
+    reports: RefCell<VecDeque<Vec<u8>>>,
+    written: RefCell<Vec<Vec<u8>>>,
+}
+
+impl MockTransport {
+    pub fn new(reports: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self {
+            reports: RefCell::new(reports.into_iter().collect()),
+            written: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Output reports written so far, oldest first
+    pub fn written(&self) -> Vec<Vec<u8>> {
+        self.written.borrow().clone()
+    }
+}
+
+impl HidTransport for MockTransport {
+    fn read_timeout(&self, buf: &mut [u8], _timeout: i32) -> HidResult<usize> {
+        let Some(report) = self.reports.borrow_mut().pop_front() else {
+            return Err(HidError::HidApiError {
+                message: "end of recorded reports".to_string(),
+            });
+        };
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        self.written.borrow_mut().push(data.to_vec());
+        Ok(data.len())
+    }
+}
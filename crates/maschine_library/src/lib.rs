@@ -1,4 +1,5 @@
 pub mod controls;
 pub mod font;
+pub mod hid;
 pub mod lights;
 pub mod screen;
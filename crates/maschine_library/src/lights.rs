@@ -1,5 +1,6 @@
-use crate::controls::Buttons;
-use hidapi::{HidDevice, HidResult};
+use crate::controls::{Buttons, PadTransform};
+use crate::hid::HidTransport;
+use hidapi::HidResult;
 use num_derive::FromPrimitive;
 
 #[derive(FromPrimitive, Debug, Clone, Copy, PartialEq)]
@@ -32,14 +33,45 @@ pub enum PadColors {
     White = 17,
 }
 
+impl PadColors {
+    /// Looks a color up by name, e.g. "LightOrange" or "light_orange" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<PadColors> {
+        let wanted: String = name
+            .chars()
+            .filter(|c| *c != '_' && *c != '-' && *c != ' ')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        (0..=PadColors::White as u8)
+            .filter_map(num::FromPrimitive::from_u8)
+            .find(|c: &PadColors| format!("{c:?}").to_ascii_lowercase() == wanted)
+    }
+}
+
+#[derive(Clone)]
 pub struct Lights {
     status: [u8; 80],
+    high_contrast: bool,
+    pad_transform: PadTransform,
 }
 
 impl Lights {
     #[allow(clippy::new_without_default, reason = "intentional")]
     pub fn new() -> Self {
-        Self { status: [0; 80] }
+        Self {
+            status: [0; 80],
+            high_contrast: false,
+            pad_transform: PadTransform::Identity,
+        }
+    }
+
+    /// Writes every light that is on at full brightness (for users who can't see Dim)
+    pub fn set_high_contrast(&mut self, on: bool) {
+        self.high_contrast = on;
+    }
+
+    /// Pads are set by logical index; `write` puts each one where the transform moved it
+    pub fn set_pad_transform(&mut self, transform: PadTransform) {
+        self.pad_transform = transform;
     }
 
     pub fn reset(&mut self) {
@@ -90,10 +122,24 @@ impl Lights {
         (color, b)
     }
 
-    pub fn write(&self, h: &HidDevice) -> HidResult<()> {
+    pub fn write(&self, h: &impl HidTransport) -> HidResult<()> {
         let mut buf = [0u8; 81];
         buf[0] = 0x80;
         buf[1..].copy_from_slice(&self.status);
+        for pad in 0..16 {
+            buf[1 + 39 + self.pad_transform.apply(pad)] = self.status[39 + pad];
+        }
+        if self.high_contrast {
+            for (i, val) in buf[1..].iter_mut().enumerate() {
+                *val = match (i, *val) {
+                    (_, 0) => 0,
+                    // Pads: color in the upper bits, brightness in the lowest two
+                    (39..55, v) if v > 3 => v | 0b11,
+                    (39..55, _) => 0,
+                    _ => Brightness::Bright as u8,
+                };
+            }
+        }
         h.write(&buf)?;
         Ok(())
     }
@@ -1,4 +1,5 @@
-use hidapi::{HidDevice, HidResult};
+use crate::hid::HidTransport;
+use hidapi::HidResult;
 
 const HEADER_HI: [u8; 9] = [0xe0, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x02, 0x00];
 const HEADER_LO: [u8; 9] = [0xe0, 0x00, 0x00, 0x02, 0x00, 0x80, 0x00, 0x02, 0x00];
@@ -40,7 +41,7 @@ impl Screen {
         }
     }
 
-    pub fn write(&self, h: &HidDevice) -> HidResult<()> {
+    pub fn write(&self, h: &impl HidTransport) -> HidResult<()> {
         let mut buf = [0u8; 265];
         buf[..9].copy_from_slice(&HEADER_HI);
         buf[9..].copy_from_slice(&self.buffer[..256]);